@@ -15,12 +15,14 @@ use tokio_util::sync::CancellationToken;
 use tracing::trace;
 use tracing::{error, instrument};
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
 
 use crate::TracingContext;
 use crate::error::PythonExecutionError;
 use crate::framing::{LengthPrefixedRead, LengthPrefixedWrite};
-use crate::InFlightMap;
+use crate::{CloseReason, InFlightMap};
 use crate::ReplySlot;
+use crate::transport::{Transport, TransportRead, TransportWrite};
 
 #[derive(Debug, Error)]
 pub enum CallbackError {
@@ -101,6 +103,8 @@ pub struct CallbackIpcChild<C> {
     cancellation_token: tokio_util::sync::CancellationToken,
     // Track message stats for adaptive throttling
     pending_count: std::sync::atomic::AtomicUsize,
+    // Set when the connection is torn down
+    close_reason: CloseReason,
 }
 
 struct CallbackWriteRequest<C> {
@@ -109,16 +113,22 @@ struct CallbackWriteRequest<C> {
 
 impl<C> CallbackIpcChild<C>
 where
-    C: Send + Sync + Encode + Decode<()> + 'static,
+    C: Send + Sync + Serialize + DeserializeOwned + 'static,
 {
     pub fn from_duplex(duplex: crate::DuplexUnixStream) -> std::sync::Arc<Self> {
         let (read_half, write_half) = duplex.into_inner().into_split();
         Self::new(read_half, write_half)
     }
-    pub fn new(
-        read_half: tokio::net::unix::OwnedReadHalf,
-        write_half: tokio::net::unix::OwnedWriteHalf,
-    ) -> std::sync::Arc<Self> {
+    /// Wire up the callback channel over any [`Transport`], splitting it internally.
+    pub fn from_transport<T: Transport>(transport: T) -> std::sync::Arc<Self> {
+        let (read_half, write_half) = transport.into_split();
+        Self::new(read_half, write_half)
+    }
+    pub fn new<R, W>(read_half: R, write_half: W) -> std::sync::Arc<Self>
+    where
+        R: TransportRead,
+        W: TransportWrite,
+    {
         use tokio::sync::mpsc::unbounded_channel;
         let (write_tx, mut write_rx) = unbounded_channel::<CallbackWriteRequest<C>>();
         let in_flight = InFlightMap::new();
@@ -134,9 +144,13 @@ where
             next_id: std::sync::atomic::AtomicU64::new(1),
             cancellation_token,
             pending_count: std::sync::atomic::AtomicUsize::new(0),
+            close_reason: CloseReason::default(),
         });
         
         // Writer task
+        let in_flight_writer = result.in_flight.clone();
+        let callback_writer = std::sync::Arc::downgrade(&result);
+        let close_reason_writer = result.close_reason.clone();
         tokio::spawn(async move {
             let mut writer = LengthPrefixedWrite::new(write_half);
             loop {
@@ -144,7 +158,18 @@ where
                     _ = cancellation_token_writer.cancelled() => break,
                     Some(write_req) = write_rx.recv() => {
                         let env = write_req.envelope;
-                        if writer.write_msg(&env).await.is_err() {
+                        if let Err(e) = writer.write_msg(&env).await {
+                            let correlation_id = env.correlation_id;
+                            let message = format!("Callback connection closed: failed to write callback: {e}");
+                            tracing::error!(event = "callback_ipc_child_write_error", correlation_id, error = ?e, "CallbackIpcChild writer task error, closing connection");
+                            crate::metrics::MetricsHandle::callback().track_error("write_error");
+                            if let Some((_, mut slot)) = in_flight_writer.0.remove(&correlation_id) {
+                                slot.try_send_stream_error(PythonExecutionError::ExecutionError { message: message.clone() });
+                                if let Some(callback) = callback_writer.upgrade() {
+                                    callback.release_pending();
+                                }
+                            }
+                            close_reason_writer.close(&cancellation_token_writer, message);
                             break;
                         }
                     }
@@ -154,6 +179,7 @@ where
         
         // Reader task with improved error handling and tracking
         let result_clone = result.clone();
+        let close_reason_reader = result.close_reason.clone();
         tokio::spawn(async move {
             let mut reader = LengthPrefixedRead::new(read_half);
             loop {
@@ -196,12 +222,13 @@ where
                     }
                 }
             }
+            // The connection is done: stop the writer and reject new callbacks
+            close_reason_reader.close(&cancellation_token_reader, "Callback reply loop exited (EOF)");
             // After the reader loop, drain in_flight with error, signalling that the child process has exited
             in_flight_reader.0.iter_mut().for_each(|mut item| {
                 let (_, slot) = item.pair_mut();
                 if let Some(sender) = slot.stream_sender.take() {
-                    let err = PythonExecutionError::ExecutionError { message: "Callback reply loop exited (EOF)".to_string() };
-                    if sender.send(Err(err)).is_err() {
+                    if sender.send(Err(close_reason_reader.error())).is_err() {
                         tracing::error!(event = "callback_ipc_child_read", error = "Failed to send EOF error to waiting task", "Failed to notify waiting task about EOF");
                     }
                 }
@@ -225,12 +252,21 @@ where
     pub fn pending_count(&self) -> usize {
         self.pending_count.load(std::sync::atomic::Ordering::SeqCst)
     }
+
+    // Saturating, since the reader resets the count when the connection closes
+    fn release_pending(&self) {
+        let _ = self.pending_count.fetch_update(
+            std::sync::atomic::Ordering::SeqCst,
+            std::sync::atomic::Ordering::SeqCst,
+            |n| n.checked_sub(1),
+        );
+    }
 }
 
 #[async_trait]
 impl<C> CallbackHandler<C> for CallbackIpcChild<C>
 where
-    C: Send + Sync + Encode + Decode<()> + 'static,
+    C: Send + Sync + Serialize + DeserializeOwned + 'static,
 {
     async fn handle(&self, callback: C) -> Result<(), PythonExecutionError> {
         let correlation_id = self.next_correlation_id();
@@ -257,6 +293,14 @@ where
             self.pending_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
         }
         
+        // Checked after inserting, so either the reader's drain sees the slot or we see the close
+        if self.cancellation_token.is_cancelled() {
+            if self.in_flight.0.remove(&correlation_id).is_some() {
+                self.release_pending();
+            }
+            return Err(self.close_reason.error());
+        }
+        
         // If we have too many pending callbacks, introduce a small adaptive backoff
         let current_pending = self.pending_count();
         if current_pending > 1000 {
@@ -269,17 +313,16 @@ where
         
         // Send the write request with careful error handling
         let write_req = CallbackWriteRequest { envelope };
-        if let Err(e) = self.write_tx.send(write_req) {
-            // Clean up in_flight entry on error and decrement pending count
-            self.in_flight.0.remove(&correlation_id);
-            self.pending_count.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        if self.write_tx.send(write_req).is_err() {
+            // The writer task has exited: clean up in_flight entry and decrement pending count
+            if self.in_flight.0.remove(&correlation_id).is_some() {
+                self.release_pending();
+            }
             
             // Track the error in metrics
             crate::metrics::MetricsHandle::callback().track_error("send_failed");
             
-            return Err(PythonExecutionError::ExecutionError { 
-                message: format!("Failed to send callback write request: {e}") 
-            });
+            return Err(self.close_reason.error());
         }
         
         // Wait for the response
@@ -294,13 +337,13 @@ where
     }
 }
 
-pub struct CallbackReceiver<M, H>
+pub struct CallbackReceiver<M, H, R = tokio::net::unix::OwnedReadHalf, W = tokio::net::unix::OwnedWriteHalf>
 where
-    M: Send + Sync + Decode<()> + 'static,
+    M: Send + Sync + DeserializeOwned + 'static,
     H: CallbackHandler<M> + Clone + Send + Sync + 'static,
 {
-    read_half: tokio::net::unix::OwnedReadHalf,
-    write_half: Option<tokio::net::unix::OwnedWriteHalf>,
+    read_half: R,
+    write_half: Option<W>,
     handler: H,
     cancellation_token: CancellationToken,
     _phantom: PhantomData<(M, H)>,
@@ -308,7 +351,7 @@ where
 
 impl<M, H> CallbackReceiver<M, H>
 where
-    M: Send + Sync + Decode<()> + 'static,
+    M: Send + Sync + DeserializeOwned + 'static,
     H: CallbackHandler<M> + Clone + Send + Sync + 'static,
 {
     pub fn from_duplex(duplex: crate::DuplexUnixStream, handler: H) -> Self {
@@ -322,6 +365,32 @@ where
             _phantom: PhantomData,
         }
     }
+}
+
+impl<M, H, R, W> CallbackReceiver<M, H, R, W>
+where
+    M: Send + Sync + DeserializeOwned + 'static,
+    H: CallbackHandler<M> + Clone + Send + Sync + 'static,
+    R: TransportRead,
+    W: TransportWrite,
+{
+    /// Receive callbacks over any [`Transport`], splitting it internally.
+    pub fn from_transport<T>(transport: T, handler: H) -> Self
+    where
+        T: Transport<ReadHalf = R, WriteHalf = W>,
+    {
+        let (read_half, write_half) = transport.into_split();
+        Self::new(read_half, write_half, handler)
+    }
+    pub fn new(read_half: R, write_half: W, handler: H) -> Self {
+        Self {
+            read_half,
+            write_half: Some(write_half),
+            handler,
+            cancellation_token: CancellationToken::new(),
+            _phantom: PhantomData,
+        }
+    }
     pub fn shutdown(&self) {
         self.cancellation_token.cancel();
     }
@@ -481,6 +550,7 @@ where
         });
         // Rest of the method remains the same...
         let cancellation_token_writer = cancellation_token.clone();
+        let close_reason_writer = CloseReason::default();
         let writer_task = tokio::spawn(async move {
             let mut writer = LengthPrefixedWrite::new(write_half.expect("write_half missing in CallbackReceiver"));
             
@@ -494,21 +564,34 @@ where
                     
                     message = reply_rx.recv() => {
                         match message {
-                            Some(reply_envelope) => {
-                                // Process write directly, one at a time
-                                let result = writer.write_msg(&reply_envelope).await;
+                            Some(first) => {
+                                // Coalesce everything already queued into one write
+                                let batch = crate::framing::recv_batch(&mut reply_rx, first, writer.batch()).await;
+                                let mut result = Ok(());
+                                for reply_envelope in &batch {
+                                    if let Err(e) = writer.queue_msg(reply_envelope).await {
+                                        result = Err(e);
+                                        break;
+                                    }
+                                }
+                                if result.is_ok() {
+                                    result = writer.flush().await;
+                                }
+                                let correlation_ids: Vec<u64> = batch.iter().map(|env| env.correlation_id).collect();
                                 if let Err(e) = result {
+                                    // Part of a frame may have been written, so the connection can't be
+                                    // reused; closing it fails every callback still waiting in the child
+                                    let reason = format!("Callback connection closed: failed to write reply: {e}");
                                     tracing::error!(event = "callback_receiver", task = "writer", 
-                                        step = "write_error", correlation_id = reply_envelope.correlation_id, 
-                                        error = ?e, "Failed to write reply envelope to socket");
-                                    // Don't break on errors - just log them and continue
-                                    
-                                    // Track error in metrics
+                                        step = "write_error", ?correlation_ids, 
+                                        error = ?e, "Failed to write reply envelopes, closing connection");
                                     crate::metrics::MetricsHandle::callback().track_error("write_failed");
+                                    close_reason_writer.close(&cancellation_token_writer, reason);
+                                    return Err(CallbackError::Ipc(e));
                                 } else {
                                     tracing::debug!(event = "callback_receiver", task = "writer", 
-                                        step = "reply_written", correlation_id = reply_envelope.correlation_id,
-                                        "Wrote reply envelope to socket");
+                                        step = "reply_written", ?correlation_ids,
+                                        "Wrote reply envelopes to socket");
                                 }
                             },
                             None => {
@@ -0,0 +1,110 @@
+//! Wire codecs for protocol frames.
+//!
+//! Frame payloads are encoded with a [`Codec`]. All codecs go through serde, so message
+//! types only need `Serialize`/`Deserialize`; bincode derives are not required.
+//!
+//! The request connection's codec is chosen per backend and agreed during the handshake
+//! (see [`crate::perform_handshake_with_codecs`]). The handshake itself and the callback
+//! connection always use the default codec, [`CodecKind::Bincode`].
+//!
+//! | Codec | Use |
+//! |-------|-----|
+//! | [`BincodeCodec`] | Default. Compact and fast. |
+//! | [`MessagePackCodec`] | Self-describing, with field names; readable by non-Rust tooling. |
+//! | [`JsonCodec`] | Human-readable, for debugging traffic. Byte buffers become number arrays. |
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Encodes and decodes frame payloads.
+pub trait Codec {
+    /// Encode `value` in a single pass.
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> io::Result<Vec<u8>>;
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T>;
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// bincode with the standard config, through serde.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> io::Result<Vec<u8>> {
+        // `encode_to_vec` runs a sizing pass first, which would serialize everything twice
+        let mut bytes = Vec::new();
+        bincode::serde::encode_into_std_write(value, &mut bytes, bincode::config::standard())
+            .map_err(invalid_data)?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map(|(value, _)| value)
+            .map_err(invalid_data)
+    }
+}
+
+/// MessagePack with named struct fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackCodec;
+
+impl Codec for MessagePackCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> io::Result<Vec<u8>> {
+        rmp_serde::to_vec_named(value).map_err(invalid_data)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        rmp_serde::from_slice(bytes).map_err(invalid_data)
+    }
+}
+
+/// JSON, for debugging.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> io::Result<Vec<u8>> {
+        serde_json::to_vec(value).map_err(invalid_data)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        serde_json::from_slice(bytes).map_err(invalid_data)
+    }
+}
+
+/// Identifies a built-in codec; this is what the handshake negotiates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CodecKind {
+    #[default]
+    Bincode,
+    MessagePack,
+    Json,
+}
+
+impl CodecKind {
+    /// Every built-in codec, in default preference order.
+    pub const ALL: [CodecKind; 3] = [CodecKind::Bincode, CodecKind::MessagePack, CodecKind::Json];
+}
+
+impl Codec for CodecKind {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> io::Result<Vec<u8>> {
+        match self {
+            CodecKind::Bincode => BincodeCodec.encode(value),
+            CodecKind::MessagePack => MessagePackCodec.encode(value),
+            CodecKind::Json => JsonCodec.encode(value),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        match self {
+            CodecKind::Bincode => BincodeCodec.decode(bytes),
+            CodecKind::MessagePack => MessagePackCodec.decode(bytes),
+            CodecKind::Json => JsonCodec.decode(bytes),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Optional per-frame compression.
+//!
+//! Compression is agreed during the handshake (see [`crate::HandshakeOptions`]). Once agreed,
+//! frames whose encoded size is at least [`threshold`] bytes are compressed before sending.
+//! A compressed frame is marked by [`COMPRESSED_FLAG`] in its length prefix, and its payload
+//! starts with the uncompressed length as a little-endian `u32` so receivers can enforce the
+//! maximum frame size before decompressing. Frames that don't shrink are sent as they are.
+//!
+//! The flag bit only has this meaning on connections that agreed on compression; elsewhere
+//! the length prefix is a plain `u32`.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::framing::FrameSizeError;
+
+/// Length prefix bit marking a compressed frame.
+pub const COMPRESSED_FLAG: u32 = 1 << 31;
+
+/// Default minimum encoded frame size that gets compressed (8 KiB).
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 8 * 1024;
+
+/// Environment variable used to pass the threshold to child processes.
+pub const COMPRESSION_THRESHOLD_ENV: &str = "KAMEO_COMPRESSION_THRESHOLD";
+
+static THRESHOLD: Lazy<AtomicUsize> = Lazy::new(|| {
+    let threshold = std::env::var(COMPRESSION_THRESHOLD_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_COMPRESSION_THRESHOLD);
+    AtomicUsize::new(threshold)
+});
+
+/// Current minimum frame size that gets compressed.
+pub fn threshold() -> usize {
+    THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Set the minimum frame size that gets compressed.
+pub fn set_threshold(bytes: usize) {
+    THRESHOLD.store(bytes, Ordering::Relaxed);
+}
+
+/// Frame compression algorithm; this is what the handshake negotiates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Compression {
+    #[default]
+    None,
+    /// zstd at level 1; best ratio for JSON-like data. Needs the `zstd` feature.
+    Zstd,
+    /// LZ4 block format; fastest. Needs the `lz4` feature.
+    Lz4,
+}
+
+impl Compression {
+    /// Algorithms compiled into this build, in default preference order.
+    pub fn supported() -> Vec<Compression> {
+        let mut supported = Vec::new();
+        if cfg!(feature = "zstd") {
+            supported.push(Compression::Zstd);
+        }
+        if cfg!(feature = "lz4") {
+            supported.push(Compression::Lz4);
+        }
+        supported
+    }
+
+    /// Name used in logs and metric labels.
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Zstd => "zstd",
+            Compression::Lz4 => "lz4",
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::bulk::compress(data, 1),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Ok(lz4_flex::block::compress(data)),
+            #[allow(unreachable_patterns)]
+            other => Err(unsupported(other)),
+        }
+    }
+
+    fn decompress(self, data: &[u8], len: usize) -> io::Result<Vec<u8>> {
+        let out = match self {
+            Compression::None => data.to_vec(),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::bulk::decompress(data, len)?,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => lz4_flex::block::decompress(data, len)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            #[allow(unreachable_patterns)]
+            other => return Err(unsupported(other)),
+        };
+        if out.len() != len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("compressed frame decoded to {} bytes, expected {len}", out.len()),
+            ));
+        }
+        Ok(out)
+    }
+}
+
+fn unsupported(compression: Compression) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} compression is not compiled in", compression.name()),
+    )
+}
+
+/// Prepare encoded frame `bytes` for the wire, returning the length prefix and payload.
+///
+/// Compresses with `compression` if the frame is at least [`threshold`] bytes and shrinks.
+pub(crate) fn pack(bytes: &[u8], compression: Compression) -> io::Result<(u32, Cow<'_, [u8]>)> {
+    if compression == Compression::None || bytes.len() < threshold() {
+        return Ok((bytes.len() as u32, Cow::Borrowed(bytes)));
+    }
+    let compressed = compression.compress(bytes)?;
+    if compressed.len() + 4 >= bytes.len() {
+        return Ok((bytes.len() as u32, Cow::Borrowed(bytes)));
+    }
+    crate::metrics::record_compression(compression.name(), bytes.len(), compressed.len() + 4);
+    let mut payload = Vec::with_capacity(compressed.len() + 4);
+    payload.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    payload.extend_from_slice(&compressed);
+    Ok((payload.len() as u32 | COMPRESSED_FLAG, Cow::Owned(payload)))
+}
+
+/// Split a received length prefix into the payload length and whether it is compressed.
+pub(crate) fn frame_len(prefix: u32, compression: Compression) -> (usize, bool) {
+    if compression != Compression::None && prefix & COMPRESSED_FLAG != 0 {
+        ((prefix & !COMPRESSED_FLAG) as usize, true)
+    } else {
+        (prefix as usize, false)
+    }
+}
+
+/// Recover the encoded frame from a received payload.
+///
+/// Fails with [`FrameSizeError::Incoming`] if the frame would decompress beyond `max_frame_size`.
+pub(crate) fn unpack(
+    payload: Vec<u8>,
+    compressed: bool,
+    compression: Compression,
+    max_frame_size: usize,
+) -> io::Result<Vec<u8>> {
+    if !compressed {
+        return Ok(payload);
+    }
+    let Some((len, data)) = payload.split_first_chunk::<4>() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "compressed frame is missing its length"));
+    };
+    let len = u32::from_le_bytes(*len) as usize;
+    FrameSizeError::check_incoming(len, max_frame_size)?;
+    compression.decompress(data, len)
+}
@@ -32,6 +32,12 @@ pub enum SubprocessIpcBackendError {
     Shutdown,
 }
 
+impl From<crate::framing::FrameSizeError> for SubprocessIpcBackendError {
+    fn from(e: crate::framing::FrameSizeError) -> Self {
+        SubprocessIpcBackendError::Protocol(e.to_string())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Encode, Decode)]
 pub enum SubprocessIpcBackendIpcError {
     Protocol(String),
@@ -94,8 +100,23 @@ pub enum PythonExecutionError {
     RuntimeError { message: String },
     #[error("Failed to serialize Rust value to Python: {message}")]
     SerializationError { message: String },
-    #[error("Failed to deserialize Python value to Rust: {message}")]
-    DeserializationError { message: String },
+    #[error("Failed to deserialize Python value to Rust at {path}: {message} (found {python_type}: {repr})")]
+    DeserializationError {
+        message: String,
+        /// Path to the offending value, e.g. `reply.items[3].price`
+        path: String,
+        /// Python type name of the offending value
+        python_type: String,
+        /// Truncated `repr()` of the offending value
+        repr: String,
+    },
+    #[error("Python {what} does not match its schema: {}", join_violations(violations))]
+    SchemaError {
+        /// What was validated, e.g. `reply`
+        what: String,
+        /// Every mismatch found, in document order
+        violations: Vec<SchemaViolation>,
+    },
     #[error("Failed to call Python function '{function}': {message}")]
     CallError { function: String, message: String },
     #[error("Failed to convert between Python and Rust types: {message}")]
@@ -104,6 +125,25 @@ pub enum PythonExecutionError {
     ChildProcessTerminated,
 }
 
+/// One place where a Python value does not match its schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct SchemaViolation {
+    /// Path to the offending value, e.g. `reply.items[3].price`
+    pub path: String,
+    /// What is wrong there, e.g. `expected int, found str: '3'`
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+fn join_violations(violations: &[SchemaViolation]) -> String {
+    violations.iter().map(SchemaViolation::to_string).collect::<Vec<_>>().join("; ")
+}
+
 #[cfg(feature = "python")]
 impl PythonExecutionError {
     pub fn from_pyerr(err: PyErr, py: Python) -> Self {
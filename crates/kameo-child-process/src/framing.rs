@@ -1,16 +1,232 @@
-use std::io;
+use std::io::{self, IoSlice};
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-use bincode::{Decode, Encode};
+use tokio::sync::mpsc::UnboundedReceiver;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::marker::Unpin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use once_cell::sync::Lazy;
 use tracing::trace;
+use std::os::fd::OwnedFd;
+use crate::codec::{Codec, CodecKind};
+use crate::compression::{self, Compression};
+use crate::transport::{TransportRead, TransportWrite};
+
+/// Default maximum size of a single frame, in either direction (64 MiB).
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 << 20;
+
+/// Environment variable used to pass the maximum frame size to child processes.
+pub const MAX_FRAME_SIZE_ENV: &str = "KAMEO_MAX_FRAME_SIZE";
+
+static MAX_FRAME_SIZE: Lazy<AtomicUsize> = Lazy::new(|| {
+    let max = std::env::var(MAX_FRAME_SIZE_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_FRAME_SIZE);
+    AtomicUsize::new(clamp_frame_size(max))
+});
+
+/// Largest frame size that can be configured; the top bit of the length prefix is reserved
+/// for [`compression::COMPRESSED_FLAG`].
+pub const MAX_FRAME_SIZE_LIMIT: usize = (u32::MAX >> 1) as usize;
+
+fn clamp_frame_size(bytes: usize) -> usize {
+    bytes.clamp(1, MAX_FRAME_SIZE_LIMIT)
+}
+
+/// Current maximum frame size for new readers and writers.
+pub fn max_frame_size() -> usize {
+    MAX_FRAME_SIZE.load(Ordering::Relaxed)
+}
+
+/// Set the maximum frame size for readers and writers created after this call.
+///
+/// Values above [`MAX_FRAME_SIZE_LIMIT`] are clamped.
+pub fn set_max_frame_size(bytes: usize) {
+    MAX_FRAME_SIZE.store(clamp_frame_size(bytes), Ordering::Relaxed);
+}
+
+/// A frame exceeded the maximum frame size.
+///
+/// Travels inside an [`io::Error`] of kind `InvalidData`; use [`FrameSizeError::from_io`] to
+/// tell it apart from other failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum FrameSizeError {
+    /// The peer announced a frame larger than we accept. The stream can't be resynchronised.
+    #[error("incoming frame of {len} bytes exceeds the maximum frame size of {max} bytes")]
+    Incoming { len: usize, max: usize },
+    /// A message encoded larger than we may send. Nothing was written.
+    #[error("outgoing frame of {len} bytes exceeds the maximum frame size of {max} bytes")]
+    Outgoing { len: usize, max: usize },
+}
+
+impl FrameSizeError {
+    /// The frame size error carried by `e`, if any.
+    pub fn from_io(e: &io::Error) -> Option<FrameSizeError> {
+        e.get_ref().and_then(|inner| inner.downcast_ref::<FrameSizeError>()).copied()
+    }
+
+    /// Fail with [`FrameSizeError::Incoming`] if `len` is over `max`.
+    pub fn check_incoming(len: usize, max: usize) -> io::Result<()> {
+        if len > max {
+            return Err(FrameSizeError::Incoming { len, max }.into());
+        }
+        Ok(())
+    }
+
+    /// Fail with [`FrameSizeError::Outgoing`] if `len` is over `max`.
+    pub fn check_outgoing(len: usize, max: usize) -> io::Result<()> {
+        if len > max {
+            return Err(FrameSizeError::Outgoing { len, max }.into());
+        }
+        Ok(())
+    }
+}
+
+impl From<FrameSizeError> for io::Error {
+    fn from(e: FrameSizeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Limits for coalescing queued frames into one vectored write.
+///
+/// Writer tasks drain everything already queued (see [`recv_batch`]), buffer it with
+/// [`LengthPrefixedWrite::queue_msg`] and friends, and write it with one
+/// [`LengthPrefixedWrite::flush`], so a burst of small replies costs one syscall instead of two
+/// per frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteBatch {
+    /// Most frames written together; 1 disables coalescing.
+    pub max_frames: usize,
+    /// Flush once this many bytes are buffered.
+    pub max_bytes: usize,
+    /// How long to wait for more frames once the queue is empty. The default of zero writes as
+    /// soon as nothing else is queued, so batching never delays a lone frame.
+    pub max_latency: Duration,
+}
+
+/// Environment variable used to pass the write batch limits to child processes, formatted as
+/// `max_frames,max_bytes,max_latency_us`.
+pub const WRITE_BATCH_ENV: &str = "KAMEO_WRITE_BATCH";
+
+/// Upper bound on [`WriteBatch::max_frames`], keeping vectored writes within `IOV_MAX`.
+const MAX_BATCH_FRAMES: usize = 512;
+
+impl Default for WriteBatch {
+    fn default() -> Self {
+        Self { max_frames: 128, max_bytes: 256 * 1024, max_latency: Duration::ZERO }
+    }
+}
+
+impl WriteBatch {
+    /// Write every frame on its own.
+    pub fn disabled() -> Self {
+        Self { max_frames: 1, ..Self::default() }
+    }
+
+    fn clamped(self) -> Self {
+        Self { max_frames: self.max_frames.clamp(1, MAX_BATCH_FRAMES), ..self }
+    }
+}
+
+impl std::fmt::Display for WriteBatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{}", self.max_frames, self.max_bytes, self.max_latency.as_micros())
+    }
+}
+
+impl std::str::FromStr for WriteBatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let [frames, bytes, latency_us] = parts[..] else {
+            return Err(format!("expected max_frames,max_bytes,max_latency_us, got {s:?}"));
+        };
+        let num = |v: &str| v.parse::<u64>().map_err(|e| format!("invalid write batch value {v:?}: {e}"));
+        Ok(Self {
+            max_frames: num(frames)? as usize,
+            max_bytes: num(bytes)? as usize,
+            max_latency: Duration::from_micros(num(latency_us)?),
+        })
+    }
+}
+
+static WRITE_BATCH: Lazy<RwLock<WriteBatch>> = Lazy::new(|| {
+    let batch = std::env::var(WRITE_BATCH_ENV)
+        .ok()
+        .and_then(|v| v.parse::<WriteBatch>().ok())
+        .unwrap_or_default();
+    RwLock::new(batch.clamped())
+});
+
+/// Current write batch limits for new writers.
+pub fn write_batch() -> WriteBatch {
+    *WRITE_BATCH.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Set the write batch limits for writers created after this call.
+pub fn set_write_batch(batch: WriteBatch) {
+    *WRITE_BATCH.write().unwrap_or_else(|e| e.into_inner()) = batch.clamped();
+}
+
+/// Collect `first` and whatever else is queued on `rx`, up to `batch.max_frames`.
+///
+/// Once the queue is empty, waits up to `batch.max_latency` for more before returning.
+pub async fn recv_batch<T>(rx: &mut UnboundedReceiver<T>, first: T, batch: WriteBatch) -> Vec<T> {
+    let mut items = vec![first];
+    let deadline = tokio::time::Instant::now() + batch.max_latency;
+    while items.len() < batch.max_frames {
+        match rx.try_recv() {
+            Ok(item) => items.push(item),
+            Err(_) if batch.max_latency.is_zero() => break,
+            Err(_) => match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(item)) => items.push(item),
+                Ok(None) | Err(_) => break,
+            },
+        }
+    }
+    items
+}
+
+/// Write all of `slices`, as few syscalls as the writer allows.
+async fn write_all_vectored<W: AsyncWrite + Unpin>(inner: &mut W, mut slices: &mut [IoSlice<'_>]) -> io::Result<()> {
+    while !slices.is_empty() {
+        let n = inner.write_vectored(slices).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        IoSlice::advance_slices(&mut slices, n);
+    }
+    Ok(())
+}
 
 pub struct LengthPrefixedRead<R> {
     inner: R,
+    codec: CodecKind,
+    compression: Compression,
+    max_frame_size: usize,
 }
 
 impl<R> LengthPrefixedRead<R> {
     pub fn new(inner: R) -> Self {
-        Self { inner }
+        Self::with_codec(inner, CodecKind::default())
+    }
+    pub fn with_codec(inner: R, codec: CodecKind) -> Self {
+        Self { inner, codec, compression: Compression::None, max_frame_size: max_frame_size() }
+    }
+    /// Accept frames compressed with `compression`, as agreed in the handshake.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+    /// Override the maximum accepted frame size for this reader.
+    pub fn with_max_frame_size(mut self, max: usize) -> Self {
+        self.max_frame_size = clamp_frame_size(max);
+        self
     }
     pub fn into_inner(self) -> R {
         self.inner
@@ -18,40 +234,192 @@ impl<R> LengthPrefixedRead<R> {
 }
 
 impl<R: AsyncRead + Unpin> LengthPrefixedRead<R> {
-    pub async fn read_msg<T: Decode<()>>(&mut self) -> io::Result<T> {
+    pub async fn read_msg<T: DeserializeOwned>(&mut self) -> io::Result<T> {
         let mut len_buf = [0u8; 4];
         self.inner.read_exact(&mut len_buf).await?;
-        let len = u32::from_le_bytes(len_buf) as usize;
+        let msg_buf = self.read_body(len_buf).await?;
+        let msg = self.codec.decode(&msg_buf)?;
+        trace!(event = "framing_read", len = msg_buf.len(), "Read length-prefixed message");
+        Ok(msg)
+    }
+
+    /// Read the frame announced by `len_buf`, decompressing it if needed.
+    async fn read_body(&mut self, len_buf: [u8; 4]) -> io::Result<Vec<u8>> {
+        let (len, compressed) = compression::frame_len(u32::from_le_bytes(len_buf), self.compression);
+        FrameSizeError::check_incoming(len, self.max_frame_size)?;
         let mut msg_buf = vec![0u8; len];
         self.inner.read_exact(&mut msg_buf).await?;
-        let (msg, _): (T, _) = bincode::decode_from_slice(&msg_buf, bincode::config::standard())
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("bincode decode error: {e}")))?;
-        trace!(event = "framing_read", len, "Read length-prefixed message");
+        compression::unpack(msg_buf, compressed, self.compression, self.max_frame_size)
+    }
+}
+
+impl<R: TransportRead> LengthPrefixedRead<R> {
+    /// Read a request-connection frame along with any shared memory fds sent with it.
+    pub async fn read_msg_with_fds<T: DeserializeOwned>(&mut self) -> io::Result<T> {
+        let mut len_buf = [0u8; 4];
+        let fds = self.inner.read_exact_with_fds(&mut len_buf).await?;
+        let msg_buf = self.read_body(len_buf).await?;
+        let fd_count = fds.len();
+        let msg = crate::shm::decode_frame(&msg_buf, fds, self.codec)?;
+        trace!(event = "framing_read", len = msg_buf.len(), fd_count, "Read length-prefixed message");
         Ok(msg)
     }
 }
 
 pub struct LengthPrefixedWrite<W> {
     inner: W,
+    codec: CodecKind,
+    compression: Compression,
+    max_frame_size: usize,
+    batch: WriteBatch,
+    /// Length prefixes and payloads queued for the next flush
+    queued: Vec<([u8; 4], Vec<u8>)>,
+    queued_bytes: usize,
 }
 
 impl<W> LengthPrefixedWrite<W> {
     pub fn new(inner: W) -> Self {
-        Self { inner }
+        Self::with_codec(inner, CodecKind::default())
+    }
+    pub fn with_codec(inner: W, codec: CodecKind) -> Self {
+        Self {
+            inner,
+            codec,
+            compression: Compression::None,
+            max_frame_size: max_frame_size(),
+            batch: write_batch(),
+            queued: Vec::new(),
+            queued_bytes: 0,
+        }
+    }
+    /// Compress frames above [`compression::threshold`] with `compression`, as agreed in the handshake.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+    /// Override the maximum frame size this writer will send.
+    pub fn with_max_frame_size(mut self, max: usize) -> Self {
+        self.max_frame_size = clamp_frame_size(max);
+        self
+    }
+    /// Override the write batch limits for this writer.
+    pub fn with_write_batch(mut self, batch: WriteBatch) -> Self {
+        self.batch = batch.clamped();
+        self
+    }
+    /// Write batch limits in effect for this writer.
+    pub fn batch(&self) -> WriteBatch {
+        self.batch
+    }
+    /// Whether frames are queued waiting for [`flush`](Self::flush).
+    pub fn has_queued(&self) -> bool {
+        !self.queued.is_empty()
     }
     pub fn into_inner(self) -> W {
         self.inner
     }
+
+    /// Size-check, compress and queue one encoded frame; returns whether the batch is now full.
+    fn push_frame(&mut self, bytes: Vec<u8>) -> io::Result<bool> {
+        FrameSizeError::check_outgoing(bytes.len(), self.max_frame_size)?;
+        let (prefix, compressed) = match compression::pack(&bytes, self.compression)? {
+            (prefix, std::borrow::Cow::Owned(payload)) => (prefix, Some(payload)),
+            (prefix, std::borrow::Cow::Borrowed(_)) => (prefix, None),
+        };
+        let payload = compressed.unwrap_or(bytes);
+        self.queued_bytes += 4 + payload.len();
+        self.queued.push((prefix.to_le_bytes(), payload));
+        Ok(self.queued.len() >= self.batch.max_frames || self.queued_bytes >= self.batch.max_bytes)
+    }
 }
 
 impl<W: AsyncWrite + Unpin> LengthPrefixedWrite<W> {
-    pub async fn write_msg<T: Encode>(&mut self, msg: &T) -> io::Result<()> {
-        let bytes = bincode::encode_to_vec(msg, bincode::config::standard()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        let len = bytes.len() as u32;
-        self.inner.write_all(&len.to_le_bytes()).await?;
-        self.inner.write_all(&bytes).await?;
+    pub async fn write_msg<T: Serialize>(&mut self, msg: &T) -> io::Result<()> {
+        self.queue_msg(msg).await?;
+        self.flush().await
+    }
+
+    /// Queue a frame for the next [`flush`](Self::flush), flushing first if the batch is full.
+    ///
+    /// Fails with [`FrameSizeError::Outgoing`] without queueing anything if the frame is too large.
+    pub async fn queue_msg<T: Serialize>(&mut self, msg: &T) -> io::Result<()> {
+        let bytes = self.codec.encode(msg)?;
+        if self.push_frame(bytes)? {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Write every queued frame with vectored writes.
+    ///
+    /// On error the queue is dropped: some of it may have been written, so the connection
+    /// can't be trusted any more.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        if self.queued.is_empty() {
+            return Ok(());
+        }
+        let queued = std::mem::take(&mut self.queued);
+        let queued_bytes = std::mem::take(&mut self.queued_bytes);
+        let mut slices: Vec<IoSlice<'_>> = queued
+            .iter()
+            .flat_map(|(prefix, payload)| [IoSlice::new(prefix), IoSlice::new(payload)])
+            .collect();
+        write_all_vectored(&mut self.inner, &mut slices).await?;
+        drop(slices);
+        trace!(event = "framing_write", frames = queued.len(), len = queued_bytes, "Flushed queued frames");
+        // Keep the allocation for the next batch
+        self.queued = queued;
+        self.queued.clear();
+        Ok(())
+    }
+}
+
+impl<W: TransportWrite> LengthPrefixedWrite<W> {
+    /// Write a request-connection frame, sending large `ShmBytes` payloads as shared memory
+    /// fds when the transport supports it.
+    pub async fn write_msg_with_fds<T: Serialize>(&mut self, msg: &T) -> io::Result<()> {
+        let (bytes, fds) = crate::shm::encode_frame(msg, self.codec, self.inner.supports_fd_passing())?;
+        self.write_frame_with_fds(&bytes, &fds).await
+    }
+
+    /// Write pre-encoded frame bytes, attaching `fds` to the length prefix.
+    ///
+    /// Fails with [`FrameSizeError::Outgoing`] before writing anything if the frame is too large.
+    pub async fn write_frame_with_fds(&mut self, bytes: &[u8], fds: &[OwnedFd]) -> io::Result<()> {
+        if fds.is_empty() {
+            self.push_frame(bytes.to_vec())?;
+            return self.flush().await;
+        }
+        FrameSizeError::check_outgoing(bytes.len(), self.max_frame_size)?;
+        let (prefix, payload) = compression::pack(bytes, self.compression)?;
+        // Anything queued must go first, and without the fds
+        self.flush().await?;
+        self.inner.write_all_with_fds(&prefix.to_le_bytes(), fds).await?;
+        self.inner.write_all(&payload).await?;
+        trace!(event = "framing_write", len = bytes.len(), wire_len = payload.len(), fd_count = fds.len(), "Wrote length-prefixed message");
+        Ok(())
+    }
+
+    /// Queue a request-connection frame for the next [`flush`](Self::flush); see
+    /// [`write_msg_with_fds`](Self::write_msg_with_fds).
+    pub async fn queue_msg_with_fds<T: Serialize>(&mut self, msg: &T) -> io::Result<()> {
+        let (bytes, fds) = crate::shm::encode_frame(msg, self.codec, self.inner.supports_fd_passing())?;
+        self.queue_frame_with_fds(bytes, fds).await
+    }
+
+    /// Queue pre-encoded frame bytes for the next [`flush`](Self::flush).
+    ///
+    /// Frames carrying fds can't share a write with other frames, so they flush the queue and
+    /// are written straight away.
+    pub async fn queue_frame_with_fds(&mut self, bytes: Vec<u8>, fds: Vec<OwnedFd>) -> io::Result<()> {
+        if !fds.is_empty() {
+            return self.write_frame_with_fds(&bytes, &fds).await;
+        }
+        if self.push_frame(bytes)? {
+            self.flush().await?;
+        }
         Ok(())
     }
 }
 
-pub type LengthPrefixedStream<S> = (LengthPrefixedRead<S>, LengthPrefixedWrite<S>); 
\ No newline at end of file
+pub type LengthPrefixedStream<S> = (LengthPrefixedRead<S>, LengthPrefixedWrite<S>);
@@ -1,19 +1,286 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 use uuid::Uuid;
-use tokio::net::{UnixListener, UnixStream};
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
 
+/// Environment variable overriding the directory for socket files.
+pub const SOCKET_DIR_ENV: &str = "KAMEO_SOCKET_DIR";
+
+/// Longest socket path the kernel accepts (`sun_path` is 108 bytes including the NUL).
+const MAX_SOCKET_PATH_LEN: usize = 107;
+
+/// Longest actor label kept in socket names.
+const MAX_SOCKET_LABEL_LEN: usize = 40;
+
+/// Directory for socket files: `KAMEO_SOCKET_DIR`, else `$XDG_RUNTIME_DIR`, else the system temp dir.
+pub fn socket_dir() -> PathBuf {
+    socket_dir_from(std::env::var_os(SOCKET_DIR_ENV), std::env::var_os("XDG_RUNTIME_DIR"))
+}
+
+/// [`socket_dir`] given the values of `KAMEO_SOCKET_DIR` and `$XDG_RUNTIME_DIR`.
+///
+/// The first that names an existing directory wins.
+pub fn socket_dir_from(socket_dir: Option<OsString>, runtime_dir: Option<OsString>) -> PathBuf {
+    [socket_dir, runtime_dir]
+        .into_iter()
+        .flatten()
+        .map(PathBuf::from)
+        .find(|dir| dir.is_dir())
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// A short, filesystem-safe label for `actor_name`.
+///
+/// Module paths are dropped and anything other than ASCII letters, digits and `_` becomes
+/// `-`, so `my_crate::PythonActor<my_crate::Msg, my_crate::Cb>-req` becomes
+/// `PythonActor-Msg-Cb-req`. Long labels lose leading words first, keeping the most specific part.
+fn socket_label(actor_name: &str) -> String {
+    let mut label = String::with_capacity(actor_name.len());
+    let mut segment = String::new();
+    let mut chars = actor_name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            // Drop the module path segment just finished
+            chars.next();
+            segment.clear();
+            continue;
+        }
+        if c.is_ascii_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            label.push_str(&segment);
+            segment.clear();
+            if !label.is_empty() && !label.ends_with('-') {
+                label.push('-');
+            }
+        }
+    }
+    label.push_str(&segment);
+    let mut label = label.trim_end_matches('-');
+    // Drop whole leading words until it fits, then cut; the label is ASCII, so any byte
+    // offset is a char boundary
+    while label.len() > MAX_SOCKET_LABEL_LEN {
+        match label.split_once('-') {
+            Some((_, rest)) => label = rest,
+            None => label = &label[label.len() - MAX_SOCKET_LABEL_LEN..],
+        }
+    }
+    label.to_string()
+}
+
+fn unique_socket_name(actor_name: &str) -> String {
+    format!("kameo-{}-{}.sock", socket_label(actor_name), Uuid::new_v4().simple())
+}
+
+/// A fresh socket path for `actor_name` in [`socket_dir`].
 pub fn unique_socket_path(actor_name: &str) -> PathBuf {
-    let mut path = std::path::PathBuf::from("/tmp");
-    let short_name = &actor_name[0..std::cmp::min(8, actor_name.len())];
-    path.push(format!(
-        "kameo-{}-{}.sock",
-        short_name,
-        Uuid::new_v4().simple()
-    ));
-    path
+    unique_socket_path_in(&socket_dir(), actor_name)
+}
+
+/// A fresh socket path for `actor_name` in `dir`.
+pub fn unique_socket_path_in(dir: &Path, actor_name: &str) -> PathBuf {
+    dir.join(unique_socket_name(actor_name))
+}
+
+/// Where a parent listens for a child's connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketAddress {
+    /// A socket file on disk.
+    Path(PathBuf),
+    /// A Linux abstract-namespace socket, which leaves nothing on disk.
+    Abstract(String),
+}
+
+impl SocketAddress {
+    /// A fresh address for `actor_name`: abstract if `abstract_namespace`, else a file in `dir`.
+    pub fn unique(actor_name: &str, dir: &Path, abstract_namespace: bool) -> Self {
+        if abstract_namespace {
+            SocketAddress::Abstract(unique_socket_name(actor_name))
+        } else {
+            SocketAddress::Path(unique_socket_path_in(dir, actor_name))
+        }
+    }
+
+    /// The form passed to children in `KAMEO_REQUEST_SOCKET`/`KAMEO_CALLBACK_SOCKET`:
+    /// a path, or `@name` for an abstract socket.
+    pub fn to_env(&self) -> String {
+        match self {
+            SocketAddress::Path(path) => path.to_string_lossy().into_owned(),
+            SocketAddress::Abstract(name) => format!("@{name}"),
+        }
+    }
+
+    /// Parse the form produced by [`to_env`](Self::to_env).
+    pub fn from_env(value: &str) -> Self {
+        match value.strip_prefix('@') {
+            Some(name) => SocketAddress::Abstract(name.to_string()),
+            None => SocketAddress::Path(PathBuf::from(value)),
+        }
+    }
+
+    /// Connect to a parent listening at this address.
+    pub async fn connect(&self) -> std::io::Result<UnixStream> {
+        match self {
+            SocketAddress::Path(path) => UnixStream::connect(path).await,
+            SocketAddress::Abstract(name) => {
+                let addr = abstract_socket_addr(name)?;
+                // Connecting to a listening Unix socket doesn't block
+                let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+                stream.set_nonblocking(true)?;
+                UnixStream::from_std(stream)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SocketAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_env())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn abstract_socket_addr(name: &str) -> std::io::Result<std::os::unix::net::SocketAddr> {
+    use std::os::linux::net::SocketAddrExt;
+    std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn abstract_socket_addr(_name: &str) -> std::io::Result<std::os::unix::net::SocketAddr> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "abstract-namespace sockets are only available on Linux",
+    ))
+}
+
+/// Listens for a single child connection.
+///
+/// The socket file is readable and writable by the owner only, and is removed as soon as the
+/// child connects, or when the listener is dropped if it never does. Connections from other
+/// users, or from a process other than the expected child, are rejected and the listener keeps
+/// waiting.
+#[derive(Debug)]
+pub struct ChildListener {
+    listener: UnixListener,
+    address: SocketAddress,
+}
+
+impl ChildListener {
+    /// Bind `address`, refusing socket paths too long for the kernel.
+    pub fn bind(address: SocketAddress) -> std::io::Result<Self> {
+        let listener = match &address {
+            SocketAddress::Path(path) => {
+                if path.as_os_str().len() > MAX_SOCKET_PATH_LEN {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!(
+                            "socket path {} is longer than {MAX_SOCKET_PATH_LEN} bytes; use a shorter {SOCKET_DIR_ENV}",
+                            path.display()
+                        ),
+                    ));
+                }
+                bind_private(path)?
+            }
+            SocketAddress::Abstract(name) => {
+                let listener = std::os::unix::net::UnixListener::bind_addr(&abstract_socket_addr(name)?)?;
+                listener.set_nonblocking(true)?;
+                UnixListener::from_std(listener)?
+            }
+        };
+        debug!(status = "listening", address = %address);
+        Ok(Self { listener, address })
+    }
+
+    /// Where the child should connect.
+    pub fn address(&self) -> &SocketAddress {
+        &self.address
+    }
+
+    /// Accept a connection from a process running as this user and remove the socket file.
+    pub async fn accept(self) -> std::io::Result<UnixStream> {
+        self.accept_from(None).await
+    }
+
+    /// Accept a connection from `child_pid` (if given) running as this user, and remove the
+    /// socket file.
+    pub async fn accept_from(self, child_pid: Option<u32>) -> std::io::Result<UnixStream> {
+        loop {
+            let (stream, _addr) = self.listener.accept().await?;
+            match verify_peer(&stream, child_pid) {
+                Ok(()) => {
+                    debug!(status = "accepted", address = %self.address);
+                    return Ok(stream);
+                }
+                Err(e) => {
+                    warn!(address = %self.address, error = %e, "Rejected connection on child socket");
+                    crate::metrics::record_rejected_connection();
+                }
+            }
+        }
+    }
+}
+
+/// Bind a socket file at `path` that only this user can ever connect to.
+///
+/// `bind` creates the file with the umask's permissions and is already listening, so the
+/// socket is bound in a fresh owner-only directory, made private there, and only then linked
+/// into place. Linking fails with `AlreadyExists` if `path` is taken, as `bind` would.
+fn bind_private(path: &Path) -> std::io::Result<UnixListener> {
+    use std::os::unix::fs::DirBuilderExt;
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let staging = parent.join(format!(".kameo-{}", Uuid::new_v4().simple()));
+    std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("s");
+    let result = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::hard_link(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    if let Err(e) = std::fs::remove_dir(&staging) {
+        warn!(path = %staging.display(), error = %e, "Failed to remove socket staging directory");
+    }
+    result
+}
+
+/// Check that the peer of `stream` runs as this user and, if given, is process `pid`.
+pub fn verify_peer(stream: &UnixStream, pid: Option<u32>) -> std::io::Result<()> {
+    let cred = stream.peer_cred()?;
+    let uid = nix::unistd::geteuid().as_raw();
+    if cred.uid() != uid {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("peer uid {} (pid {:?}) is not {uid}", cred.uid(), cred.pid()),
+        ));
+    }
+    if let Some(pid) = pid {
+        if cred.pid() != Some(pid as i32) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("peer pid {:?} is not the spawned child {pid}", cred.pid()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl Drop for ChildListener {
+    fn drop(&mut self) {
+        if let SocketAddress::Path(path) = &self.address {
+            if let Err(e) = std::fs::remove_file(path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!(path = %path.display(), error = %e, "Failed to remove socket file");
+                }
+            }
+        }
+    }
 }
 
 #[instrument(skip(exe), fields(actor_name), parent = tracing::Span::current())]
@@ -27,39 +294,352 @@ where
 {
     let socket_path = unique_socket_path(actor_name);
     let socket_path_str = socket_path.to_string_lossy().into_owned();
+    let listener = ChildListener::bind(SocketAddress::Path(socket_path.clone()))?;
 
     debug!(status = "starting", socket_path = %socket_path_str, actor_type = actor_name);
 
     let mut cmd = Command::new(exe);
-    cmd.env("KAMEO_CHILD_ACTOR", actor_name);
+    cmd.env(CHILD_ACTOR_ENV, actor_name);
     cmd.env("KAMEO_ACTOR_SOCKET", socket_path_str.clone());
     if let Ok(rust_log) = std::env::var("RUST_LOG") {
         cmd.env("RUST_LOG", rust_log);
     }
 
-    let listener = UnixListener::bind(&socket_path)?;
     debug!(status = "spawning", actor_type = actor_name);
     let child = cmd.spawn()?;
     debug!(status = "waiting", actor_type = actor_name);
-    let (stream, _addr) = listener.accept().await?;
+    let stream = listener.accept_from(child.id()).await?;
     debug!(status = "completed", actor_type = actor_name);
     Ok((Box::new(stream), child, socket_path))
 }
 
+/// Environment variable carrying the fd number of an inherited request socket.
+pub const REQUEST_FD_ENV: &str = "KAMEO_REQUEST_FD";
+
+/// Environment variable carrying the fd number of an inherited callback socket.
+pub const CALLBACK_FD_ENV: &str = "KAMEO_CALLBACK_FD";
+
+/// Fds already adopted by [`take_inherited_socket`], so none is owned twice.
+static CLAIMED_FDS: std::sync::Mutex<Vec<RawFd>> = std::sync::Mutex::new(Vec::new());
+
+/// One end of a socketpair whose other end is handed to a child at spawn.
+///
+/// Unlike [`ChildListener`], nothing is bound: no other process can connect first, and the
+/// parent doesn't wait for the child to connect back.
+#[derive(Debug)]
+pub struct InheritedSocket {
+    stream: UnixStream,
+    child_end: OwnedFd,
+}
+
+impl InheritedSocket {
+    /// Create a socketpair and arrange for `cmd` to inherit one end, with its fd number in `env`.
+    ///
+    /// The child's end stays close-on-exec in this process, so other children spawned
+    /// concurrently don't inherit it; only `cmd`'s child clears the flag, between fork and exec.
+    pub fn pass_to(cmd: &mut Command, env: &str) -> std::io::Result<Self> {
+        let (parent_end, child_end) = std::os::unix::net::UnixStream::pair()?;
+        parent_end.set_nonblocking(true)?;
+        let child_end = OwnedFd::from(child_end);
+        let fd = child_end.as_raw_fd();
+        cmd.env(env, fd.to_string());
+        keep_open_across_exec(cmd, fd);
+        Ok(Self { stream: UnixStream::from_std(parent_end)?, child_end })
+    }
+
+    /// The parent's end, once the child has been spawned.
+    ///
+    /// Closes this process's copy of the child's end, so the stream sees EOF when the child exits.
+    pub fn into_stream(self) -> UnixStream {
+        drop(self.child_end);
+        self.stream
+    }
+}
+
+#[allow(unsafe_code)]
+fn keep_open_across_exec(cmd: &mut Command, fd: RawFd) {
+    use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+    // SAFETY: the closure runs in the forked child before exec and only calls fcntl, which is
+    // async-signal-safe; it allocates nothing and touches no locks.
+    unsafe {
+        cmd.pre_exec(move || {
+            fcntl(fd, FcntlArg::F_SETFD(FdFlag::empty()))?;
+            Ok(())
+        });
+    }
+}
+
+/// Adopt the socket a parent passed through [`InheritedSocket`], if `env` names one.
+///
+/// Returns `Ok(None)` when `env` isn't set. The fd must be an open socket, and each fd is
+/// adopted at most once per process. It is made close-on-exec again, so it doesn't leak into
+/// processes this child spawns, and `env` is removed so nothing else reads it.
+///
+/// # Safety
+///
+/// The fd named by `env` must be one the parent left open for this process, and nothing else
+/// in this process may own or use it: the returned stream closes it when dropped.
+#[allow(unsafe_code)]
+pub unsafe fn take_inherited_socket(env: &str) -> std::io::Result<Option<UnixStream>> {
+    use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+    use nix::sys::stat::{fstat, SFlag};
+    let Ok(value) = std::env::var(env) else {
+        return Ok(None);
+    };
+    let fd: RawFd = value.parse().ok().filter(|fd| *fd > 2).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{env}={value:?} is not a socket fd"))
+    })?;
+    let mut claimed = CLAIMED_FDS.lock().unwrap_or_else(|e| e.into_inner());
+    if claimed.contains(&fd) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("inherited fd {fd} from {env} was already adopted"),
+        ));
+    }
+    let stat = fstat(fd)?;
+    if SFlag::from_bits_truncate(stat.st_mode) & SFlag::S_IFMT != SFlag::S_IFSOCK {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("inherited fd {fd} from {env} is not a socket"),
+        ));
+    }
+    fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+    claimed.push(fd);
+    std::env::remove_var(env);
+    // SAFETY: the caller guarantees the fd is ours alone to own
+    let stream = std::os::unix::net::UnixStream::from(unsafe { adopt_inherited_fd(fd) });
+    stream.set_nonblocking(true)?;
+    debug!(status = "adopted", fd, env);
+    Ok(Some(UnixStream::from_std(stream)?))
+}
+
+/// Take ownership of an fd a parent left open across exec.
+///
+/// # Safety
+///
+/// `fd` must be open and owned by nothing else in this process.
+#[allow(unsafe_code)]
+unsafe fn adopt_inherited_fd(fd: RawFd) -> OwnedFd {
+    use std::os::fd::FromRawFd;
+    // SAFETY: upheld by the caller; `CLAIMED_FDS` makes sure we adopt it only once
+    unsafe { OwnedFd::from_raw_fd(fd) }
+}
+
 #[instrument(fields(pid= std::process::id(), actor_name = ?std::env::var("KAMEO_CHILD_ACTOR").ok()), parent = tracing::Span::current())]
 pub async fn child_request() -> std::io::Result<Box<UnixStream>> {
+    // SAFETY: only the parent sets REQUEST_FD_ENV, naming the end of the socketpair it passed us
+    #[allow(unsafe_code)]
+    let inherited = unsafe { take_inherited_socket(REQUEST_FD_ENV) }?;
+    if let Some(stream) = inherited {
+        return Ok(Box::new(stream));
+    }
     let socket_path = std::env::var("KAMEO_REQUEST_SOCKET").map_err(|_| {
         std::io::Error::new(std::io::ErrorKind::NotFound, "KAMEO_REQUEST_SOCKET not set")
     })?;
-    let stream = UnixStream::connect(&socket_path).await?;
+    let stream = SocketAddress::from_env(&socket_path).connect().await?;
     Ok(Box::new(stream))
 }
 
 #[instrument(fields(actor_name = ?std::env::var("KAMEO_CHILD_ACTOR").ok()), parent = tracing::Span::current())]
 pub async fn child_callback() -> std::io::Result<Box<UnixStream>> {
+    // SAFETY: only the parent sets CALLBACK_FD_ENV, naming the end of the socketpair it passed us
+    #[allow(unsafe_code)]
+    let inherited = unsafe { take_inherited_socket(CALLBACK_FD_ENV) }?;
+    if let Some(stream) = inherited {
+        return Ok(Box::new(stream));
+    }
     let socket_path = std::env::var("KAMEO_CALLBACK_SOCKET").map_err(|_| {
         std::io::Error::new(std::io::ErrorKind::NotFound, "KAMEO_CALLBACK_SOCKET not set")
     })?;
-    let stream = UnixStream::connect(&socket_path).await?;
+    let stream = SocketAddress::from_env(&socket_path).connect().await?;
     Ok(Box::new(stream))
 }
+
+/// Environment variable naming the actor type a spawned child binary should run.
+pub const CHILD_ACTOR_ENV: &str = "KAMEO_CHILD_ACTOR";
+
+/// Environment variable that puts a child binary into remote worker mode, listening on this TCP address.
+pub const WORKER_LISTEN_ENV: &str = "KAMEO_WORKER_LISTEN";
+
+/// How long a remote worker waits for a new connection to say which channel it is and present its token.
+const REMOTE_CHANNEL_TAG_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Which of the two parent connections a remote worker is being offered.
+///
+/// A parent opens the request connection first, completes the handshake on it, then opens
+/// the callback connection. Each connection starts with a tag byte, then the worker's shared
+/// secret as a length byte and that many bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteChannel {
+    Request,
+    Callback,
+}
+
+impl RemoteChannel {
+    fn tag(self) -> u8 {
+        match self {
+            RemoteChannel::Request => 1,
+            RemoteChannel::Callback => 2,
+        }
+    }
+}
+
+/// Open a connection to a remote worker and announce it as `channel`, presenting `token`.
+#[instrument(skip(token), parent = tracing::Span::current())]
+pub async fn connect_remote_channel(addr: &str, channel: RemoteChannel, token: &str) -> std::io::Result<TcpStream> {
+    let token_len = u8::try_from(token.len())
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "remote worker token is longer than 255 bytes"))?;
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.set_nodelay(true)?;
+    let mut announcement = vec![channel.tag(), token_len];
+    announcement.extend_from_slice(token.as_bytes());
+    stream.write_all(&announcement).await?;
+    debug!(status = "connected", ?channel, addr);
+    Ok(stream)
+}
+
+/// Most request connections a remote worker keeps waiting while it serves another parent.
+const MAX_PENDING_REMOTE_REQUESTS: usize = 64;
+
+/// Most connections a remote worker waits on to announce their channel at once.
+const MAX_ANNOUNCING_REMOTE_CONNECTIONS: usize = 256;
+
+/// How long a remote worker backs off after a failed `accept`, e.g. when out of fds.
+const REMOTE_ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// A connection that has said which channel it is.
+type Announced = (TcpStream, std::net::SocketAddr, RemoteChannel);
+
+/// Listens for parents connecting to a remote worker, sorting connections by the channel they
+/// announce. Connections that don't present the worker's token are dropped.
+///
+/// Each connection announces itself on a task of its own, so a peer that never says anything
+/// holds up no one else. A request connection that arrives while the worker waits for the
+/// current parent's callback connection is kept for the next [`RemoteListener::accept`] of a
+/// request, so a second parent waits its turn. Callback connections only pair with the request
+/// accepted just before them; any that arrive otherwise are dropped.
+#[derive(Debug)]
+pub struct RemoteListener {
+    local_addr: std::net::SocketAddr,
+    announced: tokio::sync::mpsc::Receiver<Announced>,
+    pending_requests: std::collections::VecDeque<(TcpStream, std::net::SocketAddr)>,
+    acceptor: tokio::task::JoinHandle<()>,
+}
+
+impl RemoteListener {
+    /// Listen on `addr` for parents presenting `token`, which must not be empty.
+    pub async fn bind(addr: &str, token: &str) -> std::io::Result<Self> {
+        if token.is_empty() || token.len() > usize::from(u8::MAX) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "remote worker token must be 1 to 255 bytes",
+            ));
+        }
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (announced_tx, announced) = tokio::sync::mpsc::channel(MAX_PENDING_REMOTE_REQUESTS);
+        let acceptor = tokio::spawn(accept_remote_connections(listener, token.into(), announced_tx));
+        Ok(Self { local_addr, announced, pending_requests: Default::default(), acceptor })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        Ok(self.local_addr)
+    }
+
+    /// Accept the next connection announced as `channel`.
+    ///
+    /// Failed accepts and connections that don't announce a channel with the right token are
+    /// logged and skipped.
+    #[instrument(skip(self), parent = tracing::Span::current())]
+    pub async fn accept(&mut self, channel: RemoteChannel) -> std::io::Result<TcpStream> {
+        if channel == RemoteChannel::Request {
+            if let Some((stream, peer)) = self.pending_requests.pop_front() {
+                debug!(status = "accepted", ?channel, %peer, "Took request connection that was waiting");
+                return Ok(stream);
+            }
+        }
+        loop {
+            let (stream, peer, announced) = self
+                .announced
+                .recv()
+                .await
+                .ok_or_else(|| std::io::Error::other("remote listener stopped accepting connections"))?;
+            if announced == channel {
+                stream.set_nodelay(true)?;
+                debug!(status = "accepted", ?channel, %peer);
+                return Ok(stream);
+            }
+            match announced {
+                RemoteChannel::Request if self.pending_requests.len() < MAX_PENDING_REMOTE_REQUESTS => {
+                    debug!(%peer, "Keeping request connection from another parent until this session ends");
+                    self.pending_requests.push_back((stream, peer));
+                }
+                RemoteChannel::Request => warn!(%peer, "Dropping request connection, too many parents waiting"),
+                RemoteChannel::Callback => warn!(%peer, "Dropping callback connection with no request to pair with"),
+            }
+        }
+    }
+}
+
+impl Drop for RemoteListener {
+    fn drop(&mut self) {
+        self.acceptor.abort();
+    }
+}
+
+/// Accept connections for a [`RemoteListener`], reading each one's announcement on its own task.
+async fn accept_remote_connections(
+    listener: TcpListener,
+    token: std::sync::Arc<str>,
+    announced: tokio::sync::mpsc::Sender<Announced>,
+) {
+    let announcing = std::sync::Arc::new(tokio::sync::Semaphore::new(MAX_ANNOUNCING_REMOTE_CONNECTIONS));
+    loop {
+        // With too many peers mid-announcement, later ones wait in the listen backlog
+        let Ok(permit) = announcing.clone().acquire_owned().await else { return };
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!(error = %e, "Failed to accept remote connection, retrying");
+                tokio::time::sleep(REMOTE_ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let announced = announced.clone();
+        let token = token.clone();
+        tokio::spawn(async move {
+            let channel = read_announcement(&mut stream, peer, &token).await;
+            drop(permit);
+            if let Some(channel) = channel {
+                // Fails only once the listener is gone, dropping the connection with it
+                let _ = announced.send((stream, peer, channel)).await;
+            }
+        });
+    }
+}
+
+/// The channel a new connection announces, or `None` if it doesn't announce one with `token` in time.
+async fn read_announcement(stream: &mut TcpStream, peer: std::net::SocketAddr, token: &str) -> Option<RemoteChannel> {
+    let read = async {
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).await?;
+        let mut presented = vec![0u8; usize::from(header[1])];
+        stream.read_exact(&mut presented).await?;
+        Ok::<_, std::io::Error>((header[0], presented))
+    };
+    match tokio::time::timeout(REMOTE_CHANNEL_TAG_TIMEOUT, read).await {
+        Ok(Ok((tag, presented))) => {
+            if !std::str::from_utf8(&presented).is_ok_and(|presented| crate::tokens_match(token, presented)) {
+                warn!(%peer, "Dropping connection that presented a missing or invalid token");
+                return None;
+            }
+            match [RemoteChannel::Request, RemoteChannel::Callback].into_iter().find(|c| c.tag() == tag) {
+                Some(channel) => return Some(channel),
+                None => warn!(%peer, tag, "Dropping connection announced as an unknown channel"),
+            }
+        }
+        Ok(Err(e)) => warn!(%peer, error = %e, "Dropping connection that failed to announce its channel"),
+        Err(_) => warn!(%peer, "Dropping connection that did not announce its channel in time"),
+    }
+    None
+}
@@ -1,4 +1,4 @@
-#![forbid(unsafe_code)]
+#![deny(unsafe_code)]
 
 //! # Kameo Child Process IPC Library
 //! 
@@ -15,6 +15,7 @@
 //! - **Control Messages**: Handshake, Sync (single response), Stream (streaming response), StreamEnd
 //! - **Multiplexed Envelopes**: All messages are wrapped with correlation IDs and tracing context
 //! - **Bidirectional Communication**: Request/response and callback channels
+//! - **Wire Codecs**: bincode, MessagePack or JSON, agreed during the handshake (see [`codec`])
 //! 
 //! ### Streaming Support
 //! The library now uses a unified streaming protocol where all responses are treated as streams:
@@ -38,8 +39,8 @@
 //! ```rust
 //! use kameo_child_process::prelude::*;
 //! 
-//! // Define your message types
-//! #[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone)]
+//! // Define your message types; serde derives are all the codecs need
+//! #[derive(Serialize, Deserialize, Debug, Clone)]
 //! struct MyMessage { data: String }
 //! 
 //! impl KameoChildProcessMessage for MyMessage {
@@ -71,10 +72,16 @@ static GLOBAL_PROPAGATOR: Lazy<Arc<dyn TextMapPropagator + Send + Sync>> = Lazy:
 });
 
 pub mod callback;
+pub mod codec;
+pub use codec::{Codec, CodecKind};
+pub mod compression;
+pub use compression::Compression;
 pub mod handshake;
 pub use handshake::*;
 pub mod metrics;
 pub mod tracing_utils;
+pub mod transport;
+pub use transport::{Transport, TransportRead, TransportWrite};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -86,7 +93,7 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use std::io;
 use std::marker::PhantomData;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
 use tracing::Level;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_futures::Instrument;
@@ -96,6 +103,7 @@ use dashmap::DashMap;
 use tokio::sync::mpsc;
 pub mod error;
 pub use error::PythonExecutionError;
+use error::SubprocessIpcBackendError;
 
 
 
@@ -190,9 +198,9 @@ impl dyn AsyncReadWrite {
 
 /// Trait for messages that can be sent to a Kameo child process actor.
 pub trait KameoChildProcessMessage:
-    Send + Serialize + DeserializeOwned + Encode + Decode<()> + std::fmt::Debug + Clone + 'static
+    Send + Serialize + DeserializeOwned + std::fmt::Debug + Clone + 'static
 {
-    type Ok: Send + Serialize + DeserializeOwned + Encode + Decode<()> + std::fmt::Debug + Clone + 'static;
+    type Ok: Send + Serialize + DeserializeOwned + std::fmt::Debug + Clone + 'static;
 }
 
 /// Control message for the unified IPC protocol.
@@ -411,6 +419,27 @@ impl<R> Default for InFlightMap<R> {
     }
 }
 
+/// Why a connection was torn down.
+///
+/// Set once by whichever task notices first; pending requests are drained with it and
+/// requests made afterwards fail with it straight away.
+#[derive(Clone, Default)]
+pub(crate) struct CloseReason(Arc<std::sync::OnceLock<String>>);
+
+impl CloseReason {
+    /// Record `reason` unless one is already set, then cancel `token` to stop the connection's tasks.
+    pub(crate) fn close(&self, token: &tokio_util::sync::CancellationToken, reason: impl Into<String>) {
+        let _ = self.0.set(reason.into());
+        token.cancel();
+    }
+
+    /// The error to fail requests with once the connection is down.
+    pub(crate) fn error(&self) -> PythonExecutionError {
+        let reason = self.0.get().map(String::as_str).unwrap_or("IPC connection closed");
+        PythonExecutionError::ExecutionError { message: reason.to_string() }
+    }
+}
+
 
 
 /// Encapsulates a full-duplex UnixStream for protocol artefacts, enforcing correct split/unsplit usage.
@@ -493,6 +522,8 @@ where
     cancellation_token: tokio_util::sync::CancellationToken,
     /// Track pending requests for adaptive throttling
     pending_count: AtomicUsize,
+    /// Set when the connection is torn down
+    close_reason: CloseReason,
     /// Phantom data for message type
     _phantom: std::marker::PhantomData<M>,
 }
@@ -507,10 +538,43 @@ where
         Self::new(read_half, write_half)
     }
 
-    pub fn new(
-        read_half: tokio::net::unix::OwnedReadHalf,
-        write_half: tokio::net::unix::OwnedWriteHalf,
-    ) -> Arc<Self> {
+    /// Wire up the backend over any [`Transport`], splitting it internally.
+    pub fn from_transport<T: Transport>(transport: T) -> Arc<Self> {
+        Self::from_transport_with_codec(transport, CodecKind::default())
+    }
+
+    /// Like [`from_transport`](Self::from_transport), using the codec agreed in the handshake.
+    pub fn from_transport_with_codec<T: Transport>(transport: T, codec: CodecKind) -> Arc<Self> {
+        Self::from_transport_with_format(transport, WireFormat { codec, ..Default::default() })
+    }
+
+    /// Like [`from_transport`](Self::from_transport), using the frame format agreed in the handshake.
+    pub fn from_transport_with_format<T: Transport>(transport: T, format: WireFormat) -> Arc<Self> {
+        let (read_half, write_half) = transport.into_split();
+        Self::new_with_format(read_half, write_half, format)
+    }
+
+    pub fn new<R, W>(read_half: R, write_half: W) -> Arc<Self>
+    where
+        R: TransportRead,
+        W: TransportWrite,
+    {
+        Self::new_with_codec(read_half, write_half, CodecKind::default())
+    }
+
+    pub fn new_with_codec<R, W>(read_half: R, write_half: W, codec: CodecKind) -> Arc<Self>
+    where
+        R: TransportRead,
+        W: TransportWrite,
+    {
+        Self::new_with_format(read_half, write_half, WireFormat { codec, ..Default::default() })
+    }
+
+    pub fn new_with_format<R, W>(read_half: R, write_half: W, format: WireFormat) -> Arc<Self>
+    where
+        R: TransportRead,
+        W: TransportWrite,
+    {
         use tokio::sync::mpsc::unbounded_channel;
         use crate::error::PythonExecutionError;
         use std::sync::Arc;
@@ -528,14 +592,20 @@ where
             next_id: AtomicU64::new(1),
             cancellation_token,
             pending_count: AtomicUsize::new(0),
+            close_reason: CloseReason::default(),
             _phantom: PhantomData,
         });
         
         let result_clone = result.clone();
+        let in_flight_writer = result.in_flight.clone();
+        let backend_writer = Arc::downgrade(&result);
+        let close_reason_writer = result.close_reason.clone();
+        let close_reason_reader = result.close_reason.clone();
         
-        // Writer task with simpler direct writes
+        // Writer task, coalescing queued requests into batched writes
         tokio::spawn(async move {
-            let mut writer = crate::framing::LengthPrefixedWrite::new(write_half);
+            let mut writer = crate::framing::LengthPrefixedWrite::with_codec(write_half, format.codec)
+                .with_compression(format.compression);
             
             loop {
                 tokio::select! {
@@ -546,11 +616,51 @@ where
                     
                     message = write_rx.recv() => {
                         match message {
-                            Some(write_req) => {
-                                // Process write directly, one at a time
-                                if let Err(e) = writer.write_msg(&write_req.control).await {
-                                    tracing::error!(event = "writer_task", error = ?e, "Failed to write message");
-                                    // Don't break on errors - just log them and continue
+                            Some(first) => {
+                                // Coalesce everything already queued into one write
+                                let batch = crate::framing::recv_batch(&mut write_rx, first, writer.batch()).await;
+                                let fail_request = |correlation_id: CorrelationId, message: String| {
+                                    if let Some((_, mut slot)) = in_flight_writer.0.remove(&correlation_id) {
+                                        slot.try_send_stream_error(PythonExecutionError::ExecutionError { message });
+                                        if let Some(backend) = backend_writer.upgrade() {
+                                            backend.release_pending();
+                                        }
+                                    }
+                                };
+                                let mut written = Vec::with_capacity(batch.len());
+                                let mut result = Ok(());
+                                for write_req in batch {
+                                    let correlation_id = write_req.correlation_id;
+                                    match writer.queue_msg_with_fds(&write_req.control).await {
+                                        Ok(()) => written.push(correlation_id),
+                                        Err(e) => match FrameSizeError::from_io(&e) {
+                                            Some(size_err) => {
+                                                // Nothing was queued, so only this request fails
+                                                tracing::warn!(event = "writer_task", correlation_id, error = %size_err, "Request too large to send");
+                                                metrics::MetricsHandle::parent().track_error("frame_too_large");
+                                                fail_request(correlation_id, size_err.to_string());
+                                            }
+                                            None => {
+                                                written.push(correlation_id);
+                                                result = Err(e);
+                                                break;
+                                            }
+                                        },
+                                    }
+                                }
+                                if result.is_ok() {
+                                    result = writer.flush().await;
+                                }
+                                if let Err(e) = result {
+                                    // Part of a frame may have been written, so the connection can't be reused
+                                    let reason = format!("IPC connection closed: failed to write request: {e}");
+                                    tracing::error!(event = "writer_task", batch_len = written.len(), error = ?e, "Failed to write messages, closing connection");
+                                    metrics::MetricsHandle::parent().track_error("write_error");
+                                    for correlation_id in written {
+                                        fail_request(correlation_id, reason.clone());
+                                    }
+                                    close_reason_writer.close(&cancellation_token_writer, reason);
+                                    break;
                                 }
                             },
                             None => {
@@ -567,7 +677,9 @@ where
         
         // Reader task with improved error handling
         tokio::spawn(async move {
-            let mut reader = crate::framing::LengthPrefixedRead::new(read_half);
+            let mut reader = crate::framing::LengthPrefixedRead::with_codec(read_half, format.codec)
+                .with_compression(format.compression);
+            let mut exit_reason = "IPC backend shut down".to_string();
             
             // Initialize metrics
             metrics::init_metrics();
@@ -594,7 +706,7 @@ where
                         tracing::info!(event = "reader_task", "Reader task received shutdown signal, exiting");
                         break;
                     }
-                    result = reader.read_msg::<Control<Result<M::Ok, PythonExecutionError>>>() => {
+                    result = reader.read_msg_with_fds::<Control<Result<M::Ok, PythonExecutionError>>>() => {
                         match result {
                             Ok(ctrl) => {
                                 match ctrl {
@@ -665,6 +777,7 @@ where
                             Err(e) => {
                                 use std::io::ErrorKind;
                                 if e.kind() == ErrorKind::UnexpectedEof {
+                                    exit_reason = "IPC connection closed by child".to_string();
                                     let in_flight_len = in_flight_reader.0.len();
                                     if in_flight_len == 0 {
                                         tracing::info!(event = "reader_task", "EOF received, no in-flight requests, clean shutdown");
@@ -676,7 +789,16 @@ where
                                     }
                                     break;
                                 }
+                                if let Some(size_err) = FrameSizeError::from_io(&e) {
+                                    // The rest of the stream can't be trusted: tear down the connection
+                                    let protocol_err = SubprocessIpcBackendError::from(size_err);
+                                    error!(event = "parent_read_error", error = %protocol_err, "Oversized frame from child, closing connection");
+                                    metrics::MetricsHandle::parent().track_error("frame_too_large");
+                                    exit_reason = protocol_err.to_string();
+                                    break;
+                                }
                                 error!(event = "parent_read_error", error = ?e, "Parent reader task error, exiting");
+                                exit_reason = format!("IPC connection closed: failed to read reply: {e}");
                                 
                                 // Track error in metrics
                                 metrics::MetricsHandle::parent().track_error("read_error");
@@ -686,12 +808,13 @@ where
                     }
                 }
             }
+            // Whatever ended the reader, the connection is done: stop the writer and reject new requests
+            close_reason_reader.close(&cancellation_token_reader, exit_reason);
             // On exit, drain in_flight and send error to all pending
             in_flight_reader.0.iter_mut().for_each(|mut item| {
                 let (_corr_id, slot) = item.pair_mut();
                 if let Some(sender) = slot.stream_sender.take() {
-                    let err = PythonExecutionError::ExecutionError { message: "IPC backend reply loop exited".to_string() };
-                    if sender.send(Err(err)).is_err() {
+                    if sender.send(Err(close_reason_reader.error())).is_err() {
                         tracing::error!(event = "reader_task", error = "Failed to send shutdown error to waiting task", "Failed to notify waiting task about shutdown");
                     }
                 }
@@ -721,6 +844,33 @@ where
         self.pending_count.load(std::sync::atomic::Ordering::SeqCst)
     }
 
+    /// Decrement the pending count for a request removed from the in-flight map.
+    ///
+    /// Saturates, since the reader resets the count to zero when the connection closes.
+    fn release_pending(&self) {
+        let _ = self.pending_count.fetch_update(
+            std::sync::atomic::Ordering::SeqCst,
+            std::sync::atomic::Ordering::SeqCst,
+            |n| n.checked_sub(1),
+        );
+    }
+
+    /// Register `slot` for `correlation_id`, failing if the connection has been torn down.
+    ///
+    /// Checking after inserting closes the race with the reader draining the map on exit:
+    /// either the drain sees the slot, or we see the cancellation.
+    fn register(&self, correlation_id: CorrelationId, slot: ReplySlot<Result<M::Ok, PythonExecutionError>>) -> Result<(), PythonExecutionError> {
+        self.in_flight.0.insert(correlation_id, slot);
+        self.pending_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if self.cancellation_token.is_cancelled() {
+            if self.in_flight.0.remove(&correlation_id).is_some() {
+                self.release_pending();
+            }
+            return Err(self.close_reason.error());
+        }
+        Ok(())
+    }
+
     pub async fn send(&self, msg: M) -> Result<M::Ok, PythonExecutionError> {
         let correlation_id = self.next_correlation_id();
         let msg_type = std::any::type_name::<M>();
@@ -731,14 +881,13 @@ where
         // Create the ipc-parent-send span as a child of the ipc-message span
         let send_span = tracing_utils::create_ipc_parent_send_span(correlation_id, msg_type, &ipc_message_span);
         
-        // Create a reply slot (now always streaming)
-        let slot = ReplySlot::new();
+        // Create a reply slot (now always streaming), keeping the receiver before the
+        // request can be answered or failed and its slot removed
+        let mut slot = ReplySlot::new();
+        let mut receiver = slot.take_stream_receiver().expect("Stream receiver should be available");
         
         // Insert into in_flight map and track pending count
-        {
-            self.in_flight.0.insert(correlation_id, slot);
-            self.pending_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        }
+        self.register(correlation_id, slot)?;
         
         // Create the envelope with the ipc-parent-send span context
         let envelope = {
@@ -755,20 +904,14 @@ where
             correlation_id, 
             control: Control::Sync(envelope) 
         };
-        if let Err(e) = self.write_tx.send(write_req) {
-            self.in_flight.0.remove(&correlation_id);
-            self.pending_count.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
-            return Err(PythonExecutionError::ExecutionError { 
-                message: format!("Failed to send write request: {e}") 
-            });
+        if self.write_tx.send(write_req).is_err() {
+            // The writer task has exited, so the connection is closed
+            if self.in_flight.0.remove(&correlation_id).is_some() {
+                self.release_pending();
+            }
+            return Err(self.close_reason.error());
         }
         
-        // Wait for the response by getting the receiver from the in_flight map
-        let mut receiver = {
-            let mut slot = self.in_flight.0.get_mut(&correlation_id).expect("Slot should exist");
-            slot.take_stream_receiver().expect("Stream receiver should be available")
-        };
-        
         // Wait for the first (and only) item from the stream
         match receiver.recv().await {
             Some(Ok(Ok(result))) => Ok(result),
@@ -797,10 +940,7 @@ where
         let stream_receiver = slot.take_stream_receiver().expect("Stream receiver should be available");
         
         // Insert into in_flight map and track pending count
-        {
-            self.in_flight.0.insert(correlation_id, slot);
-            self.pending_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        }
+        self.register(correlation_id, slot)?;
         
         // Create the envelope with the ipc-parent-send span context
         let envelope = {
@@ -817,12 +957,12 @@ where
             correlation_id, 
             control: Control::Stream(envelope) 
         };
-        if let Err(e) = self.write_tx.send(write_req) {
-            self.in_flight.0.remove(&correlation_id);
-            self.pending_count.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
-            return Err(PythonExecutionError::ExecutionError { 
-                message: format!("Failed to send streaming write request: {e}") 
-            });
+        if self.write_tx.send(write_req).is_err() {
+            // The writer task has exited, so the connection is closed
+            if self.in_flight.0.remove(&correlation_id).is_some() {
+                self.release_pending();
+            }
+            return Err(self.close_reason.error());
         }
         
         // Convert the receiver into a stream using tokio_stream with type conversion
@@ -932,12 +1072,16 @@ impl From<std::io::Error> for ChildProcessLoopError {
     }
 }
 
-// Replace the entire read_next_message function with the corrected version without decoding
-async fn read_next_message(conn: &mut tokio::net::UnixStream) -> Result<Option<Vec<u8>>, io::Error> {
+/// Read the next raw frame from the parent along with any shared memory fds attached to it.
+async fn read_next_message<R: TransportRead>(
+    conn: &mut R,
+    compression: Compression,
+    max_frame_size: usize,
+) -> Result<Option<(Vec<u8>, Vec<std::os::fd::OwnedFd>)>, io::Error> {
     tracing::trace!(event = "child_read", step = "before_len", "About to read length prefix");
     let mut len_buf = [0u8; 4];
-    match conn.read_exact(&mut len_buf).await {
-        Ok(_) => {},
+    let fds = match conn.read_exact_with_fds(&mut len_buf).await {
+        Ok(fds) => fds,
         Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
             tracing::trace!(event = "child_read", step = "clean_eof", "Clean EOF detected on length read");
             return Ok(None);
@@ -946,49 +1090,113 @@ async fn read_next_message(conn: &mut tokio::net::UnixStream) -> Result<Option<V
             tracing::trace!(event = "child_read", step = "error", error = ?e);
             return Err(e);
         }
-    }
-    let msg_len = u32::from_le_bytes(len_buf) as usize;
-    tracing::trace!(event = "child_read", step = "after_len", ?len_buf, msg_len, "Read length prefix");
+    };
+    let (msg_len, compressed) = compression::frame_len(u32::from_le_bytes(len_buf), compression);
+    tracing::trace!(event = "child_read", step = "after_len", ?len_buf, msg_len, compressed, "Read length prefix");
+    FrameSizeError::check_incoming(msg_len, max_frame_size)?;
     tracing::trace!(event = "child_read", step = "before_msg", msg_len, "About to read message of len {}", msg_len);
     let mut msg_buf = vec![0u8; msg_len];
     conn.read_exact(&mut msg_buf).await?;
-    tracing::trace!(event = "child_read", step = "after_msg", len = msg_buf.len(), "Read message");
-    Ok(Some(msg_buf))
+    let msg_buf = compression::unpack(msg_buf, compressed, compression, max_frame_size)?;
+    tracing::trace!(event = "child_read", step = "after_msg", len = msg_buf.len(), fd_count = fds.len(), "Read message");
+    Ok(Some((msg_buf, fds)))
 }
 
-/// Configuration for the child actor loop concurrency
+/// An encoded reply frame and the shared memory fds to send with it.
+type ReplyFrame = (u64, Vec<u8>, Vec<std::os::fd::OwnedFd>);
+
+/// Write `first` and whatever other replies are queued to the parent as one batch.
+async fn write_replies<W: TransportWrite>(
+    writer: &mut framing::LengthPrefixedWrite<W>,
+    reply_rx: &mut tokio::sync::mpsc::UnboundedReceiver<ReplyFrame>,
+    first: ReplyFrame,
+) -> Result<(), io::Error> {
+    let batch = framing::recv_batch(reply_rx, first, writer.batch()).await;
+    let batch_len = batch.len();
+    for (correlation_id, reply_bytes, fds) in batch {
+        tracing::trace!(event = "child_ipc", step = "reply_queued", correlation_id, len = reply_bytes.len(), "Queued reply to parent");
+        writer.queue_frame_with_fds(reply_bytes, fds).await?;
+    }
+    writer.flush().await?;
+    tracing::trace!(event = "child_ipc", step = "reply_sent", batch_len, "Sent replies to parent");
+    Ok(())
+}
+
+/// Configuration for the child actor loop
 pub struct ChildActorLoopConfig {
     pub max_concurrency: usize,
+    /// Wire codec agreed in the handshake
+    pub codec: CodecKind,
+    /// Frame compression agreed in the handshake
+    pub compression: Compression,
+    /// Largest frame accepted from or sent to the parent
+    pub max_frame_size: usize,
+    /// Limits for coalescing replies into one write
+    pub write_batch: framing::WriteBatch,
 }
 
 impl Default for ChildActorLoopConfig {
     fn default() -> Self {
         Self {
             max_concurrency: 10_000,
+            codec: CodecKind::default(),
+            compression: Compression::None,
+            max_frame_size: framing::max_frame_size(),
+            write_batch: framing::write_batch(),
+        }
+    }
+}
+
+/// Encode a reply frame. If it is over `max_frame_size`, encode `error_reply` instead so only
+/// this request fails.
+fn encode_reply<O: Serialize>(
+    correlation_id: u64,
+    ctrl: &Control<O>,
+    error_reply: impl FnOnce(PythonExecutionError) -> Control<O>,
+    codec: CodecKind,
+    fd_passing: bool,
+    max_frame_size: usize,
+) -> io::Result<(Vec<u8>, Vec<std::os::fd::OwnedFd>)> {
+    let (bytes, fds) = shm::encode_frame(ctrl, codec, fd_passing)?;
+    match FrameSizeError::check_outgoing(bytes.len(), max_frame_size) {
+        Ok(()) => Ok((bytes, fds)),
+        Err(e) => {
+            tracing::warn!(event = "child_ipc", correlation_id, error = %e, "Reply too large to send, failing the request");
+            shm::encode_frame(&error_reply(PythonExecutionError::ExecutionError { message: e.to_string() }), codec, fd_passing)
         }
     }
 }
 
-pub async fn run_child_actor_loop<H, M>(
+pub async fn run_child_actor_loop<H, M, T>(
     handler: H,
-    mut conn: Box<tokio::net::UnixStream>,
+    conn: T,
     config: Option<ChildActorLoopConfig>,
 ) -> Result<(), ChildProcessLoopError>
 where
     H: ChildProcessMessageHandler<M> + Send + Clone + 'static,
     M: KameoChildProcessMessage + Send + 'static,
-    M::Ok: serde::Serialize + bincode::Encode + std::fmt::Debug + 'static,
+    T: Transport,
+    M::Ok: serde::Serialize + std::fmt::Debug + 'static,
 {
     // Clear all ambient span context for the entire select loop and all futures polled within it
     let none_span = tracing::Span::none();
     let _none_guard = none_span.enter();
     tracing::debug!(event = "run_child_actor_loop", step = "start", "run_child_actor_loop started");
     use futures::stream::{FuturesUnordered, StreamExt};
-    let _config = config.unwrap_or_default();
+    let config = config.unwrap_or_default();
+    let codec = config.codec;
+    let compression = config.compression;
+    let max_frame_size = config.max_frame_size;
+    let (mut conn_read, conn_write) = conn.into_split();
+    let fd_passing = conn_write.supports_fd_passing();
+    let mut writer = framing::LengthPrefixedWrite::with_codec(conn_write, codec)
+        .with_compression(compression)
+        .with_max_frame_size(max_frame_size)
+        .with_write_batch(config.write_batch);
     let mut in_flight = FuturesUnordered::<Box<dyn futures::Future<Output = ()> + Send + Unpin>>::new();
 
     // Make reply_tx Option and drop it on shutdown
-    let (reply_tx_inner, mut reply_rx) = tokio::sync::mpsc::unbounded_channel::<(u64, Vec<u8>)>();
+    let (reply_tx_inner, mut reply_rx) = tokio::sync::mpsc::unbounded_channel::<ReplyFrame>();
     let mut reply_tx = Some(reply_tx_inner);
     let mut shutdown = false;
     loop {
@@ -999,12 +1207,12 @@ where
                 Some(_) = in_flight.next() => {
                     tracing::trace!(event = "child_in_flight", action = "complete", in_flight_len = in_flight.len(), "Handler future completed in child in_flight");
                 }
-                read_res = read_next_message(&mut conn) => {
+                read_res = read_next_message(&mut conn_read, compression, max_frame_size) => {
                     match read_res {
-                        Ok(Some(msg)) => {
+                        Ok(Some((msg, fds))) => {
                             tracing::trace!(event = "child_ipc", step = "read", len = msg.len(), raw = ?&msg[..std::cmp::min(100, msg.len())], "Read message from parent");
-                            let ctrl: Control<M> = match bincode::decode_from_slice(&msg[..], bincode::config::standard()) {
-                                Ok((ctrl, _)) => {
+                            let ctrl: Control<M> = match shm::decode_frame(&msg[..], fds, codec) {
+                                Ok(ctrl) => {
                                     tracing::trace!(event = "bincode_decode", type_deserialized = std::any::type_name::<Control<M>>(), len = msg.len(), "Decoding Control envelope");
                                     ctrl
                                 },
@@ -1052,15 +1260,16 @@ where
                                         let reply_envelope = MultiplexEnvelope {
                                             correlation_id,
                                             inner: result,
-                                            context: envelope.context,
+                                            context: envelope.context.clone(),
                                         };
                                         let ctrl = Control::Sync(reply_envelope);
+                                        let error_reply = |e| Control::Sync(MultiplexEnvelope { correlation_id, inner: Err(e), context: envelope.context });
                                         
                                         // Encode the reply to bytes
-                                        match bincode::encode_to_vec(ctrl, bincode::config::standard()) {
-                                            Ok(reply_bytes) => {
+                                        match encode_reply(correlation_id, &ctrl, error_reply, codec, fd_passing, max_frame_size) {
+                                            Ok((reply_bytes, fds)) => {
                                                 trace!(event = "reply_encoded", correlation_id = correlation_id, reply_size = reply_bytes.len(), "Reply encoded successfully");
-                                                if let Err(e) = reply_tx.send((correlation_id, reply_bytes)) {
+                                                if let Err(e) = reply_tx.send((correlation_id, reply_bytes, fds)) {
                                                     trace!(event = "reply_send_error", correlation_id = correlation_id, error = ?e, "Failed to send reply");
                                                 } else {
                                                     trace!(event = "reply_sent", correlation_id = correlation_id, "Reply sent successfully");
@@ -1120,12 +1329,13 @@ where
                                                     
                                                     // Send as stream item
                                                     let ctrl = Control::Stream(reply_envelope);
+                                                    let error_reply = |e| Control::Stream(MultiplexEnvelope { correlation_id, inner: Err(e), context: envelope.context.clone() });
                                                     
                                                     // Encode the reply to bytes
-                                                    match bincode::encode_to_vec(ctrl, bincode::config::standard()) {
-                                                        Ok(reply_bytes) => {
+                                                    match encode_reply(correlation_id, &ctrl, error_reply, codec, fd_passing, max_frame_size) {
+                                                        Ok((reply_bytes, fds)) => {
                                                             trace!(event = "stream_reply_encoded", correlation_id = correlation_id, reply_size = reply_bytes.len(), "Stream reply encoded successfully");
-                                                            if let Err(e) = reply_tx.send((correlation_id, reply_bytes)) {
+                                                            if let Err(e) = reply_tx.send((correlation_id, reply_bytes, fds)) {
                                                                 trace!(event = "stream_reply_send_error", correlation_id = correlation_id, error = ?e, "Failed to send stream reply");
                                                                 break;
                                                             } else {
@@ -1147,10 +1357,10 @@ where
                                                 };
                                                 let end_ctrl = Control::StreamEnd(end_envelope);
                                                 
-                                                match bincode::encode_to_vec(end_ctrl, bincode::config::standard()) {
-                                                    Ok(end_bytes) => {
+                                                match shm::encode_frame(&end_ctrl, codec, false) {
+                                                    Ok((end_bytes, _)) => {
                                                         trace!(event = "stream_end_encoded", correlation_id = correlation_id, "Stream end encoded successfully");
-                                                        if let Err(e) = reply_tx.send((correlation_id, end_bytes)) {
+                                                        if let Err(e) = reply_tx.send((correlation_id, end_bytes, Vec::new())) {
                                                             trace!(event = "stream_end_send_error", correlation_id = correlation_id, error = ?e, "Failed to send stream end");
                                                         } else {
                                                             trace!(event = "stream_end_sent", correlation_id = correlation_id, "Stream end sent successfully");
@@ -1166,14 +1376,15 @@ where
                                                 let error_envelope: MultiplexEnvelope<Option<Result<M::Ok, PythonExecutionError>>> = MultiplexEnvelope {
                                                     correlation_id,
                                                     inner: Some(Err(e)),
-                                                    context: envelope.context,
+                                                    context: envelope.context.clone(),
                                                 };
                                                 let error_ctrl = Control::StreamEnd(error_envelope);
+                                                let error_reply = |e| Control::StreamEnd(MultiplexEnvelope { correlation_id, inner: Some(Err(e)), context: envelope.context });
                                                 
-                                                match bincode::encode_to_vec(error_ctrl, bincode::config::standard()) {
-                                                    Ok(error_bytes) => {
+                                                match encode_reply(correlation_id, &error_ctrl, error_reply, codec, fd_passing, max_frame_size) {
+                                                    Ok((error_bytes, fds)) => {
                                                         trace!(event = "stream_error_encoded", correlation_id = correlation_id, "Stream error encoded successfully");
-                                                        if let Err(e) = reply_tx.send((correlation_id, error_bytes)) {
+                                                        if let Err(e) = reply_tx.send((correlation_id, error_bytes, fds)) {
                                                             trace!(event = "stream_error_send_error", correlation_id = correlation_id, error = ?e, "Failed to send stream error");
                                                         } else {
                                                             trace!(event = "stream_error_sent", correlation_id = correlation_id, "Stream error sent successfully");
@@ -1210,16 +1421,11 @@ where
                         }
                     }
                 }
-                Some((correlation_id, reply_bytes)) = reply_rx.recv() => {
-                    if let Err(e) = conn.write_all(&(reply_bytes.len() as u32).to_le_bytes()).await {
-                        tracing::error!(event = "child_ipc", step = "write_len_error", correlation_id, error = %e, "Failed to write reply length to parent");
+                Some(reply) = reply_rx.recv() => {
+                    if let Err(e) = write_replies(&mut writer, &mut reply_rx, reply).await {
+                        tracing::error!(event = "child_ipc", step = "write_reply_error", error = %e, "Failed to write reply to parent");
                         break;
                     }
-                    if let Err(e) = conn.write_all(&reply_bytes).await {
-                        tracing::error!(event = "child_ipc", step = "write_reply_error", correlation_id, error = %e, "Failed to write reply to parent");
-                        break;
-                    }
-                    tracing::trace!(event = "child_ipc", step = "reply_sent", correlation_id, len = reply_bytes.len(), "Sent reply to parent");
                 }
             }
         } else {
@@ -1229,16 +1435,11 @@ where
                     tracing::trace!(event = "child_in_flight", action = "complete", in_flight_len = in_flight.len(), "Handler future completed in child in_flight");
                 }
                 maybe_reply = reply_rx.recv() => {
-                    if let Some((correlation_id, reply_bytes)) = maybe_reply {
-                        if let Err(e) = conn.write_all(&(reply_bytes.len() as u32).to_le_bytes()).await {
-                            tracing::error!(event = "child_ipc", step = "write_len_error", correlation_id, error = %e, "Failed to write reply length to parent");
-                            break;
-                        }
-                        if let Err(e) = conn.write_all(&reply_bytes).await {
-                            tracing::error!(event = "child_ipc", step = "write_reply_error", correlation_id, error = %e, "Failed to write reply to parent");
+                    if let Some(reply) = maybe_reply {
+                        if let Err(e) = write_replies(&mut writer, &mut reply_rx, reply).await {
+                            tracing::error!(event = "child_ipc", step = "write_reply_error", error = %e, "Failed to write reply to parent");
                             break;
                         }
-                        tracing::trace!(event = "child_ipc", step = "reply_sent", correlation_id, len = reply_bytes.len(), "Sent reply to parent");
                     } else {
                         tracing::trace!(event = "child_loop", step = "reply_channel_closed", "Reply channel closed");
                     }
@@ -1275,21 +1476,129 @@ pub struct RuntimeConfig {
     pub worker_threads: Option<usize>,
 }
 
-// 1. Add perform_handshake function for parent/child handshake
+/// Handshake sent by the parent: the codecs and compression it can use, most preferred first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeRequest {
+    pub codecs: Vec<CodecKind>,
+    pub compression: Vec<Compression>,
+}
+
+/// Handshake reply from the child: the codec both sides will use, or `None` if there is no
+/// codec in common, the agreed compression, and the token the child was spawned with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeResponse {
+    pub codec: Option<CodecKind>,
+    pub compression: Compression,
+    pub token: Option<String>,
+}
+
+/// Environment variable carrying the per-spawn token a child presents in the handshake.
+pub const HANDSHAKE_TOKEN_ENV: &str = "KAMEO_HANDSHAKE_TOKEN";
+
+/// What one side of the handshake can use.
+///
+/// The parent lists what it wants, most preferred first; the child lists what it supports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeOptions {
+    pub codecs: Vec<CodecKind>,
+    /// Compression is optional: with nothing in common, frames are sent uncompressed.
+    pub compression: Vec<Compression>,
+    /// The parent requires the child to present this token; the child presents it.
+    pub token: Option<String>,
+}
+
+impl Default for HandshakeOptions {
+    /// Every built-in codec, no compression.
+    fn default() -> Self {
+        Self { codecs: CodecKind::ALL.to_vec(), compression: Vec::new(), token: None }
+    }
+}
+
+impl HandshakeOptions {
+    /// Everything this build supports; what a child offers by default.
+    pub fn supported() -> Self {
+        Self { codecs: CodecKind::ALL.to_vec(), compression: Compression::supported(), token: None }
+    }
+
+    /// What a spawned child offers: everything supported, plus the token from `KAMEO_HANDSHAKE_TOKEN`.
+    pub fn for_child() -> Self {
+        Self { token: std::env::var(HANDSHAKE_TOKEN_ENV).ok(), ..Self::supported() }
+    }
+
+    /// A fresh random token for one spawn.
+    pub fn new_token() -> String {
+        uuid::Uuid::new_v4().simple().to_string()
+    }
+}
+
+/// Compare handshake tokens without leaking where they differ through timing.
+pub(crate) fn tokens_match(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected.bytes().zip(presented.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Frame format agreed in the handshake for the request connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WireFormat {
+    pub codec: CodecKind,
+    pub compression: Compression,
+}
+
+/// Perform the parent/child handshake with default options.
+///
+/// The parent offers every built-in codec and no compression; the child accepts anything
+/// this build supports and presents its `KAMEO_HANDSHAKE_TOKEN`. Returns the agreed frame format.
 pub async fn perform_handshake<M>(
     conn: &mut (impl AsyncRead + AsyncWrite + Unpin),
     is_parent: bool,
-) -> Result<(), PythonExecutionError>
+) -> Result<WireFormat, PythonExecutionError>
+where
+    M: KameoChildProcessMessage + Send + Sync + 'static,
+{
+    let options = if is_parent { HandshakeOptions::default() } else { HandshakeOptions::for_child() };
+    perform_handshake_with_options::<M>(conn, is_parent, &options).await
+}
+
+/// Perform the parent/child handshake, negotiating only the request connection's codec.
+///
+/// See [`perform_handshake_with_options`]; no compression is offered.
+pub async fn perform_handshake_with_codecs<M>(
+    conn: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    is_parent: bool,
+    codecs: &[CodecKind],
+) -> Result<CodecKind, PythonExecutionError>
+where
+    M: KameoChildProcessMessage + Send + Sync + 'static,
+{
+    let options = HandshakeOptions { codecs: codecs.to_vec(), ..Default::default() };
+    Ok(perform_handshake_with_options::<M>(conn, is_parent, &options).await?.codec)
+}
+
+/// Perform the parent/child handshake, negotiating the request connection's frame format.
+///
+/// The parent offers its codecs and compression algorithms in preference order; the child
+/// picks the first of each that is also in its own `options`. Both sides fail if there is
+/// no codec in common; without a common compression algorithm, frames are uncompressed.
+/// If the parent's `options` carry a token, the child must present the same one.
+/// The handshake itself is always encoded with the default codec.
+pub async fn perform_handshake_with_options<M>(
+    conn: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    is_parent: bool,
+    options: &HandshakeOptions,
+) -> Result<WireFormat, PythonExecutionError>
 where
     M: KameoChildProcessMessage + Send + Sync + 'static,
 {
-    use crate::Control;
-    use bincode::{decode_from_slice, encode_to_vec};
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let handshake_codec = CodecKind::default();
     if is_parent {
         // Parent sends handshake
-        let handshake_msg = Control::<M>::Handshake;
-        let handshake_bytes = encode_to_vec(&handshake_msg, bincode::config::standard())
+        let handshake_msg = HandshakeRequest {
+            codecs: options.codecs.clone(),
+            compression: options.compression.clone(),
+        };
+        let handshake_bytes = handshake_codec
+            .encode(&handshake_msg)
             .map_err(|e| PythonExecutionError::SerializationError { message: format!("Failed to encode handshake: {e}") })?;
         conn.write_all(&handshake_bytes)
             .await
@@ -1303,12 +1612,34 @@ where
         if n == 0 {
             return Err(PythonExecutionError::ExecutionError { message: "Connection closed during handshake".into() });
         }
-        let (resp, _): (Control<M>, _) =
-            decode_from_slice(&resp_buf[..n], bincode::config::standard()).map_err(|e| {
-                PythonExecutionError::SerializationError { message: format!("Failed to decode handshake response: {e}") }
-            })?;
-        if !resp.is_handshake() {
-            return Err(PythonExecutionError::ExecutionError { message: "Invalid handshake response".into() });
+        let resp: HandshakeResponse = handshake_codec.decode(&resp_buf[..n]).map_err(|e| {
+            PythonExecutionError::SerializationError { message: format!("Failed to decode handshake response: {e}") }
+        })?;
+        if let Some(expected) = &options.token {
+            if !resp.token.as_deref().is_some_and(|presented| tokens_match(expected, presented)) {
+                tracing::warn!(event = "handshake", presented = resp.token.is_some(), "Rejected child with an invalid handshake token");
+                return Err(PythonExecutionError::ExecutionError {
+                    message: "Child presented a missing or invalid handshake token".into(),
+                });
+            }
+        }
+        if resp.compression != Compression::None && !options.compression.contains(&resp.compression) {
+            return Err(PythonExecutionError::ExecutionError {
+                message: format!("Child chose compression {:?}, which was not offered", resp.compression),
+            });
+        }
+        match resp.codec {
+            Some(codec) if options.codecs.contains(&codec) => {
+                let format = WireFormat { codec, compression: resp.compression };
+                tracing::debug!(event = "handshake", ?format, "Agreed wire format");
+                Ok(format)
+            }
+            Some(codec) => Err(PythonExecutionError::ExecutionError {
+                message: format!("Child chose codec {codec:?}, which was not offered"),
+            }),
+            None => Err(PythonExecutionError::ExecutionError {
+                message: format!("Child supports none of the offered codecs {:?}", options.codecs),
+            }),
         }
     } else {
         // Child reads handshake
@@ -1320,22 +1651,35 @@ where
         if n == 0 {
             return Err(PythonExecutionError::ExecutionError { message: "Connection closed during handshake".into() });
         }
-        let (handshake, _): (Control<M>, _) =
-            decode_from_slice(&buf[..n], bincode::config::standard())
-                .map_err(|e| PythonExecutionError::SerializationError { message: format!("Failed to decode handshake: {e}") })?;
-        if !handshake.is_handshake() {
-            return Err(PythonExecutionError::ExecutionError { message: "Invalid handshake message".into() });
-        }
+        let handshake: HandshakeRequest = handshake_codec
+            .decode(&buf[..n])
+            .map_err(|e| PythonExecutionError::SerializationError { message: format!("Failed to decode handshake: {e}") })?;
+        let codec = handshake.codecs.iter().copied().find(|c| options.codecs.contains(c));
+        let compression = handshake
+            .compression
+            .iter()
+            .copied()
+            .find(|c| options.compression.contains(c))
+            .unwrap_or_default();
         // Child sends handshake response
-        let resp = Control::<M>::Handshake;
-        let resp_bytes = encode_to_vec(&resp, bincode::config::standard()).map_err(|e| {
+        let resp = HandshakeResponse { codec, compression, token: options.token.clone() };
+        let resp_bytes = handshake_codec.encode(&resp).map_err(|e| {
             PythonExecutionError::SerializationError { message: format!("Failed to encode handshake response: {e}") }
         })?;
         conn.write_all(&resp_bytes)
             .await
             .map_err(|e| PythonExecutionError::ExecutionError { message: format!("Failed to write handshake response: {e}") })?;
+        match codec {
+            Some(codec) => {
+                let format = WireFormat { codec, compression };
+                tracing::debug!(event = "handshake", ?format, "Agreed wire format");
+                Ok(format)
+            }
+            None => Err(PythonExecutionError::ExecutionError {
+                message: format!("Parent offered no supported codec: {:?}", handshake.codecs),
+            }),
+        }
     }
-    Ok(())
 }
 
 /// Kameo actor wrapper for IPC communication with child processes.
@@ -1505,16 +1849,20 @@ where
 
 
 pub mod framing;
-pub use framing::{LengthPrefixedRead, LengthPrefixedWrite};
+pub use framing::{FrameSizeError, LengthPrefixedRead, LengthPrefixedWrite, WriteBatch};
+pub mod shm;
+pub use shm::ShmBytes;
 
 /// Canonical child-side protocol artefact for parent-child IPC.
 /// Owns the socket, framing, and async orchestration. Use in production and tests.
-pub struct SubprocessIpcChild<M>
+pub struct SubprocessIpcChild<M, R = tokio::net::unix::OwnedReadHalf, W = tokio::net::unix::OwnedWriteHalf>
 where
     M: KameoChildProcessMessage + Send + 'static,
 {
-    read_half: tokio::net::unix::OwnedReadHalf,
-    write_half: tokio::net::unix::OwnedWriteHalf,
+    read_half: R,
+    write_half: W,
+    codec: CodecKind,
+    compression: Compression,
     _phantom: std::marker::PhantomData<M>,
 }
 
@@ -1525,20 +1873,48 @@ where
     /// Canonical constructor: wire up the child artefact from a DuplexUnixStream, splitting it internally.
     pub fn from_duplex(stream: DuplexUnixStream) -> Self {
         let (read_half, write_half) = stream.into_split();
-        Self { read_half, write_half, _phantom: std::marker::PhantomData }
+        Self { read_half, write_half, codec: CodecKind::default(), compression: Compression::None, _phantom: std::marker::PhantomData }
+    }
+}
+
+impl<M, R, W> SubprocessIpcChild<M, R, W>
+where
+    M: KameoChildProcessMessage + Send + 'static,
+    R: TransportRead,
+    W: TransportWrite,
+{
+    /// Wire up the child artefact over any [`Transport`], splitting it internally.
+    pub fn from_transport<T>(transport: T) -> Self
+    where
+        T: Transport<ReadHalf = R, WriteHalf = W>,
+    {
+        let (read_half, write_half) = transport.into_split();
+        Self { read_half, write_half, codec: CodecKind::default(), compression: Compression::None, _phantom: std::marker::PhantomData }
+    }
+
+    /// Use the codec agreed in the handshake instead of the default.
+    pub fn with_codec(mut self, codec: CodecKind) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Use the compression agreed in the handshake.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
     }
 
     /// Run the child protocol loop, handling messages with the provided handler.
     pub async fn run<H>(self, handler: H) -> Result<(), PythonExecutionError>
     where
         H: ChildProcessMessageHandler<M> + Send + Clone + 'static,
-        M::Ok: serde::Serialize + bincode::Encode + std::fmt::Debug + Sync + Send + 'static,
+        M::Ok: serde::Serialize + std::fmt::Debug + Sync + Send + 'static,
     {
         
         use crate::{MultiplexEnvelope, Control};
         
         tracing::debug!(event = "SubprocessIpcChild_run", step = "start", "SubprocessIpcChild run started");
-        let writer = std::sync::Arc::new(tokio::sync::Mutex::new(crate::framing::LengthPrefixedWrite::new(self.write_half)));
+        let writer = std::sync::Arc::new(tokio::sync::Mutex::new(crate::framing::LengthPrefixedWrite::with_codec(self.write_half, self.codec).with_compression(self.compression)));
         let reader_token = tokio_util::sync::CancellationToken::new();
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<MultiplexEnvelope<M>>();
         let handler_token = reader_token.clone();
@@ -1621,7 +1997,20 @@ where
                                             
                                             let stream_ctrl = Control::Stream(stream_envelope);
                                             let mut writer_guard = writer.lock().await;
-                                            if writer_guard.write_msg(&stream_ctrl).await.is_err() {
+                                            let written = match writer_guard.write_msg_with_fds(&stream_ctrl).await {
+                                                Err(e) if FrameSizeError::from_io(&e).is_some() => {
+                                                    // Nothing was written; tell the parent why this item is missing
+                                                    error!(event = "stream_item_too_large", correlation_id, error = %e, "Stream item too large to send");
+                                                    let error_ctrl: Control<Result<M::Ok, PythonExecutionError>> = Control::Stream(MultiplexEnvelope {
+                                                        correlation_id,
+                                                        inner: Err(PythonExecutionError::ExecutionError { message: e.to_string() }),
+                                                        context: envelope.context.clone(),
+                                                    });
+                                                    writer_guard.write_msg_with_fds(&error_ctrl).await
+                                                }
+                                                other => other,
+                                            };
+                                            if written.is_err() {
                                                 error!(event = "stream_item_send_failed", correlation_id, "Failed to send stream item");
                                                 break;
                                             } else {
@@ -1638,7 +2027,7 @@ where
                                         
                                         let end_ctrl = Control::StreamEnd(end_envelope);
                                         let mut writer_guard = writer.lock().await;
-                                        if writer_guard.write_msg(&end_ctrl).await.is_err() {
+                                        if writer_guard.write_msg_with_fds(&end_ctrl).await.is_err() {
                                             error!(event = "stream_end_send_failed", correlation_id, "Failed to send stream end");
                                         } else {
                                             trace!(event = "stream_end_sent", correlation_id, "Stream end sent successfully");
@@ -1654,7 +2043,7 @@ where
                                         
                                         let error_ctrl = Control::Stream(error_envelope);
                                         let mut writer_guard = writer.lock().await;
-                                        if writer_guard.write_msg(&error_ctrl).await.is_err() {
+                                        if writer_guard.write_msg_with_fds(&error_ctrl).await.is_err() {
                                             error!(event = "error_send_failed", correlation_id, "Failed to send error");
                                         } else {
                                             trace!(event = "error_sent", correlation_id, "Error sent successfully");
@@ -1669,7 +2058,7 @@ where
                                         
                                         let end_ctrl = Control::StreamEnd(end_envelope);
                                         let mut writer_guard = writer.lock().await;
-                                        if writer_guard.write_msg(&end_ctrl).await.is_err() {
+                                        if writer_guard.write_msg_with_fds(&end_ctrl).await.is_err() {
                                             error!(event = "stream_end_send_failed", correlation_id, "Failed to send stream end");
                                         } else {
                                             trace!(event = "stream_end_sent", correlation_id, "Stream end sent successfully");
@@ -1689,28 +2078,31 @@ where
             }
             tracing::info!(event = "child_ipc", step = "reader_task", "Reader task exiting");
         });
-        let reader_task = tokio::spawn(run_reader_loop(self.read_half, tx, reader_token, std::any::type_name::<M>()));
+        let reader_task = tokio::spawn(run_reader_loop(self.read_half, self.codec, self.compression, tx, reader_token, std::any::type_name::<M>()));
         let (_reader_res, _handler_res) = tokio::try_join!(reader_task, handler_task)
             .map_err(|e| PythonExecutionError::ExecutionError { message: format!("Join error: {e}") })?;
         Ok(())
     }
 }
 
-pub async fn run_reader_loop<M>(
-    read_half: tokio::net::unix::OwnedReadHalf,
+pub async fn run_reader_loop<M, R>(
+    read_half: R,
+    codec: CodecKind,
+    compression: Compression,
     tx: tokio::sync::mpsc::UnboundedSender<MultiplexEnvelope<M>>,
     cancellation_token: tokio_util::sync::CancellationToken,
     _message_type: &'static str,
 ) -> Result<(), PythonExecutionError>
 where
-    M: Decode<()> + Send + KameoChildProcessMessage + 'static,
+    M: Send + KameoChildProcessMessage + 'static,
+    R: TransportRead,
 {
-    let mut reader = crate::framing::LengthPrefixedRead::new(read_half);
+    let mut reader = crate::framing::LengthPrefixedRead::with_codec(read_half, codec).with_compression(compression);
     trace!(event = "child_reader", step = "start", "Reader loop started");
     loop {
         tokio::select! {
             _ = cancellation_token.cancelled() => break,
-            result = reader.read_msg::<Control<M>>() => {
+            result = reader.read_msg_with_fds::<Control<M>>() => {
                 let control: Control<M> = match result {
                     Ok(ctrl) => {
                         trace!(event = "child_reader", step = "read_control", "Read control message");
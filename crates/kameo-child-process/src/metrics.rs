@@ -19,6 +19,8 @@ static PARENT_MAX_INFLIGHT: AtomicU64 = AtomicU64::new(0);
 static CALLBACK_MAX_INFLIGHT: AtomicU64 = AtomicU64::new(0);
 static PARENT_ERROR_COUNT: AtomicU64 = AtomicU64::new(0);
 static CALLBACK_ERROR_COUNT: AtomicU64 = AtomicU64::new(0);
+static COMPRESSION_RAW_BYTES: AtomicU64 = AtomicU64::new(0);
+static COMPRESSION_WIRE_BYTES: AtomicU64 = AtomicU64::new(0);
 
 // OpenTelemetry instruments
 static INSTRUMENTS: Lazy<Mutex<Option<OtelInstruments>>> = Lazy::new(|| Mutex::new(None));
@@ -30,6 +32,7 @@ struct OtelInstruments {
     callback_errors_counter: Counter<u64>,
     parent_latency_histogram: Histogram<f64>,
     callback_latency_histogram: Histogram<f64>,
+    compression_ratio_histogram: Histogram<f64>,
 }
 
 /// Tracks metrics for IPC operations
@@ -237,7 +240,36 @@ impl MetricsHandle {
     }
 }
 
+/// Record one compressed frame: its encoded size and the size actually sent.
+pub fn record_compression(algorithm: &'static str, raw_bytes: usize, wire_bytes: usize) {
+    COMPRESSION_RAW_BYTES.fetch_add(raw_bytes as u64, Ordering::Relaxed);
+    COMPRESSION_WIRE_BYTES.fetch_add(wire_bytes as u64, Ordering::Relaxed);
+    let ratio = raw_bytes as f64 / wire_bytes.max(1) as f64;
+
+    // Record to OpenTelemetry if available
+    if let Some(instruments) = INSTRUMENTS.lock().unwrap().as_ref() {
+        instruments.compression_ratio_histogram.record(ratio, &[KeyValue::new("algorithm", algorithm)]);
+    }
+
+    // Record to metrics
+    histogram!("kameo_child_process_compression_ratio", "algorithm" => algorithm).record(ratio);
+    counter!("kameo_child_process_compression_raw_bytes_total", "algorithm" => algorithm).increment(raw_bytes as u64);
+    counter!("kameo_child_process_compression_wire_bytes_total", "algorithm" => algorithm).increment(wire_bytes as u64);
+
+    tracing::trace!(event = "metrics_compression", algorithm, raw_bytes, wire_bytes, ratio, "Compressed frame");
+}
+
+/// Record a connection on a child socket that failed peer verification.
+pub fn record_rejected_connection() {
+    counter!("kameo_child_process_rejected_connections_total").increment(1);
+}
+
 impl MetricsReporter {
+    /// Total (encoded, sent) bytes of compressed frames since startup
+    pub fn get_compression_totals() -> (u64, u64) {
+        (COMPRESSION_RAW_BYTES.load(Ordering::Relaxed), COMPRESSION_WIRE_BYTES.load(Ordering::Relaxed))
+    }
+
     /// Get current inflight counts
     pub fn get_inflight_counts() -> (u64, u64) {
         let parent = PARENT_INFLIGHT_COUNT.load(Ordering::SeqCst);
@@ -374,6 +406,11 @@ pub fn init_metrics() {
             .f64_histogram("kameo_child_process_callback_latency")
             .with_description("Latency of callback operations")
             .build();
+
+        let compression_ratio_histogram = meter
+            .f64_histogram("kameo_child_process_compression_ratio")
+            .with_description("Encoded size over sent size of compressed frames")
+            .build();
         
         // Register and describe metrics
         describe_gauge!("kameo_child_process_parent_inflight", "Current number of in-flight parent operations");
@@ -390,6 +427,9 @@ pub fn init_metrics() {
         
         describe_histogram!("kameo_child_process_parent_latency_ms", "Latency of parent operations in milliseconds");
         describe_histogram!("kameo_child_process_callback_latency_ms", "Latency of callback operations in milliseconds");
+        describe_histogram!("kameo_child_process_compression_ratio", "Encoded size over sent size of compressed frames");
+        describe_counter!("kameo_child_process_compression_raw_bytes_total", "Encoded bytes of frames that were compressed");
+        describe_counter!("kameo_child_process_compression_wire_bytes_total", "Bytes sent for compressed frames");
         
         // Store instruments for later use
         let instruments = OtelInstruments {
@@ -399,6 +439,7 @@ pub fn init_metrics() {
             callback_errors_counter,
             parent_latency_histogram,
             callback_latency_histogram,
+            compression_ratio_histogram,
         };
         
         *INSTRUMENTS.lock().unwrap() = Some(instruments);
@@ -0,0 +1,556 @@
+//! Shared-memory side channel for bulk byte payloads.
+//!
+//! Large byte buffers are expensive to push through the regular request path:
+//! they are encoded, copied through the socket, decoded and then copied
+//! again into Python objects. [`ShmBytes`] is a drop-in field type for such
+//! payloads. When a frame containing an `ShmBytes` of at least [`threshold`]
+//! bytes is written to the request socket, the bytes are placed in a sealed
+//! memfd segment and only a handle (fd index + length) goes into the frame. The
+//! fd itself travels alongside the frame as `SCM_RIGHTS` ancillary data.
+//!
+//! On the receiving side the fd is adopted back into an [`ShmBytes`]. In the
+//! Python child, `serde_py` maps the segment read-only into the interpreter and
+//! exposes it as a `memoryview`, so the payload is never copied into a Python
+//! `bytes` object.
+//!
+//! ## Notes
+//!
+//! - memfd segments are Linux-only; elsewhere `ShmBytes` always travels inline.
+//! - Only the request socket carries fds (requests, replies and stream items).
+//!   Callback messages always travel inline.
+//! - Segments are sealed before sending, so receivers can rely on their contents
+//!   and size not changing.
+
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+use serde::de::DeserializeOwned;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::codec::{Codec, CodecKind};
+use tokio::io::Interest;
+use tokio::net::UnixStream;
+
+/// Default minimum payload size sent through shared memory (1 MiB).
+pub const DEFAULT_SHM_THRESHOLD: usize = 1 << 20;
+
+/// Environment variable used to pass the threshold to child processes.
+pub const SHM_THRESHOLD_ENV: &str = "KAMEO_SHM_THRESHOLD";
+
+/// Newtype struct name used to hand a shared segment to `serde_py` as `(fd, len)`.
+pub const SHM_HANDLE_MARKER: &str = "__kameo_shm_handle__";
+
+/// Maximum number of fds that may accompany a single frame.
+pub const MAX_FDS_PER_FRAME: usize = 64;
+
+static THRESHOLD: Lazy<AtomicUsize> = Lazy::new(|| {
+    let threshold = std::env::var(SHM_THRESHOLD_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SHM_THRESHOLD);
+    AtomicUsize::new(threshold)
+});
+
+/// Current minimum size for sending an [`ShmBytes`] through shared memory.
+pub fn threshold() -> usize {
+    THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Set the minimum size for sending an [`ShmBytes`] through shared memory.
+///
+/// `usize::MAX` disables the side channel for this process.
+pub fn set_threshold(bytes: usize) {
+    THRESHOLD.store(bytes.max(1), Ordering::Relaxed);
+}
+
+/// A sealed, read-only memfd segment.
+#[derive(Debug)]
+pub struct ShmSegment {
+    fd: OwnedFd,
+    len: usize,
+}
+
+impl ShmSegment {
+    /// Copy `data` into a new sealed memfd segment.
+    #[cfg(target_os = "linux")]
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        use nix::fcntl::{fcntl, FcntlArg};
+        use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+        let name = std::ffi::CString::new("kameo-shm").expect("static name has no NUL");
+        let fd = memfd_create(
+            &name,
+            MemFdCreateFlag::MFD_CLOEXEC | MemFdCreateFlag::MFD_ALLOW_SEALING,
+        )?;
+        let mut file = File::from(fd);
+        file.write_all(data)?;
+        fcntl(file.as_raw_fd(), FcntlArg::F_ADD_SEALS(required_seals()))?;
+        Ok(Self {
+            fd: OwnedFd::from(file),
+            len: data.len(),
+        })
+    }
+
+    /// Shared memory segments are only available on Linux.
+    #[cfg(not(target_os = "linux"))]
+    pub fn from_bytes(_data: &[u8]) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "shared memory segments require memfd (Linux)",
+        ))
+    }
+
+    /// Adopt a received segment fd, checking that it is sealed like [`ShmSegment::from_bytes`]
+    /// seals it and holds at least `len` bytes.
+    ///
+    /// A segment the sender could still shrink would make mapped views of it fault with SIGBUS.
+    pub fn from_fd(fd: OwnedFd, len: usize) -> io::Result<Self> {
+        let file = File::from(fd);
+        #[cfg(target_os = "linux")]
+        {
+            use nix::fcntl::{fcntl, FcntlArg, SealFlag};
+            let seals = SealFlag::from_bits_truncate(fcntl(file.as_raw_fd(), FcntlArg::F_GET_SEALS)?);
+            if !seals.contains(required_seals()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("shared memory segment is not sealed (seals {seals:?})"),
+                ));
+            }
+        }
+        let size = file.metadata()?.len();
+        if (size as usize) < len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("shared memory segment holds {size} bytes, handle claims {len}"),
+            ));
+        }
+        Ok(Self {
+            fd: OwnedFd::from(file),
+            len,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Copy the segment contents into a `Vec`.
+    pub fn read_to_vec(&self) -> io::Result<Vec<u8>> {
+        let file = File::from(self.fd.try_clone()?);
+        let mut buf = vec![0u8; self.len];
+        file.read_exact_at(&mut buf, 0)?;
+        Ok(buf)
+    }
+}
+
+/// Seals that make a segment immutable for both sides.
+#[cfg(target_os = "linux")]
+fn required_seals() -> nix::fcntl::SealFlag {
+    use nix::fcntl::SealFlag;
+    SealFlag::F_SEAL_SHRINK | SealFlag::F_SEAL_GROW | SealFlag::F_SEAL_WRITE | SealFlag::F_SEAL_SEAL
+}
+
+impl AsFd for ShmSegment {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Repr {
+    Inline(Vec<u8>),
+    Shared(Arc<ShmSegment>),
+}
+
+/// Byte payload that is sent through shared memory when large enough.
+///
+/// Behaves like a `Vec<u8>` field for (de)serialization purposes. Received
+/// payloads may be backed by a shared segment; use [`ShmBytes::to_vec`] or
+/// [`ShmBytes::into_vec`] to access them from Rust.
+#[derive(Debug, Clone)]
+pub struct ShmBytes(Repr);
+
+impl ShmBytes {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self(Repr::Inline(data))
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Repr::Inline(v) => v.len(),
+            Repr::Shared(s) => s.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the payload is backed by a shared memory segment.
+    pub fn is_shared(&self) -> bool {
+        matches!(self.0, Repr::Shared(_))
+    }
+
+    /// Borrow the underlying segment, if any.
+    pub fn segment(&self) -> Option<&ShmSegment> {
+        match &self.0 {
+            Repr::Inline(_) => None,
+            Repr::Shared(s) => Some(s),
+        }
+    }
+
+    /// Copy the payload into a `Vec`.
+    pub fn to_vec(&self) -> io::Result<Vec<u8>> {
+        match &self.0 {
+            Repr::Inline(v) => Ok(v.clone()),
+            Repr::Shared(s) => s.read_to_vec(),
+        }
+    }
+
+    /// Take the payload as a `Vec`, copying only if it is backed by shared memory.
+    pub fn into_vec(self) -> io::Result<Vec<u8>> {
+        match self.0 {
+            Repr::Inline(v) => Ok(v),
+            Repr::Shared(s) => s.read_to_vec(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for ShmBytes {
+    fn from(data: Vec<u8>) -> Self {
+        Self::new(data)
+    }
+}
+
+impl PartialEq for ShmBytes {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.to_vec(), other.to_vec()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Fds collected while encoding a frame.
+struct Outbox {
+    fds: Vec<OwnedFd>,
+    /// Whether the transport can pass fds; if not, every payload is inlined.
+    fd_passing: bool,
+}
+
+thread_local! {
+    /// State of the frame being encoded; `None` outside [`encode_frame`].
+    static OUTBOX: RefCell<Option<Outbox>> = const { RefCell::new(None) };
+    /// Fds received with the frame being decoded; `None` outside [`decode_frame`].
+    static INBOX: RefCell<Option<Vec<Option<OwnedFd>>>> = const { RefCell::new(None) };
+    /// Whether `ShmBytes` should serialize shared segments as `SHM_HANDLE_MARKER`.
+    static HANDLES: RefCell<bool> = const { RefCell::new(false) };
+}
+
+/// Encode `value` as a request-connection frame, returning the frame bytes and the fds to send with it.
+///
+/// `ShmBytes` fields use a frame representation that [`decode_frame`] understands. If
+/// `fd_passing` is false, every payload is inlined and no fds are returned.
+pub fn encode_frame<T: Serialize + ?Sized>(
+    value: &T,
+    codec: CodecKind,
+    fd_passing: bool,
+) -> io::Result<(Vec<u8>, Vec<OwnedFd>)> {
+    OUTBOX.with(|o| *o.borrow_mut() = Some(Outbox { fds: Vec::new(), fd_passing }));
+    let result = codec.encode(value);
+    let fds = OUTBOX.with(|o| o.borrow_mut().take()).map(|outbox| outbox.fds).unwrap_or_default();
+    result.map(|bytes| (bytes, fds))
+}
+
+/// Decode a request-connection frame received together with `fds`.
+pub fn decode_frame<T: DeserializeOwned>(bytes: &[u8], fds: Vec<OwnedFd>, codec: CodecKind) -> io::Result<T> {
+    INBOX.with(|i| *i.borrow_mut() = Some(fds.into_iter().map(Some).collect()));
+    let result = codec.decode(bytes);
+    let unused = INBOX
+        .with(|i| i.borrow_mut().take())
+        .map(|fds| fds.into_iter().flatten().count())
+        .unwrap_or(0);
+    if unused > 0 {
+        tracing::warn!(event = "shm_decode", unused, "Frame carried fds that no ShmBytes claimed");
+    }
+    result
+}
+
+/// Run `f` with shared segments serialized as `SHM_HANDLE_MARKER` newtypes instead of bytes.
+///
+/// Used by `serde_py` to expose segments to Python without copying.
+pub fn with_shared_handles<R>(f: impl FnOnce() -> R) -> R {
+    let previous = HANDLES.with(|h| std::mem::replace(&mut *h.borrow_mut(), true));
+    let result = f();
+    HANDLES.with(|h| *h.borrow_mut() = previous);
+    result
+}
+
+fn outbox_active() -> bool {
+    OUTBOX.with(|o| o.borrow().is_some())
+}
+
+fn inbox_active() -> bool {
+    INBOX.with(|i| i.borrow().is_some())
+}
+
+impl ShmBytes {
+    /// Place this payload in the current frame's outbox, returning its fd index and length.
+    ///
+    /// Returns `None` if the payload should travel inline: no fd passing, below the
+    /// threshold, too many fds in the frame, or no memfd support.
+    fn push_outgoing(&self) -> io::Result<Option<(u32, u64)>> {
+        OUTBOX.with(|o| {
+            let mut outbox = o.borrow_mut();
+            let Some(outbox) = outbox.as_mut().filter(|o| o.fd_passing) else {
+                return Ok(None);
+            };
+            if outbox.fds.len() >= MAX_FDS_PER_FRAME {
+                return Ok(None);
+            }
+            let segment = match &self.0 {
+                Repr::Shared(segment) => segment.clone(),
+                Repr::Inline(data) if data.len() >= threshold() => match ShmSegment::from_bytes(data) {
+                    Ok(segment) => Arc::new(segment),
+                    Err(e) => {
+                        tracing::debug!(event = "shm_encode", error = %e, len = data.len(), "Falling back to inline payload");
+                        return Ok(None);
+                    }
+                },
+                Repr::Inline(_) => return Ok(None),
+            };
+            outbox.fds.push(segment.fd.try_clone()?);
+            Ok(Some(((outbox.fds.len() - 1) as u32, segment.len() as u64)))
+        })
+    }
+
+    /// Adopt fd `index` of the frame being decoded as a segment of `len` bytes.
+    fn take_incoming(index: u32, len: u64) -> io::Result<Self> {
+        let fd = INBOX
+            .with(|i| {
+                i.borrow_mut()
+                    .as_mut()
+                    .and_then(|fds| fds.get_mut(index as usize).and_then(Option::take))
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("shared memory handle {index} has no matching fd in this frame"),
+                )
+            })?;
+        let segment = ShmSegment::from_fd(fd, len as usize)?;
+        Ok(Self(Repr::Shared(Arc::new(segment))))
+    }
+}
+
+/// Bytes serialized with `serialize_bytes`, so compact codecs don't encode them element by element.
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Owned counterpart of [`RawBytes`].
+struct RawByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for RawByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+        impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+            type Value = Vec<u8>;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a byte buffer")
+            }
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(v.to_vec())
+            }
+            fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(v)
+            }
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(b) = seq.next_element::<u8>()? {
+                    data.push(b);
+                }
+                Ok(data)
+            }
+        }
+        deserializer.deserialize_byte_buf(BytesVisitor).map(RawByteBuf)
+    }
+}
+
+/// How an `ShmBytes` appears inside a request-connection frame.
+#[derive(Serialize)]
+#[serde(rename = "ShmBytes")]
+enum FrameRepr<'a> {
+    Inline(RawBytes<'a>),
+    Shared { index: u32, len: u64 },
+}
+
+/// Owned counterpart of [`FrameRepr`].
+#[derive(Deserialize)]
+#[serde(rename = "ShmBytes")]
+enum FrameReprBuf {
+    Inline(RawByteBuf),
+    Shared { index: u32, len: u64 },
+}
+
+// Native bincode always inlines; only serde codecs take part in fd passing.
+impl Encode for ShmBytes {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        match &self.0 {
+            Repr::Inline(data) => data.encode(encoder),
+            Repr::Shared(segment) => segment
+                .read_to_vec()
+                .map_err(|e| EncodeError::OtherString(format!("failed to read shm segment: {e}")))?
+                .encode(encoder),
+        }
+    }
+}
+
+impl<Context> Decode<Context> for ShmBytes {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self(Repr::Inline(Vec::<u8>::decode(decoder)?)))
+    }
+}
+
+bincode::impl_borrow_decode!(ShmBytes);
+
+impl Serialize for ShmBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if outbox_active() {
+            if let Some((index, len)) = self.push_outgoing().map_err(serde::ser::Error::custom)? {
+                return FrameRepr::Shared { index, len }.serialize(serializer);
+            }
+            return match &self.0 {
+                Repr::Inline(data) => FrameRepr::Inline(RawBytes(data)).serialize(serializer),
+                Repr::Shared(segment) => {
+                    let data = segment.read_to_vec().map_err(serde::ser::Error::custom)?;
+                    FrameRepr::Inline(RawBytes(&data)).serialize(serializer)
+                }
+            };
+        }
+        match &self.0 {
+            Repr::Inline(data) => serializer.serialize_bytes(data),
+            Repr::Shared(segment) if HANDLES.with(|h| *h.borrow()) => serializer
+                .serialize_newtype_struct(
+                    SHM_HANDLE_MARKER,
+                    &(segment.as_fd().as_raw_fd(), segment.len() as u64),
+                ),
+            Repr::Shared(segment) => {
+                let data = segment.read_to_vec().map_err(serde::ser::Error::custom)?;
+                serializer.serialize_bytes(&data)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ShmBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if inbox_active() {
+            return match FrameReprBuf::deserialize(deserializer)? {
+                FrameReprBuf::Inline(RawByteBuf(data)) => Ok(ShmBytes::new(data)),
+                FrameReprBuf::Shared { index, len } => {
+                    ShmBytes::take_incoming(index, len).map_err(serde::de::Error::custom)
+                }
+            };
+        }
+        RawByteBuf::deserialize(deserializer).map(|RawByteBuf(data)| ShmBytes::new(data))
+    }
+}
+
+/// Send `data` with `fds` attached as `SCM_RIGHTS`, writing all of `data`.
+///
+/// The fds are attached to the first byte; the rest is written without ancillary data.
+pub(crate) async fn send_with_fds(stream: &UnixStream, data: &[u8], fds: &[OwnedFd]) -> io::Result<()> {
+    use nix::sys::socket::{sendmsg, ControlMessage, MsgFlags};
+    use std::io::IoSlice;
+    let raw_fds: Vec<RawFd> = fds.iter().map(|fd| fd.as_raw_fd()).collect();
+    let mut written = 0;
+    while written < data.len() {
+        let n = stream
+            .async_io(Interest::WRITABLE, || {
+                let iov = [IoSlice::new(&data[written..])];
+                let cmsgs = if written == 0 && !raw_fds.is_empty() {
+                    vec![ControlMessage::ScmRights(&raw_fds)]
+                } else {
+                    Vec::new()
+                };
+                sendmsg::<()>(stream.as_raw_fd(), &iov, &cmsgs, MsgFlags::MSG_NOSIGNAL, None)
+                    .map_err(io::Error::from)
+            })
+            .await?;
+        if n == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        written += n;
+    }
+    Ok(())
+}
+
+/// Fill `buf` from the socket, collecting any fds sent along with it.
+///
+/// Returns `UnexpectedEof` if the peer closes before `buf` is full.
+pub(crate) async fn recv_exact_with_fds(stream: &UnixStream, buf: &mut [u8]) -> io::Result<Vec<OwnedFd>> {
+    use nix::sys::socket::{recvmsg, ControlMessageOwned, MsgFlags};
+    use std::io::IoSliceMut;
+    let mut fds = Vec::new();
+    let mut filled = 0;
+    while filled < buf.len() {
+        let mut cmsg_space = nix::cmsg_space!([RawFd; MAX_FDS_PER_FRAME]);
+        let (n, raw_fds, truncated) = stream
+            .async_io(Interest::READABLE, || {
+                let mut iov = [IoSliceMut::new(&mut buf[filled..])];
+                let msg = recvmsg::<()>(
+                    stream.as_raw_fd(),
+                    &mut iov,
+                    Some(&mut cmsg_space),
+                    MsgFlags::MSG_CMSG_CLOEXEC,
+                )
+                .map_err(io::Error::from)?;
+                let mut raw_fds = Vec::new();
+                for cmsg in msg.cmsgs() {
+                    if let ControlMessageOwned::ScmRights(received) = cmsg {
+                        raw_fds.extend(received);
+                    }
+                }
+                Ok((msg.bytes, raw_fds, msg.flags.contains(MsgFlags::MSG_CTRUNC)))
+            })
+            .await?;
+        fds.extend(raw_fds.into_iter().map(adopt_received_fd));
+        if truncated {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame carried more than {MAX_FDS_PER_FRAME} fds"),
+            ));
+        }
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        filled += n;
+    }
+    Ok(fds)
+}
+
+/// Take ownership of an fd delivered by `SCM_RIGHTS`.
+#[allow(unsafe_code)]
+fn adopt_received_fd(fd: RawFd) -> OwnedFd {
+    use std::os::fd::FromRawFd;
+    // SAFETY: the kernel installs fds received via SCM_RIGHTS as new descriptors
+    // in this process; nothing else refers to them, so we are their sole owner.
+    unsafe { OwnedFd::from_raw_fd(fd) }
+}
@@ -0,0 +1,134 @@
+//! Byte-stream transports the IPC protocol can run over.
+//!
+//! The protocol only needs an ordered, reliable, full-duplex byte stream. [`Transport`]
+//! describes such a stream and how to split it into independently owned read and write
+//! halves, so the backend, the child loop and the callback channel are not tied to Unix
+//! domain sockets.
+//!
+//! Implementations are provided for:
+//!
+//! - [`tokio::net::UnixStream`] (and [`crate::DuplexUnixStream`]): the default, and the only
+//!   transport that can pass shared memory fds (see [`crate::shm`])
+//! - [`tokio::net::TcpStream`]: for workers on another host
+//! - [`tokio::io::DuplexStream`]: an in-memory pipe, mainly for tests
+//!
+//! Transports that cannot pass fds send [`crate::ShmBytes`] payloads inline.
+
+use async_trait::async_trait;
+use std::io;
+use std::os::fd::OwnedFd;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::AsyncReadWrite;
+
+/// Read half of a [`Transport`].
+#[async_trait]
+pub trait TransportRead: AsyncRead + Send + Unpin + 'static {
+    /// Fill `buf`, returning any fds that arrived with it.
+    ///
+    /// Transports without fd passing just fill the buffer and return no fds.
+    async fn read_exact_with_fds(&mut self, buf: &mut [u8]) -> io::Result<Vec<OwnedFd>> {
+        self.read_exact(buf).await?;
+        Ok(Vec::new())
+    }
+}
+
+/// Write half of a [`Transport`].
+#[async_trait]
+pub trait TransportWrite: AsyncWrite + Send + Unpin + 'static {
+    /// Whether [`write_all_with_fds`](Self::write_all_with_fds) can attach fds.
+    fn supports_fd_passing(&self) -> bool {
+        false
+    }
+
+    /// Write all of `data`, attaching `fds` to it.
+    ///
+    /// Fails with `Unsupported` if `fds` is non-empty and the transport cannot pass fds.
+    async fn write_all_with_fds(&mut self, data: &[u8], fds: &[OwnedFd]) -> io::Result<()> {
+        if !fds.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "transport cannot pass file descriptors",
+            ));
+        }
+        self.write_all(data).await
+    }
+}
+
+/// A full-duplex byte stream that can be split into owned halves.
+pub trait Transport: AsyncReadWrite + Sized {
+    type ReadHalf: TransportRead;
+    type WriteHalf: TransportWrite;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf);
+}
+
+impl<T: Transport> Transport for Box<T> {
+    type ReadHalf = T::ReadHalf;
+    type WriteHalf = T::WriteHalf;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        (*self).into_split()
+    }
+}
+
+// --- Unix domain sockets ---
+
+impl Transport for tokio::net::UnixStream {
+    type ReadHalf = tokio::net::unix::OwnedReadHalf;
+    type WriteHalf = tokio::net::unix::OwnedWriteHalf;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        tokio::net::UnixStream::into_split(self)
+    }
+}
+
+#[async_trait]
+impl TransportRead for tokio::net::unix::OwnedReadHalf {
+    async fn read_exact_with_fds(&mut self, buf: &mut [u8]) -> io::Result<Vec<OwnedFd>> {
+        crate::shm::recv_exact_with_fds(self.as_ref(), buf).await
+    }
+}
+
+#[async_trait]
+impl TransportWrite for tokio::net::unix::OwnedWriteHalf {
+    fn supports_fd_passing(&self) -> bool {
+        true
+    }
+
+    async fn write_all_with_fds(&mut self, data: &[u8], fds: &[OwnedFd]) -> io::Result<()> {
+        if fds.is_empty() {
+            self.write_all(data).await
+        } else {
+            crate::shm::send_with_fds(self.as_ref(), data, fds).await
+        }
+    }
+}
+
+// --- TCP ---
+
+impl Transport for tokio::net::TcpStream {
+    type ReadHalf = tokio::net::tcp::OwnedReadHalf;
+    type WriteHalf = tokio::net::tcp::OwnedWriteHalf;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        tokio::net::TcpStream::into_split(self)
+    }
+}
+
+impl TransportRead for tokio::net::tcp::OwnedReadHalf {}
+impl TransportWrite for tokio::net::tcp::OwnedWriteHalf {}
+
+// --- In-memory ---
+
+impl Transport for tokio::io::DuplexStream {
+    type ReadHalf = tokio::io::ReadHalf<tokio::io::DuplexStream>;
+    type WriteHalf = tokio::io::WriteHalf<tokio::io::DuplexStream>;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        tokio::io::split(self)
+    }
+}
+
+impl TransportRead for tokio::io::ReadHalf<tokio::io::DuplexStream> {}
+impl TransportWrite for tokio::io::WriteHalf<tokio::io::DuplexStream> {}
@@ -0,0 +1,136 @@
+//! Frame compression through the framing layer, the handshake, the backend and the child loop.
+//!
+//! Lives in its own test binary because it lowers the process-wide compression threshold.
+#![cfg(all(feature = "zstd", feature = "lz4"))]
+
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+use kameo_child_process::compression::{self, COMPRESSED_FLAG};
+use kameo_child_process::error::PythonExecutionError;
+use kameo_child_process::framing::{LengthPrefixedRead, LengthPrefixedWrite};
+use kameo_child_process::metrics::MetricsReporter;
+use kameo_child_process::{
+    perform_handshake_with_options, ChildActorLoopConfig, ChildProcessMessageHandler, CodecKind, Compression,
+    FrameSizeError, HandshakeOptions, KameoChildProcessMessage, SubprocessIpcBackend, WireFormat,
+};
+
+const THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Doc {
+    body: String,
+}
+
+impl KameoChildProcessMessage for Doc {
+    type Ok = Doc;
+}
+
+#[derive(Clone)]
+struct Echo;
+#[async_trait::async_trait]
+impl ChildProcessMessageHandler<Doc> for Echo {
+    async fn handle_child_message(&mut self, msg: Doc) -> Result<Doc, PythonExecutionError> {
+        Ok(msg)
+    }
+}
+
+fn large_doc() -> Doc {
+    Doc { body: r#"{"key": "value", "n": 12345}, "#.repeat(1000) }
+}
+
+#[tokio::test]
+async fn test_large_frames_are_compressed_and_round_trip() {
+    compression::set_threshold(THRESHOLD);
+    for algorithm in [Compression::Zstd, Compression::Lz4] {
+        let (a, b) = tokio::io::duplex(1 << 20);
+        let mut writer = LengthPrefixedWrite::new(a).with_compression(algorithm);
+        let mut reader = LengthPrefixedRead::new(b).with_compression(algorithm);
+        let (raw_before, wire_before) = MetricsReporter::get_compression_totals();
+        let doc = large_doc();
+        writer.write_msg(&doc).await.unwrap();
+        assert_eq!(reader.read_msg::<Doc>().await.unwrap(), doc, "{algorithm:?}");
+        let (raw_after, wire_after) = MetricsReporter::get_compression_totals();
+        assert!(raw_after - raw_before >= doc.body.len() as u64, "{algorithm:?}");
+        assert!(wire_after - wire_before < (raw_after - raw_before) / 4, "{algorithm:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_small_frames_are_sent_uncompressed() {
+    compression::set_threshold(THRESHOLD);
+    let (a, mut b) = tokio::io::duplex(64 * 1024);
+    let mut writer = LengthPrefixedWrite::new(a).with_compression(Compression::Zstd);
+    writer.write_msg(&Doc { body: "tiny".into() }).await.unwrap();
+    let mut prefix = [0u8; 4];
+    b.read_exact(&mut prefix).await.unwrap();
+    assert_eq!(u32::from_le_bytes(prefix) & COMPRESSED_FLAG, 0);
+}
+
+#[tokio::test]
+async fn test_compressed_frame_over_max_size_is_rejected() {
+    compression::set_threshold(THRESHOLD);
+    let (a, b) = tokio::io::duplex(1 << 20);
+    let mut writer = LengthPrefixedWrite::new(a).with_compression(Compression::Zstd);
+    let mut reader = LengthPrefixedRead::new(b).with_compression(Compression::Zstd).with_max_frame_size(4096);
+    // Fits on the wire, but not once decompressed
+    writer.write_msg(&large_doc()).await.unwrap();
+    let err = reader.read_msg::<Doc>().await.unwrap_err();
+    assert!(matches!(FrameSizeError::from_io(&err), Some(FrameSizeError::Incoming { max: 4096, .. })), "{err}");
+}
+
+#[tokio::test]
+async fn test_handshake_negotiates_compression() {
+    let (mut parent, mut child) = tokio::io::duplex(1024);
+    let offered = HandshakeOptions { codecs: vec![CodecKind::Bincode], compression: vec![Compression::Lz4, Compression::Zstd], token: None };
+    let supported = HandshakeOptions { codecs: CodecKind::ALL.to_vec(), compression: vec![Compression::Zstd], token: None };
+    let (parent_format, child_format) = tokio::join!(
+        perform_handshake_with_options::<Doc>(&mut parent, true, &offered),
+        perform_handshake_with_options::<Doc>(&mut child, false, &supported),
+    );
+    let expected = WireFormat { codec: CodecKind::Bincode, compression: Compression::Zstd };
+    assert_eq!(parent_format.unwrap(), expected);
+    assert_eq!(child_format.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn test_handshake_falls_back_to_uncompressed() {
+    let (mut parent, mut child) = tokio::io::duplex(1024);
+    let offered = HandshakeOptions { codecs: vec![CodecKind::Bincode], compression: vec![Compression::Zstd], token: None };
+    let supported = HandshakeOptions::default();
+    let (parent_format, child_format) = tokio::join!(
+        perform_handshake_with_options::<Doc>(&mut parent, true, &offered),
+        perform_handshake_with_options::<Doc>(&mut child, false, &supported),
+    );
+    assert_eq!(parent_format.unwrap(), WireFormat::default());
+    assert_eq!(child_format.unwrap(), WireFormat::default());
+}
+
+#[tokio::test]
+async fn test_backend_and_child_loop_use_agreed_compression() {
+    use futures::StreamExt;
+    compression::set_threshold(THRESHOLD);
+    tokio::time::timeout(Duration::from_secs(10), async {
+        for algorithm in [Compression::Zstd, Compression::Lz4] {
+            let (parent, child) = tokio::io::duplex(64 * 1024);
+            let format = WireFormat { codec: CodecKind::Bincode, compression: algorithm };
+            let backend = SubprocessIpcBackend::<Doc>::from_transport_with_format(parent, format);
+            let config = ChildActorLoopConfig { codec: format.codec, compression: algorithm, ..Default::default() };
+            let child_task = tokio::spawn(kameo_child_process::run_child_actor_loop::<_, Doc, _>(Echo, child, Some(config)));
+            let (raw_before, _) = MetricsReporter::get_compression_totals();
+            let doc = large_doc();
+            assert_eq!(backend.send(doc.clone()).await.unwrap(), doc);
+            let mut stream = backend.send_stream(doc.clone()).await.unwrap();
+            assert_eq!(stream.next().await.unwrap().unwrap(), doc);
+            assert!(stream.next().await.is_none());
+            let small = Doc { body: "small".into() };
+            assert_eq!(backend.send(small.clone()).await.unwrap(), small);
+            let (raw_after, _) = MetricsReporter::get_compression_totals();
+            // Two requests and two replies went out compressed
+            assert!(raw_after - raw_before >= 4 * doc.body.len() as u64, "{algorithm:?}");
+            backend.shutdown();
+            drop(backend);
+            child_task.await.unwrap().unwrap();
+        }
+    }).await.expect("Test timed out");
+}
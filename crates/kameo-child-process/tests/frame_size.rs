@@ -0,0 +1,92 @@
+//! Max frame size enforcement through the backend and child loop.
+//!
+//! Lives in its own test binary because it lowers the process-wide limit.
+
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use kameo_child_process::{framing, ChildProcessMessageHandler, KameoChildProcessMessage, SubprocessIpcBackend};
+use kameo_child_process::error::PythonExecutionError;
+
+const MAX: usize = 4096;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Blob {
+    data: Vec<u8>,
+    reply_len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BlobOk {
+    data: Vec<u8>,
+}
+
+impl KameoChildProcessMessage for Blob {
+    type Ok = BlobOk;
+}
+
+/// Replies with `reply_len` bytes.
+#[derive(Clone)]
+struct SizedReply;
+#[async_trait::async_trait]
+impl ChildProcessMessageHandler<Blob> for SizedReply {
+    async fn handle_child_message(&mut self, msg: Blob) -> Result<BlobOk, PythonExecutionError> {
+        Ok(BlobOk { data: vec![1u8; msg.reply_len] })
+    }
+}
+
+fn setup() -> (std::sync::Arc<SubprocessIpcBackend<Blob>>, tokio::task::JoinHandle<Result<(), kameo_child_process::ChildProcessLoopError>>) {
+    framing::set_max_frame_size(MAX);
+    let (parent, child) = tokio::io::duplex(64 * 1024);
+    let backend = SubprocessIpcBackend::<Blob>::from_transport(parent);
+    let child_task = tokio::spawn(kameo_child_process::run_child_actor_loop::<_, Blob, _>(SizedReply, child, None));
+    (backend, child_task)
+}
+
+#[tokio::test]
+async fn test_oversized_request_fails_only_that_request() {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        let (backend, _child) = setup();
+        let err = backend.send(Blob { data: vec![0u8; MAX * 2], reply_len: 1 }).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds the maximum frame size"), "{err}");
+        assert_eq!(backend.pending_count(), 0);
+        let ok = backend.send(Blob { data: vec![0u8; 16], reply_len: 3 }).await.unwrap();
+        assert_eq!(ok, BlobOk { data: vec![1u8; 3] });
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_oversized_reply_fails_only_that_request() {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        let (backend, _child) = setup();
+        let err = backend.send(Blob { data: Vec::new(), reply_len: MAX * 2 }).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds the maximum frame size"), "{err}");
+        let ok = backend.send(Blob { data: Vec::new(), reply_len: 3 }).await.unwrap();
+        assert_eq!(ok, BlobOk { data: vec![1u8; 3] });
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_oversized_incoming_frame_tears_down_backend() {
+    use tokio::io::AsyncWriteExt;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        framing::set_max_frame_size(MAX);
+        let (parent, mut child) = tokio::io::duplex(64 * 1024);
+        let backend = SubprocessIpcBackend::<Blob>::from_transport(parent);
+        let pending = {
+            let backend = backend.clone();
+            tokio::spawn(async move { backend.send(Blob { data: Vec::new(), reply_len: 1 }).await })
+        };
+        while backend.pending_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+        // A misbehaving child announces a 4 GiB reply
+        child.write_all(&u32::MAX.to_le_bytes()).await.unwrap();
+        let err = pending.await.unwrap().unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Protocol error"), "{message}");
+        assert!(message.contains(&format!("incoming frame of {} bytes", u32::MAX)), "{message}");
+        // The backend closed its end of the connection
+        let mut buf = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut child, &mut buf).await.unwrap();
+    }).await.expect("Test timed out");
+}
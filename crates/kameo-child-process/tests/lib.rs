@@ -389,3 +389,605 @@ async fn test_streaming_basic() {
     
     trace!(event = "test_complete", name = "test_streaming_basic", "Basic streaming test completed successfully");
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ShmPayload {
+    id: u64,
+    large: kameo_child_process::ShmBytes,
+    small: kameo_child_process::ShmBytes,
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_shm_bytes_sent_as_fd() {
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        let (sock1, sock2) = tokio::net::UnixStream::pair().unwrap();
+        let (_, write_half) = sock1.into_split();
+        let (read_half, _) = sock2.into_split();
+        let mut writer = LengthPrefixedWrite::new(write_half);
+        let mut reader = LengthPrefixedRead::new(read_half);
+
+        let large: Vec<u8> = (0..kameo_child_process::shm::DEFAULT_SHM_THRESHOLD * 2)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let msg = ShmPayload {
+            id: 7,
+            large: large.clone().into(),
+            small: vec![1u8, 2, 3].into(),
+        };
+        writer.write_msg_with_fds(&msg).await.unwrap();
+        // A second frame without fds must still be framed correctly after the first
+        writer.write_msg_with_fds(&DummyMsg { id: 8 }).await.unwrap();
+
+        let received: ShmPayload = reader.read_msg_with_fds().await.unwrap();
+        assert_eq!(received.id, 7);
+        assert!(received.large.is_shared());
+        assert!(!received.small.is_shared());
+        assert_eq!(received.large.to_vec().unwrap(), large);
+        assert_eq!(received.small.to_vec().unwrap(), vec![1u8, 2, 3]);
+        let next: DummyMsg = reader.read_msg_with_fds().await.unwrap();
+        assert_eq!(next.id, 8);
+    }).await.expect("Test timed out");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_unsealed_shm_fd_is_rejected() {
+    use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+    use std::io::Write;
+    use kameo_child_process::CodecKind;
+
+    let large = vec![9u8; kameo_child_process::shm::DEFAULT_SHM_THRESHOLD * 2];
+    let msg = ShmPayload { id: 3, large: large.clone().into(), small: vec![1u8].into() };
+    let (bytes, fds) = kameo_child_process::shm::encode_frame(&msg, CodecKind::Bincode, true).unwrap();
+    assert_eq!(fds.len(), 1);
+
+    // A peer sending a memfd it can still truncate, with the same contents
+    let name = std::ffi::CString::new("unsealed").unwrap();
+    let fd = memfd_create(&name, MemFdCreateFlag::MFD_CLOEXEC | MemFdCreateFlag::MFD_ALLOW_SEALING).unwrap();
+    let mut file = std::fs::File::from(fd);
+    file.write_all(&large).unwrap();
+    let err = kameo_child_process::shm::decode_frame::<ShmPayload>(&bytes, vec![file.into()], CodecKind::Bincode)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("not sealed"), "{err}");
+
+    // The sealed fd the sender made is still accepted
+    let received: ShmPayload = kameo_child_process::shm::decode_frame(&bytes, fds, CodecKind::Bincode).unwrap();
+    assert_eq!(received.large.to_vec().unwrap(), large);
+}
+
+#[derive(Clone)]
+struct EchoChildHandler;
+#[async_trait::async_trait]
+impl kameo_child_process::ChildProcessMessageHandler<DummyParentMsg> for EchoChildHandler {
+    async fn handle_child_message(&mut self, msg: DummyParentMsg) -> Result<DummyParentOk, kameo_child_process::error::PythonExecutionError> {
+        Ok(DummyParentOk { id: msg.id })
+    }
+}
+
+async fn round_trip_over<T>(parent: T, child: T)
+where
+    T: kameo_child_process::Transport,
+{
+    use futures::StreamExt;
+    use kameo_child_process::SubprocessIpcBackend;
+    let backend = SubprocessIpcBackend::<DummyParentMsg>::from_transport(parent);
+    let child_task = tokio::spawn(kameo_child_process::run_child_actor_loop::<_, DummyParentMsg, _>(
+        EchoChildHandler,
+        child,
+        None,
+    ));
+    for id in 0..100u64 {
+        let mut stream = backend.send_stream(DummyParentMsg { id }).await.unwrap();
+        let item = stream.next().await.expect("stream ended without a reply").unwrap();
+        assert_eq!(item, DummyParentOk { id });
+        assert!(stream.next().await.is_none());
+    }
+    backend.shutdown();
+    drop(backend);
+    child_task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_tcp_transport_loopback() {
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (parent, accepted) = tokio::join!(tokio::net::TcpStream::connect(addr), listener.accept());
+        let (child, _) = accepted.unwrap();
+        round_trip_over(parent.unwrap(), child).await;
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_in_memory_duplex_transport() {
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        let (parent, child) = tokio::io::duplex(64 * 1024);
+        round_trip_over(parent, child).await;
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_shm_bytes_inline_without_fd_passing() {
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        let (a, b) = tokio::io::duplex(1024);
+        let (_, write_half) = tokio::io::split(a);
+        let (read_half, _) = tokio::io::split(b);
+        let mut writer = LengthPrefixedWrite::new(write_half);
+        let mut reader = LengthPrefixedRead::new(read_half);
+
+        let large = vec![5u8; kameo_child_process::shm::DEFAULT_SHM_THRESHOLD + 1];
+        let msg = ShmPayload { id: 1, large: large.clone().into(), small: vec![1u8].into() };
+        let (write_res, read_res) = tokio::join!(
+            writer.write_msg_with_fds(&msg),
+            reader.read_msg_with_fds::<ShmPayload>(),
+        );
+        write_res.unwrap();
+        let received = read_res.unwrap();
+        assert!(!received.large.is_shared());
+        assert_eq!(received.large.to_vec().unwrap(), large);
+    }).await.expect("Test timed out");
+}
+
+/// Shared secret of the remote workers in these tests.
+const REMOTE_TOKEN: &str = "remote-test-token";
+
+#[tokio::test]
+async fn test_remote_channels_over_tcp() {
+    use kameo_child_process::{connect_remote_channel, perform_handshake, RemoteChannel, RemoteListener};
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        let mut listener = RemoteListener::bind("127.0.0.1:0", REMOTE_TOKEN).await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let worker = tokio::spawn(async move {
+            let mut request = listener.accept(RemoteChannel::Request).await.unwrap();
+            perform_handshake::<DummyParentMsg>(&mut request, false).await.unwrap();
+            let callback = listener.accept(RemoteChannel::Callback).await.unwrap();
+            (request, callback)
+        });
+        // A stray callback connection before the request must be dropped, not paired
+        let stray = connect_remote_channel(&addr, RemoteChannel::Callback, REMOTE_TOKEN).await.unwrap();
+        let mut request = connect_remote_channel(&addr, RemoteChannel::Request, REMOTE_TOKEN).await.unwrap();
+        perform_handshake::<DummyParentMsg>(&mut request, true).await.unwrap();
+        let _callback = connect_remote_channel(&addr, RemoteChannel::Callback, REMOTE_TOKEN).await.unwrap();
+        let (child_request, _child_callback) = worker.await.unwrap();
+        drop(stray);
+        round_trip_over(request, child_request).await;
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_remote_request_waits_for_current_session() {
+    use kameo_child_process::{connect_remote_channel, perform_handshake, RemoteChannel, RemoteListener};
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        let mut listener = RemoteListener::bind("127.0.0.1:0", REMOTE_TOKEN).await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let mut first = connect_remote_channel(&addr, RemoteChannel::Request, REMOTE_TOKEN).await.unwrap();
+        let worker = tokio::spawn(async move {
+            let mut request = listener.accept(RemoteChannel::Request).await.unwrap();
+            perform_handshake::<DummyParentMsg>(&mut request, false).await.unwrap();
+            (listener, request)
+        });
+        perform_handshake::<DummyParentMsg>(&mut first, true).await.unwrap();
+        let (mut listener, _first_request) = worker.await.unwrap();
+
+        // A second parent connects before the first opens its callback connection
+        let mut second = connect_remote_channel(&addr, RemoteChannel::Request, REMOTE_TOKEN).await.unwrap();
+        let _first_callback = connect_remote_channel(&addr, RemoteChannel::Callback, REMOTE_TOKEN).await.unwrap();
+        listener.accept(RemoteChannel::Callback).await.unwrap();
+
+        // Its request connection was kept, so its handshake completes once it is served
+        let (parent, child) = tokio::join!(
+            perform_handshake::<DummyParentMsg>(&mut second, true),
+            async {
+                let mut request = listener.accept(RemoteChannel::Request).await.unwrap();
+                perform_handshake::<DummyParentMsg>(&mut request, false).await
+            },
+        );
+        parent.unwrap();
+        child.unwrap();
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_silent_remote_connection_does_not_block_others() {
+    use kameo_child_process::{connect_remote_channel, RemoteChannel, RemoteListener};
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        let mut listener = RemoteListener::bind("127.0.0.1:0", REMOTE_TOKEN).await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        // Connected first, but never announces a channel
+        let _silent: Vec<_> = futures::future::join_all((0..3).map(|_| tokio::net::TcpStream::connect(&addr)))
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        let _request = connect_remote_channel(&addr, RemoteChannel::Request, REMOTE_TOKEN).await.unwrap();
+        // Well within the time a silent peer is given to announce itself
+        tokio::time::timeout(Duration::from_secs(1), listener.accept(RemoteChannel::Request))
+            .await
+            .expect("a silent connection held up the accept")
+            .unwrap();
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_remote_connection_needs_the_worker_token() {
+    use kameo_child_process::{connect_remote_channel, RemoteChannel, RemoteListener};
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        let mut listener = RemoteListener::bind("127.0.0.1:0", REMOTE_TOKEN).await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let mut wrong = connect_remote_channel(&addr, RemoteChannel::Request, "not-the-token").await.unwrap();
+        let mut missing = connect_remote_channel(&addr, RemoteChannel::Request, "").await.unwrap();
+        let accepted = tokio::time::timeout(Duration::from_millis(500), listener.accept(RemoteChannel::Request)).await;
+        assert!(accepted.is_err(), "accepted a connection without the token");
+        // Both were closed by the worker
+        for stream in [&mut wrong, &mut missing] {
+            let mut buf = [0u8; 1];
+            assert_eq!(tokio::io::AsyncReadExt::read(stream, &mut buf).await.unwrap(), 0);
+        }
+        let _request = connect_remote_channel(&addr, RemoteChannel::Request, REMOTE_TOKEN).await.unwrap();
+        listener.accept(RemoteChannel::Request).await.unwrap();
+        assert!(RemoteListener::bind("127.0.0.1:0", "").await.is_err(), "bound without a token");
+    }).await.expect("Test timed out");
+}
+
+/// Only serde derives: the request path must not need bincode's `Encode`/`Decode`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum SerdeOnlyMsg {
+    Greet { name: String, tags: Vec<String> },
+    Payload(kameo_child_process::ShmBytes),
+}
+
+impl kameo_child_process::KameoChildProcessMessage for SerdeOnlyMsg {
+    type Ok = SerdeOnlyMsg;
+}
+
+#[derive(Clone)]
+struct SerdeOnlyEcho;
+#[async_trait::async_trait]
+impl kameo_child_process::ChildProcessMessageHandler<SerdeOnlyMsg> for SerdeOnlyEcho {
+    async fn handle_child_message(&mut self, msg: SerdeOnlyMsg) -> Result<SerdeOnlyMsg, kameo_child_process::error::PythonExecutionError> {
+        Ok(msg)
+    }
+}
+
+#[tokio::test]
+async fn test_every_codec_round_trips_serde_only_messages() {
+    use futures::StreamExt;
+    use kameo_child_process::{perform_handshake, perform_handshake_with_codecs, ChildActorLoopConfig, CodecKind, SubprocessIpcBackend};
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        for codec in CodecKind::ALL {
+            let (mut parent, mut child) = tokio::io::duplex(64 * 1024);
+            let offered = [codec];
+            let (parent_codec, child_codec) = tokio::join!(
+                perform_handshake_with_codecs::<SerdeOnlyMsg>(&mut parent, true, &offered),
+                perform_handshake::<SerdeOnlyMsg>(&mut child, false),
+            );
+            assert_eq!(parent_codec.unwrap(), codec);
+            assert_eq!(child_codec.unwrap().codec, codec);
+
+            let backend = SubprocessIpcBackend::<SerdeOnlyMsg>::from_transport_with_codec(parent, codec);
+            let config = ChildActorLoopConfig { codec, ..Default::default() };
+            let child_task = tokio::spawn(kameo_child_process::run_child_actor_loop::<_, SerdeOnlyMsg, _>(
+                SerdeOnlyEcho,
+                child,
+                Some(config),
+            ));
+            let messages = [
+                SerdeOnlyMsg::Greet { name: "snake".into(), tags: vec!["a".into(), "b".into()] },
+                SerdeOnlyMsg::Payload(vec![0u8, 1, 2, 255].into()),
+            ];
+            for msg in messages {
+                let mut stream = backend.send_stream(msg.clone()).await.unwrap();
+                let item = stream.next().await.expect("stream ended without a reply").unwrap();
+                assert_eq!(item, msg, "codec {codec:?}");
+                assert!(stream.next().await.is_none());
+            }
+            backend.shutdown();
+            drop(backend);
+            child_task.await.unwrap().unwrap();
+        }
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_handshake_picks_first_common_codec() {
+    use kameo_child_process::{perform_handshake_with_codecs, CodecKind};
+    init_tracing();
+    let (mut parent, mut child) = tokio::io::duplex(1024);
+    let (parent_codec, child_codec) = tokio::join!(
+        perform_handshake_with_codecs::<DummyParentMsg>(&mut parent, true, &[CodecKind::Json, CodecKind::MessagePack]),
+        perform_handshake_with_codecs::<DummyParentMsg>(&mut child, false, &[CodecKind::Bincode, CodecKind::MessagePack]),
+    );
+    assert_eq!(parent_codec.unwrap(), CodecKind::MessagePack);
+    assert_eq!(child_codec.unwrap(), CodecKind::MessagePack);
+}
+
+#[tokio::test]
+async fn test_handshake_fails_without_common_codec() {
+    use kameo_child_process::{perform_handshake_with_codecs, CodecKind};
+    init_tracing();
+    let (mut parent, mut child) = tokio::io::duplex(1024);
+    let (parent_codec, child_codec) = tokio::join!(
+        perform_handshake_with_codecs::<DummyParentMsg>(&mut parent, true, &[CodecKind::Json]),
+        perform_handshake_with_codecs::<DummyParentMsg>(&mut child, false, &[CodecKind::Bincode]),
+    );
+    assert!(parent_codec.is_err());
+    assert!(child_codec.is_err());
+}
+
+#[tokio::test]
+async fn test_handshake_requires_matching_token() {
+    use kameo_child_process::{perform_handshake_with_options, HandshakeOptions};
+    init_tracing();
+    let expected = HandshakeOptions { token: Some(HandshakeOptions::new_token()), ..Default::default() };
+    for (presented, ok) in [(expected.token.clone(), true), (Some(HandshakeOptions::new_token()), false), (None, false)] {
+        let (mut parent, mut child) = tokio::io::duplex(1024);
+        let child_options = HandshakeOptions { token: presented.clone(), ..HandshakeOptions::supported() };
+        let (parent_format, child_format) = tokio::join!(
+            perform_handshake_with_options::<DummyParentMsg>(&mut parent, true, &expected),
+            perform_handshake_with_options::<DummyParentMsg>(&mut child, false, &child_options),
+        );
+        assert_eq!(parent_format.is_ok(), ok, "{presented:?}");
+        // The child can't tell; the parent drops the connection
+        assert!(child_format.is_ok());
+    }
+}
+
+#[tokio::test]
+async fn test_oversized_incoming_frame_rejected_before_allocating() {
+    use kameo_child_process::FrameSizeError;
+    use tokio::io::AsyncWriteExt;
+    init_tracing();
+    let (mut raw, b) = tokio::io::duplex(1024);
+    let mut reader = LengthPrefixedRead::new(b).with_max_frame_size(1024);
+    raw.write_all(&u32::MAX.to_le_bytes()).await.unwrap();
+    let err = reader.read_msg::<DummyMsg>().await.unwrap_err();
+    assert_eq!(
+        FrameSizeError::from_io(&err),
+        Some(FrameSizeError::Incoming { len: u32::MAX as usize, max: 1024 })
+    );
+}
+
+#[tokio::test]
+async fn test_oversized_outgoing_frame_writes_nothing() {
+    use kameo_child_process::FrameSizeError;
+    init_tracing();
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    let (_, write_half) = tokio::io::split(a);
+    let (read_half, _) = tokio::io::split(b);
+    let mut writer = LengthPrefixedWrite::new(write_half).with_max_frame_size(64);
+    let mut reader = LengthPrefixedRead::new(read_half);
+
+    let big = ShmPayload { id: 1, large: vec![7u8; 1024].into(), small: vec![].into() };
+    let err = writer.write_msg_with_fds(&big).await.unwrap_err();
+    assert!(matches!(FrameSizeError::from_io(&err), Some(FrameSizeError::Outgoing { max: 64, .. })));
+    // The stream is still in sync for the next frame
+    writer.write_msg_with_fds(&DummyMsg { id: 9 }).await.unwrap();
+    let next: DummyMsg = reader.read_msg_with_fds().await.unwrap();
+    assert_eq!(next.id, 9);
+}
+
+/// Write half whose writes always fail, as if the peer had reset the connection.
+struct FailingWrite;
+
+impl tokio::io::AsyncWrite for FailingWrite {
+    fn poll_write(self: std::pin::Pin<&mut Self>, _: &mut std::task::Context<'_>, _: &[u8]) -> std::task::Poll<std::io::Result<usize>> {
+        std::task::Poll::Ready(Err(std::io::ErrorKind::ConnectionReset.into()))
+    }
+    fn poll_flush(self: std::pin::Pin<&mut Self>, _: &mut std::task::Context<'_>) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: std::pin::Pin<&mut Self>, _: &mut std::task::Context<'_>) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+impl kameo_child_process::transport::TransportWrite for FailingWrite {}
+
+#[tokio::test]
+async fn test_write_failure_fails_request_and_closes_backend() {
+    use futures::StreamExt;
+    use kameo_child_process::SubprocessIpcBackend;
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        // Keep the peer open so the reader never sees EOF; only the writer fails
+        let (a, _peer) = tokio::io::duplex(1024);
+        let (read_half, _) = tokio::io::split(a);
+        let backend = SubprocessIpcBackend::<DummyParentMsg>::new(read_half, FailingWrite);
+        let err = backend.send(DummyParentMsg { id: 1 }).await.unwrap_err();
+        assert!(err.to_string().contains("failed to write request"), "{err}");
+        assert_eq!(backend.pending_count(), 0);
+        // Later requests fail straight away with the same reason
+        let err = backend.send(DummyParentMsg { id: 2 }).await.unwrap_err();
+        assert!(err.to_string().contains("failed to write request"), "{err}");
+        let err = match backend.send_stream(DummyParentMsg { id: 3 }).await {
+            Ok(mut stream) => stream.next().await.expect("stream ended without an error").unwrap_err(),
+            Err(e) => e,
+        };
+        assert!(err.to_string().contains("failed to write request"), "{err}");
+        assert_eq!(backend.pending_count(), 0);
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_requests_after_child_exit_fail_immediately() {
+    use kameo_child_process::SubprocessIpcBackend;
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        let (parent, child) = tokio::io::duplex(1024);
+        let backend = SubprocessIpcBackend::<DummyParentMsg>::from_transport(parent);
+        drop(child);
+        let mut attempts = 0;
+        let err = loop {
+            match backend.send(DummyParentMsg { id: 1 }).await {
+                Err(e) if e.to_string().contains("closed by child") => break e,
+                // The write may fail before the reader sees EOF
+                Err(e) if e.to_string().contains("failed to write request") => break e,
+                other if attempts < 100 => {
+                    attempts += 1;
+                    tracing::debug!(?other, "Backend not torn down yet");
+                    tokio::task::yield_now().await;
+                }
+                other => panic!("backend was not torn down: {other:?}"),
+            }
+        };
+        tracing::debug!(error = %err, "Backend torn down");
+        assert_eq!(backend.pending_count(), 0);
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_callback_write_failure_fails_callback_and_closes_channel() {
+    use kameo_child_process::callback::{CallbackHandler, CallbackIpcChild};
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        let (a, _peer) = tokio::io::duplex(1024);
+        let (read_half, _) = tokio::io::split(a);
+        let callback = CallbackIpcChild::<DummyMsg>::new(read_half, FailingWrite);
+        let err = callback.handle(DummyMsg { id: 1 }).await.unwrap_err();
+        assert!(err.to_string().contains("failed to write callback"), "{err}");
+        let err = callback.handle(DummyMsg { id: 2 }).await.unwrap_err();
+        assert!(err.to_string().contains("failed to write callback"), "{err}");
+        assert_eq!(callback.pending_count(), 0);
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_reply_write_failure_closes_callback_connection() {
+    use kameo_child_process::callback::{CallbackError, CallbackHandler, CallbackIpcChild, CallbackReceiver};
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        let (parent, child) = tokio::io::duplex(1024);
+        let (read_half, _) = tokio::io::split(parent);
+        let receiver = CallbackReceiver::<DummyMsg, DummyHandler, _, _>::new(read_half, FailingWrite, DummyHandler);
+        let receiver_task = tokio::spawn(receiver.run());
+        let callback = CallbackIpcChild::<DummyMsg>::from_transport(child);
+        // Neither reply can be written, so both callers hear about it instead of waiting forever
+        let (first, second) = tokio::join!(callback.handle(DummyMsg { id: 1 }), callback.handle(DummyMsg { id: 2 }));
+        for err in [first.unwrap_err(), second.unwrap_err()] {
+            assert!(err.to_string().contains("Callback reply loop exited"), "{err}");
+        }
+        assert_eq!(callback.pending_count(), 0);
+        let result = receiver_task.await.unwrap();
+        assert!(matches!(result, Err(CallbackError::Ipc(ref e)) if e.kind() == std::io::ErrorKind::ConnectionReset), "{result:?}");
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_queued_frames_are_written_on_flush() {
+    use kameo_child_process::WriteBatch;
+    use tokio::io::AsyncReadExt;
+    init_tracing();
+    let (a, mut b) = tokio::io::duplex(64 * 1024);
+    let mut writer = LengthPrefixedWrite::new(a).with_write_batch(WriteBatch { max_frames: 4, ..Default::default() });
+    for id in 0..3 {
+        writer.queue_msg(&DummyMsg { id }).await.unwrap();
+    }
+    assert!(writer.has_queued());
+    let mut byte = [0u8; 1];
+    assert!(tokio::time::timeout(Duration::from_millis(50), b.read_exact(&mut byte)).await.is_err(), "nothing is written before flush");
+    writer.flush().await.unwrap();
+    assert!(!writer.has_queued());
+    // A full batch flushes by itself
+    for id in 3..7 {
+        writer.queue_msg(&DummyMsg { id }).await.unwrap();
+    }
+    assert!(!writer.has_queued());
+    drop(writer);
+    let mut reader = LengthPrefixedRead::new(b);
+    for id in 0..7 {
+        let msg: DummyMsg = reader.read_msg().await.unwrap();
+        assert_eq!(msg.id, id);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_fd_frames_keep_their_place_in_a_batch() {
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        let (sock1, sock2) = tokio::net::UnixStream::pair().unwrap();
+        let (_, write_half) = sock1.into_split();
+        let (read_half, _) = sock2.into_split();
+        let mut writer = LengthPrefixedWrite::new(write_half);
+        let mut reader = LengthPrefixedRead::new(read_half);
+        let large: Vec<u8> = vec![9u8; kameo_child_process::shm::DEFAULT_SHM_THRESHOLD * 2];
+        writer.queue_msg_with_fds(&DummyMsg { id: 1 }).await.unwrap();
+        writer.queue_msg_with_fds(&ShmPayload { id: 2, large: large.clone().into(), small: vec![].into() }).await.unwrap();
+        writer.queue_msg_with_fds(&DummyMsg { id: 3 }).await.unwrap();
+        writer.flush().await.unwrap();
+        assert_eq!(reader.read_msg_with_fds::<DummyMsg>().await.unwrap().id, 1);
+        let payload: ShmPayload = reader.read_msg_with_fds().await.unwrap();
+        assert_eq!(payload.id, 2);
+        assert!(payload.large.is_shared());
+        assert_eq!(payload.large.to_vec().unwrap(), large);
+        assert_eq!(reader.read_msg_with_fds::<DummyMsg>().await.unwrap().id, 3);
+    }).await.expect("Test timed out");
+}
+
+#[tokio::test]
+async fn test_recv_batch_drains_queue_up_to_max_frames() {
+    use kameo_child_process::framing::recv_batch;
+    use kameo_child_process::WriteBatch;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    for i in 1..6 {
+        tx.send(i).unwrap();
+    }
+    let batch = WriteBatch { max_frames: 3, ..Default::default() };
+    assert_eq!(recv_batch(&mut rx, 0, batch).await, vec![0, 1, 2]);
+    assert_eq!(recv_batch(&mut rx, 0, batch).await, vec![0, 3, 4]);
+    // With a latency budget, frames arriving within it join the batch
+    let sender = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        tx.send(6).unwrap();
+    });
+    let lingering = WriteBatch { max_latency: Duration::from_secs(5), ..batch };
+    assert_eq!(recv_batch(&mut rx, 0, lingering).await, vec![0, 5, 6]);
+    sender.await.unwrap();
+}
+
+#[test]
+fn test_write_batch_env_format_round_trips() {
+    use kameo_child_process::WriteBatch;
+    let batch = WriteBatch { max_frames: 16, max_bytes: 4096, max_latency: Duration::from_micros(250) };
+    assert_eq!(batch.to_string(), "16,4096,250");
+    assert_eq!(batch.to_string().parse::<WriteBatch>().unwrap(), batch);
+    assert!("16,4096".parse::<WriteBatch>().is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_requests_over_batched_unix_socket() {
+    use kameo_child_process::SubprocessIpcBackend;
+    init_tracing();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        let (parent, child) = tokio::net::UnixStream::pair().unwrap();
+        let backend = SubprocessIpcBackend::<DummyParentMsg>::from_transport(parent);
+        let child_task = tokio::spawn(kameo_child_process::run_child_actor_loop::<_, DummyParentMsg, _>(
+            EchoChildHandler,
+            child,
+            None,
+        ));
+        let requests = (0..2000u64).map(|id| {
+            let backend = backend.clone();
+            tokio::spawn(async move { (id, backend.send(DummyParentMsg { id }).await) })
+        });
+        for handle in futures::future::join_all(requests).await {
+            let (id, reply) = handle.unwrap();
+            assert_eq!(reply.unwrap(), DummyParentOk { id });
+        }
+        backend.shutdown();
+        drop(backend);
+        child_task.await.unwrap().unwrap();
+    }).await.expect("Test timed out");
+}
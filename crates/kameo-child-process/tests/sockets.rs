@@ -0,0 +1,251 @@
+//! Socket file placement, permissions and cleanup, peer checks, and sockets inherited at spawn.
+//!
+//! Lives in its own test binary because it sets environment variables.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::os::fd::IntoRawFd;
+use kameo_child_process::handshake::{
+    socket_dir_from, take_inherited_socket, unique_socket_path_in, verify_peer, ChildListener, InheritedSocket,
+    SocketAddress, SOCKET_DIR_ENV,
+};
+
+/// A scratch directory removed on drop.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("kameo-test-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_socket_dir_honours_env() {
+    let dir = ScratchDir::new();
+    let runtime = ScratchDir::new();
+    let some = |path: &Path| Some(path.as_os_str().to_owned());
+    assert_eq!(socket_dir_from(some(dir.path()), some(runtime.path())), dir.path());
+    // A directory that doesn't exist falls through to the next candidate
+    let missing = dir.path().join("missing");
+    assert_eq!(socket_dir_from(some(&missing), some(runtime.path())), runtime.path());
+    assert_eq!(socket_dir_from(some(&missing), None), std::env::temp_dir());
+    assert_eq!(socket_dir_from(None, None), std::env::temp_dir());
+}
+
+#[test]
+fn test_socket_names_are_sanitized() {
+    let dir = Path::new("/run/user/1000");
+    let path = unique_socket_path_in(dir, "my_crate::PythonActor<my_crate::Msg, my_crate::Cb>-req");
+    assert_eq!(path.parent(), Some(dir));
+    let name = file_name(&path);
+    assert!(name.starts_with("kameo-PythonActor-Msg-Cb-req-"), "{name}");
+    assert!(name.ends_with(".sock"), "{name}");
+
+    let long = format!("{}::Actor<{}>", "deep::".repeat(20), "Message".repeat(30));
+    let name = file_name(&unique_socket_path_in(dir, &long));
+    assert!(name.len() <= 90, "{name}");
+    assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)), "{name}");
+
+    // Multi-byte names used to be cut mid-character
+    let name = file_name(&unique_socket_path_in(dir, "Актор-ü"));
+    assert!(name.is_ascii(), "{name}");
+}
+
+#[test]
+fn test_socket_address_env_round_trip() {
+    for address in [SocketAddress::Path("/tmp/kameo-a.sock".into()), SocketAddress::Abstract("kameo-b".into())] {
+        assert_eq!(SocketAddress::from_env(&address.to_env()), address);
+    }
+    assert_eq!(SocketAddress::Abstract("x".into()).to_env(), "@x");
+}
+
+#[tokio::test]
+async fn test_socket_file_is_private_and_removed_after_accept() {
+    let dir = ScratchDir::new();
+    let address = SocketAddress::unique("Echo-req", dir.path(), false);
+    let SocketAddress::Path(path) = address.clone() else { unreachable!() };
+    let listener = ChildListener::bind(address.clone()).unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    // Nothing but the socket is left in the directory it was staged next to
+    let entries: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(entries, vec![path.clone()]);
+    let err = ChildListener::bind(address.clone()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+    let (parent, child) = tokio::join!(listener.accept(), address.connect());
+    let (mut parent, mut child) = (parent.unwrap(), child.unwrap());
+    assert!(!path.exists(), "socket file left behind after accept");
+    // The connection outlives the file
+    child.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    parent.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+}
+
+#[tokio::test]
+async fn test_connection_from_unexpected_pid_is_rejected() {
+    let dir = ScratchDir::new();
+    let address = SocketAddress::unique("Echo-req", dir.path(), false);
+    // This process connects, but the listener only wants a (nonexistent) child
+    let listener = ChildListener::bind(address.clone()).unwrap();
+    let accept = tokio::spawn(listener.accept_from(Some(u32::MAX)));
+    let mut intruder = address.connect().await.unwrap();
+    let mut buf = [0u8; 1];
+    let n = tokio::time::timeout(std::time::Duration::from_secs(5), intruder.read(&mut buf)).await;
+    assert!(matches!(n, Ok(Ok(0)) | Ok(Err(_))), "rejected connection was not closed: {n:?}");
+    assert!(!accept.is_finished(), "listener stopped waiting after a rejected connection");
+    accept.abort();
+
+    let address = SocketAddress::unique("Echo-req", dir.path(), false);
+    let listener = ChildListener::bind(address.clone()).unwrap();
+    let (accepted, connected) = tokio::join!(listener.accept_from(Some(std::process::id())), address.connect());
+    verify_peer(&accepted.unwrap(), Some(std::process::id())).unwrap();
+    verify_peer(&connected.unwrap(), None).unwrap();
+}
+
+#[test]
+fn test_socket_file_is_removed_on_drop() {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let _guard = rt.enter();
+    let dir = ScratchDir::new();
+    let address = SocketAddress::unique("Echo-cb", dir.path(), false);
+    let SocketAddress::Path(path) = address.clone() else { unreachable!() };
+    let listener = ChildListener::bind(address).unwrap();
+    assert!(path.exists());
+    drop(listener);
+    assert!(!path.exists(), "socket file left behind after drop");
+}
+
+#[test]
+fn test_overlong_socket_path_is_rejected() {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let _guard = rt.enter();
+    let dir = Path::new("/tmp").join("d".repeat(100));
+    let err = ChildListener::bind(SocketAddress::unique("Echo", &dir, false)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains(SOCKET_DIR_ENV), "{err}");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_abstract_socket_round_trip() {
+    let address = SocketAddress::unique("Echo-req", Path::new("/nonexistent"), true);
+    assert!(matches!(address, SocketAddress::Abstract(_)));
+    let listener = ChildListener::bind(address.clone()).unwrap();
+    let from_env = SocketAddress::from_env(&address.to_env());
+    let (parent, child) = tokio::join!(listener.accept(), from_env.connect());
+    let (mut parent, mut child) = (parent.unwrap(), child.unwrap());
+    parent.write_all(b"pong").await.unwrap();
+    let mut buf = [0u8; 4];
+    child.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"pong");
+}
+
+#[tokio::test]
+async fn test_inherited_socket_reaches_child() {
+    let script = "import os, socket\n\
+s = socket.socket(fileno=int(os.environ['KAMEO_TEST_FD']))\n\
+s.sendall(s.recv(4).upper())\n";
+    let mut cmd = tokio::process::Command::new("python3");
+    cmd.arg("-c").arg(script);
+    let socket = InheritedSocket::pass_to(&mut cmd, "KAMEO_TEST_FD").unwrap();
+    let fd = cmd.as_std().get_envs().find(|(k, _)| *k == "KAMEO_TEST_FD").unwrap().1.unwrap().to_owned();
+
+    // Another process spawned meanwhile must not inherit the child's end
+    let status = tokio::process::Command::new("python3")
+        .arg("-c")
+        .arg(format!("import os; os.fstat({})", fd.to_string_lossy()))
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .unwrap();
+    assert!(!status.success(), "fd leaked into an unrelated child");
+
+    let mut child = cmd.spawn().unwrap();
+    let mut stream = socket.into_stream();
+    stream.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"PING");
+    assert!(child.wait().await.unwrap().success());
+    // Our copy of the child's end is closed, so its exit shows up as EOF
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+}
+
+/// Held by every test that sets environment variables, so they never race each other.
+static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn env_lock() -> std::sync::MutexGuard<'static, ()> {
+    ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Adopt the fd in `env`; the tests only name fds they created and gave up.
+fn take(env: &str) -> std::io::Result<Option<tokio::net::UnixStream>> {
+    // SAFETY: each test hands over an fd it no longer owns, or one that is rejected unadopted
+    unsafe { take_inherited_socket(env) }
+}
+
+#[tokio::test]
+async fn test_take_inherited_socket_adopts_once() {
+    let (ours, theirs) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut adopted = {
+        let _env = env_lock();
+        assert!(take("KAMEO_TEST_UNSET_FD").unwrap().is_none());
+
+        let fd = theirs.into_raw_fd().to_string();
+        std::env::set_var("KAMEO_TEST_ADOPT_FD", &fd);
+        let adopted = take("KAMEO_TEST_ADOPT_FD").unwrap().unwrap();
+        // The variable is gone once adopted, and the fd can't be adopted again
+        assert!(std::env::var_os("KAMEO_TEST_ADOPT_FD").is_none());
+        assert!(take("KAMEO_TEST_ADOPT_FD").unwrap().is_none());
+        std::env::set_var("KAMEO_TEST_ADOPT_FD", &fd);
+        let err = take("KAMEO_TEST_ADOPT_FD").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        std::env::remove_var("KAMEO_TEST_ADOPT_FD");
+        adopted
+    };
+
+    let mut ours = tokio::net::UnixStream::from_std({
+        ours.set_nonblocking(true).unwrap();
+        ours
+    })
+    .unwrap();
+    ours.write_all(b"hi").await.unwrap();
+    let mut buf = [0u8; 2];
+    adopted.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hi");
+}
+
+#[test]
+fn test_take_inherited_socket_rejects_non_sockets() {
+    let _env = env_lock();
+    let file = std::fs::File::open("/dev/null").unwrap();
+    std::env::set_var("KAMEO_TEST_FILE_FD", file.into_raw_fd().to_string());
+    let err = take("KAMEO_TEST_FILE_FD").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    for bad in ["", "zero", "1"] {
+        std::env::set_var("KAMEO_TEST_BAD_FD", bad);
+        let err = take("KAMEO_TEST_BAD_FD").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{bad:?}");
+    }
+    std::env::remove_var("KAMEO_TEST_FILE_FD");
+    std::env::remove_var("KAMEO_TEST_BAD_FD");
+}
@@ -0,0 +1,424 @@
+//! `#[derive(PyConvert)]`.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Field, Fields};
+
+use crate::{apply_field_rule, apply_variant_rule, unraw, ContainerAttrs, FieldAttrs, RenameRule, VariantAttrs};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = ContainerAttrs::parse(&input.attrs)?;
+    let ident = &input.ident;
+    let private = private();
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(::kameo_snake_handler::convert::PyConvert));
+    }
+
+    let (to_python, from_python) = if uses_serde_only(input, &attrs)? {
+        generics.make_where_clause().predicates.push(syn::parse_quote!(
+            Self: #private::serde::Serialize + #private::serde::de::DeserializeOwned
+        ));
+        (quote! { #private::serde_to_python(py, self) }, quote! { #private::serde_from_python(obj) })
+    } else {
+        match &input.data {
+            Data::Struct(data) => expand_struct(&attrs, &unraw(ident), &data.fields)?,
+            Data::Enum(data) => expand_enum(&attrs, &unraw(ident), data)?,
+            Data::Union(_) => return Err(syn::Error::new_spanned(ident, "PyConvert cannot be derived for unions")),
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::kameo_snake_handler::convert::PyConvert for #ident #ty_generics #where_clause {
+            fn to_python(
+                &self,
+                py: #private::pyo3::Python<'_>,
+            ) -> ::kameo_snake_handler::serde_py::Result<#private::pyo3::PyObject> {
+                #to_python
+            }
+
+            fn from_python(
+                obj: &#private::pyo3::Bound<'_, #private::pyo3::PyAny>,
+            ) -> ::kameo_snake_handler::serde_py::Result<Self> {
+                #[allow(unused_variables)]
+                let py = obj.py();
+                #from_python
+            }
+        }
+    })
+}
+
+fn private() -> TokenStream2 {
+    quote! { ::kameo_snake_handler::convert::__private }
+}
+
+/// Whether the type needs serde's own handling of some attribute.
+fn uses_serde_only(input: &DeriveInput, attrs: &ContainerAttrs) -> syn::Result<bool> {
+    if attrs.serde_only || attrs.tag.is_some() || attrs.untagged {
+        return Ok(true);
+    }
+    let fields: Vec<&Field> = match &input.data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => {
+            for variant in &data.variants {
+                if VariantAttrs::parse(&variant.attrs)?.serde_only {
+                    return Ok(true);
+                }
+            }
+            data.variants.iter().flat_map(|variant| &variant.fields).collect()
+        }
+        Data::Union(_) => Vec::new(),
+    };
+    for field in fields {
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        if attrs.flatten || attrs.serde_only {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn expand_struct(attrs: &ContainerAttrs, ident: &str, fields: &Fields) -> syn::Result<(TokenStream2, TokenStream2)> {
+    let private = private();
+    let parsed = parse_fields(fields)?;
+    let values: Vec<TokenStream2> = fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| match &field.ident {
+            Some(ident) => quote! { &self.#ident },
+            None => {
+                let idx = syn::Index::from(idx);
+                quote! { &self.#idx }
+            }
+        })
+        .collect();
+
+    if attrs.transparent {
+        let (kept, kept_attrs) = parsed
+            .iter()
+            .enumerate()
+            .find(|(_, (_, attrs))| !attrs.skip)
+            .map(|(idx, (_, attrs))| (idx, attrs))
+            .ok_or_else(|| syn::Error::new_spanned(fields, "transparent struct without a field"))?;
+        let to_python = to_python_field(kept_attrs, &values[kept]);
+        let read = from_python_value(&parsed[kept].0.ty, kept_attrs);
+        let inits = parsed.iter().enumerate().map(|(idx, (_, attrs))| match idx == kept {
+            true => quote! { (#read)(obj)? },
+            false => skipped_value(attrs),
+        });
+        let construct = construct(&quote! { Self }, fields, inits.collect());
+        return Ok((to_python, quote! { ::core::result::Result::Ok(#construct) }));
+    }
+
+    match fields {
+        Fields::Named(_) => {
+            let name = attrs.rename.clone().unwrap_or_else(|| ident.to_string());
+            let to_python = to_python_named(&parsed, &values, attrs.rename_all);
+            let read = from_python_named(&parsed, attrs.rename_all, fields);
+            Ok((
+                quote! {
+                    #to_python
+                    #private::finish_struct(dict, #name)
+                },
+                quote! {
+                    let fields = &#private::map_fields(obj)?;
+                    ::core::result::Result::Ok(Self #read)
+                },
+            ))
+        }
+        // Newtype structs are passed through, other tuple structs become lists
+        Fields::Unnamed(_) if parsed.len() == 1 => {
+            let (field, attrs) = &parsed[0];
+            let to_python = to_python_field(attrs, &values[0]);
+            let read = from_python_value(&field.ty, attrs);
+            Ok((to_python, quote! { ::core::result::Result::Ok(Self((#read)(obj)?)) }))
+        }
+        Fields::Unnamed(_) => {
+            let expected = format!("tuple struct {ident} with {} elements", kept_count(&parsed));
+            let to_python = to_python_tuple(&parsed, &values);
+            let read = from_python_tuple(&parsed, &expected, fields);
+            Ok((
+                to_python,
+                quote! {
+                    let items = &#private::items(obj)?;
+                    ::core::result::Result::Ok(Self #read)
+                },
+            ))
+        }
+        Fields::Unit => Ok((
+            quote! { ::core::result::Result::Ok(py.None()) },
+            quote! { #private::expected_none(obj).map(|()| Self) },
+        )),
+    }
+}
+
+fn expand_enum(attrs: &ContainerAttrs, ident: &str, data: &syn::DataEnum) -> syn::Result<(TokenStream2, TokenStream2)> {
+    let private = private();
+    let mut to_arms = Vec::new();
+    let mut from_arms = Vec::new();
+    let mut names = Vec::new();
+    for variant in &data.variants {
+        let variant_attrs = VariantAttrs::parse(&variant.attrs)?;
+        let variant_ident = &variant.ident;
+        let path = quote! { Self::#variant_ident };
+        if variant_attrs.skip {
+            let message = format!("{ident}::{}", unraw(variant_ident));
+            to_arms.push(quote! {
+                #path { .. } => ::core::result::Result::Err(#private::skipped_variant(#message)),
+            });
+            continue;
+        }
+        let name = variant_attrs
+            .rename
+            .clone()
+            .unwrap_or_else(|| apply_variant_rule(attrs.rename_all, &unraw(variant_ident)));
+        let key = quote! { #private::pyo3::intern!(py, #name) };
+        names.push(name.clone());
+
+        let parsed = parse_fields(&variant.fields)?;
+        let bindings: Vec<_> = (0..parsed.len()).map(|idx| format_ident!("__field{}", idx)).collect();
+        let values: Vec<TokenStream2> = bindings.iter().map(|binding| quote! { #binding }).collect();
+        let pattern = match &variant.fields {
+            Fields::Named(_) => {
+                let idents = parsed.iter().map(|(field, _)| &field.ident);
+                quote! { #path { #(#idents: #bindings),* } }
+            }
+            Fields::Unnamed(_) => quote! { #path(#(#bindings),*) },
+            Fields::Unit => path.clone(),
+        };
+
+        let (to_python, from_python) = match &variant.fields {
+            Fields::Unit => (
+                quote! { #private::unit_variant(#key) },
+                quote! { ::core::result::Result::Ok(#path) },
+            ),
+            Fields::Unnamed(_) if parsed.len() == 1 => {
+                let (field, field_attrs) = &parsed[0];
+                let value = to_python_field(field_attrs, &values[0]);
+                let read = from_python_value(&field.ty, field_attrs);
+                (
+                    quote! { #private::variant(py, #key, #value?) },
+                    quote! { value.newtype(|value| ::core::result::Result::Ok(#path((#read)(value)?))) },
+                )
+            }
+            Fields::Unnamed(_) => {
+                let expected = format!(
+                    "tuple variant {ident}::{} with {} elements",
+                    unraw(variant_ident),
+                    kept_count(&parsed)
+                );
+                let list = to_python_tuple(&parsed, &values);
+                let read = from_python_tuple(&parsed, &expected, &variant.fields);
+                (
+                    quote! { #private::variant(py, #key, #list?) },
+                    quote! { value.tuple(|items| ::core::result::Result::Ok(#path #read)) },
+                )
+            }
+            Fields::Named(_) => {
+                let rule = variant_attrs.rename_all.or(attrs.rename_all_fields);
+                let dict = to_python_named(&parsed, &values, rule);
+                let read = from_python_named(&parsed, rule, &variant.fields);
+                (
+                    quote! {{
+                        #dict
+                        #private::variant(py, #key, dict)
+                    }},
+                    quote! { value.fields(|fields| ::core::result::Result::Ok(#path #read)) },
+                )
+            }
+        };
+        to_arms.push(quote! { #pattern => #to_python, });
+        from_arms.push(quote! { #name => #from_python, });
+    }
+
+    let to_python = match data.variants.is_empty() {
+        true => quote! { match *self {} },
+        false => quote! { match self { #(#to_arms)* } },
+    };
+    let from_python = quote! {
+        let (name, value) = #private::EnumValue::new(obj)?;
+        match name.as_str() {
+            #(#from_arms)*
+            _ => ::core::result::Result::Err(#private::unknown_variant(&name, &[#(#names),*])),
+        }
+    };
+    Ok((to_python, from_python))
+}
+
+fn parse_fields(fields: &Fields) -> syn::Result<Vec<(&Field, FieldAttrs)>> {
+    fields.iter().map(|field| Ok((field, FieldAttrs::parse(&field.attrs)?))).collect()
+}
+
+fn kept_count(parsed: &[(&Field, FieldAttrs)]) -> usize {
+    parsed.iter().filter(|(_, attrs)| !attrs.skip && !attrs.skip_deserializing).count()
+}
+
+fn field_name(field: &Field, attrs: &FieldAttrs, rule: Option<RenameRule>) -> String {
+    let ident = field.ident.as_ref().expect("named field");
+    attrs.rename.clone().unwrap_or_else(|| apply_field_rule(rule, &unraw(ident)))
+}
+
+/// A path given as a string, or a compile error pointing out that it isn't one.
+fn parse_path(path: &str) -> TokenStream2 {
+    match syn::parse_str::<syn::ExprPath>(path) {
+        Ok(path) => quote! { #path },
+        Err(_) => {
+            let message = format!("invalid path `{path}`");
+            quote! { ::core::compile_error!(#message) }
+        }
+    }
+}
+
+/// A `Result<PyObject>` for the field behind `value`, a reference.
+fn to_python_field(attrs: &FieldAttrs, value: &TokenStream2) -> TokenStream2 {
+    let private = private();
+    let serialize_with = match (&attrs.with, &attrs.serialize_with) {
+        (Some(with), _) => Some(parse_path(&format!("{with}::serialize"))),
+        (None, Some(path)) => Some(parse_path(path)),
+        (None, None) => None,
+    };
+    match serialize_with {
+        Some(path) => quote! { #private::serialize_with(py, |serializer| #path(#value, serializer)) },
+        None if attrs.py_serde || attrs.deserialize_with.is_some() => {
+            quote! { #private::serde_to_python(py, #value) }
+        }
+        None => quote! { ::kameo_snake_handler::convert::PyConvert::to_python(#value, py) },
+    }
+}
+
+/// A closure reading the field from a Python value.
+fn from_python_value(ty: &syn::Type, attrs: &FieldAttrs) -> TokenStream2 {
+    let private = private();
+    let deserialize_with = match (&attrs.with, &attrs.deserialize_with) {
+        (Some(with), _) => Some(parse_path(&format!("{with}::deserialize"))),
+        (None, Some(path)) => Some(parse_path(path)),
+        (None, None) => None,
+    };
+    match deserialize_with {
+        Some(path) => quote! {
+            |value: &#private::pyo3::Bound<'_, #private::pyo3::PyAny>| -> ::kameo_snake_handler::serde_py::Result<#ty> {
+                #private::deserialize_with(value, |deserializer| #path(deserializer))
+            }
+        },
+        None if attrs.py_serde || attrs.serialize_with.is_some() => quote! {
+            |value: &#private::pyo3::Bound<'_, #private::pyo3::PyAny>| -> ::kameo_snake_handler::serde_py::Result<#ty> {
+                #private::serde_from_python(value)
+            }
+        },
+        None => quote! { <#ty as ::kameo_snake_handler::convert::PyConvert>::from_python },
+    }
+}
+
+/// The value of a field that is never read from Python.
+fn skipped_value(attrs: &FieldAttrs) -> TokenStream2 {
+    match &attrs.default {
+        Some(Some(path)) => {
+            let path = parse_path(path);
+            quote! { #path() }
+        }
+        _ => quote! { ::core::default::Default::default() },
+    }
+}
+
+/// The value of a field missing from the Python object, returning early if it may not be.
+fn missing_value(field: &Field, attrs: &FieldAttrs, name: &str) -> TokenStream2 {
+    let private = private();
+    let ty = &field.ty;
+    match &attrs.default {
+        Some(_) => skipped_value(attrs),
+        None if attrs.with.is_some() || attrs.deserialize_with.is_some() => {
+            quote! { return ::core::result::Result::Err(#private::missing_field(#name)) }
+        }
+        None if attrs.py_serde || attrs.serialize_with.is_some() => quote! { #private::serde_missing::<#ty>(#name)? },
+        None => quote! { #private::missing::<#ty>(#name)? },
+    }
+}
+
+/// Fill a dict named `dict` with the serialized fields.
+fn to_python_named(parsed: &[(&Field, FieldAttrs)], values: &[TokenStream2], rule: Option<RenameRule>) -> TokenStream2 {
+    let private = private();
+    let sets = parsed.iter().zip(values).filter(|((_, attrs), _)| !attrs.skip && !attrs.skip_serializing).map(
+        |((field, attrs), value)| {
+            let name = field_name(field, attrs, rule);
+            let converted = to_python_field(attrs, value);
+            let set = quote! {
+                #private::pyo3::types::PyDictMethods::set_item(&dict, #private::pyo3::intern!(py, #name), #converted?)?;
+            };
+            match &attrs.skip_serializing_if {
+                Some(path) => {
+                    let path = parse_path(path);
+                    quote! { if !#path(#value) { #set } }
+                }
+                None => set,
+            }
+        },
+    );
+    quote! {
+        let dict = #private::pyo3::types::PyDict::new(py);
+        #(#sets)*
+    }
+}
+
+/// The fields of a struct or struct variant, read from a dict named `fields`.
+fn from_python_named(parsed: &[(&Field, FieldAttrs)], rule: Option<RenameRule>, fields: &Fields) -> TokenStream2 {
+    let private = private();
+    let values = parsed.iter().map(|(field, attrs)| {
+        if attrs.skip || attrs.skip_deserializing {
+            return skipped_value(attrs);
+        }
+        let name = field_name(field, attrs, rule);
+        let read = from_python_value(&field.ty, attrs);
+        let missing = missing_value(field, attrs, &name);
+        quote! {
+            match #private::field(fields, #private::pyo3::intern!(py, #name), #read)? {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => #missing,
+            }
+        }
+    });
+    construct(&TokenStream2::new(), fields, values.collect())
+}
+
+/// A `Result<PyObject>` list of the serialized elements.
+fn to_python_tuple(parsed: &[(&Field, FieldAttrs)], values: &[TokenStream2]) -> TokenStream2 {
+    let private = private();
+    let items = parsed
+        .iter()
+        .zip(values)
+        .filter(|((_, attrs), _)| !attrs.skip && !attrs.skip_serializing)
+        .map(|((_, attrs), value)| {
+            let converted = to_python_field(attrs, value);
+            quote! { #converted? }
+        });
+    quote! { #private::list(py, [#(#items),*]) }
+}
+
+/// The elements of a tuple struct or tuple variant, read from a slice named `items`.
+fn from_python_tuple(parsed: &[(&Field, FieldAttrs)], expected: &str, fields: &Fields) -> TokenStream2 {
+    let private = private();
+    let mut idx = 0usize;
+    let values = parsed.iter().map(|(field, attrs)| {
+        if attrs.skip || attrs.skip_deserializing {
+            return skipped_value(attrs);
+        }
+        let read = from_python_value(&field.ty, attrs);
+        let value = quote! { #private::element(items, #idx, #expected, #read)? };
+        idx += 1;
+        value
+    });
+    construct(&TokenStream2::new(), fields, values.collect())
+}
+
+/// `path { a: .., b: .. }` or `path(.., ..)`, with `path` left for the caller when empty.
+fn construct(path: &TokenStream2, fields: &Fields, values: Vec<TokenStream2>) -> TokenStream2 {
+    match fields {
+        Fields::Named(_) => {
+            let idents = fields.iter().map(|field| &field.ident);
+            quote! { #path { #(#idents: #values),* } }
+        }
+        Fields::Unnamed(_) => quote! { #path(#(#values),*) },
+        Fields::Unit => path.clone(),
+    }
+}
@@ -0,0 +1,513 @@
+//! Derive macros for `kameo-snake-handler`.
+//!
+//! Use them through the re-exports in `kameo_snake_handler`, which the generated code refers to.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, LitStr, Token};
+
+mod convert;
+
+/// Derive `kameo_snake_handler::stubs::PyStub`, describing how `serde_py` hands the type to
+/// Python.
+///
+/// Reads the `#[serde(...)]` attributes that change the encoding: `rename`, `rename_all`,
+/// `rename_all_fields`, `tag`, `content`, `untagged`, `transparent`, and on fields `skip`,
+/// `default`, `skip_serializing_if`, `flatten` and `with`. A field's Python type can be set by
+/// hand with `#[py_stub(type = "datetime.datetime")]`.
+#[proc_macro_derive(PyStub, attributes(py_stub))]
+pub fn derive_py_stub(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Derive `kameo_snake_handler::convert::PyConvert`, converting straight to and from the Python
+/// values `serde_py` uses for the type.
+///
+/// Follows the same `#[serde(...)]` attributes as serde. Types whose attributes change the
+/// encoding in ways not handled here (internal, adjacent or untagged tagging, `flatten`, `alias`,
+/// `from`/`into` and the like) get an implementation that calls `serde_py` instead. Fields
+/// marked `#[py_convert(serde)]` or `#[serde(with = "...")]` go through `serde_py` on their own.
+#[proc_macro_derive(PyConvert, attributes(py_convert))]
+pub fn derive_py_convert(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    convert::expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = ContainerAttrs::parse(&input.attrs)?;
+    let ident = &input.ident;
+    let base_name = attrs.rename.clone().unwrap_or_else(|| unraw(ident));
+
+    let mut generics = input.generics.clone();
+    let type_params: Vec<_> = generics.type_params().map(|param| param.ident.clone()).collect();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(::kameo_snake_handler::stubs::PyStub));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let name = quote! {
+        ::kameo_snake_handler::stubs::StubModule::generic_name(
+            #base_name,
+            ::std::vec![#(<#type_params as ::kameo_snake_handler::stubs::PyStub>::py_type(stubs)),*],
+        )
+    };
+
+    let body = match &input.data {
+        Data::Struct(data) => expand_struct(&attrs, &data.fields, &name)?,
+        Data::Enum(data) => expand_enum(&attrs, data, &name)?,
+        Data::Union(_) => return Err(syn::Error::new_spanned(ident, "PyStub cannot be derived for unions")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::kameo_snake_handler::stubs::PyStub for #ident #ty_generics #where_clause {
+            fn py_type(stubs: &mut ::kameo_snake_handler::stubs::StubModule) -> ::kameo_snake_handler::stubs::PyType {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_struct(attrs: &ContainerAttrs, fields: &Fields, name: &TokenStream2) -> syn::Result<TokenStream2> {
+    let kept: Vec<&Field> = fields
+        .iter()
+        .filter(|field| FieldAttrs::parse(&field.attrs).map(|attrs| !attrs.skip).unwrap_or(true))
+        .collect();
+    if attrs.transparent {
+        let field = kept
+            .first()
+            .ok_or_else(|| syn::Error::new_spanned(fields, "transparent struct without a field"))?;
+        return field_type(field, &FieldAttrs::parse(&field.attrs)?);
+    }
+    match fields {
+        Fields::Named(_) => {
+            let (fields, flatten) = named_fields(fields, attrs.rename_all)?;
+            Ok(quote! {
+                let name = #name;
+                if stubs.reserve(&name) {
+                    let fields = ::std::vec![#(#fields),*];
+                    let flatten = ::std::vec![#(#flatten),*];
+                    stubs.define_struct(&name, fields, flatten);
+                }
+                ::kameo_snake_handler::stubs::PyType::Named(name)
+            })
+        }
+        // Newtype structs are passed through, other tuple structs become tuples
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            let field = &unnamed.unnamed[0];
+            field_type(field, &FieldAttrs::parse(&field.attrs)?)
+        }
+        Fields::Unnamed(_) => {
+            let types = tuple_types(fields)?;
+            Ok(quote! { ::kameo_snake_handler::stubs::PyType::Tuple(::std::vec![#(#types),*]) })
+        }
+        Fields::Unit => Ok(quote! { ::kameo_snake_handler::stubs::PyType::None }),
+    }
+}
+
+fn expand_enum(attrs: &ContainerAttrs, data: &syn::DataEnum, name: &TokenStream2) -> syn::Result<TokenStream2> {
+    let mut variants = Vec::new();
+    for variant in &data.variants {
+        let variant_attrs = VariantAttrs::parse(&variant.attrs)?;
+        if variant_attrs.skip {
+            continue;
+        }
+        let variant_ident = unraw(&variant.ident);
+        let variant_name = variant_attrs
+            .rename
+            .unwrap_or_else(|| apply_variant_rule(attrs.rename_all, &variant_ident));
+        let kind = match &variant.fields {
+            Fields::Unit => quote! { ::kameo_snake_handler::stubs::VariantKind::Unit },
+            Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                let field = &unnamed.unnamed[0];
+                let ty = field_type(field, &FieldAttrs::parse(&field.attrs)?)?;
+                quote! { ::kameo_snake_handler::stubs::VariantKind::Newtype(#ty) }
+            }
+            Fields::Unnamed(_) => {
+                let types = tuple_types(&variant.fields)?;
+                quote! { ::kameo_snake_handler::stubs::VariantKind::Tuple(::std::vec![#(#types),*]) }
+            }
+            Fields::Named(_) => {
+                let rule = variant_attrs.rename_all.or(attrs.rename_all_fields);
+                let (fields, _) = named_fields(&variant.fields, rule)?;
+                quote! { ::kameo_snake_handler::stubs::VariantKind::Struct(::std::vec![#(#fields),*]) }
+            }
+        };
+        variants.push(quote! {
+            ::kameo_snake_handler::stubs::Variant {
+                name: #variant_name.to_string(),
+                ident: #variant_ident.to_string(),
+                kind: #kind,
+            }
+        });
+    }
+
+    let tagging = match (&attrs.tag, &attrs.content, attrs.untagged) {
+        (_, _, true) => quote! { ::kameo_snake_handler::stubs::Tagging::Untagged },
+        (Some(tag), Some(content), _) => quote! {
+            ::kameo_snake_handler::stubs::Tagging::Adjacent { tag: #tag.to_string(), content: #content.to_string() }
+        },
+        (Some(tag), None, _) => quote! { ::kameo_snake_handler::stubs::Tagging::Internal { tag: #tag.to_string() } },
+        (None, _, _) => quote! { ::kameo_snake_handler::stubs::Tagging::External },
+    };
+
+    Ok(quote! {
+        let name = #name;
+        if stubs.reserve(&name) {
+            let variants = ::std::vec![#(#variants),*];
+            stubs.define_enum(&name, #tagging, variants);
+        }
+        ::kameo_snake_handler::stubs::PyType::Named(name)
+    })
+}
+
+/// `Field` constructors for the kept fields, and the types of flattened fields.
+fn named_fields(fields: &Fields, rule: Option<RenameRule>) -> syn::Result<(Vec<TokenStream2>, Vec<TokenStream2>)> {
+    let mut out = Vec::new();
+    let mut flatten = Vec::new();
+    for field in fields {
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ty = field_type(field, &attrs)?;
+        if attrs.flatten {
+            flatten.push(ty);
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        let name = attrs.rename.clone().unwrap_or_else(|| apply_field_rule(rule, &unraw(ident)));
+        let required = !attrs.optional;
+        out.push(quote! {
+            ::kameo_snake_handler::stubs::Field { name: #name.to_string(), ty: #ty, required: #required }
+        });
+    }
+    Ok((out, flatten))
+}
+
+fn tuple_types(fields: &Fields) -> syn::Result<Vec<TokenStream2>> {
+    fields
+        .iter()
+        .filter_map(|field| match FieldAttrs::parse(&field.attrs) {
+            Ok(attrs) if attrs.skip => None,
+            Ok(attrs) => Some(field_type(field, &attrs)),
+            Err(err) => Some(Err(err)),
+        })
+        .collect()
+}
+
+fn field_type(field: &Field, attrs: &FieldAttrs) -> syn::Result<TokenStream2> {
+    let ty = &field.ty;
+    Ok(match (&attrs.py_type, &attrs.with, attrs.custom_serde) {
+        (Some(py_type), _, _) => quote! { ::kameo_snake_handler::stubs::PyType::External(#py_type.to_string()) },
+        (None, Some(with), _) => quote! { ::kameo_snake_handler::stubs::with_module_type(#with) },
+        (None, None, true) => quote! { ::kameo_snake_handler::stubs::PyType::Any },
+        (None, None, false) => quote! { <#ty as ::kameo_snake_handler::stubs::PyStub>::py_type(stubs) },
+    })
+}
+
+fn unraw(ident: &syn::Ident) -> String {
+    let name = ident.to_string();
+    name.strip_prefix("r#").map(str::to_owned).unwrap_or(name)
+}
+
+#[derive(Default)]
+struct ContainerAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    rename_all_fields: Option<RenameRule>,
+    tag: Option<String>,
+    content: Option<String>,
+    untagged: bool,
+    transparent: bool,
+    /// Uses attributes only serde itself implements, such as `from` or `deny_unknown_fields`
+    serde_only: bool,
+}
+
+impl ContainerAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut out = ContainerAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    out.serde_only |= !meta.input.peek(Token![=]);
+                    out.rename = serialize_name(&meta)?;
+                } else if meta.path.is_ident("rename_all") {
+                    out.serde_only |= !meta.input.peek(Token![=]);
+                    out.rename_all = serialize_name(&meta)?.map(|rule| RenameRule::parse(&meta, &rule)).transpose()?;
+                } else if meta.path.is_ident("rename_all_fields") {
+                    out.rename_all_fields =
+                        serialize_name(&meta)?.map(|rule| RenameRule::parse(&meta, &rule)).transpose()?;
+                } else if meta.path.is_ident("tag") {
+                    out.tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("content") {
+                    out.content = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("untagged") {
+                    out.untagged = true;
+                } else if meta.path.is_ident("transparent") {
+                    out.transparent = true;
+                } else if SERDE_ONLY_CONTAINER.iter().any(|name| meta.path.is_ident(name)) {
+                    out.serde_only = true;
+                    skip_meta(&meta)?;
+                } else {
+                    skip_meta(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Default)]
+struct VariantAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    skip: bool,
+    serde_only: bool,
+}
+
+impl VariantAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut out = VariantAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    out.serde_only |= !meta.input.peek(Token![=]);
+                    out.rename = serialize_name(&meta)?;
+                } else if meta.path.is_ident("rename_all") {
+                    out.serde_only |= !meta.input.peek(Token![=]);
+                    out.rename_all = serialize_name(&meta)?.map(|rule| RenameRule::parse(&meta, &rule)).transpose()?;
+                } else if meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else if meta.path.is_ident("skip_serializing") {
+                    out.skip = true;
+                    out.serde_only = true;
+                } else if SERDE_ONLY_VARIANT.iter().any(|name| meta.path.is_ident(name)) {
+                    out.serde_only = true;
+                    skip_meta(&meta)?;
+                } else {
+                    skip_meta(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    skip: bool,
+    /// The key may be missing: `default`, `skip_serializing` or `skip_serializing_if`
+    optional: bool,
+    flatten: bool,
+    with: Option<String>,
+    /// `serialize_with`/`deserialize_with`, whose output we can't know
+    custom_serde: bool,
+    py_type: Option<String>,
+    skip_serializing: bool,
+    skip_deserializing: bool,
+    skip_serializing_if: Option<String>,
+    /// `Some(None)` for `default`, `Some(Some(path))` for `default = "path"`
+    default: Option<Option<String>>,
+    serialize_with: Option<String>,
+    deserialize_with: Option<String>,
+    /// `#[py_convert(serde)]`: convert through `serde_py` even in `PyConvert`
+    py_serde: bool,
+    serde_only: bool,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut out = FieldAttrs::default();
+        for attr in attrs {
+            if attr.path().is_ident("py_stub") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("type") {
+                        out.py_type = Some(meta.value()?.parse::<LitStr>()?.value());
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `type = \"...\"`"))
+                    }
+                })?;
+            } else if attr.path().is_ident("py_convert") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("serde") {
+                        out.py_serde = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `serde`"))
+                    }
+                })?;
+            } else if attr.path().is_ident("serde") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        out.serde_only |= !meta.input.peek(Token![=]);
+                        out.rename = serialize_name(&meta)?;
+                    } else if meta.path.is_ident("skip") {
+                        out.skip = true;
+                    } else if meta.path.is_ident("default") {
+                        out.optional = true;
+                        out.default = Some(match meta.input.peek(Token![=]) {
+                            true => Some(meta.value()?.parse::<LitStr>()?.value()),
+                            false => None,
+                        });
+                    } else if meta.path.is_ident("skip_serializing") {
+                        out.optional = true;
+                        out.skip_serializing = true;
+                    } else if meta.path.is_ident("skip_serializing_if") {
+                        out.optional = true;
+                        out.skip_serializing_if = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else if meta.path.is_ident("skip_deserializing") {
+                        out.skip_deserializing = true;
+                    } else if meta.path.is_ident("flatten") {
+                        out.flatten = true;
+                    } else if meta.path.is_ident("with") {
+                        out.with = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else if meta.path.is_ident("serialize_with") {
+                        out.custom_serde = true;
+                        out.serialize_with = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else if meta.path.is_ident("deserialize_with") {
+                        out.custom_serde = true;
+                        out.deserialize_with = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else if SERDE_ONLY_FIELD.iter().any(|name| meta.path.is_ident(name)) {
+                        out.serde_only = true;
+                        skip_meta(&meta)?;
+                    } else {
+                        skip_meta(&meta)?;
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Container attributes `PyConvert` leaves to serde.
+const SERDE_ONLY_CONTAINER: &[&str] =
+    &["from", "try_from", "into", "remote", "default", "deny_unknown_fields", "variant_identifier", "field_identifier"];
+
+/// Variant attributes `PyConvert` leaves to serde.
+const SERDE_ONLY_VARIANT: &[&str] =
+    &["skip_deserializing", "other", "alias", "with", "serialize_with", "deserialize_with", "untagged"];
+
+/// Field attributes `PyConvert` leaves to serde.
+const SERDE_ONLY_FIELD: &[&str] = &["alias", "getter"];
+
+/// The value of `name = "..."`, or the `serialize` half of `name(serialize = "...", ...)`.
+fn serialize_name(meta: &ParseNestedMeta) -> syn::Result<Option<String>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse::<LitStr>()?.value()));
+    }
+    let mut name = None;
+    meta.parse_nested_meta(|inner| {
+        let value = inner.value()?.parse::<LitStr>()?.value();
+        if inner.path.is_ident("serialize") {
+            name = Some(value);
+        }
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+/// Consume an attribute we don't care about, whatever its shape.
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        content.parse::<TokenStream2>()?;
+    }
+    Ok(())
+}
+
+/// serde's `rename_all` rules.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(meta: &ParseNestedMeta, rule: &str) -> syn::Result<Self> {
+        Ok(match rule {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return Err(meta.error(format!("unknown rename rule `{rule}`"))),
+        })
+    }
+}
+
+/// Rename a `PascalCase` variant name the way serde does.
+fn apply_variant_rule(rule: Option<RenameRule>, variant: &str) -> String {
+    let snake = || {
+        let mut snake = String::new();
+        for (i, ch) in variant.char_indices() {
+            if i > 0 && ch.is_uppercase() {
+                snake.push('_');
+            }
+            snake.push(ch.to_ascii_lowercase());
+        }
+        snake
+    };
+    match rule {
+        None | Some(RenameRule::Pascal) => variant.to_string(),
+        Some(RenameRule::Lower) => variant.to_ascii_lowercase(),
+        Some(RenameRule::Upper) => variant.to_ascii_uppercase(),
+        Some(RenameRule::Camel) => lower_first(variant),
+        Some(RenameRule::Snake) => snake(),
+        Some(RenameRule::ScreamingSnake) => snake().to_ascii_uppercase(),
+        Some(RenameRule::Kebab) => snake().replace('_', "-"),
+        Some(RenameRule::ScreamingKebab) => snake().to_ascii_uppercase().replace('_', "-"),
+    }
+}
+
+/// Rename a `snake_case` field name the way serde does.
+fn apply_field_rule(rule: Option<RenameRule>, field: &str) -> String {
+    let pascal = || {
+        let mut pascal = String::new();
+        let mut capitalize = true;
+        for ch in field.chars() {
+            if ch == '_' {
+                capitalize = true;
+            } else if capitalize {
+                pascal.push(ch.to_ascii_uppercase());
+                capitalize = false;
+            } else {
+                pascal.push(ch);
+            }
+        }
+        pascal
+    };
+    match rule {
+        None | Some(RenameRule::Lower) | Some(RenameRule::Snake) => field.to_string(),
+        Some(RenameRule::Upper) | Some(RenameRule::ScreamingSnake) => field.to_ascii_uppercase(),
+        Some(RenameRule::Pascal) => pascal(),
+        Some(RenameRule::Camel) => lower_first(&pascal()),
+        Some(RenameRule::Kebab) => field.replace('_', "-"),
+        Some(RenameRule::ScreamingKebab) => field.to_ascii_uppercase().replace('_', "-"),
+    }
+}
+
+fn lower_first(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map(|first| first.to_lowercase().chain(chars).collect()).unwrap_or_default()
+}
@@ -0,0 +1,79 @@
+//! A binary whose `main` comes from `setup_python_subprocess_system!`.
+//!
+//! The parent spawns itself as a child running `PythonActor<Greet, Notice>` and asks it once:
+//!
+//! ```text
+//! cargo run --example macro_entrypoint -- <python_path> <module> <function>
+//! ```
+//!
+//! The async Python function receives `{"name": ...}` and returns a string. It may report progress
+//! with `await kameo.callback_handle(py_msg={"text": ...})`.
+
+use kameo_child_process::callback::CallbackHandler;
+use kameo_child_process::KameoChildProcessMessage;
+use kameo_snake_handler::{PythonChildProcessBuilder, PythonConfig, PythonExecutionError, SyncExecution};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Greet {
+    name: String,
+}
+
+impl KameoChildProcessMessage for Greet {
+    type Ok = String;
+}
+
+/// Sent from Python through `kameo.callback_handle`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Notice {
+    text: String,
+}
+
+#[derive(Clone)]
+struct PrintNotices;
+
+#[async_trait::async_trait]
+impl CallbackHandler<Notice> for PrintNotices {
+    async fn handle(&self, notice: Notice) -> Result<(), PythonExecutionError> {
+        println!("notice: {}", notice.text);
+        Ok(())
+    }
+}
+
+kameo_snake_handler::setup_python_subprocess_system! {
+    actor = (Greet, Notice),
+    child_init = {{
+        kameo_child_process::RuntimeConfig {
+            flavor: kameo_child_process::RuntimeFlavor::MultiThread,
+            worker_threads: Some(2),
+        }
+    }},
+    parent_init = {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let [python_path, module, function] = &args[..] else {
+            eprintln!("Usage: macro_entrypoint <python_path> <module> <function>");
+            return Ok(());
+        };
+        let config = PythonConfig {
+            python_path: vec![python_path.clone()],
+            module_name: module.clone(),
+            function_name: function.clone(),
+            env_vars: vec![],
+            is_async: true,
+            module_path: format!("{python_path}/{module}.py"),
+            sync_execution: SyncExecution::default(),
+            schema: None,
+        };
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+        runtime.block_on(async {
+            let pool = PythonChildProcessBuilder::<Greet, Notice>::new(config)
+                .with_callback_handler(PrintNotices)
+                .spawn_pool(1, None)
+                .await?;
+            let reply = pool.get_actor().ask(Greet { name: "kameo".to_string() }).await?;
+            println!("{reply:?}");
+            pool.shutdown().await;
+            Ok::<(), Box<dyn std::error::Error>>(())
+        })?
+    }
+}
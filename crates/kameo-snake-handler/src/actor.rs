@@ -12,10 +12,15 @@ use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 use tracing_futures::Instrument;
+use std::any::Any;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::pin::Pin;
 use std::future::Future;
+use tokio::sync::Semaphore;
+use once_cell::sync::OnceCell;
+
+use crate::convert::Converters;
 
 
 /// Configuration for Python subprocess execution.
@@ -46,6 +51,8 @@ use std::future::Future;
 ///     ],
 ///     is_async: true,  // For async generators
 ///     module_path: "python/my_module.py".to_string(),
+///     sync_execution: SyncExecution::Inline,
+///     schema: None,
 /// };
 /// ```
 /// 
@@ -77,6 +84,69 @@ pub struct PythonConfig {
     pub is_async: bool,
     /// Path to the Python module file (for error reporting)
     pub module_path: String,
+    /// Where sync (non-async) Python functions are executed in the child
+    #[serde(default)]
+    pub sync_execution: SyncExecution,
+    /// JSON Schemas for the message and reply types. When set, the child exposes them as
+    /// `kameo.schema` and validates every reply before deserializing it
+    #[serde(default)]
+    #[bincode(with_serde)]
+    pub schema: Option<crate::MessageSchema>,
+}
+
+/// Execution strategy for sync Python functions in the child process.
+///
+/// Async functions are always driven through `into_future` on the child's Tokio
+/// runtime; this setting only affects functions with `is_async: false`.
+///
+/// - `Inline` calls the function directly on the Tokio worker that received the
+///   request. Cheap for fast handlers, but a slow handler blocks that worker and
+///   can starve the IPC reader loop.
+/// - `Blocking` moves each call onto Tokio's blocking thread pool, with at most
+///   `max_concurrency` calls in flight. Further requests wait for a permit
+///   without occupying a worker thread.
+/// - `SubInterpreters` hosts `interpreters` isolated sub-interpreters (PEP 684,
+///   one GIL each) in the child and dispatches calls to them in parallel through
+///   `concurrent.futures.InterpreterPoolExecutor`. Requires Python 3.14+; on older
+///   versions the child fails at startup.
+///
+/// ## Sub-interpreter limitations
+///
+/// Each sub-interpreter imports the handler module itself (using the main
+/// interpreter's `sys.path`), so module state is not shared between them.
+/// Messages and replies cross interpreters by pickling, so they must be plain
+/// data, which is what `serde_py` produces. The injected `kameo` module (and so
+/// `kameo.callback_handle`) exists only in the main interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Encode, Decode)]
+pub enum SyncExecution {
+    /// Call sync functions directly on the Tokio worker thread
+    #[default]
+    Inline,
+    /// Run sync functions on the blocking thread pool
+    Blocking {
+        /// Maximum number of sync calls executing at once; must be at least 1
+        max_concurrency: usize,
+    },
+    /// Run sync functions in a pool of isolated sub-interpreters
+    SubInterpreters {
+        /// Number of sub-interpreters, and so of sync calls executing at once; must be at least 1
+        interpreters: usize,
+    },
+}
+
+impl SyncExecution {
+    /// Reject a pool that could never run a call.
+    pub(crate) fn validate(self) -> Result<(), String> {
+        match self {
+            SyncExecution::Blocking { max_concurrency: 0 } => {
+                Err("SyncExecution::Blocking needs max_concurrency of at least 1".to_string())
+            }
+            SyncExecution::SubInterpreters { interpreters: 0 } => {
+                Err("SyncExecution::SubInterpreters needs at least 1 interpreter".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Kameo actor for Python subprocess communication with unified streaming support.
@@ -132,7 +202,7 @@ pub struct PythonConfig {
 /// ```
 pub struct PythonActor<M, E>
 where
-    E: std::fmt::Debug + Send + Sync + 'static + bincode::Encode + bincode::Decode<()>,
+    E: std::fmt::Debug + Send + Sync + 'static + serde::Serialize + serde::de::DeserializeOwned,
 {
     /// Handler for Python function execution
     handler: PythonMessageHandler,
@@ -142,40 +212,79 @@ where
     _phantom: std::marker::PhantomData<(M, E)>,
 }
 
-#[derive(Debug)]
+/// Child-side handler that calls the configured Python function.
+///
+/// The function reference is shared behind an `Arc`, so cloning a handler per
+/// request never needs to attach to the interpreter. On free-threaded builds
+/// this keeps the hot path free of any global serialisation point.
+#[derive(Debug, Clone)]
 pub struct PythonMessageHandler {
-    pub py_function: Py<PyAny>,
+    pub py_function: Arc<Py<PyAny>>,
     pub config: PythonConfig,
+    /// Permits bounding concurrent sync calls off the Tokio workers,
+    /// shared between all clones of this handler
+    blocking_permits: Option<Arc<Semaphore>>,
+    /// `InterpreterPoolExecutor` for `SyncExecution::SubInterpreters`, created by
+    /// [`PythonMessageHandler::start_interpreter_pool`] or on first use
+    interpreter_pool: Option<Arc<OnceCell<Py<PyAny>>>>,
+    /// `Converters<M>` for the message type, if not `serde_py`
+    converters: Option<Arc<dyn Any + Send + Sync>>,
 }
 
 impl PythonMessageHandler {
-    pub fn clone_with_gil(&self) -> Self {
-        Python::with_gil(|py| Self {
-            py_function: self.py_function.clone_ref(py),
-            config: self.config.clone(),
-        })
+    /// The builder and child startup reject a pool size of 0; a handler built directly
+    /// with one gets a pool of 1 rather than one that deadlocks.
+    pub fn new(py_function: Py<PyAny>, config: PythonConfig) -> Self {
+        let (blocking_permits, interpreter_pool) = match config.sync_execution {
+            SyncExecution::Inline => (None, None),
+            SyncExecution::Blocking { max_concurrency } => {
+                (Some(Arc::new(Semaphore::new(max_concurrency.max(1)))), None)
+            }
+            SyncExecution::SubInterpreters { interpreters } => (
+                Some(Arc::new(Semaphore::new(interpreters.max(1)))),
+                Some(Arc::new(OnceCell::new())),
+            ),
+        };
+        Self {
+            py_function: Arc::new(py_function),
+            config,
+            blocking_permits,
+            interpreter_pool,
+            converters: None,
+        }
     }
-}
 
-impl Clone for PythonMessageHandler {
-    fn clone(&self) -> Self {
-        Python::with_gil(|py| Self {
-            py_function: self.py_function.clone_ref(py),
-            config: self.config.clone(),
-        })
+    /// Convert `M` messages and their replies with `converters` instead of `serde_py`.
+    pub fn with_converters<M: KameoChildProcessMessage>(mut self, converters: Converters<M>) -> Self {
+        self.converters = Some(Arc::new(converters));
+        self
+    }
+
+    /// Create the sub-interpreter pool now if `SyncExecution::SubInterpreters` is configured,
+    /// so a Python without `InterpreterPoolExecutor` fails once at startup rather than on
+    /// every request.
+    pub fn start_interpreter_pool(&self, py: Python<'_>) -> Result<(), PythonExecutionError> {
+        if let (Some(pool), SyncExecution::SubInterpreters { interpreters }) =
+            (&self.interpreter_pool, self.config.sync_execution)
+        {
+            pool.get_or_try_init(|| create_interpreter_pool(py, interpreters.max(1)))?;
+        }
+        Ok(())
+    }
+
+    /// Equivalent to `clone`; kept for callers written against the GIL-bound handler.
+    pub fn clone_with_gil(&self) -> Self {
+        self.clone()
     }
 }
 
 impl<M, E> PythonActor<M, E>
 where
-    E: std::fmt::Debug + Send + Sync + 'static + bincode::Encode + bincode::Decode<()>,
+    E: std::fmt::Debug + Send + Sync + 'static + serde::Serialize + serde::de::DeserializeOwned,
 {
     pub fn new(config: PythonConfig, py_function: Py<PyAny>) -> Self {
         tracing::debug!("Storing reference to Python function in handler: {:?}", py_function);
-        let handler = PythonMessageHandler {
-            py_function,
-            config,
-        };
+        let handler = PythonMessageHandler::new(py_function, config);
         Self {
             handler,
             concurrent_tasks: Arc::new(AtomicUsize::new(0)),
@@ -184,11 +293,29 @@ where
     }
 }
 
+impl<M, E> PythonActor<M, E>
+where
+    M: KameoChildProcessMessage,
+    E: std::fmt::Debug + Send + Sync + 'static + serde::Serialize + serde::de::DeserializeOwned,
+{
+    /// Convert messages and replies with `converters`, e.g. [`Converters::py_convert`] for
+    /// types deriving [`PyConvert`](crate::PyConvert).
+    pub fn with_converters(mut self, converters: Converters<M>) -> Self {
+        self.handler = self.handler.with_converters(converters);
+        self
+    }
+
+    /// See [`PythonMessageHandler::start_interpreter_pool`].
+    pub fn start_interpreter_pool(&self, py: Python<'_>) -> Result<(), PythonExecutionError> {
+        self.handler.start_interpreter_pool(py)
+    }
+}
+
 #[async_trait]
 impl<M, E> Actor for PythonActor<M, E>
 where
     M: KameoChildProcessMessage + Send + Sync + 'static,
-    E: std::fmt::Debug + Send + Sync + 'static + bincode::Encode + bincode::Decode<()>,
+    E: std::fmt::Debug + Send + Sync + 'static + serde::Serialize + serde::de::DeserializeOwned,
 {
     type Error = PythonExecutionError;
     #[allow(refining_impl_trait)]
@@ -218,7 +345,7 @@ where
 impl<M, E> Message<M> for PythonActor<M, E>
 where
     M: KameoChildProcessMessage + Send + Sync + 'static,
-    E: std::fmt::Debug + Send + Sync + 'static + bincode::Encode + bincode::Decode<()>,
+    E: std::fmt::Debug + Send + Sync + 'static + serde::Serialize + serde::de::DeserializeOwned,
 {
     type Reply = kameo::reply::DelegatedReply<Result<M::Ok, PythonExecutionError>>;
     #[tracing::instrument(skip(self, ctx, message), fields(actor_type = "PythonActor", message_type = std::any::type_name::<M>()), parent = tracing::Span::current())]
@@ -252,7 +379,7 @@ where
 impl<M, E> RuntimeAware for PythonActor<M, E>
 where
     M: KameoChildProcessMessage + Send + Sync + 'static,
-    E: std::fmt::Debug + Send + Sync + 'static + bincode::Encode + bincode::Decode<()>,
+    E: std::fmt::Debug + Send + Sync + 'static + serde::Serialize + serde::de::DeserializeOwned,
 {
     async fn init_with_runtime(self) -> Result<Self, Self::Error>
     where
@@ -276,26 +403,21 @@ pub async fn child_process_main_with_python_actor<M, E>(
 ) -> Result<(), Box<dyn std::error::Error>>
 where
     M: KameoChildProcessMessage + Send + Sync + 'static,
-    E: std::fmt::Debug + Send + Sync + 'static + bincode::Encode + bincode::Decode<()>,
+    E: std::fmt::Debug + Send + Sync + 'static + serde::Serialize + serde::de::DeserializeOwned,
 {
-    // Set up telemetry for child process
-    use crate::telemetry::{build_subscriber_with_otel_and_fmt_async_with_config, TelemetryExportConfig};
-    let (subscriber, _guard) = build_subscriber_with_otel_and_fmt_async_with_config(
-        TelemetryExportConfig {
-            otlp_enabled: true,
-            stdout_enabled: true,
-            metrics_enabled: true,
-        }
-    ).await;
-    tracing::subscriber::set_global_default(subscriber).expect("set global");
-    tracing::info!("Child process telemetry initialized");
-    
+    let _guard = init_child_telemetry().await;
+
     use kameo_child_process::{perform_handshake, run_child_actor_loop};
     tracing::info!("child_process_main_with_python_actor: about to handshake");
     let mut conn = request_conn;
-    perform_handshake::<M>(&mut conn, false).await?;
-    tracing::info!("running child actor loop");
-    match run_child_actor_loop::<_, M>(actor.handler.clone_with_gil(), conn, config).await {
+    let format = perform_handshake::<M>(&mut conn, false).await?;
+    tracing::info!(?format, "running child actor loop");
+    let config = kameo_child_process::ChildActorLoopConfig {
+        codec: format.codec,
+        compression: format.compression,
+        ..config.unwrap_or_default()
+    };
+    match run_child_actor_loop::<_, M, _>(actor.handler.clone_with_gil(), conn, Some(config)).await {
         Ok(()) => {
             tracing::info!("Child process exited cleanly (no process::exit). Returning from child_process_main_with_python_actor.");
             Ok(())
@@ -307,6 +429,94 @@ where
     }
 }
 
+/// Set up telemetry for a child or worker process; keep the guard alive for the process lifetime.
+async fn init_child_telemetry() -> crate::telemetry::OtelGuard {
+    use crate::telemetry::{build_subscriber_with_otel_and_fmt_async_with_config, TelemetryExportConfig};
+    let (subscriber, guard) = build_subscriber_with_otel_and_fmt_async_with_config(
+        TelemetryExportConfig {
+            otlp_enabled: true,
+            stdout_enabled: true,
+            metrics_enabled: true,
+        }
+    ).await;
+    tracing::subscriber::set_global_default(subscriber).expect("set global");
+    tracing::info!("Child process telemetry initialized");
+    guard
+}
+
+/// Run a Python actor as a standalone worker daemon listening on `addr` over TCP.
+///
+/// Parents connect with [`crate::PythonChildProcessBuilder::connect_remote`]. Sessions are
+/// served one at a time: the worker accepts a request connection, performs the handshake,
+/// accepts the matching callback connection, then serves requests until the parent
+/// disconnects. `on_session` is called with each session's callback handle before it starts.
+///
+/// Parents must present the shared secret in `KAMEO_HANDSHAKE_TOKEN`, which the worker
+/// refuses to start without; the worker presents it back in the handshake.
+#[instrument(skip(actor, on_session), name = "serve_remote_worker", parent = tracing::Span::current())]
+pub async fn serve_remote_worker<M, E>(
+    addr: &str,
+    actor: PythonActor<M, E>,
+    on_session: impl Fn(kameo_child_process::callback::CallbackHandle<E>),
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    M: KameoChildProcessMessage + Send + Sync + 'static,
+    E: std::fmt::Debug + Send + Sync + 'static + serde::Serialize + serde::de::DeserializeOwned,
+{
+    use kameo_child_process::callback::CallbackIpcChild;
+    use kameo_child_process::{perform_handshake, run_child_actor_loop, RemoteChannel, RemoteListener};
+    let _guard = init_child_telemetry().await;
+    let token = std::env::var(kameo_child_process::HANDSHAKE_TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| format!("Remote workers need a shared secret in {}", kameo_child_process::HANDSHAKE_TOKEN_ENV))?;
+    let mut listener = RemoteListener::bind(addr, &token).await?;
+    tracing::info!(addr = %listener.local_addr()?, "Remote worker listening");
+    loop {
+        let mut request_conn = match listener.accept(RemoteChannel::Request).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to set up request connection, waiting for next parent");
+                continue;
+            }
+        };
+        let format = match perform_handshake::<M>(&mut request_conn, false).await {
+            Ok(format) => format,
+            Err(e) => {
+                tracing::warn!(error = ?e, "Remote handshake failed, waiting for next parent");
+                continue;
+            }
+        };
+        let callback_conn = match tokio::time::timeout(
+            std::time::Duration::from_secs(30),
+            listener.accept(RemoteChannel::Callback),
+        )
+        .await
+        {
+            Ok(Ok(conn)) => conn,
+            Ok(Err(e)) => {
+                tracing::warn!(error = %e, "Failed to set up callback connection, waiting for next parent");
+                continue;
+            }
+            Err(_) => {
+                tracing::warn!("Parent did not open a callback connection, waiting for next parent");
+                continue;
+            }
+        };
+        on_session(CallbackIpcChild::<E>::from_transport(callback_conn));
+        tracing::info!("Remote session started");
+        let config = kameo_child_process::ChildActorLoopConfig {
+            codec: format.codec,
+            compression: format.compression,
+            ..Default::default()
+        };
+        match run_child_actor_loop::<_, M, _>(actor.handler.clone_with_gil(), request_conn, Some(config)).await {
+            Ok(()) => tracing::info!("Remote session ended"),
+            Err(e) => tracing::warn!(error = ?e, "Remote session ended with error"),
+        }
+    }
+}
+
 #[async_trait]
 impl<M> ChildProcessMessageHandler<M> for PythonMessageHandler
 where
@@ -337,7 +547,8 @@ impl PythonMessageHandler {
         
         let is_async = self.config.is_async;
         let function_name = self.config.function_name.clone();
-        let py_function = Python::with_gil(|py| self.py_function.clone_ref(py));
+        let py_function = self.py_function.clone();
+        let converters = self.converters.as_ref().and_then(|c| c.downcast_ref::<Converters<M>>());
         
         // Serialize Rust message to Python object
         let py_msg = {
@@ -346,7 +557,10 @@ impl PythonMessageHandler {
                 message_type = std::any::type_name::<M>()
             );
             async {
-                Python::with_gil(|py| crate::serde_py::to_pyobject(py, &message))
+                Python::with_gil(|py| match converters {
+                    Some(converters) => (converters.message)(py, &message),
+                    None => crate::serde_py::to_pyobject(py, &message),
+                })
             }.instrument(serialize_span).await
         };
         
@@ -411,19 +625,39 @@ impl PythonMessageHandler {
             
             async {
                 tracing::debug!("Calling sync Python function: {}", function_name);
-                let result = Python::with_gil(|py| {
-                    let py_func = py_function.bind(py);
-                    match py_func.call1((py_msg,)) {
-                        Ok(result) => Ok(result.into()),
-                        Err(e) => {
-                            tracing::error!(event = "call_error", function = %function_name, error = %e, "Failed to call sync Python function");
-                            Err(PythonExecutionError::CallError {
-                                function: function_name.clone(),
-                                message: e.to_string(),
-                            })
-                        },
+                let result = match &self.blocking_permits {
+                    None => call_sync_python(&py_function, py_msg, &function_name),
+                    Some(permits) => {
+                        let _permit = permits.clone().acquire_owned().await.map_err(|e| {
+                            PythonExecutionError::ExecutionError {
+                                message: format!("Blocking pool permits closed: {e}"),
+                            }
+                        })?;
+                        let function_name = function_name.clone();
+                        let interpreter_pool = self.interpreter_pool.clone();
+                        let interpreters = match self.config.sync_execution {
+                            SyncExecution::SubInterpreters { interpreters } => interpreters.max(1),
+                            _ => 1,
+                        };
+                        tokio::task::spawn_blocking(move || match interpreter_pool {
+                            None => call_sync_python(&py_function, py_msg, &function_name),
+                            Some(pool) => call_subinterpreter_python(
+                                &pool,
+                                interpreters,
+                                &py_function,
+                                py_msg,
+                                &function_name,
+                            ),
+                        })
+                        .await
+                        .map_err(|e| {
+                            tracing::error!(event = "blocking_join_error", error = %e, "Blocking Python call did not complete");
+                            PythonExecutionError::ExecutionError {
+                                message: format!("Blocking Python call did not complete: {e}"),
+                            }
+                        })?
                     }
-                });
+                };
                 
                 let result = match result {
                     Ok(obj) => obj,
@@ -443,11 +677,17 @@ impl PythonMessageHandler {
             async {
                 Python::with_gil(|py| {
                     let bound = py_output.bind(py);
-                    crate::serde_py::from_pyobject(bound).map_err(|e| {
-                        PythonExecutionError::DeserializationError {
-                            message: e.to_string(),
+                    if let Some(schema) = &self.config.schema {
+                        let violations = schema.validate_reply(bound);
+                        if !violations.is_empty() {
+                            return Err(PythonExecutionError::SchemaError { what: "reply".to_string(), violations });
                         }
-                    })
+                    }
+                    match converters {
+                        Some(converters) => (converters.reply)(bound),
+                        None => crate::serde_py::from_pyobject(bound),
+                    }
+                    .map_err(|e| e.into_execution_error("reply"))
                 })
             }.instrument(deserialize_span).await
         };
@@ -464,3 +704,156 @@ impl PythonMessageHandler {
         }
     }
 }
+
+/// Call a sync Python function with a single argument, taking the GIL on the current thread.
+fn call_sync_python(
+    py_function: &Py<PyAny>,
+    py_msg: PyObject,
+    function_name: &str,
+) -> Result<PyObject, PythonExecutionError> {
+    Python::with_gil(|py| {
+        let py_func = py_function.bind(py);
+        match py_func.call1((py_msg,)) {
+            Ok(result) => Ok(result.into()),
+            Err(e) => {
+                tracing::error!(event = "call_error", function = %function_name, error = %e, "Failed to call sync Python function");
+                Err(PythonExecutionError::CallError {
+                    function: function_name.to_string(),
+                    message: e.to_string(),
+                })
+            }
+        }
+    })
+}
+
+/// Call a sync Python function in one of the pool's sub-interpreters and wait for its result.
+///
+/// The waiting thread holds no GIL while the sub-interpreter runs, so calls in
+/// different sub-interpreters proceed in parallel.
+fn call_subinterpreter_python(
+    pool: &OnceCell<Py<PyAny>>,
+    interpreters: usize,
+    py_function: &Py<PyAny>,
+    py_msg: PyObject,
+    function_name: &str,
+) -> Result<PyObject, PythonExecutionError> {
+    Python::with_gil(|py| {
+        let executor = pool.get_or_try_init(|| create_interpreter_pool(py, interpreters))?;
+        let result = executor
+            .bind(py)
+            .call_method1("submit", (py_function.bind(py), py_msg))
+            .and_then(|future| future.call_method0("result"));
+        match result {
+            Ok(result) => Ok(result.unbind()),
+            Err(e) => {
+                tracing::error!(event = "call_error", function = %function_name, error = %e, "Failed to call Python function in sub-interpreter");
+                Err(PythonExecutionError::CallError {
+                    function: function_name.to_string(),
+                    message: e.to_string(),
+                })
+            }
+        }
+    })
+}
+
+/// Create an `InterpreterPoolExecutor` whose interpreters start with the main interpreter's `sys.path`.
+fn create_interpreter_pool(py: Python<'_>, interpreters: usize) -> Result<Py<PyAny>, PythonExecutionError> {
+    use pyo3::types::{PyDict, PyTuple};
+    let executor_cls = py
+        .import("concurrent.futures")
+        .and_then(|futures| futures.getattr("InterpreterPoolExecutor"))
+        .map_err(|e| PythonExecutionError::ExecutionError {
+            message: format!("Sub-interpreter execution requires Python 3.14+ (InterpreterPoolExecutor): {e}"),
+        })?;
+    let build = || -> PyResult<Py<PyAny>> {
+        let sys_path = py.import("sys")?.getattr("path")?;
+        let init_globals = PyDict::new(py);
+        init_globals.set_item("paths", sys_path.call_method0("copy")?)?;
+        let initargs = PyTuple::new(
+            py,
+            [
+                "import sys\nsys.path[:] = paths\n".into_pyobject(py)?.into_any(),
+                init_globals.into_any(),
+            ],
+        )?;
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("max_workers", interpreters)?;
+        kwargs.set_item("initializer", py.import("builtins")?.getattr("exec")?)?;
+        kwargs.set_item("initargs", initargs)?;
+        Ok(executor_cls.call((), Some(&kwargs))?.unbind())
+    };
+    let executor = build().map_err(PythonExecutionError::from)?;
+    tracing::info!(interpreters, "Created sub-interpreter pool");
+    Ok(executor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use kameo_child_process::SubprocessIpcBackend;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Job {
+        id: u32,
+        sleep: f64,
+    }
+
+    impl KameoChildProcessMessage for Job {
+        type Ok = u32;
+    }
+
+    fn config(sync_execution: SyncExecution) -> PythonConfig {
+        PythonConfig {
+            python_path: vec![],
+            module_name: "jobs".to_string(),
+            function_name: "handle".to_string(),
+            env_vars: vec![],
+            is_async: false,
+            module_path: String::new(),
+            sync_execution,
+            schema: None,
+        }
+    }
+
+    #[test]
+    fn test_sync_execution_rejects_empty_pools() {
+        assert!(SyncExecution::Inline.validate().is_ok());
+        assert!(SyncExecution::Blocking { max_concurrency: 1 }.validate().is_ok());
+        assert!(SyncExecution::Blocking { max_concurrency: 0 }.validate().is_err());
+        assert!(SyncExecution::SubInterpreters { interpreters: 0 }.validate().is_err());
+    }
+
+    /// A slow sync call on the blocking pool must not hold up the child loop for a fast one.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_blocking_sync_call_does_not_stall_the_child_loop() {
+        let function = Python::with_gil(|py| {
+            let code = c"import time\ndef handle(job):\n    time.sleep(job['sleep'])\n    return job['id']\n";
+            let module = PyModule::from_code(py, code, c"jobs.py", c"jobs").unwrap();
+            module.getattr("handle").unwrap().unbind()
+        });
+        let handler = PythonMessageHandler::new(function, config(SyncExecution::Blocking { max_concurrency: 2 }));
+        let (parent, child) = tokio::io::duplex(64 * 1024);
+        let backend = SubprocessIpcBackend::<Job>::from_transport(parent);
+        let child_task = tokio::spawn(kameo_child_process::run_child_actor_loop::<_, Job, _>(handler, child, None));
+
+        let started = Instant::now();
+        let slow = tokio::spawn({
+            let backend = backend.clone();
+            async move { backend.send(Job { id: 1, sleep: 2.0 }).await }
+        });
+        // Let the slow call reach Python first
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let fast = tokio::time::timeout(Duration::from_secs(1), backend.send(Job { id: 2, sleep: 0.0 }))
+            .await
+            .expect("fast call waited on the slow one")
+            .unwrap();
+        assert_eq!(fast, 2);
+        assert!(!slow.is_finished(), "slow call finished after {:?}", started.elapsed());
+        assert_eq!(slow.await.unwrap().unwrap(), 1);
+
+        backend.shutdown();
+        drop(backend);
+        child_task.await.unwrap().unwrap();
+    }
+}
@@ -5,7 +5,7 @@ use kameo_child_process::callback::{NoopCallbackHandler, CallbackHandler};
 use std::time::Duration;
 
 /// Builder for a Python child process
-/// NOTE: For PythonActor, use the macro-based entrypoint (setup_python_subprocess_system!). This builder is not supported for PythonActor.
+/// NOTE: For PythonActor, use `child::dispatch_if_child` or the `setup_python_subprocess_system!` macro as the child entrypoint. This builder is not supported for PythonActor.
 /// NOTE: SubprocessParentActor is only valid as an in-process actor with DelegatedReply. If used as a child process actor, it will panic.
 pub struct ParentActorLoopConfig {
     pub max_concurrency: usize,
@@ -25,8 +25,6 @@ where
     M: kameo_child_process::KameoChildProcessMessage + Send + Sync + 'static,
     <M as kameo_child_process::KameoChildProcessMessage>::Ok: serde::Serialize
         + for<'de> serde::Deserialize<'de>
-        + bincode::Encode
-        + bincode::Decode<()> 
         + std::fmt::Debug
         + Send
         + Sync
@@ -36,7 +34,8 @@ where
     next: std::sync::atomic::AtomicUsize,
     child: Option<tokio::process::Child>,
     write_tx: Option<tokio::sync::mpsc::UnboundedSender<kameo_child_process::WriteRequest<M>>>,
-    // callback_shutdown: Option<tokio::sync::Notify>,
+    backend: std::sync::Arc<kameo_child_process::SubprocessIpcBackend<M>>,
+    callback_shutdown: tokio_util::sync::CancellationToken,
 }
 
 impl<M> PythonChildProcessActorPool<M>
@@ -44,8 +43,6 @@ where
     M: kameo_child_process::KameoChildProcessMessage + Send + Sync + 'static,
     <M as kameo_child_process::KameoChildProcessMessage>::Ok: serde::Serialize
         + for<'de> serde::Deserialize<'de>
-        + bincode::Encode
-        + bincode::Decode<()> 
         + std::fmt::Debug
         + Send
         + Sync
@@ -62,6 +59,9 @@ where
         if let Some(write_tx) = self.write_tx.take() {
             drop(write_tx); // Close the channel to signal writer task
         }
+        // Closes the connections, which is what ends a remote worker's session
+        self.backend.shutdown();
+        self.callback_shutdown.cancel();
         if let Some(mut child) = self.child.take() {
             let _ = child.kill().await;
             let _ = child.wait().await;
@@ -69,6 +69,12 @@ where
     }
 }
 
+/// The callback channel of a child being spawned: already connected, or still to be accepted.
+enum PendingCallback {
+    Connected(tokio::net::UnixStream),
+    Listening(kameo_child_process::handshake::ChildListener),
+}
+
 /// Builder for spawning Python child processes with unified streaming support.
 /// 
 /// This builder provides a fluent interface for configuring and spawning Python subprocesses
@@ -123,13 +129,11 @@ where
     M: KameoChildProcessMessage + Send + Sync + 'static,
     <M as KameoChildProcessMessage>::Ok: serde::Serialize
         + for<'de> serde::Deserialize<'de>
-        + bincode::Encode
-        + bincode::Decode<()> 
         + std::fmt::Debug
         + Send
         + Sync
         + 'static,
-    C: Send + Sync + Clone + 'static + bincode::Encode + bincode::Decode<()> + std::fmt::Debug,
+    C: Send + Sync + Clone + 'static + serde::Serialize + serde::de::DeserializeOwned + std::fmt::Debug,
     H: CallbackHandler<C> + Clone + Send + Sync + 'static,
 {
     /// Python configuration for subprocess setup
@@ -138,6 +142,16 @@ where
     log_level: Level,
     /// Handler for callback messages
     callback_handler: H,
+    /// Wire codec for the request connection
+    codec: kameo_child_process::CodecKind,
+    /// Frame compression offered to the child for the request connection
+    compression: kameo_child_process::Compression,
+    /// Directory for the parent/child socket files; `None` uses `handshake::socket_dir()`
+    socket_dir: Option<std::path::PathBuf>,
+    /// Use Linux abstract-namespace sockets instead of socket files
+    abstract_sockets: bool,
+    /// Hand the child a socketpair at spawn instead of listening for it to connect
+    inherit_sockets: bool,
     /// Phantom data for message and callback types
     _phantom: std::marker::PhantomData<(M, C)>,
 }
@@ -147,13 +161,11 @@ where
     M: KameoChildProcessMessage + Send + Sync + 'static,
     <M as KameoChildProcessMessage>::Ok: serde::Serialize
         + for<'de> serde::Deserialize<'de>
-        + bincode::Encode
-        + bincode::Decode<()> 
         + std::fmt::Debug
         + Send
         + Sync
         + 'static,
-    C: Send + Sync + Clone + 'static + bincode::Encode + bincode::Decode<()> + std::fmt::Debug,
+    C: Send + Sync + Clone + 'static + serde::Serialize + serde::de::DeserializeOwned + std::fmt::Debug,
 {
     /// Creates a new builder with the given Python configuration and message types.
     pub fn new(python_config: crate::PythonConfig) -> Self {
@@ -174,6 +186,11 @@ where
             python_config,
             log_level: Level::INFO,
             callback_handler: NoopCallbackHandler::<C>::default(),
+            codec: kameo_child_process::CodecKind::default(),
+            compression: kameo_child_process::Compression::None,
+            socket_dir: None,
+            abstract_sockets: false,
+            inherit_sockets: false,
             _phantom: std::marker::PhantomData,
         }
     }
@@ -184,13 +201,11 @@ where
     M: KameoChildProcessMessage + Send + Sync + 'static,
     <M as KameoChildProcessMessage>::Ok: serde::Serialize
         + for<'de> serde::Deserialize<'de>
-        + bincode::Encode
-        + bincode::Decode<()> 
         + std::fmt::Debug
         + Send
         + Sync
         + 'static,
-    C: Send + Sync + Clone + 'static + bincode::Encode + bincode::Decode<()> + std::fmt::Debug,
+    C: Send + Sync + Clone + 'static + serde::Serialize + serde::de::DeserializeOwned + std::fmt::Debug,
     H: CallbackHandler<C> + Clone + Send + Sync + 'static,
 {
     /// Sets the callback handler.
@@ -202,6 +217,11 @@ where
             python_config: self.python_config,
             log_level: self.log_level,
             callback_handler: handler,
+            codec: self.codec,
+            compression: self.compression,
+            socket_dir: self.socket_dir,
+            abstract_sockets: self.abstract_sockets,
+            inherit_sockets: self.inherit_sockets,
             _phantom: std::marker::PhantomData,
         }
     }
@@ -211,16 +231,96 @@ where
         self
     }
 
+    /// Sets the wire codec for requests and replies (bincode by default).
+    ///
+    /// The codec is agreed with the child during the handshake; spawning fails if the
+    /// child does not support it. Callbacks always use bincode.
+    pub fn codec(mut self, codec: kameo_child_process::CodecKind) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Offers frame compression for requests and replies (off by default).
+    ///
+    /// Frames of at least [`kameo_child_process::compression::threshold`] bytes are
+    /// compressed. If the child wasn't built with the algorithm, frames are sent
+    /// uncompressed. Callbacks are never compressed.
+    pub fn compression(mut self, compression: kameo_child_process::Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets the directory for the parent/child socket files.
+    ///
+    /// Defaults to `KAMEO_SOCKET_DIR`, else `$XDG_RUNTIME_DIR`, else the system temp dir.
+    /// Socket files are owner-only and removed as soon as the child connects.
+    pub fn socket_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.socket_dir = Some(dir.into());
+        self
+    }
+
+    /// Uses Linux abstract-namespace sockets, which leave nothing on disk.
+    ///
+    /// Abstract sockets aren't protected by file permissions: any process in the same network
+    /// namespace that learns the name can connect. Spawning fails on other platforms.
+    pub fn abstract_sockets(mut self, enabled: bool) -> Self {
+        self.abstract_sockets = enabled;
+        self
+    }
+
+    /// Hands the child both channels as an inherited socketpair instead of listening for it.
+    ///
+    /// Nothing is bound, so no other local process can connect first and there is no accept
+    /// timeout. The fd numbers are passed in `KAMEO_REQUEST_FD` and `KAMEO_CALLBACK_FD`.
+    /// `socket_dir` and `abstract_sockets` are ignored.
+    pub fn inherit_sockets(mut self, enabled: bool) -> Self {
+        self.inherit_sockets = enabled;
+        self
+    }
+
+    /// Validates every reply against a JSON Schema derived from `M::Ok` before deserializing
+    /// it, and exposes the message and reply schemas to Python as `kameo.schema`.
+    ///
+    /// A reply that doesn't match fails the request with
+    /// [`PythonExecutionError::SchemaError`](crate::PythonExecutionError::SchemaError), listing
+    /// every offending path. With `strict`, reply dicts may only hold the keys their Rust
+    /// structs declare; otherwise extra keys are ignored, as `serde_py` ignores them. Remote
+    /// workers read their config at startup, so set [`crate::PythonConfig::schema`] there instead.
+    pub fn validate_replies(mut self, strict: bool) -> Self
+    where
+        M: crate::PyStub,
+        M::Ok: crate::PyStub,
+    {
+        self.python_config.schema = Some(crate::MessageSchema::new::<M>(strict));
+        self
+    }
+
+    /// What this builder offers in the handshake.
+    fn handshake_options(&self) -> kameo_child_process::HandshakeOptions {
+        kameo_child_process::HandshakeOptions {
+            codecs: vec![self.codec],
+            compression: match self.compression {
+                kameo_child_process::Compression::None => Vec::new(),
+                compression => vec![compression],
+            },
+            token: None,
+        }
+    }
+
     pub async fn spawn_pool(
         self,
         pool_size: usize,
         parent_config: Option<ParentActorLoopConfig>,
     ) -> std::io::Result<PythonChildProcessActorPool<M>>
     {
-        use kameo_child_process::spawn_subprocess_ipc_actor;
-        use kameo_child_process::callback::CallbackReceiver;
-        use tokio::net::UnixListener;
+        use kameo_child_process::handshake::{
+            socket_dir, ChildListener, InheritedSocket, SocketAddress, CALLBACK_FD_ENV, REQUEST_FD_ENV,
+        };
         let _parent_config = parent_config.unwrap_or_default();
+        self.python_config
+            .sync_execution
+            .validate()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
         // Serialize the PythonConfig as JSON for the child
         let config_json = serde_json::to_string(&self.python_config).map_err(|e| {
             std::io::Error::new(
@@ -228,15 +328,7 @@ where
                 format!("Failed to serialize PythonConfig: {e}"),
             )
         })?;
-        // Set up the Unix domain sockets
         let actor_name = std::any::type_name::<crate::PythonActor<M, C>>();
-        let request_socket_path = kameo_child_process::handshake::unique_socket_path(&format!("{}-req", actor_name));
-        let callback_socket_path = kameo_child_process::handshake::unique_socket_path(&format!("{}-cb", actor_name));
-        let request_endpoint = request_socket_path.to_string_lossy().to_string();
-        let callback_endpoint = callback_socket_path.to_string_lossy().to_string();
-        let request_incoming = UnixListener::bind(&request_endpoint)?;
-        let callback_incoming = UnixListener::bind(&callback_endpoint)?;
-        // Spawn child process
         let current_exe = std::env::current_exe()?;
         let mut cmd = tokio::process::Command::new(current_exe);
         cmd.envs(std::env::vars());
@@ -244,48 +336,139 @@ where
         for (key, value) in self.python_config.env_vars.iter() {
             cmd.env(key, value);
         }
-        cmd.env("KAMEO_CHILD_ACTOR", actor_name);
-        cmd.env("KAMEO_REQUEST_SOCKET", request_socket_path.to_string_lossy().as_ref());
-        cmd.env("KAMEO_CALLBACK_SOCKET", callback_socket_path.to_string_lossy().as_ref());
+        cmd.env(kameo_child_process::CHILD_ACTOR_ENV, actor_name);
+        // Never let the child pick up channels meant for this process
+        for env in ["KAMEO_REQUEST_SOCKET", "KAMEO_CALLBACK_SOCKET", REQUEST_FD_ENV, CALLBACK_FD_ENV] {
+            cmd.env_remove(env);
+        }
         cmd.env("KAMEO_PYTHON_CONFIG", config_json);
+        // Only the child we spawn knows this, so nothing else can complete the handshake
+        let token = kameo_child_process::HandshakeOptions::new_token();
+        cmd.env(kameo_child_process::HANDSHAKE_TOKEN_ENV, &token);
+        // Both ends must agree on how large a frame may be
+        cmd.env(
+            kameo_child_process::framing::MAX_FRAME_SIZE_ENV,
+            kameo_child_process::framing::max_frame_size().to_string(),
+        );
+        cmd.env(
+            kameo_child_process::compression::COMPRESSION_THRESHOLD_ENV,
+            kameo_child_process::compression::threshold().to_string(),
+        );
+        cmd.env(
+            kameo_child_process::framing::WRITE_BATCH_ENV,
+            kameo_child_process::framing::write_batch().to_string(),
+        );
+        // Keep the child interpreter free-threaded even if an extension module asks for the GIL
+        #[cfg(feature = "free-threaded")]
+        cmd.env("PYTHON_GIL", "0");
         if let Ok(rust_log) = std::env::var("RUST_LOG") {
             cmd.env("RUST_LOG", rust_log);
         }
         cmd.stdout(std::process::Stdio::inherit());
         cmd.stderr(std::process::Stdio::inherit());
-        let child = cmd.spawn()?;
-        // Accept request connection and perform handshake
-        let (mut request_conn, _addr) = tokio::time::timeout(
+        let (child, mut request_conn, callback_conn) = if self.inherit_sockets {
+            let request = InheritedSocket::pass_to(&mut cmd, REQUEST_FD_ENV)?;
+            let callback = InheritedSocket::pass_to(&mut cmd, CALLBACK_FD_ENV)?;
+            let child = cmd.spawn()?;
+            (child, request.into_stream(), PendingCallback::Connected(callback.into_stream()))
+        } else {
+            // Set up the Unix domain sockets
+            let dir = self.socket_dir.clone().unwrap_or_else(socket_dir);
+            let request_incoming = ChildListener::bind(SocketAddress::unique(&format!("{actor_name}-req"), &dir, self.abstract_sockets))?;
+            let callback_incoming = ChildListener::bind(SocketAddress::unique(&format!("{actor_name}-cb"), &dir, self.abstract_sockets))?;
+            cmd.env("KAMEO_REQUEST_SOCKET", request_incoming.address().to_env());
+            cmd.env("KAMEO_CALLBACK_SOCKET", callback_incoming.address().to_env());
+            let mut child = cmd.spawn()?;
+            let child_pid = child.id();
+            // A child that fails during startup exits before connecting; report that, not a timeout
+            let request_conn = tokio::select! {
+                conn = tokio::time::timeout(Duration::from_secs(30), request_incoming.accept_from(child_pid)) => conn??,
+                status = child.wait() => {
+                    return Err(std::io::Error::other(format!(
+                        "Child process exited with {} before connecting; see its stderr for the cause",
+                        status?
+                    )));
+                }
+            };
+            (child, request_conn, PendingCallback::Listening(callback_incoming))
+        };
+        let options = kameo_child_process::HandshakeOptions { token: Some(token), ..self.handshake_options() };
+        let format = kameo_child_process::perform_handshake_with_options::<M>(&mut request_conn, true, &options)
+            .await
+            .map_err(|e| std::io::Error::other(format!("Handshake failed: {e:?}")))?;
+        let callback_conn = match callback_conn {
+            PendingCallback::Connected(stream) => stream,
+            PendingCallback::Listening(listener) => {
+                tokio::time::timeout(Duration::from_secs(30), listener.accept_from(child.id())).await??
+            }
+        };
+        Ok(self.build_pool(request_conn, callback_conn, format, pool_size, Some(child)))
+    }
+
+    /// Connects to a remote worker daemon instead of spawning a local child.
+    ///
+    /// The worker is a binary with a child entrypoint, such as
+    /// [`dispatch_if_child`](crate::child::dispatch_if_child), started with
+    /// `KAMEO_CHILD_ACTOR`, `KAMEO_PYTHON_CONFIG`, `KAMEO_WORKER_LISTEN=<addr>` and
+    /// `KAMEO_HANDSHAKE_TOKEN=<token>`; its own `PythonConfig` decides which Python function
+    /// serves requests. Both sides must hold the same `token`: the worker drops connections
+    /// without it, and the handshake fails if the worker doesn't present it back. A worker
+    /// serves one parent at a time, so a second parent waits until the first disconnects.
+    pub async fn connect_remote(
+        self,
+        addr: &str,
+        token: &str,
+        pool_size: usize,
+    ) -> std::io::Result<PythonChildProcessActorPool<M>>
+    {
+        use kameo_child_process::{connect_remote_channel, RemoteChannel};
+        let mut request_conn = tokio::time::timeout(
             Duration::from_secs(30),
-            request_incoming.accept(),
+            connect_remote_channel(addr, RemoteChannel::Request, token),
         ).await??;
-        kameo_child_process::perform_handshake::<M>(&mut request_conn, true).await.map_err(|e| {
-            std::io::Error::new(std::io::ErrorKind::Other, format!("Handshake failed: {e:?}"))
-        })?;
-        // Accept callback connection
-        let (callback_conn, _addr) = tokio::time::timeout(
+        let options = kameo_child_process::HandshakeOptions { token: Some(token.to_string()), ..self.handshake_options() };
+        let format = kameo_child_process::perform_handshake_with_options::<M>(&mut request_conn, true, &options)
+            .await
+            .map_err(|e| std::io::Error::other(format!("Handshake failed: {e:?}")))?;
+        let callback_conn = tokio::time::timeout(
             Duration::from_secs(30),
-            callback_incoming.accept(),
+            connect_remote_channel(addr, RemoteChannel::Callback, token),
         ).await??;
-        // Backend and callback receiver setup (copied from backend builder)
-        let backend = kameo_child_process::SubprocessIpcBackend::from_duplex(
-            kameo_child_process::DuplexUnixStream::new(request_conn)
-        );
-        let receiver = CallbackReceiver::<C, H>::from_duplex(
-            kameo_child_process::DuplexUnixStream::new(callback_conn),
+        Ok(self.build_pool(request_conn, callback_conn, format, pool_size, None))
+    }
+
+    /// Wires the backend and callback receiver over the accepted connections and spawns the actors.
+    fn build_pool<T>(
+        &self,
+        request_conn: T,
+        callback_conn: T,
+        format: kameo_child_process::WireFormat,
+        pool_size: usize,
+        child: Option<tokio::process::Child>,
+    ) -> PythonChildProcessActorPool<M>
+    where
+        T: kameo_child_process::Transport,
+    {
+        use kameo_child_process::spawn_subprocess_ipc_actor;
+        use kameo_child_process::callback::CallbackReceiver;
+        let backend = kameo_child_process::SubprocessIpcBackend::from_transport_with_format(request_conn, format);
+        let receiver = CallbackReceiver::<C, H, _, _>::from_transport(
+            callback_conn,
             self.callback_handler.clone(),
         );
         let mut actors = Vec::with_capacity(pool_size);
         for _ in 0..pool_size {
             actors.push(spawn_subprocess_ipc_actor(backend.clone()));
         }
+        let callback_shutdown = receiver.cancellation_token();
         tokio::spawn(receiver.run().instrument(tracing::Span::current()));
-        Ok(PythonChildProcessActorPool {
+        PythonChildProcessActorPool {
             actors,
             next: std::sync::atomic::AtomicUsize::new(0),
-            child: Some(child),
+            child,
             write_tx: None, // No longer needed
-            // callback_shutdown: None, // No longer needed
-        })
+            backend,
+            callback_shutdown,
+        }
     }
 }
@@ -0,0 +1,362 @@
+//! The child side of a Python subprocess, for binaries that keep their own `main`.
+//!
+//! [`PythonChildProcessBuilder`](crate::PythonChildProcessBuilder) spawns the current
+//! executable again with `KAMEO_CHILD_ACTOR` naming the actor to run. Register the actor types
+//! the binary spawns and call [`dispatch_if_child`] before anything else in `main`; in the child
+//! it runs the actor and exits, and in the parent it returns straight away:
+//!
+//! ```rust,ignore
+//! use kameo_snake_handler::child::{dispatch_if_child, ChildRegistry};
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     dispatch_if_child(
+//!         ChildRegistry::new()
+//!             .actor::<ScoreMessage, ScoreCallback>()
+//!             .actor::<TradeMessage, TradeCallback>(),
+//!     );
+//!     let cli = Cli::parse();
+//!     // ... the parent's own work
+//! }
+//! ```
+//!
+//! [`ChildRegistry::actor`] converts values with `serde_py`. Types deriving
+//! [`PyConvert`](crate::PyConvert) can use [`ChildRegistry::actor_with`] and
+//! [`ChildConverters::py_convert`] instead.
+
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+
+use kameo_child_process::callback::{CallbackHandle, CallbackHandler, CallbackIpcChild};
+use kameo_child_process::{DuplexUnixStream, KameoChildProcessMessage, RuntimeConfig, RuntimeFlavor, CHILD_ACTOR_ENV};
+use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyCFunction, PyDict, PyModule, PyTuple};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{debug, info, Instrument};
+
+use crate::convert::{Converters, FromPython, PyConvert};
+use crate::{PythonActor, PythonConfig};
+
+/// How an actor's child converts its messages, replies and callback messages.
+pub struct ChildConverters<M: KameoChildProcessMessage, C> {
+    pub actor: Converters<M>,
+    /// Reads the messages Python passes to `kameo.callback_handle`
+    pub callback: FromPython<C>,
+}
+
+impl<M: KameoChildProcessMessage, C: DeserializeOwned> ChildConverters<M, C> {
+    /// Convert everything with `serde_py`.
+    pub fn serde() -> Self {
+        Self { actor: Converters::serde(), callback: crate::serde_py::from_pyobject::<C> }
+    }
+}
+
+impl<M, C> ChildConverters<M, C>
+where
+    M: KameoChildProcessMessage + PyConvert,
+    M::Ok: PyConvert,
+    C: PyConvert,
+{
+    /// Convert everything with the types' [`PyConvert`] implementations.
+    pub fn py_convert() -> Self {
+        Self { actor: Converters::py_convert(), callback: crate::convert::from_python::<C> }
+    }
+}
+
+type ChildMain = Box<dyn FnOnce(&RuntimeConfig) -> Result<(), Box<dyn Error>> + Send>;
+
+/// The actor types a binary can run as a child, by the name the parent gives in
+/// `KAMEO_CHILD_ACTOR`.
+pub struct ChildRegistry {
+    runtime: RuntimeConfig,
+    actors: Vec<(&'static str, ChildMain)>,
+}
+
+impl Default for ChildRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChildRegistry {
+    /// No actors yet, and a multi-threaded runtime with tokio's default number of workers.
+    pub fn new() -> Self {
+        Self {
+            runtime: RuntimeConfig { flavor: RuntimeFlavor::MultiThread, worker_threads: None },
+            actors: Vec::new(),
+        }
+    }
+
+    /// The tokio runtime the child runs Python on.
+    pub fn runtime(mut self, runtime: RuntimeConfig) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// Register `PythonActor<M, C>`, converting values with `serde_py`.
+    pub fn actor<M, C>(self) -> Self
+    where
+        M: KameoChildProcessMessage + Send + Sync + 'static,
+        C: Send + Sync + Clone + 'static + Serialize + DeserializeOwned + std::fmt::Debug,
+    {
+        self.actor_with::<M, C>(ChildConverters::serde())
+    }
+
+    /// Register `PythonActor<M, C>` with the given conversions.
+    pub fn actor_with<M, C>(mut self, converters: ChildConverters<M, C>) -> Self
+    where
+        M: KameoChildProcessMessage + Send + Sync + 'static,
+        C: Send + Sync + Clone + 'static + Serialize + DeserializeOwned + std::fmt::Debug,
+    {
+        let name = std::any::type_name::<PythonActor<M, C>>();
+        self.actors.push((name, Box::new(move |runtime| run_child::<M, C>(runtime, converters))));
+        self
+    }
+
+    /// Whether an actor is registered under `actor_name`.
+    pub fn contains(&self, actor_name: &str) -> bool {
+        self.actors.iter().any(|(name, _)| *name == actor_name)
+    }
+
+    /// Run the actor registered under `actor_name` until the parent disconnects.
+    ///
+    /// This builds the child's tokio runtime, so it must not be called from inside another one;
+    /// [`dispatch_if_child`] takes care of that.
+    pub fn run(self, actor_name: &str) -> Result<(), Box<dyn Error>> {
+        let Self { runtime, actors } = self;
+        match actors.into_iter().find(|(name, _)| *name == actor_name) {
+            Some((_, main)) => main(&runtime),
+            None => Err(format!("Unknown actor type: {actor_name}").into()),
+        }
+    }
+}
+
+/// Run the child and exit if this process was spawned as one, or return if it is the parent.
+///
+/// The exit code is 0 once the parent disconnects and 1 if the child fails, including when
+/// `KAMEO_CHILD_ACTOR` names an actor that is not registered. Inside a tokio runtime, such as
+/// `#[tokio::main]`, the child runs on a thread of its own.
+pub fn dispatch_if_child(registry: ChildRegistry) {
+    let Ok(actor_name) = std::env::var(CHILD_ACTOR_ENV) else {
+        return;
+    };
+    let result = if tokio::runtime::Handle::try_current().is_ok() {
+        let name = actor_name.clone();
+        std::thread::spawn(move || registry.run(&name).map_err(|e| e.to_string()))
+            .join()
+            .unwrap_or_else(|_| Err("child thread panicked".to_string()))
+    } else {
+        registry.run(&actor_name).map_err(|e| e.to_string())
+    };
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(e) => {
+            tracing::error!(actor = %actor_name, error = %e, "Child process failed");
+            eprintln!("{actor_name}: {e}");
+            std::process::exit(1)
+        }
+    }
+}
+
+fn run_child<M, C>(runtime: &RuntimeConfig, converters: ChildConverters<M, C>) -> Result<(), Box<dyn Error>>
+where
+    M: KameoChildProcessMessage + Send + Sync + 'static,
+    C: Send + Sync + Clone + 'static + Serialize + DeserializeOwned + std::fmt::Debug,
+{
+    let builder = match runtime.flavor {
+        RuntimeFlavor::MultiThread => {
+            let mut builder = tokio::runtime::Builder::new_multi_thread();
+            builder.enable_all();
+            if let Some(threads) = runtime.worker_threads {
+                builder.worker_threads(threads);
+            }
+            builder
+        }
+        RuntimeFlavor::CurrentThread => {
+            let mut builder = tokio::runtime::Builder::new_current_thread();
+            builder.enable_all();
+            builder
+        }
+    };
+    crate::setup_python_runtime(builder);
+    let root_span = tracing::info_span!("child_process", process_role = "child");
+
+    // Replaced per session in remote worker mode
+    let callback_slot: Arc<RwLock<Option<CallbackHandle<C>>>> = Arc::new(RwLock::new(None));
+    let set_callback_handle = {
+        let slot = callback_slot.clone();
+        move |handle: CallbackHandle<C>| *slot.write().unwrap_or_else(|e| e.into_inner()) = Some(handle)
+    };
+
+    let result = Python::with_gil(|py| {
+        let actor = python_actor::<M, C>(py, &converters, callback_slot)?;
+        let run = async move {
+            if let Ok(addr) = std::env::var(kameo_child_process::WORKER_LISTEN_ENV) {
+                return crate::serve_remote_worker::<M, C>(&addr, actor, set_callback_handle)
+                    .await
+                    .map_err(|e| PyRuntimeError::new_err(e.to_string()));
+            }
+            let request_conn = match kameo_child_process::child_request().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    info!(error = ?e, "Parent disconnected (request connect failed), exiting cleanly");
+                    return Ok(());
+                }
+            };
+            let callback_conn = match kameo_child_process::child_callback().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    info!(error = ?e, "Parent disconnected (callback connect failed), exiting cleanly");
+                    return Ok(());
+                }
+            };
+            set_callback_handle(
+                CallbackIpcChild::<C>::from_duplex(DuplexUnixStream::new(*callback_conn)) as Arc<dyn CallbackHandler<C>>,
+            );
+            info!(callback = std::any::type_name::<C>(), "Child connected to both sockets and set callback handle");
+            crate::child_process_main_with_python_actor::<M, C>(actor, request_conn, None)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        };
+        pyo3_async_runtimes::tokio::run(py, run.instrument(root_span))
+    });
+    result.map_err(|e| Box::new(e) as Box<dyn Error>)
+}
+
+/// Set up the `kameo` module and `sys.path` from `KAMEO_PYTHON_CONFIG`, import the configured
+/// function and wrap it in an actor.
+fn python_actor<M, C>(
+    py: Python<'_>,
+    converters: &ChildConverters<M, C>,
+    callback_slot: Arc<RwLock<Option<CallbackHandle<C>>>>,
+) -> PyResult<PythonActor<M, C>>
+where
+    M: KameoChildProcessMessage + Send + Sync + 'static,
+    C: Send + Sync + Clone + 'static + Serialize + DeserializeOwned + std::fmt::Debug,
+{
+    let config_json = std::env::var("KAMEO_PYTHON_CONFIG")
+        .map_err(|_| PyRuntimeError::new_err("KAMEO_PYTHON_CONFIG must be set in child"))?;
+    let config: PythonConfig = serde_json::from_str(&config_json)
+        .map_err(|e| PyValueError::new_err(format!("Failed to parse KAMEO_PYTHON_CONFIG: {e}")))?;
+    config.sync_execution.validate().map_err(PyValueError::new_err)?;
+
+    let sys = py.import("sys")?;
+    let modules = sys.getattr("modules")?;
+    let kameo_mod = match modules.get_item("kameo") {
+        Ok(module) => module.downcast_into::<PyModule>()?,
+        Err(_) => {
+            let module = PyModule::new(py, "kameo")?;
+            modules.set_item("kameo", &module)?;
+            debug!("Injected kameo module into sys.modules BEFORE user import");
+            module
+        }
+    };
+
+    let from_python = converters.callback;
+    let callback_handle = PyCFunction::new_closure(py, Some(c"callback_handle"), None, move |args, kwargs| -> PyResult<PyObject> {
+        let py = args.py();
+        let handle = callback_slot
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| PyRuntimeError::new_err("Callback handle not initialized yet"))?;
+        let msg = from_python(&callback_arg(args, kwargs)?)
+            .map_err(|e| PyValueError::new_err(format!("Failed to parse callback: {e}")))?;
+        let future = pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            match handle.handle(msg).await {
+                Ok(_) => Python::with_gil(|py| Ok(py.None())),
+                Err(e) => Err(PyRuntimeError::new_err(format!("Callback handler error: {e}"))),
+            }
+        })?;
+        Ok(future.unbind())
+    })?;
+    kameo_mod.setattr("callback_handle", callback_handle)?;
+    debug!("Set callback_handle on kameo module");
+
+    let schema = match &config.schema {
+        Some(schema) => crate::serde_py::to_pyobject(py, schema).map_err(|e| PyValueError::new_err(e.to_string()))?,
+        None => py.None(),
+    };
+    kameo_mod.setattr("schema", schema)?;
+
+    let sys_path = sys.getattr("path")?;
+    for path in &config.python_path {
+        sys_path.call_method1("append", (path,))?;
+        debug!(added_path = %path, "Appended to sys.path");
+    }
+    let module = py.import(&config.module_name)?;
+    debug!(module = %config.module_name, "Imported Python module");
+    crate::check_free_threaded(py);
+    let function = module.getattr(&config.function_name)?.unbind();
+    debug!(function = %config.function_name, "Located Python function");
+
+    let actor = PythonActor::<M, C>::new(config, function).with_converters(converters.actor);
+    actor.start_interpreter_pool(py).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    Ok(actor)
+}
+
+/// The one argument of `kameo.callback_handle`, `py_msg`, given by position or by keyword.
+fn callback_arg<'py>(args: &Bound<'py, PyTuple>, kwargs: Option<&Bound<'py, PyDict>>) -> PyResult<Bound<'py, PyAny>> {
+    let keyword = kwargs.map(|kwargs| kwargs.get_item("py_msg")).transpose()?.flatten();
+    let other_kwargs = kwargs.map_or(0, |kwargs| kwargs.len()) - usize::from(keyword.is_some());
+    match (args.len(), keyword) {
+        (1, None) if other_kwargs == 0 => args.get_item(0),
+        (0, Some(msg)) if other_kwargs == 0 => Ok(msg),
+        _ => Err(PyTypeError::new_err("callback_handle() takes exactly one argument, py_msg")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Ping(u32);
+
+    impl KameoChildProcessMessage for Ping {
+        type Ok = u32;
+    }
+
+    #[test]
+    fn test_registry_finds_actors_by_type_name() {
+        let name = std::any::type_name::<PythonActor<Ping, Ping>>();
+        let registry = ChildRegistry::new().actor::<Ping, Ping>();
+        assert!(registry.contains(name));
+        assert!(!registry.contains("PythonActor<Ping, Ping>"));
+
+        let err = registry.run("PythonActor<Ping, Ping>").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown actor type: PythonActor<Ping, Ping>");
+    }
+
+    #[test]
+    fn test_callback_arg_by_position_or_keyword() {
+        Python::with_gil(|py| {
+            let msg = PyDict::new(py);
+            msg.set_item("value", 1).unwrap();
+            let kwargs = |items: &[(&str, &Bound<'_, PyDict>)]| {
+                let kwargs = PyDict::new(py);
+                for (key, value) in items {
+                    kwargs.set_item(key, value).unwrap();
+                }
+                kwargs
+            };
+            let args = PyTuple::new(py, [&msg]).unwrap();
+            let empty = PyTuple::empty(py);
+
+            assert!(callback_arg(&args, None).unwrap().is(&msg));
+            assert!(callback_arg(&empty, Some(&kwargs(&[("py_msg", &msg)]))).unwrap().is(&msg));
+            for (args, kwargs) in [
+                (&empty, None),
+                (&args, Some(kwargs(&[("py_msg", &msg)]))),
+                (&empty, Some(kwargs(&[("py_msg", &msg), ("other", &msg)]))),
+                (&args, Some(kwargs(&[("other", &msg)]))),
+            ] {
+                let err = callback_arg(args, kwargs.as_ref()).unwrap_err();
+                assert!(err.is_instance_of::<PyTypeError>(py), "{err}");
+            }
+        });
+    }
+}
@@ -0,0 +1,837 @@
+//! Direct pyo3 conversions for hot message types.
+//!
+//! [`serde_py`](crate::serde_py) goes through serde's data model, which costs a dynamic type
+//! check per value and a `Vec` of keys per dict. `#[derive(PyConvert)]` writes the conversions
+//! out for each type instead. They build the same Python values as `serde_py` and read the same
+//! ones back, errors and their paths included, so a handler sees no difference:
+//!
+//! ```ignore
+//! use kameo_snake_handler::PyConvert;
+//!
+//! #[derive(Debug, Clone, Serialize, Deserialize, PyConvert)]
+//! pub struct BenchMessage {
+//!     pub id: u64,
+//!     #[serde(default, skip_serializing_if = "Option::is_none")]
+//!     pub label: Option<String>,
+//! }
+//! ```
+//!
+//! `setup_python_subprocess_system!` picks the derived conversions up by itself for every
+//! message, reply and callback type that implements [`PyConvert`], and uses `serde_py` for the
+//! rest. Elsewhere, register the actor with
+//! [`ChildConverters::py_convert`](crate::child::ChildConverters::py_convert) or pass
+//! [`Converters::py_convert`] to [`PythonActor::with_converters`](crate::PythonActor::with_converters).
+//!
+//! The derive reads the same `#[serde(...)]` attributes as serde. Types using ones it does not
+//! handle itself (internally, adjacently or untagged enums, `flatten`, `alias`, `from`/`into`,
+//! `deny_unknown_fields`, a container `default`) get an implementation that calls `serde_py`.
+//! Fields of types without [`PyConvert`], such as `chrono` or `rust_decimal` values, can be
+//! converted by `serde_py` alone with `#[py_convert(serde)]`; `#[serde(with = "...")]` fields
+//! always are.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+#[cfg(feature = "indexmap")]
+use indexmap::IndexMap;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use pyo3::{IntoPyObjectExt, PyObject};
+
+pub use kameo_snake_derive::PyConvert;
+
+use kameo_child_process::KameoChildProcessMessage;
+
+use crate::serde_py::{self, Error, PathSegment, Result};
+
+/// Types with direct conversions to and from the Python values `serde_py` uses for them.
+pub trait PyConvert: Sized {
+    /// Build the Python value `serde_py::to_pyobject` would.
+    fn to_python(&self, py: Python<'_>) -> Result<PyObject>;
+
+    /// Read a value the way `serde_py::from_pyobject` would.
+    fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self>;
+
+    /// The value of a struct field missing from its Python object, if it may be left out.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+/// Convert a Rust value to a Python object, like [`serde_py::to_pyobject`].
+pub fn to_python<T: PyConvert>(py: Python<'_>, value: &T) -> Result<PyObject> {
+    kameo_child_process::shm::with_shared_handles(|| value.to_python(py))
+}
+
+/// Convert a Python object to a Rust value, like [`serde_py::from_pyobject`].
+pub fn from_python<T: PyConvert>(obj: &Bound<'_, PyAny>) -> Result<T> {
+    T::from_python(obj).map_err(|e| e.found(obj))
+}
+
+/// Converts a message to Python.
+pub type ToPython<T> = fn(Python<'_>, &T) -> Result<PyObject>;
+
+/// Converts a Python value back to Rust.
+pub type FromPython<T> = fn(&Bound<'_, PyAny>) -> Result<T>;
+
+/// How a [`PythonActor`](crate::PythonActor) converts its messages and replies.
+pub struct Converters<M: KameoChildProcessMessage> {
+    pub message: ToPython<M>,
+    pub reply: FromPython<M::Ok>,
+}
+
+impl<M: KameoChildProcessMessage> Converters<M> {
+    /// Convert both ways with `serde_py`.
+    pub fn serde() -> Self {
+        Self { message: serde_py::to_pyobject::<M>, reply: serde_py::from_pyobject::<M::Ok> }
+    }
+}
+
+impl<M> Converters<M>
+where
+    M: KameoChildProcessMessage + PyConvert,
+    M::Ok: PyConvert,
+{
+    /// Convert both ways with the [`PyConvert`] implementations.
+    pub fn py_convert() -> Self {
+        Self { message: to_python::<M>, reply: from_python::<M::Ok> }
+    }
+}
+
+impl<M: KameoChildProcessMessage> Clone for Converters<M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M: KameoChildProcessMessage> Copy for Converters<M> {}
+
+/// Picks [`PyConvert`] for a type when it is implemented and `serde_py` otherwise.
+///
+/// The choice is made by method resolution, so it only works where `T` is a concrete type:
+/// `(&Select::<T>::new()).to_python_fn()` finds [`SelectPyConvert`] on `Select<T>` first and
+/// falls back to [`SelectSerde`] on `&Select<T>`. Both traits need to be in scope.
+#[doc(hidden)]
+pub struct Select<T>(PhantomData<T>);
+
+impl<T> Select<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Select(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+#[allow(clippy::wrong_self_convention)]
+pub trait SelectPyConvert<T> {
+    fn to_python_fn(&self) -> ToPython<T>;
+    fn from_python_fn(&self) -> FromPython<T>;
+}
+
+impl<T: PyConvert> SelectPyConvert<T> for Select<T> {
+    fn to_python_fn(&self) -> ToPython<T> {
+        to_python::<T>
+    }
+
+    fn from_python_fn(&self) -> FromPython<T> {
+        from_python::<T>
+    }
+}
+
+#[doc(hidden)]
+#[allow(clippy::wrong_self_convention)]
+pub trait SelectSerde<T> {
+    fn to_python_fn(&self) -> ToPython<T>;
+    fn from_python_fn(&self) -> FromPython<T>;
+}
+
+impl<T> SelectSerde<T> for &Select<T>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn to_python_fn(&self) -> ToPython<T> {
+        serde_py::to_pyobject::<T>
+    }
+
+    fn from_python_fn(&self) -> FromPython<T> {
+        serde_py::from_pyobject::<T>
+    }
+}
+
+macro_rules! impl_py_convert {
+    ($($ty:ty => $expected:literal),* $(,)?) => {
+        $(impl PyConvert for $ty {
+            fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+                Ok(self.into_py_any(py)?)
+            }
+
+            fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+                serde_py::extract(obj, &$expected)
+            }
+        })*
+    };
+}
+
+impl_py_convert!(
+    bool => "a boolean",
+    i8 => "i8", i16 => "i16", i32 => "i32", i64 => "i64", i128 => "i128", isize => "i64",
+    u8 => "u8", u16 => "u16", u32 => "u32", u64 => "u64", u128 => "u128", usize => "u64",
+    f32 => "f32", f64 => "f64",
+    String => "a string",
+);
+
+impl PyConvert for char {
+    fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+        Ok(self.to_string().into_py_any(py)?)
+    }
+
+    fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+        let s: String = serde_py::extract(obj, &"a character")?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(Error::Deserialization("expected single character string".to_string())),
+        }
+    }
+}
+
+impl PyConvert for () {
+    fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+        Ok(py.None())
+    }
+
+    fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+        match obj.is_none() {
+            true => Ok(()),
+            false => Err(Error::Deserialization("expected None".to_string())),
+        }
+    }
+}
+
+/// Types whose serde implementations are all there is; they convert through `serde_py`.
+macro_rules! impl_py_convert_serde {
+    ($($ty:ty),* $(,)?) => {
+        $(impl PyConvert for $ty {
+            fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+                __private::serde_to_python(py, self)
+            }
+
+            fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+                __private::serde_from_python(obj)
+            }
+        })*
+    };
+}
+
+impl_py_convert_serde!(std::path::PathBuf, uuid::Uuid, serde_json::Value);
+
+impl<T: serde_py::BufferElement> PyConvert for serde_py::PyBuffer<T> {
+    fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+        __private::serde_to_python(py, self)
+    }
+
+    fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+        __private::serde_from_python(obj)
+    }
+}
+
+impl<T: PyConvert> PyConvert for Option<T> {
+    fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+        match self {
+            Some(value) => value.to_python(py),
+            None => Ok(py.None()),
+        }
+    }
+
+    fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+        match obj.is_none() {
+            true => Ok(None),
+            false => T::from_python(obj).map(Some),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: PyConvert> PyConvert for Box<T> {
+    fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+        T::to_python(self, py)
+    }
+
+    fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+        T::from_python(obj).map(Box::new)
+    }
+}
+
+macro_rules! impl_py_convert_seq {
+    ($($ty:ident<T $(: $bound:path)*>),* $(,)?) => {
+        $(impl<T: PyConvert $(+ $bound)*> PyConvert for $ty<T> {
+            fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+                let list = PyList::empty(py);
+                for item in self {
+                    list.append(item.to_python(py)?)?;
+                }
+                Ok(list.into_any().unbind())
+            }
+
+            fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+                let mut out = $ty::new();
+                __private::for_each_item(obj, |item| {
+                    out.extend(Some(T::from_python(item)?));
+                    Ok(())
+                })?;
+                Ok(out)
+            }
+        })*
+    };
+}
+
+impl_py_convert_seq!(Vec<T>, VecDeque<T>, HashSet<T: Eq: Hash>, BTreeSet<T: Ord>);
+
+impl<T: PyConvert, const N: usize> PyConvert for [T; N] {
+    fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+        let items = self.iter().map(|item| item.to_python(py)).collect::<Result<Vec<_>>>()?;
+        Ok(PyList::new(py, items)?.into_any().unbind())
+    }
+
+    fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+        let items = __private::items(obj)?;
+        let mut out = Vec::with_capacity(N);
+        for idx in 0..N {
+            out.push(__private::element(&items, idx, "an array", T::from_python)?);
+        }
+        Ok(out.try_into().unwrap_or_else(|_| unreachable!("array of {N} elements")))
+    }
+}
+
+macro_rules! impl_py_convert_map {
+    ($($ty:ident<K $(: $bound:path)*>),* $(,)?) => {
+        $(impl<K: PyConvert + serde::Serialize $(+ $bound)*, V: PyConvert> PyConvert for $ty<K, V> {
+            fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+                let dict = PyDict::new(py);
+                for (key, value) in self {
+                    dict.set_item(__private::dict_key(py, key)?, value.to_python(py)?)?;
+                }
+                Ok(dict.into_any().unbind())
+            }
+
+            fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+                let mut out = $ty::default();
+                for (key, value) in __private::map_fields(obj)?.iter() {
+                    let k = K::from_python(&key).map_err(|e| e.at(PathSegment::Key(&key), &key))?;
+                    let v = V::from_python(&value).map_err(|e| e.at(PathSegment::Key(&key), &value))?;
+                    out.insert(k, v);
+                }
+                Ok(out)
+            }
+        })*
+    };
+}
+
+impl_py_convert_map!(HashMap<K: Eq: Hash>, BTreeMap<K: Ord>);
+#[cfg(feature = "indexmap")]
+impl_py_convert_map!(IndexMap<K: Eq: Hash>);
+
+macro_rules! impl_py_convert_tuple {
+    ($len:literal, $expected:literal => $($idx:tt $name:ident),+) => {
+        impl<$($name: PyConvert),+> PyConvert for ($($name,)+) {
+            fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+                Ok(PyList::new(py, [$(self.$idx.to_python(py)?),+])?.into_any().unbind())
+            }
+
+            fn from_python(obj: &Bound<'_, PyAny>) -> Result<Self> {
+                let items = __private::items(obj)?;
+                Ok(($(__private::element(&items, $idx, $expected, $name::from_python)?,)+))
+            }
+        }
+    };
+}
+
+impl_py_convert_tuple!(1, "a tuple of size 1" => 0 A);
+impl_py_convert_tuple!(2, "a tuple of size 2" => 0 A, 1 B);
+impl_py_convert_tuple!(3, "a tuple of size 3" => 0 A, 1 B, 2 C);
+impl_py_convert_tuple!(4, "a tuple of size 4" => 0 A, 1 B, 2 C, 3 D);
+impl_py_convert_tuple!(5, "a tuple of size 5" => 0 A, 1 B, 2 C, 3 D, 4 E);
+impl_py_convert_tuple!(6, "a tuple of size 6" => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+
+/// What `#[derive(PyConvert)]` expands to. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use pyo3;
+    pub use serde;
+
+    use pyo3::prelude::*;
+    use pyo3::types::{PyByteArray, PyBytes, PyDict, PyFloat, PyFrozenSet, PyInt, PyList, PySet, PyString, PyTuple};
+    use pyo3::PyObject;
+    use serde::de::Error as _;
+
+    use super::PyConvert;
+    use crate::serde_py::{self, Error, PathSegment, PythonDeserializer, PythonSerializer, Result};
+
+    /// Call `f` on each item of a list, tuple, set, frozenset, bytes or bytearray, the sequences
+    /// `serde_py` reads.
+    pub fn for_each_item<'py>(
+        obj: &Bound<'py, PyAny>,
+        mut f: impl FnMut(&Bound<'py, PyAny>) -> Result<()>,
+    ) -> Result<()> {
+        let mut visit = |idx: usize, item: Bound<'py, PyAny>| f(&item).map_err(|e| e.at(PathSegment::Index(idx), &item));
+        if let Ok(list) = obj.downcast::<PyList>() {
+            for (idx, item) in list.iter().enumerate() {
+                visit(idx, item)?;
+            }
+        } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+            for (idx, item) in tuple.iter().enumerate() {
+                visit(idx, item)?;
+            }
+        } else if obj.is_instance_of::<PySet>()
+            || obj.is_instance_of::<PyFrozenSet>()
+            || obj.is_instance_of::<PyBytes>()
+            || obj.is_instance_of::<PyByteArray>()
+        {
+            for (idx, item) in obj.try_iter()?.enumerate() {
+                visit(idx, item?)?;
+            }
+        } else {
+            return Err(Error::Deserialization(format!(
+                "expected list, tuple or set, got {}",
+                obj.get_type().name()?
+            )));
+        }
+        Ok(())
+    }
+
+    /// The items of a sequence, for types that index into it.
+    pub fn items<'py>(obj: &Bound<'py, PyAny>) -> Result<Vec<Bound<'py, PyAny>>> {
+        let mut items = Vec::new();
+        for_each_item(obj, |item| {
+            items.push(item.clone());
+            Ok(())
+        })?;
+        Ok(items)
+    }
+
+    /// Convert element `idx`, or report a sequence shorter than `expected` describes.
+    pub fn element<T>(
+        items: &[Bound<'_, PyAny>],
+        idx: usize,
+        expected: &'static str,
+        convert: impl FnOnce(&Bound<'_, PyAny>) -> Result<T>,
+    ) -> Result<T> {
+        let item = items.get(idx).ok_or_else(|| Error::invalid_length(idx, &expected))?;
+        convert(item).map_err(|e| e.at(PathSegment::Index(idx), item))
+    }
+
+    pub fn map_fields<'py>(obj: &Bound<'py, PyAny>) -> Result<Bound<'py, PyDict>> {
+        serde_py::object_fields(obj)?.ok_or_else(|| {
+            Error::Deserialization(match obj.get_type().name() {
+                Ok(name) => format!("expected dict or object with fields, got {name}"),
+                Err(e) => e.to_string(),
+            })
+        })
+    }
+
+    /// A map key as `serde_py` writes it. Strings, numbers and unit variants convert directly;
+    /// tuples and variants with data need the hashable forms `serde_py` gives them.
+    pub fn dict_key<K: PyConvert + serde::Serialize>(py: Python<'_>, key: &K) -> Result<PyObject> {
+        let obj = key.to_python(py)?;
+        let bound = obj.bind(py);
+        if bound.is_instance_of::<PyString>() || bound.is_instance_of::<PyInt>() || bound.is_instance_of::<PyFloat>() {
+            return Ok(obj);
+        }
+        serde_py::to_dict_key(py, key)
+    }
+
+    /// Build a struct's Python value from its fields: an instance of the class registered for
+    /// `name`, or the dict itself.
+    pub fn finish_struct(dict: Bound<'_, PyDict>, name: &str) -> Result<PyObject> {
+        match serde_py::registered_class(dict.py(), name)? {
+            Some(class) => class
+                .call((), Some(&dict))
+                .map(Bound::unbind)
+                .map_err(|e| Error::Serialization(format!("failed to construct {}: {}", class, e))),
+            None => Ok(dict.into_any().unbind()),
+        }
+    }
+
+    pub fn list<const N: usize>(py: Python<'_>, items: [PyObject; N]) -> Result<PyObject> {
+        Ok(PyList::new(py, items)?.into_any().unbind())
+    }
+
+    /// `"Variant"`, an externally tagged unit variant.
+    pub fn unit_variant(variant: &Bound<'_, PyString>) -> Result<PyObject> {
+        Ok(variant.clone().into_any().unbind())
+    }
+
+    /// `{"Variant": value}`, any other externally tagged variant.
+    pub fn variant(py: Python<'_>, variant: &Bound<'_, PyString>, value: impl Into<PyObject>) -> Result<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item(variant, value.into())?;
+        Ok(dict.into_any().unbind())
+    }
+
+    /// Convert the field under `key`, if there is one.
+    pub fn field<T>(
+        fields: &Bound<'_, PyDict>,
+        key: &Bound<'_, PyString>,
+        convert: impl FnOnce(&Bound<'_, PyAny>) -> Result<T>,
+    ) -> Result<Option<T>> {
+        match fields.get_item(key)? {
+            Some(value) => convert(&value).map(Some).map_err(|e| e.at(PathSegment::Key(key.as_any()), &value)),
+            None => Ok(None),
+        }
+    }
+
+    /// The value of a missing field, or serde's `missing field` error.
+    pub fn missing<T: PyConvert>(name: &'static str) -> Result<T> {
+        T::missing().ok_or_else(|| Error::missing_field(name))
+    }
+
+    pub fn missing_field(name: &'static str) -> Error {
+        Error::missing_field(name)
+    }
+
+    /// The value of a missing field converted by serde: `None` for options, an error otherwise.
+    pub fn serde_missing<T: serde::de::DeserializeOwned>(name: &'static str) -> Result<T> {
+        T::deserialize(MissingField(name))
+    }
+
+    pub fn serde_to_python<T: serde::Serialize + ?Sized>(py: Python<'_>, value: &T) -> Result<PyObject> {
+        value.serialize(PythonSerializer::new(py))
+    }
+
+    pub fn serde_from_python<T: serde::de::DeserializeOwned>(obj: &Bound<'_, PyAny>) -> Result<T> {
+        T::deserialize(PythonDeserializer::new(obj.clone()))
+    }
+
+    /// Run a `serialize_with` function against `serde_py`'s serializer.
+    pub fn serialize_with<'py>(
+        py: Python<'py>,
+        serialize: impl FnOnce(PythonSerializer<'py>) -> Result<PyObject>,
+    ) -> Result<PyObject> {
+        serialize(PythonSerializer::new(py))
+    }
+
+    /// Run a `deserialize_with` function against `serde_py`'s deserializer.
+    pub fn deserialize_with<'py, T>(
+        obj: &Bound<'py, PyAny>,
+        deserialize: impl FnOnce(PythonDeserializer<'py>) -> Result<T>,
+    ) -> Result<T> {
+        deserialize(PythonDeserializer::new(obj.clone()))
+    }
+
+    pub fn expected_none(obj: &Bound<'_, PyAny>) -> Result<()> {
+        <() as PyConvert>::from_python(obj)
+    }
+
+    pub fn skipped_variant(name: &str) -> Error {
+        Error::Serialization(format!("the enum variant {name} cannot be serialized"))
+    }
+
+    pub fn unknown_variant(name: &str, variants: &'static [&'static str]) -> Error {
+        Error::unknown_variant(name, variants)
+    }
+
+    /// The content of an externally tagged enum value, `"Variant"` or `{"Variant": content}`.
+    pub struct EnumValue<'py> {
+        content: Option<(Bound<'py, PyAny>, Bound<'py, PyAny>)>,
+    }
+
+    impl<'py> EnumValue<'py> {
+        /// The variant name, and what it holds.
+        pub fn new(obj: &Bound<'py, PyAny>) -> Result<(String, Self)> {
+            if let Some((key, value)) = serde_py::enum_parts(obj)? {
+                let name = key.extract::<String>()?;
+                return Ok((name, Self { content: Some((key, value)) }));
+            }
+            if let Ok(name) = obj.extract::<String>() {
+                return Ok((name, Self { content: None }));
+            }
+            Err(Error::Deserialization("invalid enum value".to_string()))
+        }
+
+        /// Convert the variant's content, reporting errors under the variant key.
+        fn content<T>(
+            self,
+            kind: &str,
+            convert: impl FnOnce(&Bound<'py, PyAny>) -> Result<T>,
+        ) -> Result<T> {
+            let Some((key, value)) = self.content else {
+                return Err(Error::Deserialization(format!(
+                    "unit enum deserializer cannot deserialize {kind} variant"
+                )));
+            };
+            convert(&value).map_err(|e| e.at(PathSegment::Key(&key), &value))
+        }
+
+        pub fn newtype<T>(self, convert: impl FnOnce(&Bound<'py, PyAny>) -> Result<T>) -> Result<T> {
+            self.content("newtype", convert)
+        }
+
+        pub fn tuple<T>(self, convert: impl FnOnce(&[Bound<'py, PyAny>]) -> Result<T>) -> Result<T> {
+            self.content("tuple", |value| {
+                let items = items(value).map_err(|_| {
+                    Error::Deserialization("expected list or tuple for tuple variant".to_string())
+                })?;
+                convert(&items)
+            })
+        }
+
+        pub fn fields<T>(self, convert: impl FnOnce(&Bound<'py, PyDict>) -> Result<T>) -> Result<T> {
+            self.content("struct", |value| match serde_py::object_fields(value)? {
+                Some(fields) => convert(&fields),
+                None => Err(Error::Deserialization("expected dict for struct variant".to_string())),
+            })
+        }
+    }
+
+    /// Stands in for a missing field, which only options accept.
+    struct MissingField(&'static str);
+
+    impl<'de> serde::Deserializer<'de> for MissingField {
+        type Error = Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+            Err(Error::missing_field(self.0))
+        }
+
+        fn deserialize_option<V: serde::de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            visitor.visit_none()
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+            unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyConvert)]
+    #[serde(rename_all = "camelCase")]
+    struct Order {
+        order_id: u64,
+        #[serde(rename = "label")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(with = "crate::serde_py::uuid")]
+        id: uuid::Uuid,
+        #[serde(skip)]
+        cached: bool,
+        prices: HashMap<String, f64>,
+        pair: (i32, char),
+        side: Side,
+        legs: Vec<Leg>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyConvert)]
+    enum Side {
+        Buy,
+        #[serde(rename = "sell")]
+        Sell,
+        Limit(f64),
+        Range(f64, f64),
+        Stop { trigger: f64, #[serde(default)] trailing: bool },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyConvert)]
+    struct Leg(u32, String);
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyConvert)]
+    struct Quantity(u32);
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyConvert)]
+    struct Heartbeat;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyConvert)]
+    #[serde(transparent)]
+    struct Wrapper {
+        inner: Quantity,
+    }
+
+    /// Internally tagged, so the derive hands it to `serde_py`.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyConvert)]
+    #[serde(tag = "type")]
+    enum Tagged {
+        Ping,
+        Order { quantity: Quantity },
+    }
+
+    fn order() -> Order {
+        Order {
+            order_id: 7,
+            name: None,
+            tags: vec!["fast".to_string()],
+            id: uuid::Uuid::from_u128(42),
+            cached: false,
+            prices: HashMap::from([("bid".to_string(), 1.5)]),
+            pair: (-3, 'x'),
+            side: Side::Stop { trigger: 2.5, trailing: true },
+            legs: vec![Leg(1, "a".to_string()), Leg(2, "b".to_string())],
+        }
+    }
+
+    /// Both paths build equal Python values, and each reads back what the other built.
+    fn assert_same<T>(value: &T)
+    where
+        T: PyConvert + Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        Python::with_gil(|py| {
+            let fast = to_python(py, value).unwrap();
+            let serde = serde_py::to_pyobject(py, value).unwrap();
+            let (fast, serde) = (fast.bind(py), serde.bind(py));
+            assert!(fast.eq(serde).unwrap(), "{fast} != {serde}");
+            assert_eq!(&from_python::<T>(serde).unwrap(), value);
+            assert_eq!(&serde_py::from_pyobject::<T>(fast).unwrap(), value);
+        });
+    }
+
+    #[test]
+    fn test_matches_serde_py() {
+        assert_same(&order());
+        assert_same(&Order { side: Side::Buy, tags: vec![], name: Some("n".to_string()), ..order() });
+        for side in [Side::Sell, Side::Limit(1.0), Side::Range(1.0, 2.0)] {
+            assert_same(&side);
+        }
+        assert_same(&Quantity(3));
+        assert_same(&Heartbeat);
+        assert_same(&Wrapper { inner: Quantity(4) });
+        assert_same(&Tagged::Ping);
+        assert_same(&Tagged::Order { quantity: Quantity(5) });
+        assert_same(&Some(vec![(1u8, 2u16)]));
+        assert_same(&BTreeMap::from([("a".to_string(), [1i64, 2, 3])]));
+    }
+
+    #[test]
+    fn test_reads_what_serde_py_reads() {
+        Python::with_gil(|py| {
+            let fields = PyDict::new(py);
+            fields.set_item("orderId", 7).unwrap();
+            fields.set_item("id", "00000000-0000-0000-0000-00000000002a").unwrap();
+            fields.set_item("prices", PyDict::new(py)).unwrap();
+            fields.set_item("pair", (1, "y")).unwrap();
+            fields.set_item("side", "Buy").unwrap();
+            fields.set_item("legs", pyo3::types::PySet::empty(py).unwrap()).unwrap();
+            let fields = fields.into_any();
+            let fast: Order = from_python(&fields).unwrap();
+            let serde: Order = serde_py::from_pyobject(&fields).unwrap();
+            assert_eq!(fast, serde);
+            assert_eq!((fast.name, fast.tags, fast.side), (None, vec![], Side::Buy));
+        });
+    }
+
+    #[test]
+    fn test_errors_match_serde_py() {
+        Python::with_gil(|py| {
+            let check = |value: Bound<'_, PyAny>| {
+                let fast = from_python::<Order>(&value).unwrap_err().to_string();
+                let serde = serde_py::from_pyobject::<Order>(&value).unwrap_err().to_string();
+                assert_eq!(fast, serde);
+                fast
+            };
+            let mut bad = order();
+            bad.legs[1].0 = 9;
+            let value = to_python(py, &bad).unwrap().into_bound(py);
+            value.get_item("legs").unwrap().get_item(1).unwrap().set_item(0, "nine").unwrap();
+            let message = check(value.clone());
+            assert!(message.contains("at legs[1][0]: invalid type: string \"nine\", expected u32"), "{message}");
+
+            value.set_item("side", PyDict::from_sequence(&PyList::new(py, [("Stop", PyDict::new(py))]).unwrap()).unwrap()).unwrap();
+            let message = check(value.clone());
+            assert!(message.contains("at side.Stop: missing field `trigger`"), "{message}");
+
+            value.set_item("side", "Sideways").unwrap();
+            let message = check(value.clone());
+            assert!(message.contains("unknown variant `Sideways`"), "{message}");
+
+            let value = to_python(py, &order()).unwrap().into_bound(py);
+            value.del_item("orderId").unwrap();
+            let message = check(value);
+            assert!(message.contains("missing field `orderId`"), "{message}");
+        });
+    }
+
+    #[test]
+    fn test_builds_registered_classes() {
+        Python::with_gil(|py| {
+            let module = pyo3::types::PyModule::from_code(
+                py,
+                c"class Leg:\n    def __init__(self, **fields):\n        self.__dict__.update(fields)\n",
+                c"kameo_test_convert.py",
+                c"kameo_test_convert",
+            )
+            .unwrap();
+            py.import("sys").unwrap().getattr("modules").unwrap().set_item("kameo_test_convert", module).unwrap();
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyConvert)]
+            #[serde(rename = "ConvertLeg")]
+            struct Leg {
+                price: f64,
+            }
+            serde_py::register_class("ConvertLeg", "kameo_test_convert.Leg");
+            let built = to_python(py, &Leg { price: 1.5 }).unwrap();
+            serde_py::unregister_class("ConvertLeg");
+            let built = built.bind(py);
+            assert_eq!(built.get_type().name().unwrap().to_string(), "Leg");
+            assert_eq!(from_python::<Leg>(built).unwrap(), Leg { price: 1.5 });
+        });
+    }
+
+    #[test]
+    fn test_map_keys_match_serde_py() {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, PyConvert)]
+        enum Kind {
+            Plain,
+            Code(u16),
+            Pair(i8, String),
+        }
+        let cells = BTreeMap::from([((1, -2), true), ((0, 5), false)]);
+        let kinds = HashMap::from([(Kind::Plain, 1), (Kind::Code(3), 2), (Kind::Pair(-1, "x".to_string()), 3)]);
+        Python::with_gil(|py| {
+            let fast = to_python(py, &cells).unwrap();
+            let slow = serde_py::to_pyobject(py, &cells).unwrap();
+            assert!(fast.bind(py).eq(slow.bind(py)).unwrap());
+            assert_eq!(fast.bind(py).repr().unwrap().to_string(), "{(0, 5): False, (1, -2): True}");
+            assert_eq!(from_python::<BTreeMap<(i32, i32), bool>>(fast.bind(py)).unwrap(), cells);
+
+            let fast = to_python(py, &kinds).unwrap();
+            let slow = serde_py::to_pyobject(py, &kinds).unwrap();
+            assert!(fast.bind(py).eq(slow.bind(py)).unwrap());
+            assert_eq!(from_python::<HashMap<Kind, i32>>(fast.bind(py)).unwrap(), kinds);
+        });
+    }
+
+    #[test]
+    #[allow(clippy::needless_borrow)] // the borrow is what the macros write
+    fn test_selects_py_convert_when_implemented() {
+        #[allow(unused_imports)]
+        use super::{SelectPyConvert as _, SelectSerde as _};
+        /// Converts to a different value by hand than through serde, to tell the paths apart.
+        #[derive(Serialize, Deserialize)]
+        struct Both;
+        impl PyConvert for Both {
+            fn to_python(&self, py: Python<'_>) -> Result<PyObject> {
+                Ok("py_convert".into_py_any(py)?)
+            }
+
+            fn from_python(_obj: &Bound<'_, PyAny>) -> Result<Self> {
+                Ok(Both)
+            }
+        }
+        #[derive(Serialize, Deserialize)]
+        struct SerdeOnly(u32);
+        Python::with_gil(|py| {
+            let fast = (&Select::<Both>::new()).to_python_fn()(py, &Both).unwrap();
+            assert_eq!(fast.extract::<String>(py).unwrap(), "py_convert");
+            let serde = (&Select::<SerdeOnly>::new()).to_python_fn()(py, &SerdeOnly(3)).unwrap();
+            assert_eq!(serde.extract::<u32>(py).unwrap(), 3);
+        });
+    }
+}
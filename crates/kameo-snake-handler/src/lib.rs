@@ -43,6 +43,8 @@
 //!         env_vars: vec![("PYTHONPATH".to_string(), "/path/to/modules".to_string())],
 //!         is_async: false,
 //!         module_path: "python/my_module.py".to_string(),
+//!         sync_execution: SyncExecution::Blocking { max_concurrency: 4 },
+//!         schema: None,
 //!     };
 //! 
 //!     // Spawn Python subprocess pool
@@ -80,21 +82,36 @@
 //!     # return {"result": "done"}
 //! ```
 
+// Lets `#[derive(PyStub)]` output, which names `::kameo_snake_handler`, compile in this crate
+extern crate self as kameo_snake_handler;
+
 pub mod serde_py;
-pub use serde_py::{from_pyobject, to_pyobject, FromPyAny};
+pub use serde_py::{from_pyobject, to_pyobject, FromPyAny, PyBuffer};
+
+pub mod stubs;
+pub use stubs::PyStub;
+
+pub mod schema;
+pub use schema::MessageSchema;
+
+pub mod convert;
+pub use convert::PyConvert;
 
 mod error;
 pub use error::ErrorReply;
 pub use kameo_child_process::error::PythonExecutionError;
+pub use kameo_child_process::ShmBytes;
 
 mod builder;
 pub use builder::PythonChildProcessBuilder;
 
 mod actor;
-pub use actor::{child_process_main_with_python_actor, PythonActor, PythonConfig};
+pub use actor::{child_process_main_with_python_actor, serve_remote_worker, PythonActor, PythonConfig, SyncExecution};
 
 mod macros;
 
+pub mod child;
+
 pub mod telemetry;
 
 pub use crate::actor::PythonMessageHandler;
@@ -105,9 +122,37 @@ pub fn setup_python_runtime(builder: tokio::runtime::Builder) {
     pyo3_async_runtimes::tokio::init(builder);
 }
 
+/// Returns whether the running interpreter has the GIL enabled.
+///
+/// Always `true` on interpreters that predate free-threading (no `sys._is_gil_enabled`).
+pub fn python_gil_enabled(py: pyo3::Python<'_>) -> bool {
+    use pyo3::prelude::*;
+    py.import("sys")
+        .and_then(|sys| sys.call_method0("_is_gil_enabled"))
+        .and_then(|enabled| enabled.extract::<bool>())
+        .unwrap_or(true)
+}
+
+/// Logs the interpreter's GIL status in the child after the user module is imported.
+///
+/// With the `free-threaded` feature a warning is emitted when the GIL is still
+/// enabled (e.g. a non-free-threaded build, or an extension module re-enabled it),
+/// since sync handlers will then be serialised.
+pub fn check_free_threaded(py: pyo3::Python<'_>) -> bool {
+    let gil_enabled = python_gil_enabled(py);
+    tracing::debug!(gil_enabled, "Python interpreter GIL status");
+    #[cfg(feature = "free-threaded")]
+    if gil_enabled {
+        tracing::warn!(
+            "free-threaded feature is enabled but the Python GIL is active; sync handlers will not run in parallel"
+        );
+    }
+    !gil_enabled
+}
+
 pub mod prelude {
     pub use super::{
         setup_python_runtime, PythonActor, PythonChildProcessBuilder, PythonConfig,
-        PythonExecutionError,
+        PythonExecutionError, SyncExecution,
     };
 }
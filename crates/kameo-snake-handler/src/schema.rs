@@ -0,0 +1,566 @@
+//! JSON Schemas for message types, and validation of Python values against them.
+//!
+//! Schemas are built from the same [`PyStub`] descriptions as [`stubs`](crate::stubs), so they
+//! follow the `serde_py` encoding: structs are objects, enums are an `anyOf` of their variants
+//! shaped by serde's tagging, and `Option` fields may be `null` or left out. Values with a
+//! native Python form carry an `x-python-type` keyword (`datetime.datetime`, `uuid.UUID`, ...)
+//! instead of a JSON type.
+//!
+//! When [`PythonConfig::schema`](crate::PythonConfig::schema) is set, the child exposes the
+//! schemas to Python as `kameo.schema` and checks every reply against them before
+//! deserializing it, so a wrong-shaped reply fails with every offending path at once. In strict
+//! mode keys the Rust type doesn't declare are reported too:
+//!
+//! ```text
+//! Python reply does not match its schema: reply.RewardResult.total_currency: expected int,
+//! found str: '10'; reply.RewardResult.bonus: unexpected key
+//! ```
+
+use kameo_child_process::error::SchemaViolation;
+use kameo_child_process::KameoChildProcessMessage;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyByteArray, PyBytes, PyDict, PyFloat, PyFrozenSet, PyInt, PyList, PyMemoryView, PySet, PyString, PyTuple};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::serde_py::{is_number_sequence, join_path, object_fields, truncated_repr, Marker, PathSegment};
+use crate::stubs::{python_name, PyDefinition, PyStub, PyType, StubModule};
+
+/// The JSON Schema dialect of generated schemas.
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// JSON Schemas for a message type and its reply type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageSchema {
+    /// Schema of the messages handed to Python
+    pub message: Value,
+    /// Schema replies are validated against
+    pub reply: Value,
+    /// Whether objects may only hold the keys their Rust struct declares
+    pub strict: bool,
+}
+
+impl MessageSchema {
+    /// Schemas for `M` and `M::Ok`.
+    ///
+    /// With `strict`, extra keys in a reply are reported instead of being ignored the way
+    /// `serde_py` ignores them. Structs that `#[serde(flatten)]` a map accept any key either way.
+    pub fn new<M>(strict: bool) -> Self
+    where
+        M: KameoChildProcessMessage + PyStub,
+        M::Ok: PyStub,
+    {
+        Self { message: json_schema::<M>(strict), reply: json_schema::<M::Ok>(strict), strict }
+    }
+
+    /// Every place where `reply` does not match [`MessageSchema::reply`]; empty if it matches.
+    pub fn validate_reply(&self, reply: &Bound<'_, PyAny>) -> Vec<SchemaViolation> {
+        validate(reply, &self.reply, "reply")
+    }
+}
+
+/// The JSON Schema of `T`, with the definitions it refers to under `$defs`.
+pub fn json_schema<T: PyStub>(strict: bool) -> Value {
+    let mut stubs = StubModule::new();
+    let root = T::py_type(&mut stubs);
+    let mut schema = Map::new();
+    schema.insert("$schema".to_string(), json!(DIALECT));
+    if let Value::Object(root) = type_schema(&root) {
+        schema.extend(root);
+    }
+    let defs: Map<String, Value> =
+        stubs.py_definitions().into_iter().map(|definition| definition_schema(definition, strict)).collect();
+    if !defs.is_empty() {
+        schema.insert("$defs".to_string(), Value::Object(defs));
+    }
+    Value::Object(schema)
+}
+
+fn type_schema(ty: &PyType) -> Value {
+    match ty {
+        PyType::Any => json!({}),
+        PyType::None => json!({"type": "null"}),
+        PyType::Bool => json!({"type": "boolean"}),
+        PyType::Int => json!({"type": "integer"}),
+        PyType::Float => json!({"type": "number"}),
+        PyType::Str => json!({"type": "string"}),
+        PyType::Bytes => json!({"x-python-type": "bytes"}),
+        PyType::List(item) => json!({"type": "array", "items": type_schema(item)}),
+        PyType::Tuple(items) => json!({
+            "type": "array",
+            "prefixItems": items.iter().map(type_schema).collect::<Vec<_>>(),
+            "minItems": items.len(),
+            "maxItems": items.len(),
+        }),
+        PyType::Dict(key, value) => {
+            let mut schema = json!({"type": "object", "additionalProperties": type_schema(value)});
+            // Keys are whatever Python objects serde_py reads them from, not only strings
+            if **key != PyType::Str {
+                schema["propertyNames"] = type_schema(key);
+            }
+            schema
+        }
+        PyType::Optional(inner) if matches!(**inner, PyType::Any | PyType::None) => type_schema(inner),
+        PyType::Optional(inner) => json!({"anyOf": [type_schema(inner), {"type": "null"}]}),
+        PyType::Union(items) if items.len() == 1 => type_schema(&items[0]),
+        PyType::Union(items) => json!({"anyOf": items.iter().map(type_schema).collect::<Vec<_>>()}),
+        PyType::Literal(value) => json!({"const": value}),
+        PyType::Named(name) => json!({"$ref": format!("#/$defs/{}", python_name(name))}),
+        PyType::External(path) => json!({"x-python-type": path}),
+    }
+}
+
+fn definition_schema(definition: PyDefinition, strict: bool) -> (String, Value) {
+    match definition {
+        PyDefinition::TypedDict { name, fields, open } => {
+            let properties: Map<String, Value> =
+                fields.iter().map(|field| (field.name.clone(), type_schema(&field.ty))).collect();
+            // serde fills in a missing `Option` field with `None`
+            let required: Vec<&str> = fields
+                .iter()
+                .filter(|field| field.required && !matches!(field.ty, PyType::Optional(_)))
+                .map(|field| field.name.as_str())
+                .collect();
+            let mut schema = json!({"type": "object", "properties": properties, "required": required});
+            if strict && !open {
+                schema["additionalProperties"] = Value::Bool(false);
+            }
+            (name, schema)
+        }
+        PyDefinition::Alias { name, ty } => (name, type_schema(&ty)),
+    }
+}
+
+/// Every place where `value` does not match `schema`, with paths starting at `root`.
+///
+/// Understands the keywords generated by [`json_schema`]: `type`, `properties`, `required`,
+/// `additionalProperties`, `propertyNames`, `items`, `prefixItems`, `anyOf`, `const`, `$ref`
+/// into `$defs`, and `x-python-type`. Values are read the way `serde_py` reads them, so
+/// objects with fields stand in for dicts and tuples and sets for lists. `bool` is not
+/// accepted where an `int` is expected.
+pub fn validate(value: &Bound<'_, PyAny>, schema: &Value, root: &str) -> Vec<SchemaViolation> {
+    let validator = Validator { defs: schema.get("$defs") };
+    let mut violations = Vec::new();
+    validator.check(value, schema, root, &mut violations);
+    violations
+}
+
+static ANY: Value = Value::Bool(true);
+
+struct Validator<'s> {
+    defs: Option<&'s Value>,
+}
+
+impl<'s> Validator<'s> {
+    /// Follow a `$ref` into `$defs`; other schemas are returned as they are.
+    fn resolve(&self, schema: &'s Value) -> &'s Value {
+        let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+            return schema;
+        };
+        reference
+            .strip_prefix("#/$defs/")
+            .and_then(|name| self.defs?.get(name))
+            .unwrap_or(&ANY)
+    }
+
+    fn check(&self, value: &Bound<'_, PyAny>, schema: &'s Value, path: &str, out: &mut Vec<SchemaViolation>) {
+        let schema = self.resolve(schema);
+        if let Some(branches) = schema.get("anyOf").and_then(Value::as_array) {
+            return self.check_any_of(value, branches, path, out);
+        }
+        if let Some(expected) = schema.get("const").and_then(Value::as_str) {
+            if value.extract::<String>().ok().as_deref() != Some(expected) {
+                out.push(mismatch(path, &format!("'{expected}'"), value));
+            }
+            return;
+        }
+        if let Some(python_type) = schema.get("x-python-type").and_then(Value::as_str) {
+            if !is_python_type(value, python_type) {
+                out.push(mismatch(path, python_type, value));
+            }
+            return;
+        }
+        let Some(kind) = schema.get("type").and_then(Value::as_str) else { return };
+        if kind == "object" {
+            match fields_of(value) {
+                Some(fields) => self.check_object(&fields, schema, path, out),
+                None => out.push(mismatch(path, "dict", value)),
+            }
+        } else if !kind_matches(kind, value) {
+            out.push(mismatch(path, kind_name(kind), value));
+        } else if kind == "array" {
+            self.check_array(value, schema, path, out);
+        }
+    }
+
+    fn check_array(&self, value: &Bound<'_, PyAny>, schema: &'s Value, path: &str, out: &mut Vec<SchemaViolation>) {
+        let items: Vec<Bound<'_, PyAny>> = match value.try_iter().and_then(|items| items.collect()) {
+            Ok(items) => items,
+            Err(e) => return out.push(violation(path, format!("could not iterate: {e}"))),
+        };
+        let item_path = |idx: usize| join_path(path, &PathSegment::Index(idx).render());
+        if let Some(prefix) = schema.get("prefixItems").and_then(Value::as_array) {
+            if items.len() != prefix.len() {
+                return out.push(violation(path, format!("expected {} items, found {}", prefix.len(), items.len())));
+            }
+            for (idx, (item, item_schema)) in items.iter().zip(prefix).enumerate() {
+                self.check(item, item_schema, &item_path(idx), out);
+            }
+        } else if let Some(item_schema) = schema.get("items") {
+            for (idx, item) in items.iter().enumerate() {
+                self.check(item, item_schema, &item_path(idx), out);
+            }
+        }
+    }
+
+    fn check_object(&self, fields: &Bound<'_, PyDict>, schema: &'s Value, path: &str, out: &mut Vec<SchemaViolation>) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !fields.contains(name).unwrap_or(false) {
+                let key = PyString::new(fields.py(), name).into_any();
+                out.push(violation(&join_path(path, &PathSegment::Key(&key).render()), "missing required key".to_string()));
+            }
+        }
+        for (key, item) in fields.iter() {
+            let item_path = join_path(path, &PathSegment::Key(&key).render());
+            if let Some(key_schema) = schema.get("propertyNames") {
+                self.check(&key, key_schema, &item_path, out);
+            }
+            let declared = key.extract::<String>().ok().and_then(|key| properties?.get(&key));
+            match (declared, schema.get("additionalProperties")) {
+                (Some(item_schema), _) => self.check(&item, item_schema, &item_path, out),
+                (None, Some(Value::Bool(false))) => out.push(violation(&item_path, "unexpected key".to_string())),
+                (None, Some(item_schema @ Value::Object(_))) => self.check(&item, item_schema, &item_path, out),
+                (None, _) => {}
+            }
+        }
+    }
+
+    /// Report the mismatches of the branch `value` was evidently meant to match: the one whose
+    /// tag it carries, else the only one of the right kind. Otherwise list what was expected.
+    fn check_any_of(&self, value: &Bound<'_, PyAny>, branches: &'s [Value], path: &str, out: &mut Vec<SchemaViolation>) {
+        let mut results = Vec::with_capacity(branches.len());
+        for branch in branches {
+            let mut violations = Vec::new();
+            self.check(value, branch, path, &mut violations);
+            if violations.is_empty() {
+                return;
+            }
+            results.push(violations);
+        }
+        let fields = fields_of(value);
+        let tagged: Vec<usize> =
+            (0..branches.len()).filter(|&idx| self.tag_matches(&branches[idx], fields.as_ref()) == Some(true)).collect();
+        let chosen = match tagged[..] {
+            [idx] => Some(idx),
+            _ => match (0..branches.len()).filter(|&idx| self.accepts_kind(&branches[idx], value)).collect::<Vec<_>>()[..] {
+                [idx] => Some(idx),
+                _ => None,
+            },
+        };
+        match chosen {
+            Some(idx) => out.append(&mut results[idx]),
+            None => {
+                let expected: Vec<String> = branches.iter().map(|branch| self.describe(branch)).collect();
+                out.push(mismatch(path, &expected.join(" or "), value));
+            }
+        }
+    }
+
+    /// Whether `fields` carries the tag of an object `branch`: the values of its `const`
+    /// properties, or for a single required key (an externally tagged variant) that key.
+    /// `None` when the branch has no tag.
+    fn tag_matches(&self, branch: &'s Value, fields: Option<&Bound<'_, PyDict>>) -> Option<bool> {
+        let fields = fields?;
+        let branch = self.resolve(branch);
+        let properties = branch.get("properties")?.as_object()?;
+        let tags: Vec<(&String, &str)> =
+            properties.iter().filter_map(|(key, schema)| Some((key, schema.get("const")?.as_str()?))).collect();
+        if !tags.is_empty() {
+            return Some(tags.iter().all(|(key, tag)| {
+                let value = fields.get_item(key.as_str()).ok().flatten();
+                value.and_then(|value| value.extract::<String>().ok()).as_deref() == Some(*tag)
+            }));
+        }
+        match (properties.len(), branch.get("required")?.as_array()?.as_slice()) {
+            (1, [Value::String(key)]) => Some(fields.contains(key.as_str()).unwrap_or(false)),
+            _ => None,
+        }
+    }
+
+    /// Whether `value` is the right kind of Python value for `branch`, ignoring its contents.
+    fn accepts_kind(&self, branch: &'s Value, value: &Bound<'_, PyAny>) -> bool {
+        let branch = self.resolve(branch);
+        if let Some(branches) = branch.get("anyOf").and_then(Value::as_array) {
+            return branches.iter().any(|branch| self.accepts_kind(branch, value));
+        }
+        if branch.get("const").is_some() {
+            return value.is_instance_of::<PyString>();
+        }
+        if let Some(python_type) = branch.get("x-python-type").and_then(Value::as_str) {
+            return is_python_type(value, python_type);
+        }
+        match branch.get("type").and_then(Value::as_str) {
+            Some(kind) => kind_matches(kind, value),
+            None => true,
+        }
+    }
+
+    /// A short description of what `schema` accepts, e.g. `'Done'`, `int` or `ReplyItems`.
+    fn describe(&self, schema: &'s Value) -> String {
+        if let Some(name) = schema.get("$ref").and_then(Value::as_str).and_then(|reference| reference.strip_prefix("#/$defs/")) {
+            return name.to_string();
+        }
+        if let Some(branches) = schema.get("anyOf").and_then(Value::as_array) {
+            return branches.iter().map(|branch| self.describe(branch)).collect::<Vec<_>>().join(" or ");
+        }
+        if let Some(value) = schema.get("const").and_then(Value::as_str) {
+            return format!("'{value}'");
+        }
+        if let Some(python_type) = schema.get("x-python-type").and_then(Value::as_str) {
+            return python_type.to_string();
+        }
+        schema.get("type").and_then(Value::as_str).map_or("Any", kind_name).to_string()
+    }
+}
+
+/// The fields of a value read as a struct or map, as `serde_py` reads them.
+fn fields_of<'py>(value: &Bound<'py, PyAny>) -> Option<Bound<'py, PyDict>> {
+    if !kind_matches("object", value) {
+        return None;
+    }
+    object_fields(value).ok().flatten()
+}
+
+fn is_sequence(value: &Bound<'_, PyAny>) -> bool {
+    value.is_instance_of::<PyList>()
+        || value.is_instance_of::<PyTuple>()
+        || value.is_instance_of::<PySet>()
+        || value.is_instance_of::<PyFrozenSet>()
+        || value.is_instance_of::<PyBytes>()
+        || value.is_instance_of::<PyByteArray>()
+}
+
+fn kind_matches(kind: &str, value: &Bound<'_, PyAny>) -> bool {
+    let is_bool = value.is_instance_of::<PyBool>();
+    let is_int = value.is_instance_of::<PyInt>() && !is_bool;
+    match kind {
+        "null" => value.is_none(),
+        "boolean" => is_bool,
+        "integer" => is_int,
+        "number" => is_int || value.is_instance_of::<PyFloat>(),
+        "string" => value.is_instance_of::<PyString>(),
+        "array" => is_sequence(value),
+        "object" => !(value.is_none()
+            || is_bool
+            || is_int
+            || value.is_instance_of::<PyFloat>()
+            || value.is_instance_of::<PyString>()
+            || is_sequence(value)),
+        _ => true,
+    }
+}
+
+/// The Python name for a JSON Schema `type`.
+fn kind_name(kind: &str) -> &str {
+    match kind {
+        "null" => "None",
+        "boolean" => "bool",
+        "integer" => "int",
+        "number" => "float",
+        "string" => "str",
+        "array" => "list",
+        "object" => "dict",
+        other => other,
+    }
+}
+
+/// Whether `value` is accepted where `python_type` is expected. Values `serde_py` converts
+/// from other forms, such as ISO strings for datetimes, are accepted too.
+fn is_python_type(value: &Bound<'_, PyAny>, python_type: &str) -> bool {
+    if python_type == "bytes" {
+        return value.is_instance_of::<PyBytes>()
+            || value.is_instance_of::<PyByteArray>()
+            || value.is_instance_of::<PyMemoryView>();
+    }
+    if python_type == "memoryview" {
+        // What `PyBuffer` reads: anything with the buffer protocol, or a list of numbers
+        return is_number_sequence(value) || PyMemoryView::from(value).is_ok();
+    }
+    match Marker::ALL.into_iter().find(|marker| marker.python_type() == python_type) {
+        Some(marker) => marker.to_wire(value).is_ok(),
+        // A type given with `#[py_stub(type = ...)]`, which serde_py knows nothing about
+        None => true,
+    }
+}
+
+fn violation(path: &str, message: String) -> SchemaViolation {
+    SchemaViolation { path: path.to_string(), message }
+}
+
+fn mismatch(path: &str, expected: &str, value: &Bound<'_, PyAny>) -> SchemaViolation {
+    let found = value.get_type().name().map(|name| name.to_string()).unwrap_or_else(|_| "?".to_string());
+    violation(path, format!("expected {expected}, found {found}: {}", truncated_repr(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use kameo_child_process::error::PythonExecutionError;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyStub)]
+    struct Order {
+        item: String,
+        quantity: u32,
+        note: Option<String>,
+        #[serde(with = "crate::serde_py::uuid")]
+        id: uuid::Uuid,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyStub)]
+    #[serde(tag = "type")]
+    enum Command {
+        Buy(Order),
+        Cancel { id: u32 },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyStub)]
+    enum Reply {
+        Done,
+        Filled { orders: Vec<Order>, total: f64 },
+        Rejected(String),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, PyStub)]
+    struct Labelled {
+        name: String,
+        #[serde(flatten)]
+        extra: HashMap<String, i64>,
+    }
+
+    impl KameoChildProcessMessage for Command {
+        type Ok = Reply;
+    }
+
+    fn eval<'py>(py: Python<'py>, code: &str) -> Bound<'py, PyAny> {
+        py.eval(&CString::new(code).unwrap(), None, None).unwrap()
+    }
+
+    fn report(violations: &[SchemaViolation]) -> Vec<String> {
+        violations.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_json_schema_follows_serde_py_encoding() {
+        let schema = MessageSchema::new::<Command>(true);
+        assert_eq!(schema.message["$schema"], DIALECT);
+        assert_eq!(schema.message["$ref"], "#/$defs/Command");
+        let defs = &schema.message["$defs"];
+        assert_eq!(defs["Command"], json!({"anyOf": [{"$ref": "#/$defs/CommandBuy"}, {"$ref": "#/$defs/CommandCancel"}]}));
+        assert_eq!(
+            defs["CommandBuy"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "type": {"const": "Buy"},
+                    "item": {"type": "string"},
+                    "quantity": {"type": "integer"},
+                    "note": {"anyOf": [{"type": "string"}, {"type": "null"}]},
+                    "id": {"x-python-type": "uuid.UUID"},
+                },
+                "required": ["type", "item", "quantity", "id"],
+                "additionalProperties": false,
+            })
+        );
+        let defs = &schema.reply["$defs"];
+        assert_eq!(defs["Reply"]["anyOf"][0], json!({"const": "Done"}));
+        assert_eq!(
+            defs["ReplyRejected"],
+            json!({
+                "type": "object",
+                "properties": {"Rejected": {"type": "string"}},
+                "required": ["Rejected"],
+                "additionalProperties": false,
+            })
+        );
+        let lenient = MessageSchema::new::<Command>(false);
+        assert!(lenient.reply["$defs"]["ReplyFilledFields"].get("additionalProperties").is_none());
+        // A flattened map can hold any key, strict or not
+        assert!(json_schema::<Labelled>(true)["$defs"]["Labelled"].get("additionalProperties").is_none());
+    }
+
+    #[test]
+    fn test_serde_py_output_matches_its_schema() {
+        let order = Order { item: "tea".into(), quantity: 2, note: None, id: uuid::Uuid::nil() };
+        let schema = MessageSchema::new::<Command>(true);
+        Python::with_gil(|py| {
+            let messages = [Command::Buy(order.clone()), Command::Cancel { id: 7 }];
+            for message in &messages {
+                let value = crate::serde_py::to_pyobject(py, message).unwrap();
+                assert_eq!(report(&validate(value.bind(py), &schema.message, "message")), Vec::<String>::new(), "{message:?}");
+            }
+            let replies = [Reply::Done, Reply::Filled { orders: vec![order.clone()], total: 3.5 }, Reply::Rejected("no".into())];
+            for reply in &replies {
+                let value = crate::serde_py::to_pyobject(py, reply).unwrap();
+                assert_eq!(report(&schema.validate_reply(value.bind(py))), Vec::<String>::new(), "{reply:?}");
+            }
+
+            // Objects with fields stand in for dicts, as they do for serde_py
+            let module = PyModule::from_code(
+                py,
+                c"import dataclasses, uuid\n\n@dataclasses.dataclass\nclass Order:\n    item: str\n    quantity: int\n    note: str | None\n    id: uuid.UUID\n",
+                c"kameo_schema_test.py",
+                c"kameo_schema_test",
+            )
+            .unwrap();
+            let order_object = module.getattr("Order").unwrap().call1(("tea", 2, py.None(), eval(py, "__import__('uuid').UUID(int=0)"))).unwrap();
+            let filled = eval(py, "{'Filled': {'orders': [], 'total': 1}}");
+            filled.get_item("Filled").unwrap().get_item("orders").unwrap().call_method1("append", (&order_object,)).unwrap();
+            assert!(schema.validate_reply(&filled).is_empty());
+            let reply: Reply = crate::serde_py::from_pyobject(&filled).unwrap();
+            assert_eq!(reply, Reply::Filled { orders: vec![order], total: 1.0 });
+
+            let labelled = eval(py, "{'name': 'a', 'x': 1, 'y': 2}");
+            assert!(validate(&labelled, &json_schema::<Labelled>(true), "value").is_empty());
+        });
+    }
+
+    #[test]
+    fn test_violations_name_every_offending_path() {
+        Python::with_gil(|py| {
+            let reply = eval(
+                py,
+                "{'Filled': {'orders': [{'item': 'tea', 'quantity': '2', 'id': 5}, {'item': 'tea', 'id': '00000000-0000-0000-0000-000000000000'}], 'total': True, 'fee': 1}}",
+            );
+            let expected = [
+                "reply.Filled.orders[0].quantity: expected int, found str: '2'",
+                "reply.Filled.orders[0].id: expected uuid.UUID, found int: 5",
+                "reply.Filled.orders[1].quantity: missing required key",
+                "reply.Filled.total: expected float, found bool: True",
+                "reply.Filled.fee: unexpected key",
+            ];
+            assert_eq!(report(&MessageSchema::new::<Command>(true).validate_reply(&reply)), expected);
+            assert_eq!(report(&MessageSchema::new::<Command>(false).validate_reply(&reply)), expected[..4]);
+
+            let schema = MessageSchema::new::<Command>(false);
+            assert_eq!(
+                report(&schema.validate_reply(&eval(py, "'Dne'"))),
+                ["reply: expected 'Done', found str: 'Dne'"],
+                "only the unit variant is a string"
+            );
+            assert_eq!(report(&validate(&eval(py, "{'type': 'Cancel'}"), &schema.message, "message")), ["message.id: missing required key"]);
+            assert_eq!(
+                report(&validate(&eval(py, "{'type': 'Sell'}"), &schema.message, "message")),
+                ["message: expected CommandBuy or CommandCancel, found dict: {'type': 'Sell'}"],
+                "no variant has the tag, so the value is reported against all of them"
+            );
+            let error = PythonExecutionError::SchemaError { what: "reply".into(), violations: schema.validate_reply(&eval(py, "{'Rejected': 1}")) };
+            assert_eq!(
+                error.to_string(),
+                "Python reply does not match its schema: reply.Rejected: expected str, found int: 1"
+            );
+        });
+    }
+}
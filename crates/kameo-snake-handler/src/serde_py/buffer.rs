@@ -0,0 +1,310 @@
+//! Numeric vectors passed to Python as buffers instead of lists.
+//!
+//! A `Vec<f64>` becomes a list of boxed floats, one object per element. [`PyBuffer`] wraps the
+//! vector in a newtype marker instead: [`PythonSerializer`](super::ser::PythonSerializer) hands
+//! it to Python as a writable `memoryview` of the element type over a single copy of its bytes
+//! (two on big-endian hosts, which swap them), and
+//! [`PythonDeserializer`](super::de::PythonDeserializer) copies the elements of any object with
+//! the buffer protocol (`memoryview`, `array.array`, numpy arrays) straight into the vector.
+//! Lists and tuples of numbers are still accepted, element by element.
+//!
+//! Other formats see the elements as little-endian bytes, or as a plain sequence when they are
+//! human readable, like JSON.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyMemoryView, PyTuple};
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Result};
+
+const MARKER_PREFIX: &str = "$kameo_snake_handler::buffer::";
+
+/// A vector of numbers that `serde_py` passes to Python as a `memoryview` and reads back from
+/// any buffer, without converting element by element.
+///
+/// ```rust,ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Signal {
+///     rate: u32,
+///     samples: PyBuffer<f32>,
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PyBuffer<T>(pub Vec<T>);
+
+impl<T> PyBuffer<T> {
+    pub fn new(values: Vec<T>) -> Self {
+        PyBuffer(values)
+    }
+
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> From<Vec<T>> for PyBuffer<T> {
+    fn from(values: Vec<T>) -> Self {
+        PyBuffer(values)
+    }
+}
+
+impl<T> From<PyBuffer<T>> for Vec<T> {
+    fn from(buffer: PyBuffer<T>) -> Self {
+        buffer.0
+    }
+}
+
+impl<T> FromIterator<T> for PyBuffer<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        PyBuffer(iter.into_iter().collect())
+    }
+}
+
+impl<T> Deref for PyBuffer<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for PyBuffer<T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        &mut self.0
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A number type [`PyBuffer`] can hold: the fixed-size integers and floats.
+pub trait BufferElement:
+    sealed::Sealed + pyo3::buffer::Element + Copy + Serialize + for<'de> Deserialize<'de> + 'static
+{
+    /// Name of the newtype marker for buffers of this type
+    const MARKER: &'static str;
+    /// `struct` format character with this type's size on every supported platform
+    const TYPECODE: &'static str;
+
+    #[doc(hidden)]
+    fn as_ne_bytes(values: &[Self]) -> &[u8];
+
+    #[doc(hidden)]
+    fn extend_le_bytes(values: &[Self], out: &mut Vec<u8>);
+
+    #[doc(hidden)]
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_buffer_element {
+    ($($ty:ident => $typecode:literal),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl BufferElement for $ty {
+                const MARKER: &'static str = concat!("$kameo_snake_handler::buffer::", stringify!($ty));
+                const TYPECODE: &'static str = $typecode;
+
+                fn as_ne_bytes(values: &[Self]) -> &[u8] {
+                    zerocopy::IntoBytes::as_bytes(values)
+                }
+
+                fn extend_le_bytes(values: &[Self], out: &mut Vec<u8>) {
+                    out.reserve(std::mem::size_of_val(values));
+                    for value in values {
+                        out.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+
+                fn from_le_bytes(bytes: &[u8]) -> Self {
+                    $ty::from_le_bytes(bytes.try_into().expect("chunk of the element size"))
+                }
+            }
+        )*
+
+        /// Buffer kinds by marker name: the typecode and a reader for the element type.
+        const KINDS: &[(&str, &str, ReadBuffer)] = &[
+            $(($ty::MARKER, $typecode, read_buffer::<$ty>)),*
+        ];
+    };
+}
+
+type ReadBuffer = fn(&Bound<'_, PyAny>) -> Result<()>;
+
+impl_buffer_element!(
+    i8 => "b", u8 => "B", i16 => "h", u16 => "H", i32 => "i", u32 => "I",
+    i64 => "q", u64 => "Q", f32 => "f", f64 => "d",
+);
+
+/// The typecode and reader for a buffer marker name, if `name` is one.
+pub(crate) fn buffer_kind(name: &str) -> Option<(&'static str, ReadBuffer)> {
+    if !name.starts_with(MARKER_PREFIX) {
+        return None;
+    }
+    KINDS.iter().find(|(marker, ..)| *marker == name).map(|&(_, typecode, read)| (typecode, read))
+}
+
+/// View the little-endian bytes of a buffer, given as a `bytearray`, as elements of `typecode`.
+pub(crate) fn into_memoryview(py: Python<'_>, typecode: &str, bytes: PyObject) -> Result<PyObject> {
+    let bytes = bytes.into_bound(py);
+    let view = if cfg!(target_endian = "big") {
+        let array = py.import("array")?.getattr("array")?.call1((typecode, bytes))?;
+        array.call_method0("byteswap")?;
+        PyMemoryView::from(&array)?
+    } else {
+        PyMemoryView::from(&bytes)?.call_method1("cast", (typecode,))?.downcast_into::<PyMemoryView>()?
+    };
+    Ok(view.into_any().unbind())
+}
+
+/// Whether `input` should be read element by element rather than through the buffer protocol.
+pub(crate) fn is_number_sequence(input: &Bound<'_, PyAny>) -> bool {
+    input.is_instance_of::<PyList>() || input.is_instance_of::<PyTuple>()
+}
+
+thread_local! {
+    /// Elements read from a Python buffer, on their way from the deserializer to the
+    /// [`BufferVisitor`] of the same element type, which serde gives no typed path to.
+    static READ: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+}
+
+/// Copy the contents of any buffer of `T`s, contiguous or not, for [`take_read`].
+fn read_buffer<T: BufferElement>(input: &Bound<'_, PyAny>) -> Result<()> {
+    let buffer = pyo3::buffer::PyBuffer::<T>::get(input).map_err(|e| {
+        let name = std::any::type_name::<T>();
+        Error::Deserialization(format!("expected a buffer of {name}: {e}"))
+    })?;
+    let values = buffer.to_vec(input.py())?;
+    READ.with(|read| *read.borrow_mut() = Some(Box::new(values)));
+    Ok(())
+}
+
+/// The elements last read by [`read_buffer`], if they were `T`s.
+fn take_read<T: BufferElement>() -> Option<Vec<T>> {
+    let values = READ.with(|read| read.borrow_mut().take())?;
+    values.downcast::<Vec<T>>().ok().map(|values| *values)
+}
+
+/// The elements of a [`PyBuffer`], as bytes for compact formats and a sequence otherwise.
+struct Contents<'a, T>(&'a [T]);
+
+impl<T: BufferElement> Serialize for Contents<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+            for value in self.0 {
+                seq.serialize_element(value)?;
+            }
+            seq.end()
+        } else if cfg!(target_endian = "little") {
+            serializer.serialize_bytes(T::as_ne_bytes(self.0))
+        } else {
+            let mut bytes = Vec::new();
+            T::extend_le_bytes(self.0, &mut bytes);
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+}
+
+impl<T: BufferElement> Serialize for PyBuffer<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(T::MARKER, &Contents(&self.0))
+    }
+}
+
+struct ContentsVisitor<T>(PhantomData<T>);
+
+impl<'de, T: BufferElement> Visitor<'de> for ContentsVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a buffer of {}", std::any::type_name::<T>())
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> std::result::Result<Vec<T>, E> {
+        let size = std::mem::size_of::<T>();
+        if !bytes.len().is_multiple_of(size) {
+            return Err(E::invalid_length(bytes.len(), &self));
+        }
+        Ok(bytes.chunks_exact(size).map(T::from_le_bytes).collect())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> std::result::Result<Vec<T>, E> {
+        self.visit_bytes(&bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Vec<T>, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+struct BufferVisitor<T>(PhantomData<T>);
+
+impl<'de, T: BufferElement> Visitor<'de> for BufferVisitor<T> {
+    type Value = PyBuffer<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a buffer of {}", std::any::type_name::<T>())
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error> {
+        if let Some(values) = take_read::<T>() {
+            return Ok(PyBuffer(values));
+        }
+        let visitor = ContentsVisitor(PhantomData);
+        let values = if deserializer.is_human_readable() {
+            deserializer.deserialize_seq(visitor)?
+        } else {
+            deserializer.deserialize_bytes(visitor)?
+        };
+        Ok(PyBuffer(values))
+    }
+
+    // Formats that drop newtype wrappers hand over the contents directly
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> std::result::Result<Self::Value, E> {
+        ContentsVisitor(PhantomData).visit_bytes(bytes).map(PyBuffer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> std::result::Result<Self::Value, A::Error> {
+        ContentsVisitor(PhantomData).visit_seq(seq).map(PyBuffer)
+    }
+}
+
+impl<'de, T: BufferElement> Deserialize<'de> for PyBuffer<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(T::MARKER, BufferVisitor(PhantomData))
+    }
+}
+
+/// Stands in for a Python buffer whose elements [`read_buffer`] has already copied out for
+/// [`BufferVisitor`]; any other visitor gets an error.
+pub(crate) struct ReadBufferDeserializer;
+
+impl<'de> Deserializer<'de> for ReadBufferDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        READ.with(|read| read.borrow_mut().take());
+        Err(Error::Deserialization("a Python buffer can only be read as a PyBuffer".to_string()))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
@@ -0,0 +1,98 @@
+//! `chrono` types as `datetime` objects.
+//!
+//! | Rust | Python | other formats |
+//! |------|--------|---------------|
+//! | [`datetime`]: `DateTime<Utc>` | aware `datetime.datetime` in UTC | RFC 3339 string |
+//! | [`fixed_offset_datetime`]: `DateTime<FixedOffset>` | aware `datetime.datetime` | RFC 3339 string |
+//! | [`naive_datetime`]: `NaiveDateTime` | naive `datetime.datetime` | ISO 8601 string |
+//! | [`naive_date`]: `NaiveDate` | `datetime.date` | `YYYY-MM-DD` |
+//! | [`duration`]: `TimeDelta` | `datetime.timedelta` | `(seconds, nanoseconds)` |
+//!
+//! Python keeps microseconds, so nanoseconds are truncated on the way in. Each module has an
+//! `option` submodule for `Option` fields.
+
+use super::markers::Marker;
+use ::chrono::{DateTime, FixedOffset, NaiveDateTime, SecondsFormat, TimeDelta, Utc};
+
+const NAIVE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+fn parse_fixed(wire: &str) -> Result<DateTime<FixedOffset>, ::chrono::ParseError> {
+    DateTime::parse_from_rfc3339(wire)
+}
+
+/// `DateTime<Utc>`; naive Python datetimes are taken to be UTC.
+pub mod datetime {
+    use super::*;
+
+    super::super::markers::marked_with_module!(
+        Marker::DateTime,
+        DateTime<Utc>,
+        String,
+        |value: &DateTime<Utc>| value.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        |wire: String| parse_fixed(&wire)
+            .map(|value| value.with_timezone(&Utc))
+            .or_else(|err| wire.parse::<NaiveDateTime>().map(|naive| naive.and_utc()).map_err(|_| err))
+    );
+}
+
+/// `DateTime<FixedOffset>`, keeping the offset of the Python datetime.
+pub mod fixed_offset_datetime {
+    use super::*;
+
+    super::super::markers::marked_with_module!(
+        Marker::DateTime,
+        DateTime<FixedOffset>,
+        String,
+        |value: &DateTime<FixedOffset>| value.to_rfc3339_opts(SecondsFormat::AutoSi, false),
+        |wire: String| parse_fixed(&wire)
+    );
+}
+
+/// `NaiveDateTime`; an aware Python datetime keeps its wall-clock time and drops the offset.
+pub mod naive_datetime {
+    use super::*;
+
+    super::super::markers::marked_with_module!(
+        Marker::DateTime,
+        NaiveDateTime,
+        String,
+        |value: &NaiveDateTime| value.format(NAIVE_FORMAT).to_string(),
+        |wire: String| wire
+            .parse::<NaiveDateTime>()
+            .or_else(|err| parse_fixed(&wire).map(|value| value.naive_local()).map_err(|_| err))
+    );
+}
+
+/// `NaiveDate`.
+pub mod naive_date {
+    use super::*;
+    use ::chrono::NaiveDate;
+
+    super::super::markers::marked_with_module!(
+        Marker::Date,
+        NaiveDate,
+        String,
+        |value: &NaiveDate| value.format("%Y-%m-%d").to_string(),
+        |wire: String| wire.parse::<NaiveDate>()
+    );
+}
+
+/// `TimeDelta` (also known as `chrono::Duration`).
+pub mod duration {
+    use super::*;
+
+    super::super::markers::marked_with_module!(
+        Marker::TimeDelta,
+        TimeDelta,
+        (i64, u32),
+        |value: &TimeDelta| {
+            let (seconds, nanos) = (value.num_seconds(), value.subsec_nanos());
+            if nanos < 0 {
+                (seconds - 1, (nanos + 1_000_000_000) as u32)
+            } else {
+                (seconds, nanos as u32)
+            }
+        },
+        |(seconds, nanos): (i64, u32)| TimeDelta::new(seconds, nanos).ok_or("timedelta out of range")
+    );
+}
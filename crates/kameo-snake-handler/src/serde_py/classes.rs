@@ -0,0 +1,67 @@
+//! Python classes that `to_pyobject` builds instead of dicts.
+//!
+//! Serialization produces plain dicts for structs unless a class is registered under the
+//! struct's serde name (its identifier, or `#[serde(rename = "...")]`). A registered class is
+//! called with the serialized fields as keyword arguments, which suits dataclasses, Pydantic
+//! models, attrs classes and any class whose `__init__` takes its fields by name.
+//!
+//! Classes are looked up by dotted path and imported when first needed, so they can be
+//! registered before the interpreter starts.
+
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use super::{Error, Result};
+
+static CLASSES: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Lets serialization skip the lock while nothing is registered.
+static ANY_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// Build instances of `python_class` for Rust structs serialized under `rust_name`.
+///
+/// `python_class` is `package.module.Class`, or `package.module:Outer.Inner` for nested classes.
+pub fn register_class(rust_name: impl Into<String>, python_class: impl Into<String>) {
+    let mut classes = CLASSES.write().unwrap_or_else(|e| e.into_inner());
+    classes.insert(rust_name.into(), python_class.into());
+    ANY_REGISTERED.store(true, Ordering::Release);
+}
+
+/// Go back to dicts for `rust_name`. Returns whether a class was registered.
+pub fn unregister_class(rust_name: &str) -> bool {
+    let mut classes = CLASSES.write().unwrap_or_else(|e| e.into_inner());
+    let removed = classes.remove(rust_name).is_some();
+    ANY_REGISTERED.store(!classes.is_empty(), Ordering::Release);
+    removed
+}
+
+/// The class registered for `rust_name`, imported.
+pub(crate) fn registered_class<'py>(py: Python<'py>, rust_name: &str) -> Result<Option<Bound<'py, PyAny>>> {
+    if !ANY_REGISTERED.load(Ordering::Acquire) {
+        return Ok(None);
+    }
+    let path = match CLASSES.read().unwrap_or_else(|e| e.into_inner()).get(rust_name) {
+        Some(path) => path.clone(),
+        None => return Ok(None),
+    };
+    import_class(py, &path)
+        .map(Some)
+        .map_err(|e| Error::Serialization(format!("failed to import class {path} for {rust_name}: {e}")))
+}
+
+fn import_class<'py>(py: Python<'py>, path: &str) -> PyResult<Bound<'py, PyAny>> {
+    let (module, qualname) = match path.split_once(':') {
+        Some(split) => split,
+        None => path.rsplit_once('.').ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err("expected module.Class or module:Class")
+        })?,
+    };
+    let mut class = py.import(module)?.into_any();
+    for name in qualname.split('.') {
+        class = class.getattr(name)?;
+    }
+    Ok(class)
+}
@@ -1,6 +1,10 @@
 use pyo3::{
     prelude::*,
-    types::{PyAny, PyBool, PyDict, PyFloat, PyInt, PyList, PyString},
+    buffer::PyBuffer,
+    types::{
+        PyAny, PyBool, PyByteArray, PyBytes, PyDict, PyFloat, PyFrozenSet, PyInt, PyList, PyMemoryView,
+        PyModule, PySequence, PySet, PyString, PyTuple, PyType,
+    },
     Bound, FromPyObject,
 };
 use serde::de::{
@@ -8,7 +12,9 @@ use serde::de::{
 };
 use tracing::{error, instrument, trace};
 
-use super::{Error, Result};
+use super::buffer::{self, ReadBufferDeserializer};
+use super::markers::{self, Marker};
+use super::{Error, PathSegment, Result};
 
 impl From<PyErr> for Error {
     fn from(err: PyErr) -> Self {
@@ -22,6 +28,12 @@ impl From<pyo3::DowncastError<'_, '_>> for Error {
     }
 }
 
+impl From<pyo3::DowncastIntoError<'_>> for Error {
+    fn from(e: pyo3::DowncastIntoError<'_>) -> Self {
+        Error::Deserialization(e.to_string())
+    }
+}
+
 /// Convert a Python object to a Rust value
 #[instrument(
     skip(obj),
@@ -34,7 +46,7 @@ where
     T: for<'de> Deserialize<'de>,
 {
     let deserializer = PythonDeserializer { input: obj.clone() };
-    match T::deserialize(deserializer) {
+    match T::deserialize(deserializer).map_err(|e| e.found(obj)) {
         Ok(value) => {
             trace!(status = "success", target_type = std::any::type_name::<T>());
             Ok(value)
@@ -50,11 +62,211 @@ pub struct PythonDeserializer<'py> {
     input: Bound<'py, PyAny>,
 }
 
+impl<'py> PythonDeserializer<'py> {
+    pub fn new(input: Bound<'py, PyAny>) -> Self {
+        Self { input }
+    }
+}
+
 pub struct SeqDeserializer<'py> {
-    seq: Bound<'py, PyList>,
+    seq: Bound<'py, PySequence>,
+    len: usize,
     idx: usize,
 }
 
+impl<'py> SeqDeserializer<'py> {
+    /// Iterate a list, tuple, set, frozenset, bytes or bytearray; `None` for anything else.
+    ///
+    /// Sets have no order to index by, so they are snapshotted into a tuple first.
+    fn new(input: &Bound<'py, PyAny>) -> Result<Option<Self>> {
+        let seq = if input.is_instance_of::<PyList>()
+            || input.is_instance_of::<PyTuple>()
+            || input.is_instance_of::<PyBytes>()
+            || input.is_instance_of::<PyByteArray>()
+        {
+            input.downcast::<PySequence>()?.clone()
+        } else if input.is_instance_of::<PySet>() || input.is_instance_of::<PyFrozenSet>() {
+            let items = input.try_iter()?.collect::<PyResult<Vec<_>>>()?;
+            PyTuple::new(input.py(), items)?.downcast::<PySequence>()?.clone()
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(Self { len: seq.len()?, seq, idx: 0 }))
+    }
+}
+
+impl<'py> MapDeserializer<'py> {
+    fn new(map: Bound<'py, PyDict>) -> Self {
+        let keys = map.keys().iter().collect();
+        Self { map, keys, current_idx: 0 }
+    }
+}
+
+/// The variant name and payload of an externally tagged enum value: a dict with one item, or a
+/// `(name, payload)` tuple, which is how variants with data are written as dict keys.
+pub(crate) fn enum_parts<'py>(input: &Bound<'py, PyAny>) -> Result<Option<(Bound<'py, PyAny>, Bound<'py, PyAny>)>> {
+    if let Ok(dict) = input.downcast::<PyDict>() {
+        if dict.len() == 1 {
+            return Ok(dict.iter().next());
+        }
+    } else if let Ok(tuple) = input.downcast::<PyTuple>() {
+        if tuple.len() == 2 && tuple.get_item(0)?.is_instance_of::<PyString>() {
+            return Ok(Some((tuple.get_item(0)?, tuple.get_item(1)?)));
+        }
+    }
+    Ok(None)
+}
+
+/// The fields of a Python object that stands in for a struct or map, as a dict.
+///
+/// Tried in order: a dict itself, a Pydantic model (`model_dump()`), a dataclass
+/// (`__dataclass_fields__`), an attrs class (`__attrs_attrs__`), `__slots__` and finally the
+/// instance `__dict__`. Nested objects are left as they are and read the same way when their
+/// fields are deserialized. Names starting with `_` are skipped for `__slots__` and `__dict__`.
+/// Returns `None` for classes, modules, callables and anything without fields.
+pub(crate) fn object_fields<'py>(input: &Bound<'py, PyAny>) -> Result<Option<Bound<'py, PyDict>>> {
+    if let Ok(dict) = input.downcast::<PyDict>() {
+        return Ok(Some(dict.clone()));
+    }
+    if input.is_instance_of::<PyType>() || input.is_instance_of::<PyModule>() {
+        return Ok(None);
+    }
+    let py = input.py();
+    let class = input.get_type();
+    if class.hasattr("model_dump")? {
+        let dumped = input.call_method0("model_dump")?;
+        return Ok(Some(dumped.downcast_into::<PyDict>().map_err(|e| {
+            Error::Deserialization(format!("model_dump() did not return a dict: {e}"))
+        })?));
+    }
+    let fields = PyDict::new(py);
+    if class.hasattr("__dataclass_fields__")? {
+        // `dataclasses.fields` leaves out ClassVar and InitVar pseudo-fields
+        for field in py.import("dataclasses")?.call_method1("fields", (input,))?.try_iter()? {
+            let name = field?.getattr("name")?;
+            fields.set_item(&name, input.getattr(name.downcast::<PyString>()?)?)?;
+        }
+        return Ok(Some(fields));
+    }
+    if let Ok(attrs) = class.getattr("__attrs_attrs__") {
+        for attr in attrs.try_iter()? {
+            let name = attr?.getattr("name")?;
+            fields.set_item(&name, input.getattr(name.downcast::<PyString>()?)?)?;
+        }
+        return Ok(Some(fields));
+    }
+    let mut found = false;
+    for base in class.mro().iter() {
+        let Some(slots) = base.downcast::<PyType>()?.getattr("__dict__")?.get_item("__slots__").ok() else {
+            continue;
+        };
+        found = true;
+        let names: Vec<Bound<'py, PyAny>> = if slots.is_instance_of::<PyString>() {
+            vec![slots]
+        } else {
+            slots.try_iter()?.collect::<PyResult<_>>()?
+        };
+        for name in names {
+            let name = name.downcast_into::<PyString>()?;
+            if name.to_str()?.starts_with('_') || fields.contains(&name)? {
+                continue;
+            }
+            // Unset slots raise AttributeError; leave them out
+            if let Ok(value) = input.getattr(&name) {
+                fields.set_item(name, value)?;
+            }
+        }
+    }
+    if !input.is_callable() {
+        if let Ok(dict) = input.getattr("__dict__") {
+            for (name, value) in dict.downcast::<PyDict>()?.iter() {
+                if !name.downcast::<PyString>()?.to_str()?.starts_with('_') {
+                    fields.set_item(name, value)?;
+                }
+            }
+            found = true;
+        }
+    }
+    Ok(found.then_some(fields))
+}
+
+/// Visit a Python int with the narrowest type that holds it.
+fn visit_int<'de, V: Visitor<'de>>(input: &Bound<'_, PyAny>, visitor: V) -> Result<V::Value> {
+    if let Ok(v) = i64::extract_bound(input) {
+        visitor.visit_i64(v)
+    } else if let Ok(v) = u64::extract_bound(input) {
+        visitor.visit_u64(v)
+    } else if let Ok(v) = i128::extract_bound(input) {
+        visitor.visit_i128(v)
+    } else if let Ok(v) = u128::extract_bound(input) {
+        visitor.visit_u128(v)
+    } else {
+        Err(Error::Deserialization(format!("integer {input} does not fit in 128 bits")))
+    }
+}
+
+fn is_bytes_like(input: &Bound<'_, PyAny>) -> bool {
+    input.is_instance_of::<PyBytes>() || input.is_instance_of::<PyByteArray>() || input.is_instance_of::<PyMemoryView>()
+}
+
+/// Visit `bytes`, `bytearray` or a `memoryview` as a byte buffer.
+fn visit_bytes_like<'de, V: Visitor<'de>>(input: &Bound<'_, PyAny>, visitor: V) -> Result<V::Value> {
+    match input.downcast::<PyBytes>() {
+        Ok(bytes) => visitor.visit_bytes(bytes.as_bytes()),
+        Err(_) => visitor.visit_byte_buf(extract_bytes_like(input)?),
+    }
+}
+
+fn extract_bytes_like(input: &Bound<'_, PyAny>) -> Result<Vec<u8>> {
+    if let Ok(bytes) = input.downcast::<PyBytes>() {
+        Ok(bytes.as_bytes().to_vec())
+    } else if let Ok(bytes) = input.downcast::<PyByteArray>() {
+        Ok(bytes.to_vec())
+    } else {
+        Ok(PyBuffer::<u8>::get(input)?.to_vec(input.py())?)
+    }
+}
+
+/// Extract a primitive, reporting a mismatch the way serde does: `invalid type: string "x",
+/// expected f64`, or `invalid value` for ints out of range.
+pub(crate) fn extract<T>(input: &Bound<'_, PyAny>, expected: &dyn de::Expected) -> Result<T>
+where
+    T: for<'a> FromPyObject<'a>,
+{
+    let err = match T::extract_bound(input) {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+    let text;
+    let unexpected = if input.is_none() {
+        de::Unexpected::Option
+    } else if let Ok(b) = input.downcast::<PyBool>() {
+        de::Unexpected::Bool(b.is_true())
+    } else if input.is_instance_of::<PyInt>() {
+        let unexpected = match input.extract::<i64>() {
+            Ok(i) => de::Unexpected::Signed(i),
+            Err(_) => match input.extract::<u64>() {
+                Ok(u) => de::Unexpected::Unsigned(u),
+                Err(_) => de::Unexpected::Other("big integer"),
+            },
+        };
+        return Err(de::Error::invalid_value(unexpected, expected));
+    } else if input.is_instance_of::<PyFloat>() {
+        de::Unexpected::Float(input.extract()?)
+    } else if let Ok(s) = input.downcast::<PyString>() {
+        text = s.to_string();
+        de::Unexpected::Str(&text)
+    } else if input.is_instance_of::<PyDict>() {
+        de::Unexpected::Map
+    } else if input.is_instance_of::<PyList>() || input.is_instance_of::<PyTuple>() {
+        de::Unexpected::Seq
+    } else {
+        // Some other object whose conversion failed; Python's message says more than its type
+        return Err(err.into());
+    };
+    Err(de::Error::invalid_type(unexpected, expected))
+}
+
 pub struct MapDeserializer<'py> {
     map: Bound<'py, PyDict>,
     keys: Vec<Bound<'py, PyAny>>,
@@ -62,7 +274,8 @@ pub struct MapDeserializer<'py> {
 }
 
 pub struct EnumDeserializer<'py> {
-    input: Bound<'py, PyAny>,
+    variant: Bound<'py, PyAny>,
+    value: Bound<'py, PyAny>,
 }
 
 pub struct UnitEnumDeserializer {
@@ -86,21 +299,25 @@ where
             if input.is_instance_of::<PyBool>() {
                 visitor.visit_bool(bool::extract_bound(input)?)
             } else if input.is_instance_of::<PyInt>() {
-                visitor.visit_i64(i64::extract_bound(input)?)
+                visit_int(input, visitor)
             } else if input.is_instance_of::<PyFloat>() {
                 visitor.visit_f64(f64::extract_bound(input)?)
             } else if input.is_instance_of::<PyString>() {
                 visitor.visit_string(String::extract_bound(input)?)
-            } else if input.is_instance_of::<PyList>() {
-                let list = input.downcast::<PyList>()?;
-                visitor.visit_seq(SeqDeserializer { seq: list.clone(), idx: 0 })
-            } else if input.is_instance_of::<PyDict>() {
-                let dict = input.downcast::<PyDict>()?;
-                let mut keys = Vec::new();
-                for k in dict.keys().iter() {
-                    keys.push(k.clone());
-                }
-                visitor.visit_map(MapDeserializer { map: dict.clone(), keys, current_idx: 0 })
+            } else if is_bytes_like(input) {
+                // A sequence of ints rather than `visit_bytes`, which `serde_json::Value` rejects;
+                // byte buffer types accept both
+                let bytes = extract_bytes_like(input)?;
+                visitor.visit_seq(de::value::SeqDeserializer::<_, Error>::new(bytes.into_iter()))
+            } else if let Some(seq) = SeqDeserializer::new(input)? {
+                visitor.visit_seq(seq)
+            } else if let Ok(dict) = input.downcast::<PyDict>() {
+                // Before the marker types, whose checks import modules
+                visitor.visit_map(MapDeserializer::new(dict.clone()))
+            } else if let Some(wire) = markers::native_to_wire(input)? {
+                PythonDeserializer { input: wire }.deserialize_any(visitor)
+            } else if let Some(fields) = object_fields(input)? {
+                visitor.visit_map(MapDeserializer::new(fields))
             } else {
                 Err(Error::Deserialization(format!("unsupported Python type: {}", input.get_type().name()?)))
             }
@@ -111,84 +328,111 @@ where
     where
         V: Visitor<'de>,
     {
-        visitor.visit_bool(bool::extract_bound(&self.input)?)
+        let value = extract::<bool>(&self.input, &visitor)?;
+        visitor.visit_bool(value)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i8(i8::extract_bound(&self.input)?)
+        let value = extract::<i8>(&self.input, &visitor)?;
+        visitor.visit_i8(value)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i16(i16::extract_bound(&self.input)?)
+        let value = extract::<i16>(&self.input, &visitor)?;
+        visitor.visit_i16(value)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i32(i32::extract_bound(&self.input)?)
+        let value = extract::<i32>(&self.input, &visitor)?;
+        visitor.visit_i32(value)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i64(i64::extract_bound(&self.input)?)
+        let value = extract::<i64>(&self.input, &visitor)?;
+        visitor.visit_i64(value)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u8(u8::extract_bound(&self.input)?)
+        let value = extract::<u8>(&self.input, &visitor)?;
+        visitor.visit_u8(value)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u16(u16::extract_bound(&self.input)?)
+        let value = extract::<u16>(&self.input, &visitor)?;
+        visitor.visit_u16(value)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u32(u32::extract_bound(&self.input)?)
+        let value = extract::<u32>(&self.input, &visitor)?;
+        visitor.visit_u32(value)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u64(u64::extract_bound(&self.input)?)
+        let value = extract::<u64>(&self.input, &visitor)?;
+        visitor.visit_u64(value)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = extract::<i128>(&self.input, &visitor)?;
+        visitor.visit_i128(value)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = extract::<u128>(&self.input, &visitor)?;
+        visitor.visit_u128(value)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f32(f32::extract_bound(&self.input)?)
+        let value = extract::<f32>(&self.input, &visitor)?;
+        visitor.visit_f32(value)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f64(f64::extract_bound(&self.input)?)
+        let value = extract::<f64>(&self.input, &visitor)?;
+        visitor.visit_f64(value)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let s = String::extract_bound(&self.input)?;
+        let s = extract::<String>(&self.input, &visitor)?;
         let mut chars = s.chars();
         match (chars.next(), chars.next()) {
             (Some(c), None) => visitor.visit_char(c),
@@ -202,7 +446,8 @@ where
     where
         V: Visitor<'de>,
     {
-        visitor.visit_string(String::extract_bound(&self.input)?)
+        let value = extract::<String>(&self.input, &visitor)?;
+        visitor.visit_string(value)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -216,8 +461,12 @@ where
     where
         V: Visitor<'de>,
     {
-        let bytes = Vec::<u8>::extract_bound(&self.input)?;
-        visitor.visit_bytes(&bytes)
+        if is_bytes_like(&self.input) {
+            visit_bytes_like(&self.input, visitor)
+        } else {
+            // A list of ints, for example
+            visitor.visit_byte_buf(Vec::<u8>::extract_bound(&self.input)?)
+        }
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
@@ -256,10 +505,20 @@ where
         self.deserialize_unit(visitor)
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if let Some(marker) = Marker::from_name(name) {
+            let input = marker.to_wire(&self.input)?;
+            return visitor.visit_newtype_struct(PythonDeserializer { input });
+        }
+        if let Some((_, read)) = buffer::buffer_kind(name) {
+            if !buffer::is_number_sequence(&self.input) {
+                read(&self.input)?;
+                return visitor.visit_newtype_struct(ReadBufferDeserializer);
+            }
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -267,21 +526,13 @@ where
     where
         V: Visitor<'de>,
     {
-        if !self.input.is_instance_of::<PyList>() {
-            return Err(Error::Deserialization(format!(
-                "expected list, got {}",
+        match SeqDeserializer::new(&self.input)? {
+            Some(seq) => visitor.visit_seq(seq),
+            None => Err(Error::Deserialization(format!(
+                "expected list, tuple or set, got {}",
                 self.input.get_type().name()?
-            )));
+            ))),
         }
-
-        let list = self
-            .input
-            .downcast::<PyList>()
-            .map_err(|_| Error::Deserialization("expected list".to_string()))?;
-        visitor.visit_seq(SeqDeserializer {
-            seq: list.clone(),
-            idx: 0,
-        })
     }
 
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
@@ -307,27 +558,13 @@ where
     where
         V: Visitor<'de>,
     {
-        if !self.input.is_instance_of::<PyDict>() {
-            return Err(Error::Deserialization(format!(
-                "expected dict, got {}",
+        match object_fields(&self.input)? {
+            Some(fields) => visitor.visit_map(MapDeserializer::new(fields)),
+            None => Err(Error::Deserialization(format!(
+                "expected dict or object with fields, got {}",
                 self.input.get_type().name()?
-            )));
+            ))),
         }
-
-        let dict = self
-            .input
-            .downcast::<PyDict>()
-            .map_err(|_| Error::Deserialization("expected dict".to_string()))?;
-        let mut keys = Vec::new();
-        for k in dict.keys().iter() {
-            keys.push(k.clone());
-        }
-
-        visitor.visit_map(MapDeserializer {
-            map: dict.clone(),
-            keys,
-            current_idx: 0,
-        })
     }
 
     fn deserialize_struct<V>(
@@ -351,12 +588,8 @@ where
     where
         V: Visitor<'de>,
     {
-        let _py = self.input.py();
-
-        if let Ok(dict) = self.input.downcast::<PyDict>() {
-            if dict.len() == 1 {
-                return visitor.visit_enum(EnumDeserializer { input: self.input });
-            }
+        if let Some((variant, value)) = enum_parts(&self.input)? {
+            return visitor.visit_enum(EnumDeserializer { variant, value });
         }
 
         if let Ok(s) = String::extract_bound(&self.input) {
@@ -391,15 +624,21 @@ where
     where
         T: DeserializeSeed<'de>,
     {
-        if self.idx >= self.seq.len() {
+        if self.idx >= self.len {
             return Ok(None);
         }
 
-        let element = self.seq.get_item(self.idx)?;
+        let idx = self.idx;
+        let element = self.seq.get_item(idx)?;
         self.idx += 1;
 
-        seed.deserialize(PythonDeserializer { input: element })
+        seed.deserialize(PythonDeserializer { input: element.clone() })
             .map(Some)
+            .map_err(|e| e.at(PathSegment::Index(idx), &element))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.idx)
     }
 }
 
@@ -420,6 +659,7 @@ where
         let key = &self.keys[self.current_idx];
         seed.deserialize(PythonDeserializer { input: key.clone() })
             .map(Some)
+            .map_err(|e| e.at(PathSegment::Key(key), key))
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -434,7 +674,8 @@ where
             .ok_or_else(|| Error::Deserialization("missing value for key".to_string()))?;
         self.current_idx += 1;
 
-        seed.deserialize(PythonDeserializer { input: value })
+        seed.deserialize(PythonDeserializer { input: value.clone() })
+            .map_err(|e| e.at(PathSegment::Key(key), &value))
     }
 }
 
@@ -449,17 +690,7 @@ where
     where
         V: DeserializeSeed<'de>,
     {
-        let dict = self
-            .input
-            .downcast::<PyDict>()
-            .map_err(|_| Error::Deserialization("expected dict for enum variant".to_string()))?;
-
-        let (key, _) = dict
-            .iter()
-            .next()
-            .ok_or_else(|| Error::Deserialization("empty dict for enum variant".to_string()))?;
-
-        let key_str = String::extract_bound(&key)?;
+        let key_str = String::extract_bound(&self.variant)?;
         let variant = seed.deserialize(
             serde::de::value::StringDeserializer::<Self::Error>::new(key_str),
         )?;
@@ -481,76 +712,43 @@ where
     where
         T: DeserializeSeed<'de>,
     {
-        let dict = self
-            .input
-            .downcast::<PyDict>()
-            .map_err(|_| Error::Deserialization("expected dict for enum variant".to_string()))?;
-
-        let (_, value) = dict
-            .iter()
-            .next()
-            .ok_or_else(|| Error::Deserialization("empty dict for enum variant".to_string()))?;
+        let (key, value) = (self.variant, self.value);
 
         seed.deserialize(PythonDeserializer {
             input: value.clone(),
         })
+        .map_err(|e| e.at(PathSegment::Key(&key), &value))
     }
 
     fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let dict = self
-            .input
-            .downcast::<PyDict>()
-            .map_err(|_| Error::Deserialization("expected dict for enum variant".to_string()))?;
+        let (key, value) = (self.variant, self.value);
 
-        let (_, value) = dict
-            .iter()
-            .next()
-            .ok_or_else(|| Error::Deserialization("empty dict for enum variant".to_string()))?;
-
-        if let Ok(list) = value.downcast::<PyList>() {
-            visitor.visit_seq(SeqDeserializer {
-                seq: list.clone(),
-                idx: 0,
-            })
-        } else {
-            Err(Error::Deserialization(
-                "expected list for tuple variant".to_string(),
-            ))
+        match SeqDeserializer::new(&value)? {
+            Some(seq) => visitor.visit_seq(seq),
+            None => Err(Error::Deserialization(
+                "expected list or tuple for tuple variant".to_string(),
+            )),
         }
+        .map_err(|e| e.at(PathSegment::Key(&key), &value))
     }
 
     fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let dict = self
-            .input
-            .downcast::<PyDict>()
-            .map_err(|_| Error::Deserialization("expected dict for enum variant".to_string()))?;
+        let (key, value) = (self.variant, self.value);
 
-        let (_, value) = dict
-            .iter()
-            .next()
-            .ok_or_else(|| Error::Deserialization("empty dict for enum variant".to_string()))?;
-
-        if let Ok(dict) = value.downcast::<PyDict>() {
-            let mut keys = Vec::new();
-            for k in dict.keys().iter() {
-                keys.push(k.clone());
-            }
-            visitor.visit_map(MapDeserializer {
-                map: dict.clone(),
-                keys,
-                current_idx: 0,
-            })
+        if let Some(fields) = object_fields(&value)? {
+            visitor.visit_map(MapDeserializer::new(fields))
         } else {
             Err(Error::Deserialization(
                 "expected dict for struct variant".to_string(),
             ))
         }
+        .map_err(|e| e.at(PathSegment::Key(&key), &value))
     }
 }
 
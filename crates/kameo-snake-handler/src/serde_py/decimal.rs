@@ -0,0 +1,13 @@
+//! `rust_decimal::Decimal` as `decimal.Decimal`.
+//!
+//! Other formats see the decimal's string form. Python decimals in exponent notation
+//! (`1E+2`) are accepted; NaN and infinities are not.
+
+use super::markers::{marked_with_module, Marker};
+use ::rust_decimal::Decimal;
+
+fn parse(wire: String) -> Result<Decimal, ::rust_decimal::Error> {
+    Decimal::from_str_exact(&wire).or_else(|_| Decimal::from_scientific(&wire))
+}
+
+marked_with_module!(Marker::Decimal, Decimal, String, |value: &Decimal| value.to_string(), parse);
@@ -0,0 +1,288 @@
+//! Newtype markers for values that have a native Python type.
+//!
+//! serde has no notion of a date or a decimal, so the `with` modules in [`super::chrono`],
+//! [`super::time`], [`super::decimal`] and [`super::uuid`] wrap their value in a newtype struct
+//! with one of the names below. Other serializers see a plain string (or a `(seconds, nanos)`
+//! pair for durations); [`PythonSerializer`](super::ser::PythonSerializer) and
+//! [`PythonDeserializer`](super::de::PythonDeserializer) recognise the name and convert to and
+//! from `datetime`, `decimal.Decimal` and `uuid.UUID`.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyFloat, PyInt, PyString, PyTuple};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::marker::PhantomData;
+
+use super::{Error, Result};
+
+/// Which native Python type a marked newtype stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Marker {
+    /// `datetime.datetime`, as an ISO 8601 string with or without a UTC offset
+    DateTime,
+    /// `datetime.date`, as `YYYY-MM-DD`
+    Date,
+    /// `datetime.timedelta`, as `(seconds, nanoseconds)` with `0 <= nanoseconds < 1e9`
+    TimeDelta,
+    /// `decimal.Decimal`, as its string form
+    Decimal,
+    /// `uuid.UUID`, as the hyphenated string form
+    Uuid,
+}
+
+impl Marker {
+    /// Most specific first: a `datetime` is also a `date`
+    pub(crate) const ALL: [Marker; 5] = [Marker::DateTime, Marker::Date, Marker::TimeDelta, Marker::Decimal, Marker::Uuid];
+
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Marker::DateTime => "$kameo_snake_handler::datetime",
+            Marker::Date => "$kameo_snake_handler::date",
+            Marker::TimeDelta => "$kameo_snake_handler::timedelta",
+            Marker::Decimal => "$kameo_snake_handler::decimal",
+            Marker::Uuid => "$kameo_snake_handler::uuid",
+        }
+    }
+
+    /// The Python type this marker converts to, as `module.Class`.
+    pub(crate) const fn python_type(self) -> &'static str {
+        match self {
+            Marker::DateTime => "datetime.datetime",
+            Marker::Date => "datetime.date",
+            Marker::TimeDelta => "datetime.timedelta",
+            Marker::Decimal => "decimal.Decimal",
+            Marker::Uuid => "uuid.UUID",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        if !name.starts_with("$kameo_snake_handler::") {
+            return None;
+        }
+        Marker::ALL.into_iter().find(|marker| marker.name() == name)
+    }
+
+    /// Build the Python object from the serialized (wire) form of a marked value.
+    pub(crate) fn into_python(self, py: Python<'_>, inner: PyObject) -> Result<PyObject> {
+        let inner = inner.bind(py);
+        let object = match self {
+            Marker::DateTime => {
+                let iso = normalize_iso_datetime(inner.downcast::<PyString>()?.to_str()?);
+                py.import("datetime")?.getattr("datetime")?.call_method1("fromisoformat", (iso,))?
+            }
+            Marker::Date => py.import("datetime")?.getattr("date")?.call_method1("fromisoformat", (inner,))?,
+            Marker::TimeDelta => {
+                let (seconds, nanos) = match inner.extract::<Vec<i64>>()?[..] {
+                    [seconds, nanos] => (seconds, nanos),
+                    _ => return Err(Error::Serialization("malformed timedelta".to_string())),
+                };
+                let kwargs = PyDict::new(py);
+                kwargs.set_item("seconds", seconds)?;
+                kwargs.set_item("microseconds", nanos / 1000)?;
+                py.import("datetime")?.getattr("timedelta")?.call((), Some(&kwargs))?
+            }
+            Marker::Decimal => py.import("decimal")?.getattr("Decimal")?.call1((inner,))?,
+            Marker::Uuid => py.import("uuid")?.getattr("UUID")?.call1((inner,))?,
+        };
+        Ok(object.unbind())
+    }
+
+    /// Turn a Python object into the wire form of a marked value.
+    ///
+    /// Strings (and numbers, for durations and decimals) are passed through, so values that
+    /// were converted by hand still deserialize.
+    pub(crate) fn to_wire<'py>(self, input: &Bound<'py, PyAny>) -> Result<Bound<'py, PyAny>> {
+        if let Some(wire) = self.native_to_wire(input)? {
+            return Ok(wire);
+        }
+        let py = input.py();
+        let is_number = input.is_instance_of::<PyInt>() || input.is_instance_of::<PyFloat>();
+        match self {
+            Marker::TimeDelta if is_number => {
+                let seconds: f64 = input.extract()?;
+                let whole = seconds.floor();
+                let nanos = ((seconds - whole) * 1e9).round().min(999_999_999.0);
+                return Ok(PyTuple::new(py, [whole as i64, nanos as i64])?.into_any());
+            }
+            Marker::Decimal if is_number => return Ok(input.str()?.into_any()),
+            _ => {}
+        }
+        if input.is_instance_of::<PyString>() {
+            return Ok(input.clone());
+        }
+        Err(Error::Deserialization(format!("expected {}, got {}", self.python_type(), input.get_type().name()?)))
+    }
+
+    /// The wire form of `input` if it is an instance of this marker's Python type.
+    fn native_to_wire<'py>(self, input: &Bound<'py, PyAny>) -> Result<Option<Bound<'py, PyAny>>> {
+        let py = input.py();
+        let wire = match self {
+            Marker::DateTime => {
+                if !input.is_instance(&py.import("datetime")?.getattr("datetime")?)? {
+                    return Ok(None);
+                }
+                input.call_method0("isoformat")?
+            }
+            Marker::Date => {
+                let datetime = py.import("datetime")?;
+                if input.is_instance(&datetime.getattr("datetime")?)? {
+                    input.call_method0("date")?.call_method0("isoformat")?
+                } else if input.is_instance(&datetime.getattr("date")?)? {
+                    input.call_method0("isoformat")?
+                } else {
+                    return Ok(None);
+                }
+            }
+            Marker::TimeDelta => {
+                if !input.is_instance(&py.import("datetime")?.getattr("timedelta")?)? {
+                    return Ok(None);
+                }
+                let days: i64 = input.getattr("days")?.extract()?;
+                let seconds: i64 = input.getattr("seconds")?.extract()?;
+                let micros: u32 = input.getattr("microseconds")?.extract()?;
+                PyTuple::new(py, [days * 86_400 + seconds, i64::from(micros) * 1000])?.into_any()
+            }
+            Marker::Decimal => {
+                if !input.is_instance(&py.import("decimal")?.getattr("Decimal")?)? {
+                    return Ok(None);
+                }
+                input.str()?.into_any()
+            }
+            Marker::Uuid => {
+                if !input.is_instance(&py.import("uuid")?.getattr("UUID")?)? {
+                    return Ok(None);
+                }
+                input.str()?.into_any()
+            }
+        };
+        Ok(Some(wire))
+    }
+}
+
+/// The wire form of a `datetime`, `date`, `timedelta`, `Decimal` or `UUID`, for
+/// self-describing targets that never ask for a marker by name: `serde_json::Value` and the
+/// content buffered for internally tagged and untagged enums.
+pub(crate) fn native_to_wire<'py>(input: &Bound<'py, PyAny>) -> Result<Option<Bound<'py, PyAny>>> {
+    for marker in Marker::ALL {
+        if let Some(wire) = marker.native_to_wire(input)? {
+            return Ok(Some(wire));
+        }
+    }
+    Ok(None)
+}
+
+/// Make an RFC 3339 string acceptable to `datetime.fromisoformat` on every supported Python:
+/// `Z` becomes `+00:00` and fractional seconds are cut to microseconds.
+fn normalize_iso_datetime(iso: &str) -> String {
+    let (base, offset) = match iso.strip_suffix('Z') {
+        Some(base) => (base, "+00:00"),
+        None => match iso.rfind(['+', '-']).filter(|&i| i > iso.find('T').unwrap_or(usize::MAX)) {
+            Some(i) => (&iso[..i], &iso[i..]),
+            None => (iso, ""),
+        },
+    };
+    match base.split_once('.') {
+        Some((whole, fraction)) => {
+            let micros: String = fraction.chars().chain(std::iter::repeat('0')).take(6).collect();
+            format!("{whole}.{micros}{offset}")
+        }
+        None => format!("{base}{offset}"),
+    }
+}
+
+/// Serialize `value` wrapped in `marker`'s newtype.
+pub(crate) fn serialize_marked<S, T>(marker: Marker, value: &T, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize + ?Sized,
+{
+    serializer.serialize_newtype_struct(marker.name(), value)
+}
+
+/// Deserialize a value written by [`serialize_marked`].
+pub(crate) fn deserialize_marked<'de, D, T>(marker: Marker, deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct MarkedVisitor<T>(Marker, PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for MarkedVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a {:?} value", self.0)
+        }
+
+        fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<T, D::Error> {
+            T::deserialize(deserializer)
+        }
+
+        // Formats that drop newtype wrappers hand over the inner value directly
+        fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<T, E> {
+            T::deserialize(de::value::StrDeserializer::new(v))
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> std::result::Result<T, A::Error> {
+            T::deserialize(de::value::SeqAccessDeserializer::new(seq))
+        }
+    }
+
+    deserializer.deserialize_newtype_struct(marker.name(), MarkedVisitor(marker, PhantomData))
+}
+
+/// Define a `with` module for `$ty` and an `option` submodule for `Option<$ty>`, given
+/// functions converting to and from the marked form.
+macro_rules! marked_with_module {
+    ($marker:expr, $ty:ty, $wire:ty, $to_wire:expr, $from_wire:expr) => {
+        pub fn serialize<S: serde::Serializer>(value: &$ty, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+            let wire: $wire = $to_wire(value);
+            $crate::serde_py::markers::serialize_marked($marker, &wire, serializer)
+        }
+
+        pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> ::std::result::Result<$ty, D::Error> {
+            let wire: $wire = $crate::serde_py::markers::deserialize_marked($marker, deserializer)?;
+            $from_wire(wire).map_err(<D::Error as serde::de::Error>::custom)
+        }
+
+        /// The same conversion for `Option` fields; `None` is Python `None`.
+        pub mod option {
+            #[allow(unused_imports)]
+            use super::*;
+            use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+            #[derive(Serialize, Deserialize)]
+            #[serde(transparent)]
+            struct Marked(#[serde(with = "super")] $ty);
+
+            pub fn serialize<S: Serializer>(value: &Option<$ty>, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+                value.clone().map(Marked).serialize(serializer)
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Option<$ty>, D::Error> {
+                Ok(Option::<Marked>::deserialize(deserializer)?.map(|Marked(value)| value))
+            }
+        }
+    };
+}
+
+pub(crate) use marked_with_module;
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_iso_datetime;
+
+    #[test]
+    fn test_normalize_iso_datetime() {
+        let cases = [
+            ("2024-01-02T03:04:05Z", "2024-01-02T03:04:05+00:00"),
+            ("2024-01-02T03:04:05.123456789+05:30", "2024-01-02T03:04:05.123456+05:30"),
+            ("2024-01-02T03:04:05.5-08:00", "2024-01-02T03:04:05.500000-08:00"),
+            ("2024-01-02T03:04:05.000001", "2024-01-02T03:04:05.000001"),
+            ("2024-01-02T03:04:05", "2024-01-02T03:04:05"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_iso_datetime(input), expected, "{input}");
+        }
+    }
+}
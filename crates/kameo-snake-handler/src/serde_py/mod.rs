@@ -1,11 +1,28 @@
-use pyo3::{prelude::*, types::PyAny};
+use pyo3::{prelude::*, types::{PyAny, PyString}};
 use serde::Deserialize;
 
+mod buffer;
+#[cfg(feature = "chrono")]
+pub mod chrono;
+mod classes;
 mod de;
+#[cfg(feature = "rust_decimal")]
+pub mod decimal;
+mod markers;
 mod ser;
+#[cfg(feature = "time")]
+pub mod time;
+pub mod uuid;
 
-pub use de::from_pyobject;
-pub use ser::to_pyobject;
+pub(crate) use classes::registered_class;
+pub use buffer::{BufferElement, PyBuffer};
+pub(crate) use buffer::is_number_sequence;
+pub use classes::{register_class, unregister_class};
+pub use de::{from_pyobject, PythonDeserializer};
+pub(crate) use de::{enum_parts, extract, object_fields};
+pub(crate) use markers::Marker;
+pub(crate) use ser::to_dict_key;
+pub use ser::{to_pyobject, PythonSerializer};
 
 /// Trait for types that can be converted from PyAny
 pub trait FromPyAny: Sized {
@@ -29,6 +46,125 @@ pub enum Error {
     Serialization(String),
     Deserialization(String),
     UnsupportedType(String),
+    /// A deserialization error together with where in the Python value it happened
+    AtPath(Box<PathError>),
+}
+
+/// Longest `repr()` of an offending value kept in a [`PathError`], in characters.
+pub const MAX_REPR_LEN: usize = 80;
+
+/// Where deserialization failed, and what was found there.
+#[derive(Debug)]
+pub struct PathError {
+    /// Path from the root value, e.g. `items[3].price`; empty when the root itself is at fault
+    pub path: String,
+    /// Python type name of the offending value
+    pub python_type: String,
+    /// `repr()` of the offending value, truncated to [`MAX_REPR_LEN`] characters
+    pub repr: String,
+    /// What went wrong
+    pub error: Error,
+}
+
+/// One step from a container to the value inside it.
+pub(crate) enum PathSegment<'a, 'py> {
+    Index(usize),
+    Key(&'a Bound<'py, PyAny>),
+}
+
+impl PathSegment<'_, '_> {
+    /// `name` for string keys that are identifiers, `[...]` for everything else.
+    pub(crate) fn render(&self) -> String {
+        let key = match self {
+            PathSegment::Index(idx) => return format!("[{idx}]"),
+            PathSegment::Key(key) => key,
+        };
+        match key.downcast::<PyString>().ok().and_then(|key| key.to_str().ok().map(str::to_owned)) {
+            Some(name) if is_identifier(&name) => name,
+            _ => format!("[{}]", truncated_repr(key)),
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+pub(crate) fn truncated_repr(value: &Bound<'_, PyAny>) -> String {
+    let repr = match value.repr() {
+        Ok(repr) => repr.to_string(),
+        Err(_) => return "<repr() failed>".to_string(),
+    };
+    match repr.char_indices().nth(MAX_REPR_LEN) {
+        Some((end, _)) => format!("{}...", &repr[..end]),
+        None => repr,
+    }
+}
+
+/// Join a segment onto the front of a path built from the leaf upwards.
+pub(crate) fn join_path(segment: &str, path: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else if path.starts_with('[') {
+        format!("{segment}{path}")
+    } else {
+        format!("{segment}.{path}")
+    }
+}
+
+impl Error {
+    /// Path to the offending value, for errors returned by [`from_pyobject`].
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::AtPath(located) => Some(&located.path),
+            _ => None,
+        }
+    }
+
+    /// Record that this error happened at `value`, unless a more precise location is known.
+    pub(crate) fn found(self, value: &Bound<'_, PyAny>) -> Self {
+        match self {
+            Error::AtPath(_) => self,
+            error => Error::AtPath(Box::new(PathError {
+                path: String::new(),
+                python_type: value.get_type().name().map(|name| name.to_string()).unwrap_or_default(),
+                repr: truncated_repr(value),
+                error,
+            })),
+        }
+    }
+
+    /// Record that this error happened inside `value`, reached from its container by `segment`.
+    pub(crate) fn at(self, segment: PathSegment<'_, '_>, value: &Bound<'_, PyAny>) -> Self {
+        match self.found(value) {
+            Error::AtPath(mut located) => {
+                located.path = join_path(&segment.render(), &located.path);
+                Error::AtPath(located)
+            }
+            error => error,
+        }
+    }
+
+    /// Convert to the error reported for a Python return value, naming the root `root`.
+    pub fn into_execution_error(self, root: &str) -> kameo_child_process::error::PythonExecutionError {
+        let (path, python_type, repr, message) = match self {
+            Error::AtPath(located) => {
+                (join_path(root, &located.path), located.python_type, located.repr, located.error.message())
+            }
+            error => (root.to_string(), String::new(), String::new(), error.message()),
+        };
+        kameo_child_process::error::PythonExecutionError::DeserializationError { message, path, python_type, repr }
+    }
+
+    /// The error without its kind prefix.
+    fn message(&self) -> String {
+        match self {
+            Error::Serialization(msg) | Error::Deserialization(msg) | Error::UnsupportedType(msg) => msg.clone(),
+            Error::AtPath(located) => located.error.message(),
+            Error::Py(err) => err.to_string(),
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -38,6 +174,21 @@ impl std::fmt::Display for Error {
             Error::Serialization(msg) => write!(f, "Serialization error: {}", msg),
             Error::Deserialization(msg) => write!(f, "Deserialization error: {}", msg),
             Error::UnsupportedType(msg) => write!(f, "Unsupported type: {}", msg),
+            Error::AtPath(located) if located.path.is_empty() => write!(
+                f,
+                "Deserialization error: {} (found {}: {})",
+                located.error.message(),
+                located.python_type,
+                located.repr
+            ),
+            Error::AtPath(located) => write!(
+                f,
+                "Deserialization error at {}: {} (found {}: {})",
+                located.path,
+                located.error.message(),
+                located.python_type,
+                located.repr
+            ),
         }
     }
 }
@@ -65,11 +216,11 @@ impl From<serde::de::value::Error> for Error {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use proptest::collection::{hash_map, vec};
+    use proptest::collection::{btree_map, hash_map, vec};
     use proptest::prelude::*;
     use proptest::strategy::{BoxedStrategy, Strategy};
     use serde::{Deserialize, Serialize};
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     mod nested_option_helper {
         use serde::{self, Deserialize, Deserializer, Serializer};
@@ -522,6 +673,82 @@ mod tests {
         .boxed()
     }
 
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    struct Payload {
+        id: u64,
+        label: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[serde(tag = "type")]
+    enum InternallyTaggedEnum {
+        Unit,
+        NewType(Payload),
+        StreamFibonacci { count: u32 },
+        Struct { x: i32, y: String, z: bool, items: Vec<i16> },
+    }
+
+    fn internally_tagged_enum_strategy() -> BoxedStrategy<InternallyTaggedEnum> {
+        prop_oneof![
+            Just(InternallyTaggedEnum::Unit),
+            (any::<u64>(), prop::option::of(any::<String>()))
+                .prop_map(|(id, label)| InternallyTaggedEnum::NewType(Payload { id, label })),
+            any::<u32>().prop_map(|count| InternallyTaggedEnum::StreamFibonacci { count }),
+            (any::<i32>(), any::<String>(), any::<bool>(), vec(any::<i16>(), 0..5))
+                .prop_map(|(x, y, z, items)| InternallyTaggedEnum::Struct { x, y, z, items })
+        ]
+        .boxed()
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[serde(tag = "type", content = "payload")]
+    enum AdjacentlyTaggedEnum {
+        Unit,
+        NewType(i32),
+        Tuple(i32, String, bool),
+        Struct { x: i32, y: String, z: bool },
+        Nested(ComplexEnum),
+    }
+
+    fn adjacently_tagged_enum_strategy() -> BoxedStrategy<AdjacentlyTaggedEnum> {
+        prop_oneof![
+            Just(AdjacentlyTaggedEnum::Unit),
+            any::<i32>().prop_map(AdjacentlyTaggedEnum::NewType),
+            (any::<i32>(), any::<String>(), any::<bool>())
+                .prop_map(|(i, s, b)| AdjacentlyTaggedEnum::Tuple(i, s, b)),
+            (any::<i32>(), any::<String>(), any::<bool>())
+                .prop_map(|(x, y, z)| AdjacentlyTaggedEnum::Struct { x, y, z }),
+            complex_enum_strategy().prop_map(AdjacentlyTaggedEnum::Nested)
+        ]
+        .boxed()
+    }
+
+    // Variants are tried in order, so each one must reject the others' values
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[serde(untagged)]
+    enum UntaggedEnum {
+        Nothing,
+        Flag(bool),
+        Number(i64),
+        Text(String),
+        Pair(i32, String),
+        Tagged(InternallyTaggedEnum),
+        Point { x: i32, y: String },
+    }
+
+    fn untagged_enum_strategy() -> BoxedStrategy<UntaggedEnum> {
+        prop_oneof![
+            Just(UntaggedEnum::Nothing),
+            any::<bool>().prop_map(UntaggedEnum::Flag),
+            any::<i64>().prop_map(UntaggedEnum::Number),
+            any::<String>().prop_map(UntaggedEnum::Text),
+            (any::<i32>(), any::<String>()).prop_map(|(i, s)| UntaggedEnum::Pair(i, s)),
+            internally_tagged_enum_strategy().prop_map(UntaggedEnum::Tagged),
+            (any::<i32>(), any::<String>()).prop_map(|(x, y)| UntaggedEnum::Point { x, y })
+        ]
+        .boxed()
+    }
+
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
     struct AsyncTestStruct {
         future_result: Option<i32>,
@@ -545,6 +772,47 @@ mod tests {
             .boxed()
     }
 
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+    enum KeyEnum {
+        Red,
+        Code(u16),
+        Pair(i8, String),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct KeyedMaps {
+        by_id: HashMap<u32, String>,
+        by_cell: BTreeMap<(i32, i32), bool>,
+        by_kind: BTreeMap<KeyEnum, i64>,
+        by_signed: HashMap<i64, Option<u8>>,
+    }
+
+    fn key_enum_strategy() -> BoxedStrategy<KeyEnum> {
+        prop_oneof![
+            Just(KeyEnum::Red),
+            any::<u16>().prop_map(KeyEnum::Code),
+            (any::<i8>(), any::<String>()).prop_map(|(i, s)| KeyEnum::Pair(i, s)),
+        ]
+        .boxed()
+    }
+
+    fn keyed_maps_strategy() -> BoxedStrategy<KeyedMaps> {
+        (
+            hash_map(any::<u32>(), any::<String>(), 0..8),
+            btree_map((any::<i32>(), any::<i32>()), any::<bool>(), 0..8),
+            btree_map(key_enum_strategy(), any::<i64>(), 0..8),
+            hash_map(any::<i64>(), any::<Option<u8>>(), 0..8),
+        )
+            .prop_map(|(by_id, by_cell, by_kind, by_signed)| KeyedMaps { by_id, by_cell, by_kind, by_signed })
+            .boxed()
+    }
+
+    /// The keys of a dict, in order, as `repr()` strings.
+    fn dict_key_reprs(dict: &Bound<'_, PyAny>) -> Vec<String> {
+        let dict = dict.downcast::<pyo3::types::PyDict>().unwrap();
+        dict.keys().iter().map(|key| key.repr().unwrap().to_string()).collect()
+    }
+
     proptest! {
         #[test]
         fn test_all_primitives_roundtrip(test_struct in all_primitives_strategy()) {
@@ -569,6 +837,33 @@ mod tests {
             });
         }
 
+        #[test]
+        fn test_internally_tagged_enum_roundtrip(value in internally_tagged_enum_strategy()) {
+            Python::with_gil(|py| {
+                let py_obj = to_pyobject(py, &value).unwrap();
+                let roundtrip: InternallyTaggedEnum = from_pyobject(py_obj.bind(py)).unwrap();
+                assert_eq!(value, roundtrip);
+            });
+        }
+
+        #[test]
+        fn test_adjacently_tagged_enum_roundtrip(value in adjacently_tagged_enum_strategy()) {
+            Python::with_gil(|py| {
+                let py_obj = to_pyobject(py, &value).unwrap();
+                let roundtrip: AdjacentlyTaggedEnum = from_pyobject(py_obj.bind(py)).unwrap();
+                assert_eq!(value, roundtrip);
+            });
+        }
+
+        #[test]
+        fn test_untagged_enum_roundtrip(values in vec(untagged_enum_strategy(), 0..5)) {
+            Python::with_gil(|py| {
+                let py_obj = to_pyobject(py, &values).unwrap();
+                let roundtrip: Vec<UntaggedEnum> = from_pyobject(py_obj.bind(py)).unwrap();
+                assert_eq!(values, roundtrip);
+            });
+        }
+
         #[test]
         fn test_async_struct_roundtrip(value in async_test_struct_strategy()) {
             Python::with_gil(|py| {
@@ -605,5 +900,649 @@ mod tests {
                 assert_eq!(value, roundtrip);
             });
         }
+
+        #[test]
+        fn test_map_keys_roundtrip_as_hashable_values(value in keyed_maps_strategy()) {
+            Python::with_gil(|py| {
+                let py_obj = to_pyobject(py, &value).unwrap();
+                let dict = py_obj.bind(py);
+                let cells = dict.get_item("by_cell").unwrap();
+                let expected: Vec<String> = value.by_cell.keys().map(|(x, y)| format!("({x}, {y})")).collect();
+                prop_assert_eq!(dict_key_reprs(&cells), expected, "tuple keys, in BTreeMap order");
+                for key in dict.get_item("by_id").unwrap().downcast::<pyo3::types::PyDict>().unwrap().keys() {
+                    prop_assert!(key.is_instance_of::<pyo3::types::PyInt>());
+                }
+                let roundtrip: KeyedMaps = from_pyobject(dict).unwrap();
+                prop_assert_eq!(value, roundtrip);
+                Ok(())
+            })?;
+        }
+
+        #[test]
+        fn test_index_map_keeps_insertion_order(entries in vec((any::<String>(), any::<i32>()), 0..12)) {
+            let map: indexmap::IndexMap<String, i32> = entries.into_iter().collect();
+            Python::with_gil(|py| {
+                let py_obj = to_pyobject(py, &map).unwrap();
+                let expected: Vec<String> = map.keys().map(|key| key.into_pyobject(py).unwrap().repr().unwrap().to_string()).collect();
+                prop_assert_eq!(dict_key_reprs(py_obj.bind(py)), expected);
+                let roundtrip: indexmap::IndexMap<String, i32> = from_pyobject(py_obj.bind(py)).unwrap();
+                prop_assert!(roundtrip.iter().eq(map.iter()), "dict order is kept on the way back");
+                Ok(())
+            })?;
+        }
+    }
+
+    fn eval<'py>(py: Python<'py>, code: &str) -> Bound<'py, PyAny> {
+        let code = std::ffi::CString::new(code).unwrap();
+        py.eval(&code, None, None).unwrap()
+    }
+
+    #[test]
+    fn test_any_accepts_tuples_sets_and_bytes() {
+        use serde_json::{json, Value};
+        Python::with_gil(|py| {
+            let cases = [
+                ("(1, 'a', None)", json!([1, "a", null])),
+                ("((1, 2), [3, (4,)])", json!([[1, 2], [3, [4]]])),
+                ("{'k': (True, 1.5)}", json!({"k": [true, 1.5]})),
+                ("{3}", json!([3])),
+                ("frozenset({'x'})", json!(["x"])),
+                ("b'ab'", json!([97, 98])),
+                ("bytearray(b'ab')", json!([97, 98])),
+                ("memoryview(b'ab')", json!([97, 98])),
+                ("2**64 - 1", json!(u64::MAX)),
+                ("-2**63", json!(i64::MIN)),
+            ];
+            for (code, expected) in cases {
+                let value: Value = from_pyobject(&eval(py, code)).unwrap();
+                assert_eq!(value, expected, "{code}");
+            }
+            let mut set: Vec<i64> = from_pyobject(&eval(py, "{1, 2, 3}")).unwrap();
+            set.sort();
+            assert_eq!(set, [1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn test_untagged_enum_from_tuple_and_bytes() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(untagged)]
+        enum Reply {
+            Pair(i32, String),
+            Raw(Vec<u8>),
+            Text(String),
+        }
+        Python::with_gil(|py| {
+            let pair: Reply = from_pyobject(&eval(py, "(7, 'seven')")).unwrap();
+            assert_eq!(pair, Reply::Pair(7, "seven".into()));
+            let raw: Reply = from_pyobject(&eval(py, "b'\\x00\\xff'")).unwrap();
+            assert_eq!(raw, Reply::Raw(vec![0, 255]));
+            let text: Reply = from_pyobject(&eval(py, "'hi'")).unwrap();
+            assert_eq!(text, Reply::Text("hi".into()));
+        });
+    }
+
+    #[test]
+    fn test_typed_targets_accept_tuples_and_bytes() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Typed {
+            pair: (i32, String),
+            items: Vec<u32>,
+            data: Vec<u8>,
+        }
+        Python::with_gil(|py| {
+            let typed: Typed = from_pyobject(&eval(py, "{'pair': (1, 'x'), 'items': (2, 3), 'data': b'ok'}")).unwrap();
+            assert_eq!(typed, Typed { pair: (1, "x".into()), items: vec![2, 3], data: b"ok".to_vec() });
+            let err = from_pyobject::<Vec<u32>>(&eval(py, "'not a list'")).unwrap_err();
+            assert!(err.to_string().contains("got str"), "{err}");
+        });
+    }
+
+    #[test]
+    fn test_big_ints_fall_back_to_wider_types() {
+        struct AnyInt(String);
+        impl<'de> Deserialize<'de> for AnyInt {
+            fn deserialize<D: serde::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+                struct V;
+                impl serde::de::Visitor<'_> for V {
+                    type Value = AnyInt;
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("an int")
+                    }
+                    fn visit_i64<E>(self, v: i64) -> std::result::Result<AnyInt, E> {
+                        Ok(AnyInt(format!("i64 {v}")))
+                    }
+                    fn visit_u64<E>(self, v: u64) -> std::result::Result<AnyInt, E> {
+                        Ok(AnyInt(format!("u64 {v}")))
+                    }
+                    fn visit_i128<E>(self, v: i128) -> std::result::Result<AnyInt, E> {
+                        Ok(AnyInt(format!("i128 {v}")))
+                    }
+                    fn visit_u128<E>(self, v: u128) -> std::result::Result<AnyInt, E> {
+                        Ok(AnyInt(format!("u128 {v}")))
+                    }
+                }
+                d.deserialize_any(V)
+            }
+        }
+        Python::with_gil(|py| {
+            let cases = [
+                ("-5", "i64 -5"),
+                ("2**63", "u64 9223372036854775808"),
+                ("-2**100", "i128 -1267650600228229401496703205376"),
+                ("2**127", "u128 170141183460469231731687303715884105728"),
+            ];
+            for (code, expected) in cases {
+                assert_eq!(from_pyobject::<AnyInt>(&eval(py, code)).unwrap().0, expected, "{code}");
+            }
+            assert!(from_pyobject::<AnyInt>(&eval(py, "2**128")).is_err());
+            assert_eq!(from_pyobject::<i128>(&eval(py, "-2**100")).unwrap(), -(1i128 << 100));
+            assert_eq!(from_pyobject::<u128>(&eval(py, "2**127")).unwrap(), 1u128 << 127);
+        });
+    }
+
+    /// Run `code` and return the `value` it defines.
+    fn run<'py>(py: Python<'py>, code: &str) -> Bound<'py, PyAny> {
+        let globals = pyo3::types::PyDict::new(py);
+        py.run(&std::ffi::CString::new(code).unwrap(), Some(&globals), None).unwrap();
+        globals.get_item("value").unwrap().unwrap()
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u32,
+        tags: Vec<String>,
+        customer: Customer,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Customer {
+        name: String,
+    }
+
+    fn order() -> Order {
+        Order { id: 7, tags: vec!["a".into()], customer: Customer { name: "Ada".into() } }
+    }
+
+    #[test]
+    fn test_objects_deserialize_as_structs() {
+        Python::with_gil(|py| {
+            let sources = [
+                // dataclasses, nested, with a ClassVar that isn't a field
+                "from dataclasses import dataclass, field\n\
+                 from typing import ClassVar\n\
+                 @dataclass\n\
+                 class Customer:\n    name: str\n\
+                 @dataclass\n\
+                 class Order:\n    id: int\n    customer: Customer\n    tags: list = field(default_factory=list)\n    kind: ClassVar[str] = 'x'\n\
+                 value = Order(7, Customer('Ada'), ['a'])",
+                // Pydantic-style: model_dump() returns plain dicts
+                "class Order:\n    def model_dump(self):\n        return {'id': 7, 'tags': ['a'], 'customer': {'name': 'Ada'}}\n\
+                 value = Order()",
+                // attrs-style: __attrs_attrs__ lists the fields
+                "class A:\n    def __init__(self, name): self.name = name\n\
+                 class Customer:\n    __attrs_attrs__ = (A('name'),)\n    def __init__(self): self.name = 'Ada'\n\
+                 class Order:\n    __attrs_attrs__ = (A('id'), A('tags'), A('customer'))\n\
+                 \x20   def __init__(self): self.id, self.tags, self.customer, self.hidden = 7, ['a'], Customer(), 1\n\
+                 value = Order()",
+                // __slots__, including an inherited slot and a private one
+                "class Base:\n    __slots__ = ('id',)\n\
+                 class Customer:\n    __slots__ = 'name'\n    def __init__(self): self.name = 'Ada'\n\
+                 class Order(Base):\n    __slots__ = ('tags', 'customer', '_cache')\n\
+                 \x20   def __init__(self): self.id, self.tags, self.customer, self._cache = 7, ('a',), Customer(), {}\n\
+                 value = Order()",
+                // plain __dict__, skipping private attributes
+                "class Customer:\n    def __init__(self): self.name = 'Ada'\n\
+                 class Order:\n    def __init__(self): self.id, self.tags, self.customer, self._secret = 7, ['a'], Customer(), 1\n\
+                 value = Order()",
+            ];
+            for code in sources {
+                let value: Order = from_pyobject(&run(py, code)).unwrap_or_else(|e| panic!("{e}\n{code}"));
+                assert_eq!(value, order(), "{code}");
+            }
+            // Self-describing targets see the same fields
+            let value: serde_json::Value = from_pyobject(&run(py, sources[4])).unwrap();
+            assert_eq!(value, serde_json::json!({"id": 7, "tags": ["a"], "customer": {"name": "Ada"}}));
+            // Classes and functions are not field bags
+            assert!(from_pyobject::<Customer>(&eval(py, "int")).is_err());
+            assert!(from_pyobject::<serde_json::Value>(&eval(py, "len")).is_err());
+        });
+    }
+
+    #[test]
+    fn test_registered_class_is_built_instead_of_dict() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct RegisteredPoint {
+            x: i32,
+            y: i32,
+        }
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct RegisteredLine {
+            start: RegisteredPoint,
+            end: RegisteredPoint,
+        }
+        Python::with_gil(|py| {
+            run(
+                py,
+                "import sys, types\n\
+                 from dataclasses import dataclass\n\
+                 shapes = types.ModuleType('kameo_test_shapes')\n\
+                 @dataclass\n\
+                 class Point:\n    x: int\n    y: int\n\
+                 class Outer:\n    Point = Point\n\
+                 shapes.Point, shapes.Outer = Point, Outer\n\
+                 sys.modules['kameo_test_shapes'] = shapes\n\
+                 value = None",
+            );
+            let line = RegisteredLine { start: RegisteredPoint { x: 1, y: 2 }, end: RegisteredPoint { x: 3, y: 4 } };
+            let plain = to_pyobject(py, &line).unwrap();
+            assert!(plain.bind(py).getattr("start").is_err(), "dicts by default");
+
+            register_class("RegisteredPoint", "kameo_test_shapes.Point");
+            let built = to_pyobject(py, &line).unwrap();
+            let built = built.bind(py);
+            assert!(built.downcast::<pyo3::types::PyDict>().is_ok(), "unregistered structs stay dicts");
+            let start = built.get_item("start").unwrap();
+            assert_eq!(start.get_type().name().unwrap().to_string(), "Point");
+            assert_eq!(start.getattr("y").unwrap().extract::<i32>().unwrap(), 2);
+            let roundtrip: RegisteredLine = from_pyobject(built).unwrap();
+            assert_eq!(roundtrip, line);
+
+            register_class("RegisteredPoint", "kameo_test_shapes:Outer.Point");
+            let start = to_pyobject(py, &line.start).unwrap();
+            assert_eq!(start.bind(py).get_type().name().unwrap().to_string(), "Point");
+
+            register_class("RegisteredPoint", "kameo_test_shapes.Missing");
+            let err = to_pyobject(py, &line).unwrap_err();
+            assert!(err.to_string().contains("kameo_test_shapes.Missing"), "{err}");
+
+            assert!(unregister_class("RegisteredPoint"));
+            assert!(!unregister_class("RegisteredPoint"));
+            assert!(to_pyobject(py, &line.start).unwrap().bind(py).downcast::<pyo3::types::PyDict>().is_ok());
+        });
+    }
+
+    #[test]
+    fn test_errors_name_the_offending_path() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Item {
+            price: f64,
+        }
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Reply {
+            items: Vec<Item>,
+            meta: HashMap<String, (u8, bool)>,
+        }
+        Python::with_gil(|py| {
+            let cases = [
+                (
+                    "{'items': [{'price': 1.0}] * 3 + [{'price': 'cheap'}], 'meta': {}}",
+                    "items[3].price",
+                    "str",
+                    "'cheap'",
+                ),
+                ("{'items': [], 'meta': {'a b': (300, True)}}", "meta['a b'][0]", "int", "300"),
+                ("{'items': [{}], 'meta': {}}", "items[0]", "dict", "{}"),
+                ("[]", "", "list", "[]"),
+            ];
+            for (code, path, python_type, repr) in cases {
+                let err = from_pyobject::<Reply>(&eval(py, code)).unwrap_err();
+                let Error::AtPath(located) = &err else { panic!("no path for {code}: {err}") };
+                assert_eq!(located.path, path, "{err}");
+                assert_eq!(located.python_type, python_type, "{err}");
+                assert_eq!(located.repr, repr, "{err}");
+            }
+
+            let err = from_pyobject::<Reply>(&eval(py, cases[0].0)).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Deserialization error at items[3].price: invalid type: string \"cheap\", expected f64 (found str: 'cheap')"
+            );
+            let err = from_pyobject::<Reply>(&eval(py, cases[1].0)).unwrap_err();
+            assert!(err.to_string().contains("invalid value: integer `300`, expected u8"), "{err}");
+
+            let long = eval(py, "{'items': [{'price': 'x' * 500}], 'meta': {}}");
+            let err = from_pyobject::<Reply>(&long).unwrap_err();
+            let Error::AtPath(located) = &err else { panic!("{err}") };
+            assert_eq!(located.repr.chars().count(), MAX_REPR_LEN + 3, "truncated with an ellipsis");
+            assert!(err.to_string().starts_with("Deserialization error at items[0].price: "), "{err}");
+            assert!(err.to_string().ends_with("xxx...)"), "{err}");
+
+            let err = from_pyobject::<Reply>(&eval(py, "{'items': [{'price': None}], 'meta': {}}")).unwrap_err();
+            match err.into_execution_error("reply") {
+                kameo_child_process::error::PythonExecutionError::DeserializationError { path, python_type, repr, .. } => {
+                    assert_eq!((path.as_str(), python_type.as_str(), repr.as_str()), ("reply.items[0].price", "NoneType", "None"));
+                }
+                other => panic!("unexpected {other:?}"),
+            }
+        });
+    }
+
+    #[test]
+    fn test_tagged_enums_use_flat_dicts() {
+        use serde_json::{json, Value};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        enum Command {
+            StreamFibonacci { count: u32 },
+            Lookup {
+                #[serde(with = "super::uuid")]
+                id: ::uuid::Uuid,
+            },
+        }
+
+        Python::with_gil(|py| {
+            let obj = to_pyobject(py, &Command::StreamFibonacci { count: 10 }).unwrap();
+            let json: Value = from_pyobject(obj.bind(py)).unwrap();
+            assert_eq!(json, json!({"type": "stream_fibonacci", "count": 10}));
+
+            let from_python: Command = from_pyobject(&eval(py, "{'count': 3, 'type': 'stream_fibonacci'}")).unwrap();
+            assert_eq!(from_python, Command::StreamFibonacci { count: 3 });
+
+            // Buffered content keeps native values in their wire form, so markers still apply
+            let lookup = run(py, "import uuid\nvalue = {'type': 'lookup', 'id': uuid.UUID(int=7)}");
+            let from_python: Command = from_pyobject(&lookup).unwrap();
+            assert_eq!(from_python, Command::Lookup { id: ::uuid::Uuid::from_u128(7) });
+            let json: Value = from_pyobject(&lookup).unwrap();
+            assert_eq!(json["id"], "00000000-0000-0000-0000-000000000007");
+
+            let native = run(
+                py,
+                "import datetime, decimal\n\
+                 value = [datetime.datetime(2024, 1, 2, 3, 4, 5), datetime.date(2024, 1, 2),\n\
+                 \x20        datetime.timedelta(seconds=1, microseconds=2), decimal.Decimal('1.50')]",
+            );
+            let json: Value = from_pyobject(&native).unwrap();
+            assert_eq!(json, json!(["2024-01-02T03:04:05", "2024-01-02", [1, 2000], "1.50"]));
+
+            let err = from_pyobject::<Command>(&eval(py, "{'type': 'reboot'}")).unwrap_err();
+            assert!(err.to_string().contains("unknown variant `reboot`"), "{err}");
+        });
+    }
+
+    #[test]
+    fn test_uuid_round_trips_as_uuid_object() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Tagged {
+            #[serde(with = "super::uuid")]
+            id: ::uuid::Uuid,
+            #[serde(with = "super::uuid::option")]
+            parent: Option<::uuid::Uuid>,
+        }
+        let value = Tagged { id: ::uuid::Uuid::new_v4(), parent: None };
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json["id"], value.id.hyphenated().to_string(), "other formats see a string");
+
+        Python::with_gil(|py| {
+            let obj = to_pyobject(py, &value).unwrap();
+            let id = obj.bind(py).get_item("id").unwrap();
+            assert_eq!(id.get_type().name().unwrap().to_string(), "UUID");
+            assert_eq!(id.str().unwrap().to_string(), value.id.hyphenated().to_string());
+            assert!(obj.bind(py).get_item("parent").unwrap().is_none());
+            let roundtrip: Tagged = from_pyobject(obj.bind(py)).unwrap();
+            assert_eq!(roundtrip, value);
+
+            let from_python: Tagged = from_pyobject(&run(
+                py,
+                "import uuid\nvalue = {'id': uuid.UUID(int=1), 'parent': '00000000-0000-0000-0000-000000000002'}",
+            ))
+            .unwrap();
+            assert_eq!(from_python.id, ::uuid::Uuid::from_u128(1));
+            assert_eq!(from_python.parent, Some(::uuid::Uuid::from_u128(2)));
+
+            let err = from_pyobject::<Tagged>(&eval(py, "{'id': 1, 'parent': None}")).unwrap_err();
+            assert!(err.to_string().contains("expected uuid.UUID, got int"), "{err}");
+        });
+    }
+
+    #[test]
+    fn test_map_keys_from_python() {
+        Python::with_gil(|py| {
+            let maps: KeyedMaps = from_pyobject(&eval(
+                py,
+                "{'by_id': {7: 'a'}, 'by_cell': {(1, -2): True}, \
+                  'by_kind': {'Red': 1, ('Code', 3): 2, ('Pair', (-1, 'x')): 3}, 'by_signed': {-5: None}}",
+            ))
+            .unwrap();
+            assert_eq!(maps.by_id[&7], "a");
+            assert!(maps.by_cell[&(1, -2)]);
+            let kinds: Vec<_> = maps.by_kind.into_iter().collect();
+            assert_eq!(kinds, [(KeyEnum::Red, 1), (KeyEnum::Code(3), 2), (KeyEnum::Pair(-1, "x".to_string()), 3)]);
+
+            let err = from_pyobject::<HashMap<u32, String>>(&eval(py, "{'7': 'a'}")).unwrap_err();
+            assert!(err.to_string().contains("'7'"), "{err}");
+
+            #[derive(Debug, PartialEq, Eq, Hash, Serialize)]
+            struct Point {
+                x: i32,
+            }
+            let err = to_pyobject(py, &HashMap::from([(Point { x: 1 }, 1)])).unwrap_err();
+            assert!(err.to_string().contains("struct (Point) cannot be a dict key"), "{err}");
+        });
+    }
+
+    #[test]
+    fn test_buffers_round_trip_as_memoryviews() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Signal {
+            rate: u32,
+            samples: PyBuffer<f64>,
+            counts: PyBuffer<i16>,
+        }
+        let value = Signal {
+            rate: 48_000,
+            samples: PyBuffer(vec![0.5, -1.25, f64::MAX, 3.0]),
+            counts: PyBuffer(vec![-3, 0, i16::MAX]),
+        };
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json["samples"][1], -1.25, "human readable formats see a sequence");
+        let bytes = bincode::serde::encode_to_vec(&value, bincode::config::standard()).unwrap();
+        let (decoded, _): (Signal, _) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
+        assert_eq!(decoded, value);
+
+        Python::with_gil(|py| {
+            let obj = to_pyobject(py, &value).unwrap();
+            let samples = obj.bind(py).get_item("samples").unwrap();
+            assert_eq!(samples.get_type().name().unwrap().to_string(), "memoryview");
+            assert_eq!(samples.getattr("format").unwrap().extract::<String>().unwrap(), "d");
+            assert!(!samples.getattr("readonly").unwrap().extract::<bool>().unwrap());
+            let elements = samples.call_method0("tolist").unwrap();
+            assert_eq!(elements.extract::<Vec<f64>>().unwrap(), value.samples.0);
+            let roundtrip: Signal = from_pyobject(obj.bind(py)).unwrap();
+            assert_eq!(roundtrip, value);
+
+            let from_python: Signal = from_pyobject(&run(
+                py,
+                "import array\n\
+                 data = array.array('d', [1.0, 2.0, 3.0, 4.0, 5.0])\n\
+                 value = {'rate': 1, 'samples': memoryview(data)[::2], 'counts': array.array('h', [1, 2])}",
+            ))
+            .unwrap();
+            assert_eq!(from_python.samples.0, vec![1.0, 3.0, 5.0], "strided views are read too");
+            assert_eq!(from_python.counts.0, vec![1, 2]);
+            let listed: PyBuffer<u8> = from_pyobject(&eval(py, "[1, 2, 255]")).unwrap();
+            assert_eq!(listed.0, vec![1, 2, 255], "lists are read element by element");
+
+            let err = from_pyobject::<Signal>(&run(
+                py,
+                "import array\nvalue = {'rate': 1, 'samples': array.array('f', [1.0]), 'counts': []}",
+            ))
+            .unwrap_err();
+            assert!(err.to_string().contains("samples"), "{err}");
+            assert!(err.to_string().contains("expected a buffer of f64"), "{err}");
+        });
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_values_round_trip_as_datetime() {
+        use ::chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Event {
+            #[serde(with = "super::chrono::datetime")]
+            at: DateTime<Utc>,
+            #[serde(with = "super::chrono::fixed_offset_datetime")]
+            local: DateTime<FixedOffset>,
+            #[serde(with = "super::chrono::naive_datetime")]
+            naive: NaiveDateTime,
+            #[serde(with = "super::chrono::naive_date")]
+            day: NaiveDate,
+            #[serde(with = "super::chrono::duration")]
+            took: TimeDelta,
+            #[serde(with = "super::chrono::datetime::option")]
+            ended: Option<DateTime<Utc>>,
+        }
+        let offset = FixedOffset::east_opt(5 * 3600 + 1800).unwrap();
+        let value = Event {
+            at: Utc.with_ymd_and_hms(2024, 2, 29, 23, 59, 58).unwrap() + TimeDelta::microseconds(123_456),
+            local: offset.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+            naive: NaiveDate::from_ymd_opt(1999, 12, 31).unwrap().and_hms_micro_opt(1, 2, 3, 7).unwrap(),
+            day: NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            took: TimeDelta::microseconds(-1_500_000),
+            ended: Some(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()),
+        };
+        let bytes = bincode::serde::encode_to_vec(&value, bincode::config::standard()).unwrap();
+        let (decoded, _): (Event, _) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
+        assert_eq!(decoded, value, "other formats see the wire form");
+
+        Python::with_gil(|py| {
+            let obj = to_pyobject(py, &value).unwrap();
+            let dict = obj.bind(py);
+            let type_name = |key: &str| dict.get_item(key).unwrap().get_type().name().unwrap().to_string();
+            assert_eq!(type_name("at"), "datetime");
+            assert_eq!(type_name("day"), "date");
+            assert_eq!(type_name("took"), "timedelta");
+            assert_eq!(
+                dict.get_item("local").unwrap().call_method0("isoformat").unwrap().to_string(),
+                "2024-01-02T03:04:05+05:30"
+            );
+            assert!(dict.get_item("naive").unwrap().getattr("tzinfo").unwrap().is_none());
+            assert_eq!(dict.get_item("took").unwrap().call_method0("total_seconds").unwrap().extract::<f64>().unwrap(), -1.5);
+            let roundtrip: Event = from_pyobject(dict).unwrap();
+            assert_eq!(roundtrip, value);
+
+            let from_python: Event = from_pyobject(&run(
+                py,
+                "import datetime as dt\n\
+                 value = {'at': dt.datetime(2024, 1, 1, 12), 'local': '2024-01-01T12:00:00Z',\n\
+                 \x20        'naive': dt.datetime(2024, 1, 1, 12, tzinfo=dt.timezone.utc), 'day': dt.datetime(2024, 1, 1, 12),\n\
+                 \x20        'took': dt.timedelta(days=1, microseconds=5), 'ended': None}",
+            ))
+            .unwrap();
+            let noon = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+            assert_eq!(from_python.at, noon, "naive datetimes are taken as UTC");
+            assert_eq!(from_python.local, noon.fixed_offset());
+            assert_eq!(from_python.naive, noon.naive_utc());
+            assert_eq!(from_python.day, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+            assert_eq!(from_python.took, TimeDelta::days(1) + TimeDelta::microseconds(5));
+            assert_eq!(from_python.ended, None);
+        });
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_values_round_trip_as_datetime() {
+        use ::time::macros::{date, datetime};
+        use ::time::{Date, Duration, OffsetDateTime, PrimitiveDateTime};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Event {
+            #[serde(with = "super::time::offset_date_time")]
+            at: OffsetDateTime,
+            #[serde(with = "super::time::primitive_date_time")]
+            naive: PrimitiveDateTime,
+            #[serde(with = "super::time::date")]
+            day: Date,
+            #[serde(with = "super::time::duration::option")]
+            took: Option<Duration>,
+        }
+        let value = Event {
+            at: datetime!(2024-01-02 03:04:05.25 -08:00),
+            naive: datetime!(2024-01-02 03:04:05),
+            day: date!(2024-07-01),
+            took: Some(Duration::new(-3, 250_000_000)),
+        };
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json["day"], "2024-07-01");
+        assert_eq!(serde_json::from_value::<Event>(json).unwrap(), value);
+        // Corrupt wire values are errors, not wrapped or panicking durations
+        for took in [serde_json::json!([0, 2_147_483_648u32]), serde_json::json!([i64::MIN, 3_000_000_000u32])] {
+            let mut json = serde_json::to_value(&value).unwrap();
+            json["took"] = took;
+            let err = serde_json::from_value::<Event>(json).unwrap_err();
+            assert!(err.to_string().contains("out of range"), "{err}");
+        }
+
+        Python::with_gil(|py| {
+            let obj = to_pyobject(py, &value).unwrap();
+            let dict = obj.bind(py);
+            assert_eq!(
+                dict.get_item("at").unwrap().call_method0("isoformat").unwrap().to_string(),
+                "2024-01-02T03:04:05.250000-08:00"
+            );
+            assert_eq!(dict.get_item("naive").unwrap().call_method0("isoformat").unwrap().to_string(), "2024-01-02T03:04:05");
+            assert_eq!(dict.get_item("day").unwrap().get_type().name().unwrap().to_string(), "date");
+            assert_eq!(dict.get_item("took").unwrap().call_method0("total_seconds").unwrap().extract::<f64>().unwrap(), -2.75);
+            let roundtrip: Event = from_pyobject(dict).unwrap();
+            assert_eq!(roundtrip, value);
+        });
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_decimals_round_trip_as_decimal() {
+        use ::rust_decimal::Decimal;
+        use std::str::FromStr;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Price {
+            #[serde(with = "super::decimal")]
+            amount: Decimal,
+        }
+        let value = Price { amount: Decimal::from_str("-1234.5600").unwrap() };
+        Python::with_gil(|py| {
+            let obj = to_pyobject(py, &value).unwrap();
+            let amount = obj.bind(py).get_item("amount").unwrap();
+            assert_eq!(amount.get_type().name().unwrap().to_string(), "Decimal");
+            assert_eq!(amount.str().unwrap().to_string(), "-1234.5600", "scale is kept");
+            let roundtrip: Price = from_pyobject(obj.bind(py)).unwrap();
+            assert_eq!(roundtrip.amount.scale(), 4);
+            assert_eq!(roundtrip, value);
+
+            for (code, expected) in [("Decimal('1E+2')", "100"), ("Decimal('0.1')", "0.1"), ("7", "7"), ("'2.50'", "2.50")] {
+                let input = run(py, &format!("from decimal import Decimal\nvalue = {{'amount': {code}}}"));
+                let parsed: Price = from_pyobject(&input).unwrap();
+                assert_eq!(parsed.amount, Decimal::from_str(expected).unwrap(), "{code}");
+            }
+            let nan = run(py, "from decimal import Decimal\nvalue = {'amount': Decimal('NaN')}");
+            assert!(from_pyobject::<Price>(&nan).is_err());
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_shm_bytes_exposed_as_memoryview() {
+        use kameo_child_process::shm::{decode_frame, encode_frame, DEFAULT_SHM_THRESHOLD};
+        use kameo_child_process::{CodecKind, ShmBytes};
+
+        let data: Vec<u8> = (0..DEFAULT_SHM_THRESHOLD + 17).map(|i| (i % 253) as u8).collect();
+        let (bytes, fds) = encode_frame(&ShmBytes::new(data.clone()), CodecKind::default(), true).unwrap();
+        assert_eq!(fds.len(), 1);
+        let shared: ShmBytes = decode_frame(&bytes, fds, CodecKind::default()).unwrap();
+        assert!(shared.is_shared());
+
+        Python::with_gil(|py| {
+            let py_obj = to_pyobject(py, &shared).unwrap();
+            let view = py_obj.bind(py);
+            assert_eq!(view.get_type().name().unwrap().to_string(), "memoryview");
+            assert!(view.getattr("readonly").unwrap().extract::<bool>().unwrap());
+            assert_eq!(view.len().unwrap(), data.len());
+            let roundtrip: ShmBytes = from_pyobject(view).unwrap();
+            assert_eq!(roundtrip.to_vec().unwrap(), data);
+        });
     }
 }
@@ -1,6 +1,6 @@
 use pyo3::{
     prelude::*,
-    types::{PyDict, PyList},
+    types::{PyByteArray, PyDict, PyList, PyTuple},
     Bound, IntoPyObjectExt, PyObject, Python,
 };
 use serde::ser::{
@@ -9,6 +9,8 @@ use serde::ser::{
 };
 use tracing::{error, instrument, trace};
 
+use super::buffer;
+use super::markers::Marker;
 use super::Error;
 use super::Result;
 
@@ -23,7 +25,7 @@ pub fn to_pyobject<T>(py: Python<'_>, value: &T) -> Result<PyObject>
 where
     T: Serialize,
 {
-    match value.serialize(PythonSerializer::new(py)) {
+    match kameo_child_process::shm::with_shared_handles(|| value.serialize(PythonSerializer::new(py))) {
         Ok(result) => {
             trace!(status = "success", source_type = std::any::type_name::<T>());
             Ok(result)
@@ -35,16 +37,64 @@ where
     }
 }
 
+/// Map a shared memory segment, given as an `[fd, len]` pair, into a read-only `memoryview`.
+///
+/// `mmap` duplicates the fd, so the mapping outlives the Rust-side segment.
+fn shared_memoryview(py: Python<'_>, handle: PyObject) -> Result<PyObject> {
+    let (fd, len) = match handle.extract::<Vec<u64>>(py)?[..] {
+        [fd, len] => (fd as i32, len as usize),
+        _ => return Err(Error::Serialization("malformed shared memory handle".to_string())),
+    };
+    let mmap = py.import("mmap")?;
+    let access_read = mmap.getattr("ACCESS_READ")?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("access", access_read)?;
+    let mapped = mmap.getattr("mmap")?.call((fd, len), Some(&kwargs))?;
+    let view = py.import("builtins")?.getattr("memoryview")?.call1((mapped,))?;
+    trace!(event = "shm_memoryview", len, "Mapped shared memory segment into Python");
+    Ok(view.unbind())
+}
+
+/// A variant with data as a dict key: `(name, payload)`.
+fn variant_key(py: Python<'_>, variant: &str, payload: Bound<'_, PyAny>) -> Result<PyObject> {
+    Ok(PyTuple::new(py, [variant.into_bound_py_any(py)?, payload])?.into_any().unbind())
+}
+
 pub struct PythonSerializer<'py> {
     py: Python<'py>,
+    /// Serializing the contents of a `PyBuffer`, which come as bytes to non-human-readable formats
+    buffer_contents: bool,
+    /// Serializing a dict key, which has to be hashable
+    key: bool,
 }
 
 impl<'py> PythonSerializer<'py> {
     pub fn new(py: Python<'py>) -> Self {
-        Self { py }
+        Self { py, buffer_contents: false, key: false }
+    }
+
+    /// A serializer for dict keys: sequences become tuples, and variants with data become
+    /// `(name, payload)` tuples. Maps and structs are rejected, as Python cannot hash dicts.
+    fn key(py: Python<'py>) -> Self {
+        Self { py, buffer_contents: false, key: true }
+    }
+
+    /// Serializer for a value nested in this one; values inside keys are keys too.
+    fn nested(py: Python<'py>, key: bool) -> Self {
+        Self { py, buffer_contents: false, key }
+    }
+
+    fn unhashable(&self, what: &str) -> Error {
+        Error::Serialization(format!("a {what} cannot be a dict key, as Python dicts cannot hash it"))
     }
 }
 
+/// Convert a map key to its Python form: ints, strings and tuples stay as they are, and unit
+/// variants are strings.
+pub(crate) fn to_dict_key<T: ?Sized + Serialize>(py: Python<'_>, key: &T) -> Result<PyObject> {
+    key.serialize(PythonSerializer::key(py))
+}
+
 impl<'py> serde::Serializer for PythonSerializer<'py> {
     type Ok = PyObject;
     type Error = Error;
@@ -57,6 +107,10 @@ impl<'py> serde::Serializer for PythonSerializer<'py> {
     type SerializeStruct = PythonMapSerializer<'py>;
     type SerializeStructVariant = PythonMapSerializer<'py>;
 
+    fn is_human_readable(&self) -> bool {
+        !self.buffer_contents
+    }
+
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
         Ok(v.into_bound_py_any(self.py)?.into())
     }
@@ -110,6 +164,10 @@ impl<'py> serde::Serializer for PythonSerializer<'py> {
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        if self.buffer_contents {
+            // Mutable, so the view handed to Python is writable
+            return Ok(PyByteArray::new(self.py, v).into_any().unbind());
+        }
         Ok(v.into_bound_py_any(self.py)?.into())
     }
 
@@ -138,7 +196,20 @@ impl<'py> serde::Serializer for PythonSerializer<'py> {
         Ok(variant.into_bound_py_any(self.py)?.into())
     }
 
-    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Self::Ok> {
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, name: &'static str, value: &T) -> Result<Self::Ok> {
+        if name == kameo_child_process::shm::SHM_HANDLE_MARKER {
+            let py = self.py;
+            return shared_memoryview(py, value.serialize(self)?);
+        }
+        if let Some(marker) = Marker::from_name(name) {
+            let py = self.py;
+            return marker.into_python(py, value.serialize(self)?);
+        }
+        if let Some((typecode, _)) = buffer::buffer_kind(name) {
+            let py = self.py;
+            let bytes = value.serialize(PythonSerializer { py, buffer_contents: true, key: false })?;
+            return buffer::into_memoryview(py, typecode, bytes);
+        }
         value.serialize(self)
     }
 
@@ -149,6 +220,10 @@ impl<'py> serde::Serializer for PythonSerializer<'py> {
         variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok> {
+        if self.key {
+            let payload = value.serialize(Self::key(self.py))?;
+            return variant_key(self.py, variant, payload.into_bound(self.py));
+        }
         let dict = PyDict::new(self.py);
         dict.set_item(variant, value.serialize(Self::new(self.py))?)?;
         Ok(dict.into_bound_py_any(self.py)?.into())
@@ -159,6 +234,7 @@ impl<'py> serde::Serializer for PythonSerializer<'py> {
             py: self.py,
             list: PyList::empty(self.py),
             next_key: None,
+            key: self.key,
         })
     }
 
@@ -183,19 +259,28 @@ impl<'py> serde::Serializer for PythonSerializer<'py> {
     ) -> Result<Self::SerializeTupleVariant> {
         let mut seq = PythonSeqSerializer::new(self.py)?;
         seq.next_key = Some(variant.to_string());
+        seq.key = self.key;
         Ok(seq)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        if self.key {
+            return Err(self.unhashable("map"));
+        }
         PythonMapSerializer::new(self.py)
     }
 
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        if self.key {
+            return Err(self.unhashable(&format!("struct ({name})")));
+        }
         let dict = PyDict::new(self.py);
         Ok(PythonMapSerializer {
             py: self.py,
             dict,
             next_key: None,
+            pending_key: None,
+            class: super::classes::registered_class(self.py, name)?,
         })
     }
 
@@ -206,6 +291,9 @@ impl<'py> serde::Serializer for PythonSerializer<'py> {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
+        if self.key {
+            return Err(self.unhashable(&format!("struct variant ({variant})")));
+        }
         let mut map = PythonMapSerializer::new(self.py)?;
         map.next_key = Some(variant.to_string());
         Ok(map)
@@ -216,6 +304,8 @@ pub struct PythonSeqSerializer<'py> {
     py: Python<'py>,
     list: Bound<'py, PyList>,
     next_key: Option<String>,
+    /// Part of a dict key, so finished as a tuple
+    key: bool,
 }
 
 impl<'py> PythonSeqSerializer<'py> {
@@ -224,6 +314,7 @@ impl<'py> PythonSeqSerializer<'py> {
             py,
             list: PyList::empty(py),
             next_key: None,
+            key: false,
         })
     }
 }
@@ -233,12 +324,19 @@ impl SerializeSeq for PythonSeqSerializer<'_> {
     type Error = Error;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        let element = value.serialize(PythonSerializer::new(self.py))?;
+        let element = value.serialize(PythonSerializer::nested(self.py, self.key))?;
         self.list.append(element)?;
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok> {
+        if self.key {
+            let tuple = self.list.to_tuple();
+            return match self.next_key {
+                Some(variant) => variant_key(self.py, &variant, tuple.into_any()),
+                None => Ok(tuple.into_any().unbind()),
+            };
+        }
         if let Some(variant) = self.next_key {
             let dict = PyDict::new(self.py);
             dict.set_item(variant, self.list)?;
@@ -290,19 +388,22 @@ impl SerializeTupleVariant for PythonSeqSerializer<'_> {
     }
 
     fn end(self) -> Result<Self::Ok> {
-        let variant = self
-            .next_key
-            .ok_or_else(|| Error::Serialization("missing variant key".to_string()))?;
-        let outer = PyDict::new(self.py);
-        outer.set_item(variant, self.list)?;
-        Ok(outer.into_bound_py_any(self.py)?.into())
+        if self.next_key.is_none() {
+            return Err(Error::Serialization("missing variant key".to_string()));
+        }
+        SerializeSeq::end(self)
     }
 }
 
 pub struct PythonMapSerializer<'py> {
     py: Python<'py>,
     dict: Bound<'py, PyDict>,
+    /// Variant name of a struct variant
     next_key: Option<String>,
+    /// Key of the map entry being serialized
+    pending_key: Option<PyObject>,
+    /// Registered class to build from the fields instead of returning the dict
+    class: Option<Bound<'py, PyAny>>,
 }
 
 impl<'py> PythonMapSerializer<'py> {
@@ -311,6 +412,8 @@ impl<'py> PythonMapSerializer<'py> {
             py,
             dict: PyDict::new(py),
             next_key: None,
+            pending_key: None,
+            class: None,
         })
     }
 }
@@ -320,17 +423,13 @@ impl SerializeMap for PythonMapSerializer<'_> {
     type Error = Error;
 
     fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
-        self.next_key = Some(
-            key.serialize(PythonSerializer::new(self.py))?
-                .extract(self.py)
-                .map_err(|e| Error::Serialization(format!("failed to extract key: {}", e)))?,
-        );
+        self.pending_key = Some(to_dict_key(self.py, key)?);
         Ok(())
     }
 
     fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
         let key = self
-            .next_key
+            .pending_key
             .take()
             .ok_or_else(|| Error::Serialization("missing key in map serialization".to_string()))?;
         let value = value.serialize(PythonSerializer::new(self.py))?;
@@ -362,6 +461,11 @@ impl SerializeStruct for PythonMapSerializer<'_> {
     }
 
     fn end(self) -> Result<Self::Ok> {
+        if let Some(class) = self.class {
+            return class.call((), Some(&self.dict)).map(Bound::unbind).map_err(|e| {
+                Error::Serialization(format!("failed to construct {}: {}", class, e))
+            });
+        }
         Ok(self
             .dict
             .into_bound_py_any(self.py)
@@ -0,0 +1,97 @@
+//! `time` types as `datetime` objects.
+//!
+//! | Rust | Python | other formats |
+//! |------|--------|---------------|
+//! | [`offset_date_time`]: `OffsetDateTime` | aware `datetime.datetime` | RFC 3339 string |
+//! | [`primitive_date_time`]: `PrimitiveDateTime` | naive `datetime.datetime` | ISO 8601 string |
+//! | [`date`]: `Date` | `datetime.date` | `YYYY-MM-DD` |
+//! | [`duration`]: `Duration` | `datetime.timedelta` | `(seconds, nanoseconds)` |
+//!
+//! Python keeps microseconds, so nanoseconds are truncated on the way in. Each module has an
+//! `option` submodule for `Option` fields.
+
+use super::markers::Marker;
+use ::time::format_description::well_known::Rfc3339;
+use ::time::{OffsetDateTime, PrimitiveDateTime};
+
+/// Parse an RFC 3339 string, taking a missing offset to be UTC.
+fn parse_lenient(wire: &str) -> Result<OffsetDateTime, ::time::error::Parse> {
+    OffsetDateTime::parse(wire, &Rfc3339).or_else(|err| OffsetDateTime::parse(&format!("{wire}Z"), &Rfc3339).map_err(|_| err))
+}
+
+fn format_rfc3339(value: &OffsetDateTime) -> String {
+    // Only fails for years outside 0..=9999, which Python cannot represent either
+    value.format(&Rfc3339).unwrap_or_default()
+}
+
+/// `OffsetDateTime`; naive Python datetimes are taken to be UTC.
+pub mod offset_date_time {
+    use super::*;
+
+    super::super::markers::marked_with_module!(
+        Marker::DateTime,
+        OffsetDateTime,
+        String,
+        format_rfc3339,
+        |wire: String| parse_lenient(&wire)
+    );
+}
+
+/// `PrimitiveDateTime`; an aware Python datetime keeps its wall-clock time and drops the offset.
+pub mod primitive_date_time {
+    use super::*;
+
+    super::super::markers::marked_with_module!(
+        Marker::DateTime,
+        PrimitiveDateTime,
+        String,
+        |value: &PrimitiveDateTime| format_rfc3339(&value.assume_utc()).trim_end_matches('Z').to_string(),
+        |wire: String| parse_lenient(&wire).map(|value| PrimitiveDateTime::new(value.date(), value.time()))
+    );
+}
+
+/// `Date`.
+pub mod date {
+    use super::*;
+    use ::time::macros::format_description;
+    use ::time::Date;
+
+    const FORMAT: &[::time::format_description::BorrowedFormatItem<'static>] =
+        format_description!("[year]-[month]-[day]");
+
+    super::super::markers::marked_with_module!(
+        Marker::Date,
+        Date,
+        String,
+        |value: &Date| value.format(FORMAT).unwrap_or_default(),
+        |wire: String| Date::parse(&wire, FORMAT)
+    );
+}
+
+/// `Duration`.
+pub mod duration {
+    use super::*;
+    use ::time::Duration;
+
+    super::super::markers::marked_with_module!(
+        Marker::TimeDelta,
+        Duration,
+        (i64, u32),
+        |value: &Duration| {
+            let (seconds, nanos) = (value.whole_seconds(), value.subsec_nanoseconds());
+            if nanos < 0 {
+                (seconds - 1, (nanos + 1_000_000_000) as u32)
+            } else {
+                (seconds, nanos as u32)
+            }
+        },
+        |(seconds, nanos): (i64, u32)| {
+            if nanos >= 1_000_000_000 {
+                return Err("timedelta nanoseconds out of range");
+            }
+            Duration::seconds(seconds)
+                .checked_add(Duration::nanoseconds(i64::from(nanos)))
+                .ok_or("timedelta out of range")
+        }
+    );
+}
@@ -0,0 +1,21 @@
+//! `uuid::Uuid` as `uuid.UUID`.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Order {
+//!     #[serde(with = "kameo_snake_handler::serde_py::uuid")]
+//!     id: uuid::Uuid,
+//! }
+//! ```
+//!
+//! Other formats see the hyphenated string.
+
+use super::markers::{marked_with_module, Marker};
+
+marked_with_module!(
+    Marker::Uuid,
+    ::uuid::Uuid,
+    String,
+    |value: &::uuid::Uuid| value.hyphenated().to_string(),
+    |wire: String| ::uuid::Uuid::parse_str(&wire)
+);
@@ -0,0 +1,824 @@
+//! Python type stubs for Rust message types.
+//!
+//! `#[derive(PyStub)]` describes a type the way [`serde_py`](crate::serde_py) hands it to
+//! Python, and [`StubModule`] renders the collected types as a Python module of `TypedDict`s
+//! and `Literal`-tagged unions, so handlers can be checked with mypy or pyright:
+//!
+//! ```ignore
+//! use kameo_snake_handler::stubs::StubModule;
+//!
+//! StubModule::new()
+//!     .message::<TestMessage>()
+//!     .add::<TestCallbackMessage>()
+//!     .write("python/messages.pyi")?;
+//! ```
+//!
+//! | Rust | Python |
+//! |------|--------|
+//! | struct with named fields | `TypedDict` |
+//! | newtype struct | the inner type |
+//! | tuple, tuple struct | `tuple[...]` |
+//! | `Vec`, sets | `list[...]` |
+//! | maps | `dict[...]`, keyed by the hashable form: `tuple` for sequences, `(name, payload)` for variants with data |
+//! | `Option<T>` | `Optional[T]` |
+//! | unit variant | `Literal["Variant"]` (or a dict holding just the tag) |
+//! | other variants | a `TypedDict` per variant, shaped by the enum's serde tagging |
+//!
+//! Type names come from the serde name of the Rust type. Two Rust types with the same name
+//! share one definition, the first one registered. Structs registered with
+//! [`register_class`](crate::serde_py::register_class) are still described as dicts.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::path::Path;
+
+pub use kameo_snake_derive::PyStub;
+
+use kameo_child_process::KameoChildProcessMessage;
+
+/// A Python type expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PyType {
+    Any,
+    None,
+    Bool,
+    Int,
+    Float,
+    Str,
+    Bytes,
+    List(Box<PyType>),
+    Tuple(Vec<PyType>),
+    Dict(Box<PyType>, Box<PyType>),
+    Optional(Box<PyType>),
+    Union(Vec<PyType>),
+    Literal(String),
+    /// A definition in the [`StubModule`], by serde name
+    Named(String),
+    /// A type written out as is, e.g. `datetime.datetime`; the module before the last dot is
+    /// imported
+    External(String),
+}
+
+impl PyType {
+    fn render(&self) -> String {
+        match self {
+            PyType::Any => "Any".to_string(),
+            PyType::None => "None".to_string(),
+            PyType::Bool => "bool".to_string(),
+            PyType::Int => "int".to_string(),
+            PyType::Float => "float".to_string(),
+            PyType::Str => "str".to_string(),
+            PyType::Bytes => "bytes".to_string(),
+            PyType::List(item) => format!("list[{}]", item.render()),
+            PyType::Tuple(items) if items.is_empty() => "tuple[()]".to_string(),
+            PyType::Tuple(items) => format!("tuple[{}]", render_all(items)),
+            PyType::Dict(key, value) => format!("dict[{}, {}]", key.render(), value.render()),
+            PyType::Optional(inner) if matches!(**inner, PyType::Any | PyType::None) => inner.render(),
+            PyType::Optional(inner) => format!("Optional[{}]", inner.render()),
+            PyType::Union(items) if items.len() == 1 => items[0].render(),
+            PyType::Union(items) => format!("Union[{}]", render_all(items)),
+            PyType::Literal(value) => format!("Literal[{}]", string_literal(value)),
+            // Quoted, so definitions can refer to each other in any order
+            PyType::Named(name) => string_literal(&python_name(name)),
+            PyType::External(path) => path.clone(),
+        }
+    }
+
+    /// A fragment of a Python identifier, used to name generic instantiations.
+    fn ident(&self) -> String {
+        match self {
+            PyType::List(item) => format!("List{}", item.ident()),
+            PyType::Tuple(items) => format!("Tuple{}", items.iter().map(PyType::ident).collect::<String>()),
+            PyType::Dict(key, value) => format!("Dict{}{}", key.ident(), value.ident()),
+            PyType::Optional(inner) => format!("Optional{}", inner.ident()),
+            PyType::Union(items) => items.iter().map(PyType::ident).collect(),
+            PyType::Named(name) => python_name(name),
+            PyType::External(path) => path.rsplit('.').next().unwrap_or(path).to_string(),
+            PyType::Literal(value) => python_name(value),
+            other => {
+                let rendered = other.render();
+                let mut chars = rendered.chars();
+                chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+            }
+        }
+    }
+
+    fn collect_imports(&self, imports: &mut BTreeSet<String>) {
+        match self {
+            PyType::List(inner) | PyType::Optional(inner) => inner.collect_imports(imports),
+            PyType::Dict(key, value) => {
+                key.collect_imports(imports);
+                value.collect_imports(imports);
+            }
+            PyType::Tuple(items) | PyType::Union(items) => items.iter().for_each(|item| item.collect_imports(imports)),
+            PyType::External(path) => {
+                if let Some((module, _)) = path.rsplit_once('.') {
+                    imports.insert(module.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn render_all(items: &[PyType]) -> String {
+    items.iter().map(PyType::render).collect::<Vec<_>>().join(", ")
+}
+
+/// A key of a `TypedDict`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub ty: PyType,
+    /// `false` for keys that may be missing, rendered as `NotRequired[...]`
+    pub required: bool,
+}
+
+/// How an enum marks which variant a value is, following serde's enum representations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tagging {
+    /// `{"Variant": payload}`, or `"Variant"` for unit variants
+    External,
+    /// `{"<tag>": "Variant", ...fields}`
+    Internal { tag: String },
+    /// `{"<tag>": "Variant", "<content>": payload}`
+    Adjacent { tag: String, content: String },
+    /// The payload alone
+    Untagged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    /// The serde name, which is what Python sees
+    pub name: String,
+    /// The Rust name, used to name the variant's `TypedDict`
+    pub ident: String,
+    pub kind: VariantKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariantKind {
+    Unit,
+    Newtype(PyType),
+    Tuple(Vec<PyType>),
+    Struct(Vec<Field>),
+}
+
+#[derive(Debug, Clone)]
+enum Definition {
+    Struct { fields: Vec<Field>, flatten: Vec<PyType> },
+    Enum { tagging: Tagging, variants: Vec<Variant> },
+}
+
+/// A top-level definition of the rendered module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PyDefinition {
+    /// A `TypedDict`; `open` when a flattened map lets it hold keys besides `fields`
+    TypedDict { name: String, fields: Vec<Field>, open: bool },
+    /// `name: TypeAlias = ty`
+    Alias { name: String, ty: PyType },
+}
+
+impl PyDefinition {
+    fn typed_dict(name: &str, fields: Vec<Field>) -> Self {
+        PyDefinition::TypedDict { name: name.to_string(), fields, open: false }
+    }
+}
+
+/// Types implemented by `#[derive(PyStub)]`, with their Python shape.
+pub trait PyStub {
+    /// The Python type of `Self`, registering any definitions it needs in `stubs`.
+    fn py_type(stubs: &mut StubModule) -> PyType;
+}
+
+/// A Python module of type definitions, collected from [`PyStub`] types.
+#[derive(Debug, Default)]
+pub struct StubModule {
+    /// In registration order; `None` while a definition is being built
+    definitions: Vec<(String, Option<Definition>)>,
+    index: HashMap<String, usize>,
+    /// `(message, reply)` pairs added with [`StubModule::message`]
+    messages: Vec<(PyType, PyType)>,
+}
+
+impl StubModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a type and everything it refers to.
+    pub fn add<T: PyStub>(mut self) -> Self {
+        T::py_type(&mut self);
+        self
+    }
+
+    /// Add a message type and its reply type.
+    pub fn message<M>(mut self) -> Self
+    where
+        M: KameoChildProcessMessage + PyStub,
+        M::Ok: PyStub,
+    {
+        let message = M::py_type(&mut self);
+        let reply = <M::Ok as PyStub>::py_type(&mut self);
+        self.messages.push((message, reply));
+        self
+    }
+
+    /// Claim `name` for a definition; `false` if it is already defined or being defined.
+    pub fn reserve(&mut self, name: &str) -> bool {
+        if self.index.contains_key(name) {
+            return false;
+        }
+        self.index.insert(name.to_string(), self.definitions.len());
+        self.definitions.push((name.to_string(), None));
+        true
+    }
+
+    /// The name of a generic type instantiated with `args`, e.g. `Page_Order` for `Page<Order>`.
+    pub fn generic_name(name: &str, args: Vec<PyType>) -> String {
+        args.iter().fold(name.to_string(), |name, arg| format!("{name}_{}", arg.ident()))
+    }
+
+    pub fn define_struct(&mut self, name: &str, fields: Vec<Field>, flatten: Vec<PyType>) {
+        self.define(name, Definition::Struct { fields, flatten });
+    }
+
+    pub fn define_enum(&mut self, name: &str, tagging: Tagging, variants: Vec<Variant>) {
+        self.define(name, Definition::Enum { tagging, variants });
+    }
+
+    fn define(&mut self, name: &str, definition: Definition) {
+        if let Some(&idx) = self.index.get(name) {
+            self.definitions[idx].1 = Some(definition);
+        }
+    }
+
+    /// The keys of the struct `ty` refers to, including flattened ones; `None` if it isn't a
+    /// struct.
+    fn struct_fields(&self, ty: &PyType) -> Option<Vec<Field>> {
+        let PyType::Named(name) = ty else { return None };
+        match &self.definitions[*self.index.get(name)?].1 {
+            Some(Definition::Struct { fields, flatten }) => {
+                let mut all: Vec<Field> = flatten.iter().filter_map(|ty| self.struct_fields(ty)).flatten().collect();
+                all.extend(fields.iter().cloned());
+                Some(all)
+            }
+            _ => None,
+        }
+    }
+
+    /// The Python type of `ty` as a dict key, the hashable form serde_py gives it: sequences are
+    /// tuples and enum variants with data `(name, payload)` tuples. Types that cannot be keys
+    /// are `Any`.
+    pub fn key_type(&self, ty: PyType) -> PyType {
+        self.key_form(ty, &mut Vec::new())
+    }
+
+    /// [`key_type`](Self::key_type), with the enums being expanded in `seen`; a key nested in
+    /// itself is `Any`.
+    fn key_form(&self, ty: PyType, seen: &mut Vec<String>) -> PyType {
+        match ty {
+            PyType::List(_) => PyType::External("tuple".to_string()),
+            PyType::Tuple(items) => PyType::Tuple(items.into_iter().map(|item| self.key_form(item, seen)).collect()),
+            PyType::Optional(inner) => PyType::Optional(Box::new(self.key_form(*inner, seen))),
+            PyType::Union(items) => PyType::Union(items.into_iter().map(|item| self.key_form(item, seen)).collect()),
+            PyType::Dict(..) => PyType::Any,
+            PyType::Named(name) if !seen.contains(&name) => {
+                seen.push(name.clone());
+                let key = self.enum_key_form(&name, seen);
+                seen.pop();
+                key.unwrap_or(PyType::Any)
+            }
+            PyType::Named(_) => PyType::Any,
+            other => other,
+        }
+    }
+
+    /// The key form of the enum `name`, leaving out variants serde_py cannot hash; `None` if
+    /// it isn't an enum or has no such variants.
+    fn enum_key_form(&self, name: &str, seen: &mut Vec<String>) -> Option<PyType> {
+        let Some(Definition::Enum { tagging, variants }) = &self.definitions[*self.index.get(name)?].1 else {
+            return None;
+        };
+        let mut members = Vec::new();
+        for variant in variants {
+            let payload = match &variant.kind {
+                VariantKind::Unit => None,
+                VariantKind::Newtype(ty) => Some(self.key_form(ty.clone(), seen)),
+                VariantKind::Tuple(items) => Some(self.key_form(PyType::Tuple(items.clone()), seen)),
+                VariantKind::Struct(_) => continue,
+            };
+            members.push(match (tagging, payload) {
+                (Tagging::External, None) => PyType::Literal(variant.name.clone()),
+                (Tagging::External, Some(payload)) => {
+                    PyType::Tuple(vec![PyType::Literal(variant.name.clone()), payload])
+                }
+                (Tagging::Untagged, payload) => payload.unwrap_or(PyType::None),
+                // Tagged with a dict, which Python cannot hash
+                _ => return None,
+            });
+        }
+        (!members.is_empty()).then_some(PyType::Union(members))
+    }
+
+    /// The module's top-level definitions as Python sees them, in registration order.
+    pub(crate) fn py_definitions(&self) -> Vec<PyDefinition> {
+        let mut out = Vec::new();
+        for (name, definition) in &self.definitions {
+            match definition {
+                Some(Definition::Struct { flatten, .. }) => {
+                    let fields = self.struct_fields(&PyType::Named(name.clone())).unwrap_or_default();
+                    let open = flatten.iter().any(|ty| self.struct_fields(ty).is_none());
+                    out.push(PyDefinition::TypedDict { name: python_name(name), fields, open });
+                }
+                Some(Definition::Enum { tagging, variants }) => self.enum_definitions(&mut out, name, tagging, variants),
+                None => {}
+            }
+        }
+        out
+    }
+
+    /// Render the module as Python source, valid both as a `.pyi` stub and as a `.py` module.
+    pub fn render(&self) -> String {
+        let mut body = String::new();
+        let mut imports = BTreeSet::new();
+        for definition in self.py_definitions() {
+            match definition {
+                PyDefinition::TypedDict { name, fields, .. } => typed_dict(&mut body, &name, &fields, &mut imports),
+                PyDefinition::Alias { name, ty } => {
+                    ty.collect_imports(&mut imports);
+                    // Annotated, since a lone forward reference would otherwise read as a `str` variable
+                    writeln!(body, "\n{name}: TypeAlias = {}", ty.render()).unwrap();
+                }
+            }
+        }
+        for (message, reply) in &self.messages {
+            message.collect_imports(&mut imports);
+            reply.collect_imports(&mut imports);
+        }
+
+        let mut out = String::new();
+        out.push_str("# Generated from Rust types by kameo_snake_handler::stubs. Do not edit.\n");
+        out.push_str("from __future__ import annotations\n\n");
+        for module in &imports {
+            writeln!(out, "import {module}").unwrap();
+        }
+        out.push_str("from typing import Any, Literal, NotRequired, Optional, TypeAlias, TypedDict, Union\n");
+        out.push_str(&body);
+        if !self.messages.is_empty() {
+            out.push_str("\n# Handler signatures: message type -> reply type\n");
+            for (message, reply) in &self.messages {
+                writeln!(out, "# {} -> {}", message.render(), reply.render()).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Render the module and write it to `path`.
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.render())
+    }
+
+    /// Push a `TypedDict` per variant that needs one, then the enum's alias.
+    fn enum_definitions(&self, out: &mut Vec<PyDefinition>, name: &str, tagging: &Tagging, variants: &[Variant]) {
+        let enum_name = python_name(name);
+        let mut members = Vec::new();
+        for variant in variants {
+            let variant_name = format!("{enum_name}{}", python_name(&variant.ident));
+            let tag_field =
+                |tag: &str| Field { name: tag.to_string(), ty: PyType::Literal(variant.name.clone()), required: true };
+            let payload = match &variant.kind {
+                VariantKind::Unit => None,
+                VariantKind::Newtype(ty) => Some(ty.clone()),
+                VariantKind::Tuple(items) => Some(PyType::Tuple(items.clone())),
+                VariantKind::Struct(fields) => {
+                    // Internally tagged struct variants hold their fields next to the tag
+                    if !matches!(tagging, Tagging::Internal { .. }) {
+                        let fields_name = format!("{variant_name}Fields");
+                        out.push(PyDefinition::typed_dict(&fields_name, fields.clone()));
+                        Some(PyType::Named(fields_name))
+                    } else {
+                        None
+                    }
+                }
+            };
+            let member = match tagging {
+                Tagging::External => match payload {
+                    None => PyType::Literal(variant.name.clone()),
+                    Some(payload) => {
+                        let fields = vec![Field { name: variant.name.clone(), ty: payload, required: true }];
+                        out.push(PyDefinition::typed_dict(&variant_name, fields));
+                        PyType::Named(variant_name)
+                    }
+                },
+                Tagging::Internal { tag } => {
+                    let mut fields = vec![tag_field(tag)];
+                    match &variant.kind {
+                        VariantKind::Struct(variant_fields) => fields.extend(variant_fields.iter().cloned()),
+                        VariantKind::Newtype(ty) => match self.struct_fields(ty) {
+                            Some(inner) => fields.extend(inner),
+                            // Maps and untyped values carry arbitrary keys besides the tag
+                            None => {
+                                members.push(PyType::Dict(Box::new(PyType::Str), Box::new(PyType::Any)));
+                                continue;
+                            }
+                        },
+                        _ => {}
+                    }
+                    out.push(PyDefinition::typed_dict(&variant_name, fields));
+                    PyType::Named(variant_name)
+                }
+                Tagging::Adjacent { tag, content } => {
+                    let mut fields = vec![tag_field(tag)];
+                    if let Some(payload) = payload {
+                        fields.push(Field { name: content.clone(), ty: payload, required: true });
+                    }
+                    out.push(PyDefinition::typed_dict(&variant_name, fields));
+                    PyType::Named(variant_name)
+                }
+                Tagging::Untagged => payload.unwrap_or(PyType::None),
+            };
+            members.push(member);
+        }
+        let union = match members.len() {
+            0 => PyType::Any,
+            _ => PyType::Union(members),
+        };
+        out.push(PyDefinition::Alias { name: enum_name, ty: union });
+    }
+}
+
+/// Write a `TypedDict` definition, using the functional syntax when a key isn't a valid
+/// attribute name.
+fn typed_dict(out: &mut String, name: &str, fields: &[Field], imports: &mut BTreeSet<String>) {
+    let mut key_type = |field: &Field| {
+        field.ty.collect_imports(imports);
+        match field.required {
+            true => field.ty.render(),
+            false => format!("NotRequired[{}]", field.ty.render()),
+        }
+    };
+    out.push('\n');
+    if fields.iter().all(|field| is_identifier(&field.name)) {
+        writeln!(out, "\nclass {name}(TypedDict):").unwrap();
+        if fields.is_empty() {
+            out.push_str("    pass\n");
+        }
+        for field in fields {
+            writeln!(out, "    {}: {}", field.name, key_type(field)).unwrap();
+        }
+    } else {
+        let keys: Vec<String> =
+            fields.iter().map(|field| format!("{}: {}", string_literal(&field.name), key_type(field))).collect();
+        writeln!(out, "\n{name} = TypedDict({}, {{{}}})", string_literal(name), keys.join(", ")).unwrap();
+    }
+}
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+    "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+    "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !PYTHON_KEYWORDS.contains(&name)
+}
+
+/// A valid Python identifier for a serde name.
+pub(crate) fn python_name(name: &str) -> String {
+    let mut ident: String = name.chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if PYTHON_KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+fn string_literal(value: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            c if c.is_control() => write!(literal, "\\u{:04x}", c as u32).unwrap(),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// The Python type produced by a `#[serde(with = "...")]` module from
+/// [`serde_py`](crate::serde_py), or `Any` for other modules.
+pub fn with_module_type(path: &str) -> PyType {
+    let segments: Vec<&str> = path.split("::").collect();
+    let Some(start) = segments.iter().position(|segment| *segment == "serde_py") else {
+        return PyType::Any;
+    };
+    let mut module = &segments[start + 1..];
+    let optional = module.last() == Some(&"option");
+    if optional {
+        module = &module[..module.len() - 1];
+    }
+    let ty = match module {
+        ["uuid"] => "uuid.UUID",
+        ["decimal"] => "decimal.Decimal",
+        ["chrono", "datetime" | "fixed_offset_datetime" | "naive_datetime"]
+        | ["time", "offset_date_time" | "primitive_date_time"] => "datetime.datetime",
+        ["chrono", "naive_date"] | ["time", "date"] => "datetime.date",
+        ["chrono" | "time", "duration"] => "datetime.timedelta",
+        _ => return PyType::Any,
+    };
+    let ty = PyType::External(ty.to_string());
+    match optional {
+        true => PyType::Optional(Box::new(ty)),
+        false => ty,
+    }
+}
+
+macro_rules! impl_py_stub {
+    ($py_type:expr => $($ty:ty),* $(,)?) => {
+        $(impl PyStub for $ty {
+            fn py_type(_stubs: &mut StubModule) -> PyType {
+                $py_type
+            }
+        })*
+    };
+}
+
+impl_py_stub!(PyType::Bool => bool);
+impl_py_stub!(PyType::Int => i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_py_stub!(PyType::Float => f32, f64);
+impl_py_stub!(PyType::Str => char, str, String, std::path::PathBuf, uuid::Uuid);
+impl_py_stub!(PyType::None => ());
+impl_py_stub!(PyType::Any => serde_json::Value);
+
+impl<T: crate::serde_py::BufferElement> PyStub for crate::serde_py::PyBuffer<T> {
+    fn py_type(_stubs: &mut StubModule) -> PyType {
+        PyType::External("memoryview".to_string())
+    }
+}
+
+impl<T: PyStub + ?Sized> PyStub for &T {
+    fn py_type(stubs: &mut StubModule) -> PyType {
+        T::py_type(stubs)
+    }
+}
+
+impl<T: PyStub + ?Sized> PyStub for Box<T> {
+    fn py_type(stubs: &mut StubModule) -> PyType {
+        T::py_type(stubs)
+    }
+}
+
+impl<T: PyStub + ?Sized> PyStub for std::sync::Arc<T> {
+    fn py_type(stubs: &mut StubModule) -> PyType {
+        T::py_type(stubs)
+    }
+}
+
+impl<T: PyStub> PyStub for Option<T> {
+    fn py_type(stubs: &mut StubModule) -> PyType {
+        PyType::Optional(Box::new(T::py_type(stubs)))
+    }
+}
+
+macro_rules! impl_py_stub_list {
+    ($($ty:ty),*) => {
+        $(impl<T: PyStub> PyStub for $ty {
+            fn py_type(stubs: &mut StubModule) -> PyType {
+                PyType::List(Box::new(T::py_type(stubs)))
+            }
+        })*
+    };
+}
+
+impl_py_stub_list!(Vec<T>, VecDeque<T>, [T], HashSet<T>, BTreeSet<T>);
+
+impl<T: PyStub, const N: usize> PyStub for [T; N] {
+    fn py_type(stubs: &mut StubModule) -> PyType {
+        let item = T::py_type(stubs);
+        PyType::Tuple(vec![item; N])
+    }
+}
+
+impl<K: PyStub, V: PyStub> PyStub for HashMap<K, V> {
+    fn py_type(stubs: &mut StubModule) -> PyType {
+        let key = K::py_type(stubs);
+        PyType::Dict(Box::new(stubs.key_type(key)), Box::new(V::py_type(stubs)))
+    }
+}
+
+impl<K: PyStub, V: PyStub> PyStub for BTreeMap<K, V> {
+    fn py_type(stubs: &mut StubModule) -> PyType {
+        let key = K::py_type(stubs);
+        PyType::Dict(Box::new(stubs.key_type(key)), Box::new(V::py_type(stubs)))
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K: PyStub, V: PyStub> PyStub for indexmap::IndexMap<K, V> {
+    fn py_type(stubs: &mut StubModule) -> PyType {
+        let key = K::py_type(stubs);
+        PyType::Dict(Box::new(stubs.key_type(key)), Box::new(V::py_type(stubs)))
+    }
+}
+
+macro_rules! impl_py_stub_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: PyStub),+> PyStub for ($($name,)+) {
+            fn py_type(stubs: &mut StubModule) -> PyType {
+                PyType::Tuple(vec![$($name::py_type(stubs)),+])
+            }
+        }
+    };
+}
+
+impl_py_stub_tuple!(A);
+impl_py_stub_tuple!(A, B);
+impl_py_stub_tuple!(A, B, C);
+impl_py_stub_tuple!(A, B, C, D);
+impl_py_stub_tuple!(A, B, C, D, E);
+impl_py_stub_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PyStub)]
+    struct Item {
+        price: f64,
+        #[serde(rename = "label")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(with = "crate::serde_py::uuid")]
+        id: uuid::Uuid,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PyStub)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum Command {
+        Stop,
+        StreamFibonacci { count: u32 },
+        Buy(Item),
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PyStub)]
+    enum Reply {
+        Done,
+        Items(Vec<Item>, u32),
+        Quote { price: f64, #[serde(skip)] cached: bool },
+        Nested(Box<Reply>),
+    }
+
+    impl KameoChildProcessMessage for Command {
+        type Ok = Reply;
+    }
+
+    #[test]
+    fn test_renders_typed_dicts_and_tagged_unions() {
+        let rendered = StubModule::new().message::<Command>().render();
+        for expected in [
+            "import uuid\n",
+            "class Item(TypedDict):\n    price: float\n    label: Optional[str]\n    tags: NotRequired[list[str]]\n    id: uuid.UUID\n",
+            "class CommandStop(TypedDict):\n    type: Literal[\"stop\"]\n",
+            "class CommandStreamFibonacci(TypedDict):\n    type: Literal[\"stream_fibonacci\"]\n    count: int\n",
+            "class CommandBuy(TypedDict):\n    type: Literal[\"buy\"]\n    price: float\n",
+            "Command: TypeAlias = Union[\"CommandStop\", \"CommandStreamFibonacci\", \"CommandBuy\"]",
+            "class ReplyItems(TypedDict):\n    Items: tuple[list[\"Item\"], int]\n",
+            "class ReplyQuoteFields(TypedDict):\n    price: float\n\n",
+            "class ReplyQuote(TypedDict):\n    Quote: \"ReplyQuoteFields\"\n",
+            "class ReplyNested(TypedDict):\n    Nested: \"Reply\"\n",
+            "Reply: TypeAlias = Union[Literal[\"Done\"], \"ReplyItems\", \"ReplyQuote\", \"ReplyNested\"]",
+            "# \"Command\" -> \"Reply\"",
+        ] {
+            assert!(rendered.contains(expected), "missing {expected:?} in\n{rendered}");
+        }
+    }
+
+    #[test]
+    fn test_adjacent_untagged_and_unusual_keys() {
+        #[derive(Serialize, PyStub)]
+        #[allow(dead_code)]
+        #[serde(tag = "t", content = "c")]
+        enum Adjacent {
+            Unit,
+            Pair(i32, String),
+        }
+        #[derive(Serialize, PyStub)]
+        #[allow(dead_code)]
+        #[serde(untagged)]
+        enum Untagged {
+            Flag(bool),
+            Map(HashMap<String, Adjacent>),
+        }
+        #[derive(Serialize, PyStub)]
+        #[serde(rename_all = "kebab-case")]
+        struct Keys {
+            from_addr: String,
+            r#class: u8,
+            #[serde(flatten)]
+            page: Page<Untagged>,
+        }
+        #[derive(Serialize, PyStub)]
+        struct Page<T> {
+            items: Vec<T>,
+        }
+
+        let rendered = StubModule::new().add::<Keys>().render();
+        for expected in [
+            "Keys = TypedDict(\"Keys\", {\"items\": list[\"Untagged\"], \"from-addr\": str, \"class\": int})",
+            "Untagged: TypeAlias = Union[bool, dict[str, \"Adjacent\"]]",
+            "class AdjacentUnit(TypedDict):\n    t: Literal[\"Unit\"]\n",
+            "class AdjacentPair(TypedDict):\n    t: Literal[\"Pair\"]\n    c: tuple[int, str]\n",
+            "class Page_Untagged(TypedDict):",
+        ] {
+            assert!(rendered.contains(expected), "missing {expected:?} in\n{rendered}");
+        }
+    }
+
+    #[test]
+    fn test_dict_keys_render_in_key_form() {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, PyStub)]
+        #[allow(dead_code)]
+        enum KeyEnum {
+            Esc,
+            Code(u32),
+            Chord(u8, char),
+            Named { name: String },
+        }
+        #[derive(Serialize, PyStub)]
+        struct Keymap {
+            bindings: HashMap<KeyEnum, String>,
+            sequences: BTreeMap<Vec<u8>, KeyEnum>,
+            by_item: HashMap<Item, u32>,
+        }
+
+        let rendered = StubModule::new().add::<Keymap>().render();
+        for expected in [
+            "    bindings: dict[Union[Literal[\"Esc\"], tuple[Literal[\"Code\"], int], tuple[Literal[\"Chord\"], tuple[int, str]]], str]\n",
+            "    sequences: dict[tuple, \"KeyEnum\"]\n",
+            "    by_item: dict[Any, int]\n",
+        ] {
+            assert!(rendered.contains(expected), "missing {expected:?} in\n{rendered}");
+        }
+
+        let keymap = Keymap {
+            bindings: HashMap::from([(KeyEnum::Esc, "quit".into()), (KeyEnum::Code(3), "copy".into())]),
+            sequences: BTreeMap::new(),
+            by_item: HashMap::new(),
+        };
+        let schema = crate::schema::json_schema::<Keymap>(true);
+        Python::with_gil(|py| {
+            let value = crate::serde_py::to_pyobject(py, &keymap.bindings).unwrap();
+            let bindings = &schema["$defs"]["Keymap"]["properties"]["bindings"];
+            let violations = crate::schema::validate(value.bind(py), bindings, "bindings");
+            assert!(violations.is_empty(), "{violations:?}");
+        });
+    }
+
+    #[test]
+    fn test_rendered_module_runs_and_matches_serde_py() {
+        let rendered = StubModule::new().message::<Command>().render();
+        let item = Item { price: 2.0, name: None, tags: vec!["a".into()], id: uuid::Uuid::nil() };
+        Python::with_gil(|py| {
+            let module = PyModule::from_code(
+                py,
+                &std::ffi::CString::new(rendered).unwrap(),
+                c"kameo_stub_test.py",
+                c"kameo_stub_test",
+            )
+            .unwrap();
+            let list = py.import("builtins").unwrap().getattr("list").unwrap();
+            let keys = |value: &Bound<'_, PyAny>| -> Vec<String> { list.call1((value,)).unwrap().extract().unwrap() };
+            let cases = [
+                ("CommandStreamFibonacci", crate::serde_py::to_pyobject(py, &Command::StreamFibonacci { count: 3 })),
+                ("CommandBuy", crate::serde_py::to_pyobject(py, &Command::Buy(item.clone()))),
+                ("Item", crate::serde_py::to_pyobject(py, &item)),
+                ("ReplyQuote", crate::serde_py::to_pyobject(py, &Reply::Quote { price: 1.5, cached: true })),
+            ];
+            for (name, value) in cases {
+                let value = value.unwrap();
+                let annotations = module.getattr(name).unwrap().getattr("__annotations__").unwrap();
+                let mut expected = keys(&annotations);
+                let mut actual = keys(value.bind(py));
+                expected.sort();
+                actual.sort();
+                assert_eq!(actual, expected, "{name}");
+            }
+            let stop = crate::serde_py::to_pyobject(py, &Command::Stop).unwrap();
+            assert_eq!(stop.bind(py).get_item("type").unwrap().extract::<String>().unwrap(), "stop");
+            let done = crate::serde_py::to_pyobject(py, &Reply::Done).unwrap();
+            assert_eq!(done.extract::<String>(py).unwrap(), "Done", "unit variants are bare strings");
+        });
+    }
+}
@@ -6,6 +6,7 @@ It handles things like power, category bonuses, competition results, and rewards
 """
 
 import random
+import sys
 from typing import Dict, Any
 
 class LogicError(Exception):
@@ -86,6 +87,20 @@ def calculate_reward(currency: int, points: int) -> Dict[str, int]:
     total = currency + bonus
     return {"total_currency": total, "bonus_currency": bonus}
 
+def cpu_burn(iterations: int) -> int:
+    """Pure-Python CPU-bound loop used to check parallel execution."""
+    if not isinstance(iterations, int) or iterations < 0:
+        raise LogicError(f"Invalid iteration count: {iterations}")
+    acc = 0
+    for i in range(iterations):
+        acc = (acc * 31 + i) % 1_000_000_007
+    return acc
+
+def gil_enabled() -> bool:
+    """Whether this interpreter runs with the GIL (always True before 3.13)."""
+    is_gil_enabled = getattr(sys, "_is_gil_enabled", None)
+    return True if is_gil_enabled is None else bool(is_gil_enabled())
+
 def handle_message(message: Dict[str, Any]) -> Dict[str, Any]:
     """
     Handle incoming messages from the Rust code.
@@ -160,5 +175,8 @@ def handle_message(message: Dict[str, Any]) -> Dict[str, Any]:
             raise LogicError(f"currency/teef and points/victory_points must be non-negative, got {currency_val}, {points_val}")
         result = calculate_reward(currency_val, points_val)
         return {"RewardResult": {"total_currency": result["total_currency"], "bonus_currency": result["bonus_currency"]}}
+    elif "CpuBurn" in message:
+        checksum = cpu_burn(message["CpuBurn"].get("iterations"))
+        return {"CpuBurnResult": {"checksum": checksum, "gil_enabled": gil_enabled()}}
     else:
         raise LogicError("Invalid message type.")
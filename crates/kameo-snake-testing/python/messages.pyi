@@ -0,0 +1,270 @@
+# Generated from Rust types by kameo_snake_handler::stubs. Do not edit.
+from __future__ import annotations
+
+from typing import Any, Literal, NotRequired, Optional, TypeAlias, TypedDict, Union
+
+
+class TestMessageCalculatePowerFields(TypedDict):
+    count: int
+
+
+class TestMessageCalculatePower(TypedDict):
+    CalculatePower: "TestMessageCalculatePowerFields"
+
+
+class TestMessageCalculateCategoryBonusFields(TypedDict):
+    category_name: str
+    base_power: int
+
+
+class TestMessageCalculateCategoryBonus(TypedDict):
+    CalculateCategoryBonus: "TestMessageCalculateCategoryBonusFields"
+
+
+class TestMessageCalculateCompetitionResultFields(TypedDict):
+    attacker_power: int
+    defender_power: int
+
+
+class TestMessageCalculateCompetitionResult(TypedDict):
+    CalculateCompetitionResult: "TestMessageCalculateCompetitionResultFields"
+
+
+class TestMessageCalculateRewardFields(TypedDict):
+    currency: int
+    points: int
+
+
+class TestMessageCalculateReward(TypedDict):
+    CalculateReward: "TestMessageCalculateRewardFields"
+
+
+class TestMessageCallbackRoundtripFields(TypedDict):
+    value: int
+
+
+class TestMessageCallbackRoundtrip(TypedDict):
+    CallbackRoundtrip: "TestMessageCallbackRoundtripFields"
+
+
+class TestMessageStreamFibonacciFields(TypedDict):
+    count: int
+
+
+class TestMessageStreamFibonacci(TypedDict):
+    StreamFibonacci: "TestMessageStreamFibonacciFields"
+
+
+class TestMessageStreamRandomNumbersFields(TypedDict):
+    count: int
+    max_value: int
+
+
+class TestMessageStreamRandomNumbers(TypedDict):
+    StreamRandomNumbers: "TestMessageStreamRandomNumbersFields"
+
+
+class TestMessageStreamWithDelaysFields(TypedDict):
+    count: int
+    delay_ms: int
+
+
+class TestMessageStreamWithDelays(TypedDict):
+    StreamWithDelays: "TestMessageStreamWithDelaysFields"
+
+
+class TestMessageStreamWithErrorsFields(TypedDict):
+    count: int
+    error_at: Optional[int]
+
+
+class TestMessageStreamWithErrors(TypedDict):
+    StreamWithErrors: "TestMessageStreamWithErrorsFields"
+
+
+class TestMessageStreamLargeDatasetFields(TypedDict):
+    count: int
+
+
+class TestMessageStreamLargeDataset(TypedDict):
+    StreamLargeDataset: "TestMessageStreamLargeDatasetFields"
+
+
+class TestMessageCpuBurnFields(TypedDict):
+    iterations: int
+
+
+class TestMessageCpuBurn(TypedDict):
+    CpuBurn: "TestMessageCpuBurnFields"
+
+TestMessage: TypeAlias = Union["TestMessageCalculatePower", "TestMessageCalculateCategoryBonus", "TestMessageCalculateCompetitionResult", "TestMessageCalculateReward", "TestMessageCallbackRoundtrip", "TestMessageStreamFibonacci", "TestMessageStreamRandomNumbers", "TestMessageStreamWithDelays", "TestMessageStreamWithErrors", "TestMessageStreamLargeDataset", "TestMessageCpuBurn"]
+
+
+class TestResponsePowerFields(TypedDict):
+    power: int
+
+
+class TestResponsePower(TypedDict):
+    Power: "TestResponsePowerFields"
+
+
+class TestResponseCategoryBonusFields(TypedDict):
+    bonus: int
+
+
+class TestResponseCategoryBonus(TypedDict):
+    CategoryBonus: "TestResponseCategoryBonusFields"
+
+
+class TestResponseCompetitionResultFields(TypedDict):
+    victory: bool
+
+
+class TestResponseCompetitionResult(TypedDict):
+    CompetitionResult: "TestResponseCompetitionResultFields"
+
+
+class TestResponseRewardResultFields(TypedDict):
+    total_currency: int
+    bonus_currency: int
+
+
+class TestResponseRewardResult(TypedDict):
+    RewardResult: "TestResponseRewardResultFields"
+
+
+class TestResponseCallbackRoundtripResultFields(TypedDict):
+    value: int
+
+
+class TestResponseCallbackRoundtripResult(TypedDict):
+    CallbackRoundtripResult: "TestResponseCallbackRoundtripResultFields"
+
+
+class TestResponseStreamItemFields(TypedDict):
+    index: int
+    value: int
+
+
+class TestResponseStreamItem(TypedDict):
+    StreamItem: "TestResponseStreamItemFields"
+
+
+class TestResponseStreamErrorFields(TypedDict):
+    index: int
+    error: str
+
+
+class TestResponseStreamError(TypedDict):
+    StreamError: "TestResponseStreamErrorFields"
+
+
+class TestResponseStreamCompleteFields(TypedDict):
+    total_items: int
+
+
+class TestResponseStreamComplete(TypedDict):
+    StreamComplete: "TestResponseStreamCompleteFields"
+
+
+class TestResponseCpuBurnResultFields(TypedDict):
+    checksum: int
+    gil_enabled: bool
+
+
+class TestResponseCpuBurnResult(TypedDict):
+    CpuBurnResult: "TestResponseCpuBurnResultFields"
+
+TestResponse: TypeAlias = Union["TestResponsePower", "TestResponseCategoryBonus", "TestResponseCompetitionResult", "TestResponseRewardResult", "TestResponseCallbackRoundtripResult", "TestResponseStreamItem", "TestResponseStreamError", "TestResponseStreamComplete", "TestResponseCpuBurnResult"]
+
+
+class TraderMessageOrderDetailsFields(TypedDict):
+    item: str
+    currency: int
+
+
+class TraderMessageOrderDetails(TypedDict):
+    OrderDetails: "TraderMessageOrderDetailsFields"
+
+TraderMessage: TypeAlias = "TraderMessageOrderDetails"
+
+
+class TraderResponseOrderResultFields(TypedDict):
+    result: str
+
+
+class TraderResponseOrderResult(TypedDict):
+    OrderResult: "TraderResponseOrderResultFields"
+
+TraderResponse: TypeAlias = "TraderResponseOrderResult"
+
+
+class BenchMessage(TypedDict):
+    id: int
+    py_sleep_ms: int
+    rust_sleep_ms: int
+
+
+class BenchResponsePowerFields(TypedDict):
+    power: int
+
+
+class BenchResponsePower(TypedDict):
+    Power: "BenchResponsePowerFields"
+
+
+class BenchResponseCategoryBonusFields(TypedDict):
+    bonus: int
+
+
+class BenchResponseCategoryBonus(TypedDict):
+    CategoryBonus: "BenchResponseCategoryBonusFields"
+
+
+class BenchResponseCompetitionResultFields(TypedDict):
+    victory: bool
+
+
+class BenchResponseCompetitionResult(TypedDict):
+    CompetitionResult: "BenchResponseCompetitionResultFields"
+
+
+class BenchResponseRewardResultFields(TypedDict):
+    total_currency: int
+    bonus_currency: int
+
+
+class BenchResponseRewardResult(TypedDict):
+    RewardResult: "BenchResponseRewardResultFields"
+
+
+class BenchResponseCallbackRoundtripResultFields(TypedDict):
+    value: int
+
+
+class BenchResponseCallbackRoundtripResult(TypedDict):
+    CallbackRoundtripResult: "BenchResponseCallbackRoundtripResultFields"
+
+BenchResponse: TypeAlias = Union["BenchResponsePower", "BenchResponseCategoryBonus", "BenchResponseCompetitionResult", "BenchResponseRewardResult", "BenchResponseCallbackRoundtripResult"]
+
+
+class TestCallbackMessage(TypedDict):
+    value: int
+
+
+class TraderCallbackMessage(TypedDict):
+    value: int
+
+
+class BenchCallback(TypedDict):
+    id: int
+    rust_sleep_ms: int
+
+
+class BenchCallbackReply(TypedDict):
+    id: int
+
+# Handler signatures: message type -> reply type
+# "TestMessage" -> "TestResponse"
+# "TraderMessage" -> "TraderResponse"
+# "BenchMessage" -> "BenchResponse"
@@ -1,8 +1,12 @@
 use bincode::{Decode, Encode};
 use kameo::reply::Reply;
-use kameo_child_process::KameoChildProcessMessage;
+use kameo_child_process::{CodecKind, Compression, KameoChildProcessMessage};
 use kameo_child_process::prelude::SubprocessIpcActorExt;
 use kameo_snake_handler::prelude::*;
+use kameo_snake_handler::child::{dispatch_if_child, ChildConverters, ChildRegistry};
+use kameo_snake_handler::convert::Converters;
+use kameo_snake_handler::stubs::StubModule;
+use kameo_snake_handler::{PyConvert, PyStub};
 use kameo_snake_handler::telemetry::build_subscriber_with_otel_and_fmt_async_with_config;
 use kameo_snake_handler::telemetry::TelemetryExportConfig;
 use serde::{Deserialize, Serialize};
@@ -10,7 +14,6 @@ use std::time::Duration;
 use thiserror::Error;
 use tokio::time::timeout;
 use tracing::{error, info};
-use tracing_futures::Instrument;
 use rand::{Rng, thread_rng};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -20,7 +23,6 @@ use std::fmt::Write as _;
 use futures::stream::StreamExt;
 use kameo_child_process::error::PythonExecutionError;
 use kameo_child_process::callback::{CallbackHandler};
-use pyo3::pyfunction;
 
 
 /// Custom error type for logic operations
@@ -35,7 +37,7 @@ pub enum TestError {
 }
 
 /// Message types that can be sent to Python subprocess
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PyStub, PyConvert)]
 pub enum TestMessage {
     CalculatePower {
         count: u32,
@@ -74,6 +76,10 @@ pub enum TestMessage {
     StreamLargeDataset {
         count: u32,
     },
+    // CPU-bound sync work for free-threaded parallelism checks
+    CpuBurn {
+        iterations: u32,
+    },
 }
 
 impl Default for TestMessage {
@@ -83,7 +89,7 @@ impl Default for TestMessage {
 }
 
 /// Response types from Python subprocess
-#[derive(Debug, Serialize, Deserialize, Clone, Decode, Encode)]
+#[derive(Debug, Serialize, Deserialize, Clone, Decode, Encode, PyStub, PyConvert)]
 pub enum TestResponse {
     Power {
         power: u32,
@@ -113,6 +119,10 @@ pub enum TestResponse {
     StreamComplete {
         total_items: u32,
     },
+    CpuBurnResult {
+        checksum: u64,
+        gil_enabled: bool,
+    },
 }
 
 impl Reply for TestResponse {
@@ -131,7 +141,7 @@ impl KameoChildProcessMessage for TestMessage {
     type Ok = TestResponse;
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PyStub, PyConvert)]
 pub struct TestCallbackMessage {
     pub value: u32,
 }
@@ -178,12 +188,12 @@ impl CallbackHandler<BenchCallback> for CountingCallbackHandler {
 }
 
 // --- DSPy Trader Demo Types ---
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PyStub, PyConvert)]
 pub enum TraderMessage {
     OrderDetails { item: String, currency: u32 },
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Decode, Encode)]
+#[derive(Debug, Serialize, Deserialize, Clone, Decode, Encode, PyStub, PyConvert)]
 pub enum TraderResponse {
     OrderResult { result: String },
 }
@@ -204,19 +214,19 @@ impl KameoChildProcessMessage for TraderMessage {
     type Ok = TraderResponse;
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PyStub, PyConvert)]
 pub struct TraderCallbackMessage {
     pub value: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PyStub, PyConvert)]
 pub struct BenchMessage {
     pub id: u64,
     pub py_sleep_ms: u64,
     pub rust_sleep_ms: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Decode, Encode)]
+#[derive(Debug, Serialize, Deserialize, Clone, Decode, Encode, PyStub, PyConvert)]
 pub enum BenchResponse {
     Power { power: u32 },
     CategoryBonus { bonus: u32 },
@@ -241,13 +251,13 @@ impl KameoChildProcessMessage for BenchMessage {
     type Ok = BenchResponse;
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PyStub, PyConvert)]
 pub struct BenchCallback {
     pub id: u64,
     pub rust_sleep_ms: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PyStub, PyConvert)]
 pub struct BenchCallbackReply {
     pub id: u64,
 }
@@ -276,6 +286,8 @@ async fn run_sync_tests(python_path: Vec<String>) -> Result<(), Box<dyn std::err
         env_vars: vec![],
         is_async: false,
         module_path: "crates/kameo-snake-testing/python/logic.py".to_string(),
+        sync_execution: SyncExecution::Blocking { max_concurrency: 8 },
+        schema: None,
     };
     tracing::trace!(
         event = "test_spawn",
@@ -284,6 +296,7 @@ async fn run_sync_tests(python_path: Vec<String>) -> Result<(), Box<dyn std::err
     );
     let sync_pool = PythonChildProcessBuilder::<TestMessage, TestCallbackMessage>::new(sync_config)
         .with_callback_handler(TestCallbackHandler)
+        .validate_replies(true)
         .spawn_pool(POOL_SIZE, None)
         .await?;
     let sync_ref = sync_pool.get_actor();
@@ -379,9 +392,13 @@ async fn run_async_tests(python_path: Vec<String>) -> Result<(), Box<dyn std::er
         env_vars: vec![],
         is_async: true,
         module_path: "crates/kameo-snake-testing/python/logic_async.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
+    // Hand the child its sockets at spawn; the sync tests cover the listening path
     let async_pool = PythonChildProcessBuilder::<TestMessage, TestCallbackMessage>::new(async_config)
         .with_callback_handler(TestCallbackHandler)
+        .inherit_sockets(true)
         .spawn_pool(POOL_SIZE, None)
         .await?;
     let async_ref = async_pool.get_actor();
@@ -494,6 +511,8 @@ async fn run_streaming_tests(python_path: Vec<String>) -> Result<(), Box<dyn std
         env_vars: vec![],
         is_async: true,
         module_path: "crates/kameo-snake-testing/python/logic_streaming.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
     let streaming_pool = PythonChildProcessBuilder::<TestMessage, TestCallbackMessage>::new(streaming_config)
         .with_callback_handler(TestCallbackHandler)
@@ -720,6 +739,8 @@ async fn run_streaming_throughput_test(python_path: Vec<String>) -> Result<(), B
         env_vars: vec![],
         is_async: true,
         module_path: "crates/kameo-snake-testing/python/logic_streaming.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
     let streaming_pool = PythonChildProcessBuilder::<TestMessage, TestCallbackMessage>::new(streaming_config)
         .with_callback_handler(TestCallbackHandler)
@@ -806,6 +827,8 @@ async fn run_streaming_error_handling_test(python_path: Vec<String>) -> Result<(
         env_vars: vec![],
         is_async: true,
         module_path: "crates/kameo-snake-testing/python/logic_streaming.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
     let streaming_pool = PythonChildProcessBuilder::<TestMessage, TestCallbackMessage>::new(streaming_config)
         .with_callback_handler(TestCallbackHandler)
@@ -882,6 +905,8 @@ async fn run_invalid_config_tests(
         env_vars: vec![],
         is_async: false,
         module_path: "crates/kameo-snake-testing/python/non_existent_module.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
 
     let spawn_result = timeout(
@@ -904,6 +929,8 @@ async fn run_invalid_config_tests(
         env_vars: vec![],
         is_async: false,
         module_path: "crates/kameo-snake-testing/python/logic.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
     let spawn_result = timeout(
         Duration::from_secs(31),
@@ -925,6 +952,8 @@ async fn run_invalid_config_tests(
         env_vars: vec![],
         is_async: false,
         module_path: "crates/kameo-snake-testing/python/logic.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
     let spawn_result = timeout(
         Duration::from_secs(32),
@@ -949,6 +978,8 @@ async fn run_trader_demo(python_path: Vec<String>) -> Result<(), Box<dyn std::er
         env_vars: vec![],
         is_async: true,
         module_path: "crates/kameo-snake-testing/python/dspy_trader.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
     let trader_pool = PythonChildProcessBuilder::<TraderMessage, TraderCallbackMessage>::new(trader_config)
         .with_callback_handler(TestCallbackHandler)
@@ -970,6 +1001,174 @@ async fn run_trader_demo(python_path: Vec<String>) -> Result<(), Box<dyn std::er
     Ok(())
 }
 
+const CPU_BURN_PARALLELISM: usize = 4;
+const CPU_BURN_MIN_SPEEDUP: f64 = 2.0;
+
+/// Times `CPU_BURN_PARALLELISM` concurrent `CpuBurn` requests against a single one.
+///
+/// Uses a one-child pool, so any speedup comes from parallel execution inside that
+/// child. Returns whether the handler's interpreter reported the GIL as enabled and
+/// the measured speedup (`CPU_BURN_PARALLELISM` means perfectly parallel).
+async fn measure_cpu_burn_speedup(
+    label: &str,
+    python_path: Vec<String>,
+    sync_execution: SyncExecution,
+) -> Result<(bool, f64), Box<dyn std::error::Error>> {
+    const ITERATIONS: u32 = 2_000_000;
+    let config = PythonConfig {
+        python_path: python_path.clone(),
+        module_name: "logic".to_string(),
+        function_name: "handle_message".to_string(),
+        env_vars: vec![],
+        is_async: false,
+        module_path: "crates/kameo-snake-testing/python/logic.py".to_string(),
+        sync_execution,
+        schema: None,
+    };
+    let pool = PythonChildProcessBuilder::<TestMessage, TestCallbackMessage>::new(config)
+        .with_callback_handler(TestCallbackHandler)
+        .spawn_pool(CPU_BURN_PARALLELISM, None)
+        .await?;
+
+    // Warm up, then time a single request as the serial baseline
+    let actor = pool.get_actor();
+    actor
+        .ask(TestMessage::CpuBurn { iterations: 1_000 })
+        .await
+        .map_err(|e| format!("CpuBurn warm-up failed: {e:?}"))?;
+    let t0 = Instant::now();
+    let gil_enabled = match actor.ask(TestMessage::CpuBurn { iterations: ITERATIONS }).await {
+        Ok(TestResponse::CpuBurnResult { gil_enabled, .. }) => gil_enabled,
+        other => return Err(format!("Unexpected CpuBurn response: {other:?}").into()),
+    };
+    let single = t0.elapsed();
+
+    let t0 = Instant::now();
+    let results = futures::future::join_all((0..CPU_BURN_PARALLELISM).map(|_| {
+        let actor = pool.get_actor();
+        async move { actor.ask(TestMessage::CpuBurn { iterations: ITERATIONS }).await }
+    }))
+    .await;
+    let parallel = t0.elapsed();
+    for result in results {
+        if !matches!(result, Ok(TestResponse::CpuBurnResult { .. })) {
+            return Err(format!("CpuBurn request failed: {result:?}").into());
+        }
+    }
+
+    let speedup = (single.as_secs_f64() * CPU_BURN_PARALLELISM as f64) / parallel.as_secs_f64();
+    println!(
+        "{label}: gil_enabled={gil_enabled} single={:.3}s parallel({CPU_BURN_PARALLELISM})={:.3}s speedup={speedup:.2}x",
+        single.as_secs_f64(),
+        parallel.as_secs_f64()
+    );
+    Ok((gil_enabled, speedup))
+}
+
+/// Verifies that CPU-bound sync handlers run in parallel inside a single child.
+///
+/// On a free-threaded interpreter the batch should take well under
+/// `CPU_BURN_PARALLELISM` times the single-request time; with the GIL it is reported only.
+async fn run_free_threaded_tests(python_path: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let (gil_enabled, speedup) = measure_cpu_burn_speedup(
+        "free-threaded",
+        python_path,
+        SyncExecution::Blocking { max_concurrency: CPU_BURN_PARALLELISM },
+    )
+    .await?;
+    if cfg!(feature = "free-threaded") && gil_enabled {
+        return Err("free-threaded feature enabled but the child interpreter has the GIL enabled".into());
+    }
+    if !gil_enabled && speedup < CPU_BURN_MIN_SPEEDUP {
+        return Err(format!(
+            "CPU-bound sync handlers did not run in parallel: speedup {speedup:.2}x < {CPU_BURN_MIN_SPEEDUP}x"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Verifies that sub-interpreters with their own GIL run CPU-bound sync handlers in parallel.
+///
+/// Requires Python 3.14+ in the child (`InterpreterPoolExecutor`).
+async fn run_subinterpreter_tests(python_path: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, speedup) = measure_cpu_burn_speedup(
+        "subinterpreters",
+        python_path,
+        SyncExecution::SubInterpreters { interpreters: CPU_BURN_PARALLELISM },
+    )
+    .await?;
+    if speedup < CPU_BURN_MIN_SPEEDUP {
+        return Err(format!(
+            "Sub-interpreter handlers did not run in parallel: speedup {speedup:.2}x < {CPU_BURN_MIN_SPEEDUP}x"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Runs `logic.py` in a worker daemon on 127.0.0.1 and talks to it with `connect_remote`.
+///
+/// Connects twice to check that the worker serves a new parent once the first disconnects.
+async fn run_remote_worker_tests(python_path: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let config = PythonConfig {
+        python_path: python_path.clone(),
+        module_name: "logic".to_string(),
+        function_name: "handle_message".to_string(),
+        env_vars: vec![],
+        is_async: false,
+        module_path: "crates/kameo-snake-testing/python/logic.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
+    };
+    // Reserve a free port for the worker
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.to_string();
+    let token = kameo_child_process::HandshakeOptions::new_token();
+    let mut worker = tokio::process::Command::new(std::env::current_exe()?)
+        .env(kameo_child_process::CHILD_ACTOR_ENV, std::any::type_name::<PythonActor<TestMessage, TestCallbackMessage>>())
+        .env("KAMEO_PYTHON_CONFIG", serde_json::to_string(&config)?)
+        .env(kameo_child_process::WORKER_LISTEN_ENV, &addr)
+        .env(kameo_child_process::HANDSHAKE_TOKEN_ENV, &token)
+        .env("PYTHONPATH", python_path.join(":"))
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let result = async {
+        // The second session also checks that each session negotiates its own wire format
+        let sessions = [(CodecKind::Bincode, Compression::None), (CodecKind::MessagePack, Compression::Zstd)];
+        for (session, (codec, compression)) in sessions.into_iter().enumerate() {
+            // The worker needs a moment to start Python and bind
+            let mut attempts = 0;
+            let pool = loop {
+                match PythonChildProcessBuilder::<TestMessage, TestCallbackMessage>::new(config.clone())
+                    .with_callback_handler(TestCallbackHandler)
+                    .codec(codec)
+                    .compression(compression)
+                    .connect_remote(&addr, &token, POOL_SIZE)
+                    .await
+                {
+                    Ok(pool) => break pool,
+                    Err(e) if attempts < 50 => {
+                        attempts += 1;
+                        tracing::debug!(error = %e, attempts, "Remote worker not ready yet");
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                    }
+                    Err(e) => return Err(format!("Could not connect to remote worker at {addr}: {e}").into()),
+                }
+            };
+            let resp = pool.get_actor().ask(TestMessage::CalculatePower { count: 100 }).await;
+            if !matches!(resp, Ok(TestResponse::Power { .. })) {
+                return Err(format!("Remote session {session}: unexpected response {resp:?}").into());
+            }
+            pool.shutdown().await;
+        }
+        Ok::<(), Box<dyn std::error::Error>>(())
+    }
+    .await;
+    let _ = worker.kill().await;
+    result
+}
+
 async fn run_bench_throughput_test(python_path: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
     const N: usize = 10000;
     const MAX_SLEEP_MS: u64 = 10;
@@ -981,6 +1180,8 @@ async fn run_bench_throughput_test(python_path: Vec<String>) -> Result<(), Box<d
         env_vars: vec![],
         is_async: true,
         module_path: "crates/kameo-snake-testing/python/bench_async.py".to_string(),
+        sync_execution: SyncExecution::Inline,
+        schema: None,
     };
     let callback_count = Arc::new(AtomicUsize::new(0));
     let callback_handler = CountingCallbackHandler { counter: callback_count.clone() };
@@ -1094,88 +1295,311 @@ async fn run_bench_throughput_test(python_path: Vec<String>) -> Result<(), Box<d
 
 
 
-kameo_snake_handler::setup_python_subprocess_system! {
-    actor = (TestMessage, TestCallbackMessage),
-    actor = (TraderMessage, TraderCallbackMessage),
-    actor = (BenchMessage, BenchCallback),
-    child_init = {{
-        kameo_child_process::RuntimeConfig {
-            flavor: kameo_child_process::RuntimeFlavor::MultiThread,
-            worker_threads: Some(8),
-        }
-    }},
-    parent_init = {
-        // Create parent runtime
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .worker_threads(2)
-            .thread_name("test-main")
-            .enable_all()
-            .build()?;
-
-        // Initialize the callback handle before any tests run
-
-        let args: Vec<String> = env::args().collect();
-        let run_all = args.len() == 1;
-        let run_sync = run_all || args.iter().any(|a| a == "sync");
-        let run_async = run_all || args.iter().any(|a| a == "async");
-        let run_trader = run_all || args.iter().any(|a| a == "trader");
-        let run_bench = run_all || args.iter().any(|a| a == "bench");
-        let run_module = args.iter().any(|a| a == "module");
-        let run_streaming = run_all || args.iter().any(|a| a == "streaming");
-        let run_streaming_throughput = run_all || args.iter().any(|a| a == "streaming-throughput");
-        let run_streaming_errors = run_all || args.iter().any(|a| a == "streaming-errors");
-        if args.iter().any(|a| a == "--help" || a == "-h") {
-            println!("Usage: kameo-snake-testing [sync] [async] [trader] [bench] [module] [streaming] [streaming-throughput] [streaming-errors]");
-            println!("  If no args, runs all tests.");
-            return Ok(());
-        }
+/// Answers bench messages in-process, so the IPC benchmark measures only the wire.
+#[derive(Clone)]
+struct BenchEchoHandler;
 
-        runtime.block_on(async {
-            // Use OpenTelemetry + fmt subscriber, respects RUST_LOG/env_filter
-            let (subscriber, _guard) = build_subscriber_with_otel_and_fmt_async_with_config(
-                TelemetryExportConfig {
-                    otlp_enabled: true,
-                    stdout_enabled: true,
-                    metrics_enabled: true,
+#[async_trait::async_trait]
+impl kameo_child_process::ChildProcessMessageHandler<BenchMessage> for BenchEchoHandler {
+    async fn handle_child_message(&mut self, msg: BenchMessage) -> Result<BenchResponse, PythonExecutionError> {
+        Ok(BenchResponse::CallbackRoundtripResult { value: msg.id as u32 })
+    }
+}
+
+/// Ops/sec for `total` requests, `concurrency` at a time, over a Unix socket pair.
+async fn measure_ipc_throughput(
+    write_batch: kameo_child_process::WriteBatch,
+    total: usize,
+    concurrency: usize,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    use kameo_child_process::{framing, ChildActorLoopConfig, SubprocessIpcBackend};
+    // Both ends are in this process, so the global setting covers the backend and the child loop
+    framing::set_write_batch(write_batch);
+    let (parent, child) = tokio::net::UnixStream::pair()?;
+    let backend = SubprocessIpcBackend::<BenchMessage>::from_transport(parent);
+    let config = ChildActorLoopConfig { write_batch, ..Default::default() };
+    let child_task = tokio::spawn(kameo_child_process::run_child_actor_loop::<_, BenchMessage, _>(
+        BenchEchoHandler,
+        child,
+        Some(config),
+    ));
+    let next = Arc::new(AtomicUsize::new(0));
+    let start = Instant::now();
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let backend = backend.clone();
+            let next = next.clone();
+            tokio::spawn(async move {
+                while next.fetch_add(1, Ordering::Relaxed) < total {
+                    let msg = BenchMessage { id: 1, py_sleep_ms: 0, rust_sleep_ms: 0 };
+                    backend.send(msg).await?;
                 }
-            ).await;
-            tracing::subscriber::set_global_default(subscriber).expect("set global");
-            tracing::info!("Parent runtime initialized");
-
-            let python_path = std::env::current_dir()?
-                .join("crates")
-                .join("kameo-snake-testing")
-                .join("python");
-            let site_packages = "crates/kameo-snake-testing/python/venv/lib/python3.13/site-packages";
-            let python_path_vec = vec![
-                site_packages.to_string(),
-                python_path.to_string_lossy().to_string(),
-            ];
-            if run_sync {
-                run_sync_tests(python_path_vec.clone()).await?;
-            }
-            if run_async {
-                run_async_tests(python_path_vec.clone()).await?;
-            }
-            if run_trader {
-                run_trader_demo(python_path_vec.clone()).await?;
-            }
-            if run_bench {
-                run_bench_throughput_test(python_path_vec.clone()).await?;
-            }
-            if run_module {
-                run_invalid_config_tests(python_path_vec.clone()).await?;
-            }
-            if run_streaming {
-                run_streaming_tests(python_path_vec.clone()).await?;
-            }
-            if run_streaming_throughput {
-                run_streaming_throughput_test(python_path_vec.clone()).await?;
-            }
-            if run_streaming_errors {
-                run_streaming_error_handling_test(python_path_vec.clone()).await?;
-            }
-            Ok::<(), Box<dyn std::error::Error>>(())
-        })?
+                Ok::<(), PythonExecutionError>(())
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.await??;
+    }
+    let elapsed = start.elapsed();
+    backend.shutdown();
+    drop(backend);
+    child_task.await??;
+    Ok(total as f64 / elapsed.as_secs_f64())
+}
+
+/// Compares request throughput with write coalescing off and on.
+async fn run_ipc_write_batch_bench() -> Result<(), Box<dyn std::error::Error>> {
+    use kameo_child_process::WriteBatch;
+    const TOTAL: usize = 200_000;
+    const CONCURRENCY: usize = 256;
+    let original = kameo_child_process::framing::write_batch();
+    // Warm up allocators and the runtime before measuring
+    measure_ipc_throughput(WriteBatch::default(), TOTAL / 10, CONCURRENCY).await?;
+    let unbatched = measure_ipc_throughput(WriteBatch::disabled(), TOTAL, CONCURRENCY).await?;
+    let batched = measure_ipc_throughput(WriteBatch::default(), TOTAL, CONCURRENCY).await?;
+    kameo_child_process::framing::set_write_batch(original);
+    let mut table = String::new();
+    writeln!(table, "\n┏━━━━━━━━━━━━━━━━━━━━━━━━━━━┳━━━━━━━━━━━━━━━━━━━━━━━┓").unwrap();
+    writeln!(table,   "┃ IPC write batching        ┃ {:>9} requests    ┃", TOTAL).unwrap();
+    writeln!(table,   "┣━━━━━━━━━━━━━━━━━━━━━━━━━━━╋━━━━━━━━━━━━━━━━━━━━━━━┫").unwrap();
+    writeln!(table,   "┃ One write per frame       ┃ {:>9.0} ops/sec     ┃", unbatched).unwrap();
+    writeln!(table,   "┃ Coalesced writes          ┃ {:>9.0} ops/sec     ┃", batched).unwrap();
+    writeln!(table,   "┃ Speedup                   ┃ {:>9.2}x            ┃", batched / unbatched).unwrap();
+    writeln!(table,   "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━┻━━━━━━━━━━━━━━━━━━━━━━━┛").unwrap();
+    println!("{}", table);
+    Ok(())
+}
+
+/// Regenerate `python/messages.pyi` from the message types above, so the test handlers can be
+/// type checked against what the Rust side actually sends.
+fn write_message_stubs(python_dir: &std::path::Path) -> std::io::Result<()> {
+    let path = python_dir.join("messages.pyi");
+    StubModule::new()
+        .message::<TestMessage>()
+        .message::<TraderMessage>()
+        .message::<BenchMessage>()
+        .add::<TestCallbackMessage>()
+        .add::<TraderCallbackMessage>()
+        .add::<BenchCallback>()
+        .add::<BenchCallbackReply>()
+        .write(&path)?;
+    info!(path = %path.display(), "Wrote message stubs");
+    Ok(())
+}
+
+/// Nanoseconds to convert `message` to Python and `reply` back, averaged over `total` rounds.
+fn measure_conversion<M: KameoChildProcessMessage>(
+    py: pyo3::Python<'_>,
+    converters: Converters<M>,
+    message: &M,
+    reply: &pyo3::Bound<'_, pyo3::PyAny>,
+    total: usize,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    for _ in 0..total {
+        std::hint::black_box((converters.message)(py, std::hint::black_box(message))?);
+        std::hint::black_box((converters.reply)(std::hint::black_box(reply))?);
+    }
+    Ok(start.elapsed().as_nanos() as f64 / total as f64)
+}
+
+/// Compares `serde_py` with the derived `PyConvert` conversions for the message types.
+fn run_conversion_bench() -> Result<(), Box<dyn std::error::Error>> {
+    const TOTAL: usize = 200_000;
+    fn compare<M>(py: pyo3::Python<'_>, message: M, reply: M::Ok) -> Result<(f64, f64), Box<dyn std::error::Error>>
+    where
+        M: KameoChildProcessMessage + PyConvert,
+        M::Ok: PyConvert,
+    {
+        let reply = kameo_snake_handler::to_pyobject(py, &reply)?.into_bound(py);
+        // Warm up both paths before measuring
+        measure_conversion(py, Converters::<M>::serde(), &message, &reply, TOTAL / 10)?;
+        measure_conversion(py, Converters::<M>::py_convert(), &message, &reply, TOTAL / 10)?;
+        let serde = measure_conversion(py, Converters::<M>::serde(), &message, &reply, TOTAL)?;
+        let fast = measure_conversion(py, Converters::<M>::py_convert(), &message, &reply, TOTAL)?;
+        Ok((serde, fast))
+    }
+    let rows = pyo3::Python::with_gil(|py| {
+        Ok::<_, Box<dyn std::error::Error>>([
+            (
+                "BenchMessage",
+                compare(
+                    py,
+                    BenchMessage { id: 42, py_sleep_ms: 0, rust_sleep_ms: 0 },
+                    BenchResponse::RewardResult { total_currency: 100, bonus_currency: 10 },
+                )?,
+            ),
+            (
+                "TestMessage",
+                compare(
+                    py,
+                    TestMessage::CalculateCategoryBonus { category_name: "warrior".to_string(), base_power: 7 },
+                    TestResponse::CategoryBonus { bonus: 3 },
+                )?,
+            ),
+        ])
+    })?;
+    let mut table = String::new();
+    writeln!(table, "\n┏━━━━━━━━━━━━━━━┳━━━━━━━━━━━━━━━┳━━━━━━━━━━━━━━━┳━━━━━━━━━━┓").unwrap();
+    writeln!(table,   "┃ ns/round trip ┃ serde_py      ┃ PyConvert     ┃ Speedup  ┃").unwrap();
+    writeln!(table,   "┣━━━━━━━━━━━━━━━╋━━━━━━━━━━━━━━━╋━━━━━━━━━━━━━━━╋━━━━━━━━━━┫").unwrap();
+    for (name, (serde, fast)) in rows {
+        writeln!(table, "┃ {:<13} ┃ {:>13.0} ┃ {:>13.0} ┃ {:>7.2}x ┃", name, serde, fast, serde / fast).unwrap();
+    }
+    writeln!(table,   "┗━━━━━━━━━━━━━━━┻━━━━━━━━━━━━━━━┻━━━━━━━━━━━━━━━┻━━━━━━━━━━┛").unwrap();
+    println!("{}", table);
+    Ok(())
+}
+
+/// Compares a `Vec<f64>` with a `PyBuffer<f64>` of the same numbers going through `serde_py`.
+fn run_buffer_bench() -> Result<(), Box<dyn std::error::Error>> {
+    use kameo_snake_handler::{from_pyobject, to_pyobject, PyBuffer};
+    const TOTAL_ELEMENTS: usize = 20_000_000;
+    fn measure<T>(py: pyo3::Python<'_>, value: &T, total: usize) -> Result<f64, Box<dyn std::error::Error>>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let start = Instant::now();
+        for _ in 0..total {
+            let obj = to_pyobject(py, std::hint::black_box(value))?;
+            std::hint::black_box(from_pyobject::<T>(obj.bind(py))?);
+        }
+        Ok(start.elapsed().as_nanos() as f64 / total as f64)
     }
+    let rows = pyo3::Python::with_gil(|py| {
+        [16, 1024, 65536]
+            .into_iter()
+            .map(|len| {
+                let values: Vec<f64> = (0..len).map(|i| i as f64 * 0.5).collect();
+                let buffer = PyBuffer(values.clone());
+                let total = (TOTAL_ELEMENTS / len).max(10);
+                measure(py, &values, total / 10)?;
+                measure(py, &buffer, total / 10)?;
+                Ok::<_, Box<dyn std::error::Error>>((len, measure(py, &values, total)?, measure(py, &buffer, total)?))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    let mut table = String::new();
+    writeln!(table, "\n┏━━━━━━━━━━━━━━━┳━━━━━━━━━━━━━━━┳━━━━━━━━━━━━━━━┳━━━━━━━━━━┓").unwrap();
+    writeln!(table,   "┃ f64 elements  ┃ Vec (ns)      ┃ PyBuffer (ns) ┃ Speedup  ┃").unwrap();
+    writeln!(table,   "┣━━━━━━━━━━━━━━━╋━━━━━━━━━━━━━━━╋━━━━━━━━━━━━━━━╋━━━━━━━━━━┫").unwrap();
+    for (len, list, buffer) in rows {
+        writeln!(table, "┃ {:<13} ┃ {:>13.0} ┃ {:>13.0} ┃ {:>7.2}x ┃", len, list, buffer, list / buffer).unwrap();
+    }
+    writeln!(table,   "┗━━━━━━━━━━━━━━━┻━━━━━━━━━━━━━━━┻━━━━━━━━━━━━━━━┻━━━━━━━━━━┛").unwrap();
+    println!("{}", table);
+    Ok(())
+}
+
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dispatch_if_child(
+        ChildRegistry::new()
+            .runtime(kameo_child_process::RuntimeConfig {
+                flavor: kameo_child_process::RuntimeFlavor::MultiThread,
+                worker_threads: Some(8),
+            })
+            .actor_with(ChildConverters::<TestMessage, TestCallbackMessage>::py_convert())
+            .actor_with(ChildConverters::<TraderMessage, TraderCallbackMessage>::py_convert())
+            .actor_with(ChildConverters::<BenchMessage, BenchCallback>::py_convert()),
+    );
+
+    // Create parent runtime
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("test-main")
+        .enable_all()
+        .build()?;
+
+    // Initialize the callback handle before any tests run
+
+    let args: Vec<String> = env::args().collect();
+    let run_all = args.len() == 1;
+    let run_sync = run_all || args.iter().any(|a| a == "sync");
+    let run_async = run_all || args.iter().any(|a| a == "async");
+    let run_trader = run_all || args.iter().any(|a| a == "trader");
+    let run_bench = run_all || args.iter().any(|a| a == "bench");
+    let run_bench_ipc = run_all || args.iter().any(|a| a == "bench-ipc");
+    let run_bench_convert = run_all || args.iter().any(|a| a == "bench-convert");
+    let run_module = args.iter().any(|a| a == "module");
+    let run_streaming = run_all || args.iter().any(|a| a == "streaming");
+    let run_streaming_throughput = run_all || args.iter().any(|a| a == "streaming-throughput");
+    let run_streaming_errors = run_all || args.iter().any(|a| a == "streaming-errors");
+    let run_free_threaded = args.iter().any(|a| a == "free-threaded");
+    let run_subinterpreters = args.iter().any(|a| a == "subinterpreters");
+    let run_remote = run_all || args.iter().any(|a| a == "remote");
+    let run_stubs = args.iter().any(|a| a == "stubs");
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("Usage: kameo-snake-testing [sync] [async] [trader] [bench] [bench-ipc] [bench-convert] [module] [streaming] [streaming-throughput] [streaming-errors] [free-threaded] [subinterpreters] [remote] [stubs]");
+        println!("  If no args, runs all tests.");
+        return Ok(());
+    }
+
+    runtime.block_on(async {
+        // Use OpenTelemetry + fmt subscriber, respects RUST_LOG/env_filter
+        let (subscriber, _guard) = build_subscriber_with_otel_and_fmt_async_with_config(
+            TelemetryExportConfig {
+                otlp_enabled: true,
+                stdout_enabled: true,
+                metrics_enabled: true,
+            }
+        ).await;
+        tracing::subscriber::set_global_default(subscriber).expect("set global");
+        tracing::info!("Parent runtime initialized");
+
+        let python_path = std::env::current_dir()?
+            .join("crates")
+            .join("kameo-snake-testing")
+            .join("python");
+        let site_packages = "crates/kameo-snake-testing/python/venv/lib/python3.13/site-packages";
+        let python_path_vec = vec![
+            site_packages.to_string(),
+            python_path.to_string_lossy().to_string(),
+        ];
+        if run_sync {
+            run_sync_tests(python_path_vec.clone()).await?;
+        }
+        if run_async {
+            run_async_tests(python_path_vec.clone()).await?;
+        }
+        if run_trader {
+            run_trader_demo(python_path_vec.clone()).await?;
+        }
+        if run_bench {
+            run_bench_throughput_test(python_path_vec.clone()).await?;
+        }
+        if run_bench_ipc {
+            run_ipc_write_batch_bench().await?;
+        }
+        if run_bench_convert {
+            run_conversion_bench()?;
+            run_buffer_bench()?;
+        }
+        if run_module {
+            run_invalid_config_tests(python_path_vec.clone()).await?;
+        }
+        if run_streaming {
+            run_streaming_tests(python_path_vec.clone()).await?;
+        }
+        if run_streaming_throughput {
+            run_streaming_throughput_test(python_path_vec.clone()).await?;
+        }
+        if run_streaming_errors {
+            run_streaming_error_handling_test(python_path_vec.clone()).await?;
+        }
+        if run_free_threaded {
+            run_free_threaded_tests(python_path_vec.clone()).await?;
+        }
+        if run_subinterpreters {
+            run_subinterpreter_tests(python_path_vec.clone()).await?;
+        }
+        if run_remote {
+            run_remote_worker_tests(python_path_vec.clone()).await?;
+        }
+        if run_stubs {
+            write_message_stubs(&python_path)?;
+        }
+        Ok::<(), Box<dyn std::error::Error>>(())
+    })
 }